@@ -0,0 +1,502 @@
+//! Rule-based alerts over a monitor's events, run by `orderbook-alerts`.
+//!
+//! Rules and sinks are read from TOML:
+//!
+//! ```toml
+//! [[rule]]
+//! name = "sol-move"
+//! kind = "price_move"
+//! market = "SOL/USDC"
+//! percent = 2.0
+//! window_secs = 60
+//!
+//! [[rule]]
+//! name = "wide-spread"
+//! kind = "spread"
+//! max_bps = 50.0
+//!
+//! [[rule]]
+//! name = "quiet"
+//! kind = "silence"
+//! minutes = 10
+//!
+//! [[rule]]
+//! name = "whale"
+//! kind = "large_fill"
+//! min_size = 10000
+//!
+//! [[sink]]
+//! kind = "stdout"
+//!
+//! [[sink]]
+//! kind = "webhook"
+//! url = "https://hooks.example.com/orderbook"
+//! ```
+//!
+//! A rule without `market` watches every market, each on its own. Price
+//! moves, spreads and silences alert when they start and not again until
+//! the condition has cleared; every large fill alerts.
+
+use crate::follow::SeenEvents;
+use serde::{Deserialize, Serialize};
+use solana_orderbook_monitor::{analytics::spread, BookSnapshot, OrderbookEvent, OrderbookEventType, OrderbookMonitor};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AlertError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Invalid alert config: {0}")]
+    Config(#[from] toml::de::Error),
+    #[error("Invalid rule {0}: {1}")]
+    InvalidRule(String, String),
+    #[error("Webhook failed: {0}")]
+    Webhook(#[from] reqwest::Error),
+}
+
+/// What a rule looks for
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Condition {
+    /// Fill prices moving more than `percent` within `window_secs`
+    PriceMove { percent: f64, window_secs: i64 },
+    /// Snapshot spread above `max_spread` price units or `max_bps` of the mid
+    Spread { max_spread: Option<i64>, max_bps: Option<f64> },
+    /// No events for `minutes`
+    Silence { minutes: i64 },
+    /// A single fill of at least `min_size`
+    LargeFill { min_size: u64 },
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    /// Market watched; all markets when absent
+    pub market: Option<String>,
+    #[serde(flatten)]
+    pub condition: Condition,
+}
+
+impl Rule {
+    fn watches(&self, market: &str) -> bool {
+        self.market.as_deref().is_none_or(|m| m == market)
+    }
+
+    fn validate(&self) -> Result<(), AlertError> {
+        let problem = match self.condition {
+            Condition::PriceMove { percent, window_secs } if percent <= 0.0 || window_secs <= 0 => {
+                "percent and window_secs must be positive"
+            }
+            Condition::Spread { max_spread: None, max_bps: None } => "needs max_spread or max_bps",
+            Condition::Silence { minutes } if minutes <= 0 => "minutes must be positive",
+            _ => return Ok(()),
+        };
+        Err(AlertError::InvalidRule(self.name.clone(), problem.to_string()))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SinkConfig {
+    Stdout,
+    /// Alerts appended to `path` as JSON Lines
+    File { path: PathBuf },
+    /// Alerts POSTed to `url` as JSON
+    Webhook { url: String },
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct AlertConfig {
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+    #[serde(default, rename = "sink")]
+    pub sinks: Vec<SinkConfig>,
+}
+
+impl AlertConfig {
+    pub fn load(path: &Path) -> Result<Self, AlertError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+}
+
+impl std::str::FromStr for AlertConfig {
+    type Err = AlertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let config: AlertConfig = toml::from_str(s)?;
+        for rule in &config.rules {
+            rule.validate()?;
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    pub rule: String,
+    pub market: Option<String>,
+    /// Unix time of the event, snapshot or clock tick that raised the alert
+    pub timestamp: i64,
+    pub message: String,
+}
+
+/// Where alerts go
+pub enum Sink {
+    Stdout,
+    File(File),
+    Webhook { url: String, client: reqwest::blocking::Client },
+}
+
+impl Sink {
+    pub fn open(config: &SinkConfig) -> Result<Self, AlertError> {
+        Ok(match config {
+            SinkConfig::Stdout => Sink::Stdout,
+            SinkConfig::File { path } => Sink::File(OpenOptions::new().create(true).append(true).open(path)?),
+            SinkConfig::Webhook { url } => Sink::Webhook { url: url.clone(), client: reqwest::blocking::Client::new() },
+        })
+    }
+
+    pub fn send(&mut self, alert: &Alert) -> Result<(), AlertError> {
+        match self {
+            Sink::Stdout => println!("[{}] {}", alert.rule, alert.message),
+            Sink::File(file) => {
+                serde_json::to_writer(&mut *file, alert).map_err(io::Error::from)?;
+                writeln!(file)?;
+            }
+            Sink::Webhook { url, client } => {
+                client.post(url.as_str()).json(alert).send()?.error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates the rules over the events and snapshots of one monitor
+pub struct AlertEngine {
+    rules: Vec<Rule>,
+    /// Recent fills per market as `(timestamp, price)`, kept for the longest
+    /// price-move window
+    fills: HashMap<String, VecDeque<(i64, u64)>>,
+    horizon: i64,
+    last_event: HashMap<String, i64>,
+    /// Time of the first tick, standing in for the last event until one is seen
+    started: Option<i64>,
+    /// `(rule, market)` pairs whose condition held when last checked; `""`
+    /// is the market of a silence rule over all markets before any event
+    firing: HashSet<(usize, String)>,
+    seen_events: SeenEvents,
+    seen_snapshots: usize,
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        let horizon = rules
+            .iter()
+            .filter_map(|rule| match rule.condition {
+                Condition::PriceMove { window_secs, .. } => Some(window_secs),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        AlertEngine {
+            rules,
+            fills: HashMap::new(),
+            horizon,
+            last_event: HashMap::new(),
+            started: None,
+            firing: HashSet::new(),
+            seen_events: SeenEvents::default(),
+            seen_snapshots: 0,
+        }
+    }
+
+    /// Record whether `(rule, market)` holds; true when it just started to
+    fn edge(&mut self, rule: usize, market: &str, holds: bool) -> bool {
+        let key = (rule, market.to_string());
+        if holds {
+            self.firing.insert(key)
+        } else {
+            self.firing.remove(&key);
+            false
+        }
+    }
+
+    pub fn on_event(&mut self, event: &OrderbookEvent) -> Vec<Alert> {
+        let market = event.market_name.as_str();
+        let last = self.last_event.entry(market.to_string()).or_insert(event.timestamp);
+        *last = (*last).max(event.timestamp);
+        for (i, rule) in self.rules.iter().enumerate() {
+            if matches!(rule.condition, Condition::Silence { .. }) && rule.watches(market) {
+                self.firing.remove(&(i, market.to_string()));
+                self.firing.remove(&(i, String::new()));
+            }
+        }
+        if event.event_type != OrderbookEventType::OrderFilled {
+            return Vec::new();
+        }
+
+        let fills = self.fills.entry(market.to_string()).or_default();
+        fills.push_back((event.timestamp, event.price));
+        while fills.front().is_some_and(|&(t, _)| t < event.timestamp - self.horizon) {
+            fills.pop_front();
+        }
+        let fills = fills.clone();
+
+        let mut alerts = Vec::new();
+        for i in 0..self.rules.len() {
+            if !self.rules[i].watches(market) {
+                continue;
+            }
+            let message = match self.rules[i].condition {
+                Condition::LargeFill { min_size } if event.size >= min_size => Some(format!(
+                    "{} fill of {} at {} on {}",
+                    if event.is_bid { "Bid" } else { "Ask" },
+                    event.size,
+                    event.price,
+                    market
+                )),
+                Condition::PriceMove { percent, window_secs } => {
+                    // Largest move from any fill in the window to this one
+                    let (moved, from) = fills
+                        .iter()
+                        .filter(|&&(t, _)| t >= event.timestamp - window_secs)
+                        .map(|&(_, p)| ((event.price as f64 - p as f64) / p as f64 * 100.0, p))
+                        .fold((0.0f64, event.price), |best, m| if m.0.abs() > best.0.abs() { m } else { best });
+                    self.edge(i, market, moved.abs() >= percent).then(|| {
+                        format!(
+                            "{} moved {:+.2}% from {} to {} within {}s",
+                            market, moved, from, event.price, window_secs
+                        )
+                    })
+                }
+                _ => None,
+            };
+            if let Some(message) = message {
+                alerts.push(self.alert(i, Some(market), event.timestamp, message));
+            }
+        }
+        alerts
+    }
+
+    pub fn on_snapshot(&mut self, snapshot: &BookSnapshot) -> Vec<Alert> {
+        let Some(spread) = spread(snapshot) else {
+            return Vec::new();
+        };
+        let market = snapshot.market_name.as_str();
+        let mut alerts = Vec::new();
+        for i in 0..self.rules.len() {
+            let Condition::Spread { max_spread, max_bps } = self.rules[i].condition else {
+                continue;
+            };
+            if !self.rules[i].watches(market) {
+                continue;
+            }
+            let wide = max_spread.is_some_and(|max| spread.absolute > i128::from(max)) || max_bps.is_some_and(|max| spread.bps > max);
+            if self.edge(i, market, wide) {
+                let message = format!(
+                    "{} spread widened to {} ({:.1} bps), bid {} ask {}",
+                    market, spread.absolute, spread.bps, spread.best_bid, spread.best_ask
+                );
+                alerts.push(self.alert(i, Some(market), snapshot.timestamp, message));
+            }
+        }
+        alerts
+    }
+
+    /// Check the silence rules at unix time `now`. A rule without `market`
+    /// checks each market seen so far, or all of them as one until an event
+    /// arrives.
+    pub fn tick(&mut self, now: i64) -> Vec<Alert> {
+        let started = *self.started.get_or_insert(now);
+        let mut alerts = Vec::new();
+        for i in 0..self.rules.len() {
+            let Condition::Silence { minutes } = self.rules[i].condition else {
+                continue;
+            };
+            let markets: Vec<Option<String>> = match &self.rules[i].market {
+                Some(market) => vec![Some(market.clone())],
+                None if self.last_event.is_empty() => vec![None],
+                None => self.last_event.keys().cloned().map(Some).collect(),
+            };
+            for market in markets {
+                let last = market.as_ref().and_then(|m| self.last_event.get(m)).copied();
+                let quiet = now - last.unwrap_or(started) >= minutes * 60;
+                if self.edge(i, market.as_deref().unwrap_or(""), quiet) {
+                    let message =
+                        format!("No events on {} for {} minutes", market.as_deref().unwrap_or("any market"), minutes);
+                    alerts.push(self.alert(i, market.as_deref(), now, message));
+                }
+            }
+        }
+        alerts
+    }
+
+    /// Evaluate what the monitor recorded since the last update. A recreated
+    /// monitor, or one holding fewer snapshots than already seen, is read
+    /// from the start; a recreated one also drops the fills and firing
+    /// conditions of the old account.
+    pub fn update(&mut self, monitor: &OrderbookMonitor) -> Vec<Alert> {
+        let (recreated, new_events) = self.seen_events.advance(monitor);
+        if recreated {
+            self.fills.clear();
+            self.firing.clear();
+        }
+        if recreated || monitor.snapshots.len() < self.seen_snapshots {
+            self.seen_snapshots = 0;
+        }
+        let mut alerts = Vec::new();
+        for event in new_events {
+            alerts.extend(self.on_event(event));
+        }
+        for snapshot in &monitor.snapshots[self.seen_snapshots..] {
+            alerts.extend(self.on_snapshot(snapshot));
+        }
+        self.seen_snapshots = monitor.snapshots.len();
+        alerts
+    }
+
+    fn alert(&self, rule: usize, market: Option<&str>, timestamp: i64, message: String) -> Alert {
+        Alert { rule: self.rules[rule].name.clone(), market: market.map(str::to_string), timestamp, message }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_orderbook_monitor::PriceLevel;
+
+    const CONFIG: &str = r#"
+        [[rule]]
+        name = "move"
+        kind = "price_move"
+        market = "SOL/USDC"
+        percent = 5.0
+        window_secs = 60
+
+        [[rule]]
+        name = "spread"
+        kind = "spread"
+        max_spread = 10
+
+        [[rule]]
+        name = "quiet"
+        kind = "silence"
+        minutes = 5
+
+        [[rule]]
+        name = "whale"
+        kind = "large_fill"
+        min_size = 100
+
+        [[sink]]
+        kind = "file"
+        path = "alerts.jsonl"
+    "#;
+
+    fn fill(timestamp: i64, market_name: &str, price: u64, size: u64) -> OrderbookEvent {
+        OrderbookEvent {
+            timestamp,
+            market_name: market_name.to_string(),
+            price,
+            size,
+            is_bid: true,
+            event_type: OrderbookEventType::OrderFilled,
+        }
+    }
+
+    fn snapshot(timestamp: i64, bid: u64, ask: u64) -> BookSnapshot {
+        BookSnapshot {
+            slot: 1,
+            timestamp,
+            market_name: "SOL/USDC".to_string(),
+            event_index: 0,
+            bids: vec![PriceLevel { price: bid, size: 1 }],
+            asks: vec![PriceLevel { price: ask, size: 1 }],
+        }
+    }
+
+    fn fired(alerts: Vec<Alert>) -> Vec<String> {
+        alerts.into_iter().map(|a| a.rule).collect()
+    }
+
+    #[test]
+    fn test_rules_over_synthetic_stream() {
+        let config: AlertConfig = CONFIG.parse().unwrap();
+        assert_eq!(config.sinks, vec![SinkConfig::File { path: "alerts.jsonl".into() }]);
+        let mut engine = AlertEngine::new(config.rules);
+
+        // A 6% move within the window alerts once, and again only after it cleared
+        assert!(engine.on_event(&fill(1_000, "SOL/USDC", 100, 1)).is_empty());
+        assert!(engine.on_event(&fill(1_030, "SOL/USDC", 103, 1)).is_empty());
+        let alerts = engine.on_event(&fill(1_050, "SOL/USDC", 106, 1));
+        assert_eq!(fired(alerts.clone()), vec!["move"]);
+        assert_eq!(alerts[0].message, "SOL/USDC moved +6.00% from 100 to 106 within 60s");
+        assert!(engine.on_event(&fill(1_055, "SOL/USDC", 107, 1)).is_empty());
+        // 100 has left the window and 103 -> 104 is below the threshold
+        assert!(engine.on_event(&fill(1_070, "SOL/USDC", 104, 1)).is_empty());
+        assert_eq!(fired(engine.on_event(&fill(1_080, "SOL/USDC", 98, 1))), vec!["move"]);
+        // The move rule is for SOL/USDC only; large fills are watched everywhere
+        assert_eq!(fired(engine.on_event(&fill(1_090, "BTC/USDC", 50_000, 250))), vec!["whale"]);
+        assert!(engine.on_event(&fill(1_095, "BTC/USDC", 40_000, 1)).is_empty());
+
+        assert!(engine.on_snapshot(&snapshot(1_100, 100, 105)).is_empty());
+        assert_eq!(fired(engine.on_snapshot(&snapshot(1_110, 100, 120))), vec!["spread"]);
+        assert!(engine.on_snapshot(&snapshot(1_120, 100, 121)).is_empty());
+        assert!(engine.on_snapshot(&snapshot(1_130, 100, 101)).is_empty());
+        assert_eq!(fired(engine.on_snapshot(&snapshot(1_140, 90, 101))), vec!["spread"]);
+    }
+
+    #[test]
+    fn test_silence_and_invalid_rules() {
+        let mut engine = AlertEngine::new(CONFIG.parse::<AlertConfig>().unwrap().rules);
+        assert!(engine.tick(1_000).is_empty());
+        let alerts = engine.tick(1_300);
+        assert_eq!(alerts[0].message, "No events on any market for 5 minutes");
+        assert!(engine.tick(1_400).is_empty());
+        // An event re-arms the rule
+        engine.on_event(&fill(1_500, "SOL/USDC", 100, 1));
+        assert!(engine.tick(1_700).is_empty());
+        assert_eq!(fired(engine.tick(1_800)), vec!["quiet"]);
+        // Without a market the rule watches each market on its own
+        engine.on_event(&fill(1_900, "BTC/USDC", 50_000, 1));
+        assert!(engine.tick(2_100).is_empty());
+        let alerts = engine.tick(2_200);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].market.as_deref(), Some("BTC/USDC"));
+        assert_eq!(alerts[0].message, "No events on BTC/USDC for 5 minutes");
+
+        let invalid = "[[rule]]\nname = \"bad\"\nkind = \"spread\"\n";
+        assert!(matches!(invalid.parse::<AlertConfig>(), Err(AlertError::InvalidRule(name, _)) if name == "bad"));
+        assert!(matches!("[[rule]]\nname = \"x\"\nkind = \"nope\"\n".parse::<AlertConfig>(), Err(AlertError::Config(_))));
+    }
+
+    fn monitor(events: Vec<OrderbookEvent>) -> OrderbookMonitor {
+        OrderbookMonitor {
+            initialized: true,
+            authority: Default::default(),
+            event_count: events.len() as u64,
+            events,
+            snapshots: vec![],
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn test_recreated_monitor_starts_over() {
+        let mut engine = AlertEngine::new(CONFIG.parse::<AlertConfig>().unwrap().rules);
+        let first = vec![fill(1_000, "SOL/USDC", 100, 1), fill(1_010, "SOL/USDC", 120, 1)];
+        assert_eq!(fired(engine.update(&monitor(first))), vec!["move"]);
+
+        // The old account's fills are no base for a move, and the move
+        // firing on it does not hold back the new account's
+        assert!(engine.update(&monitor(vec![fill(1_020, "SOL/USDC", 106, 1)])).is_empty());
+        let alerts = engine.update(&monitor(vec![fill(1_020, "SOL/USDC", 106, 1), fill(1_030, "SOL/USDC", 100, 1)]));
+        assert_eq!(fired(alerts), vec!["move"]);
+    }
+}
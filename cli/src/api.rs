@@ -0,0 +1,446 @@
+//! HTTP/JSON API over the indexer database, served by `orderbook-api`.
+//!
+//! Every route reads the local SQLite index only; no RPC node is involved.
+//! Market names go into paths percent-encoded, e.g. `/markets/SOL%2FUSDC/stats`.
+//! Events are paged in timestamp order: a full page carries a `next_cursor`
+//! to pass back as `cursor` for the next one. Errors are JSON objects with
+//! an `error` message. The OpenAPI document is served at `/openapi.json`.
+
+use crate::{
+    export::{candle_records, CandleRecord, EventFilter, EventKind, EventRecord, LevelRecord, Side, SnapshotRecord},
+    indexer::{EventCursor, IndexedEvent, IndexerError, MarketSummary, Store},
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use solana_orderbook_monitor::{
+    analytics::{event_type_counts, price_stats, realized_volatility, side_counts, vwap},
+    candles::{parse_interval, resample, Trade},
+    OrderbookEvent, OrderbookEventType,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1_000;
+/// Bars over which candle indicators are computed unless `period` is given
+pub const DEFAULT_PERIOD: usize = 14;
+
+#[derive(Error, Debug)]
+pub enum ApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error(transparent)]
+    Index(#[from] IndexerError),
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            ApiError::BadRequest(_) | ApiError::Index(IndexerError::InvalidCursor(_)) => StatusCode::BAD_REQUEST,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Index(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(ErrorBody { error: self.to_string() })).into_response()
+    }
+}
+
+/// Filters shared by the event routes; `from` is inclusive and `to` exclusive
+#[derive(Deserialize, IntoParams, Debug, Default)]
+#[into_params(parameter_in = Query)]
+pub struct EventsQuery {
+    /// Only events of this monitor account
+    pub monitor: Option<String>,
+    pub market: Option<String>,
+    /// Unix timestamp
+    pub from: Option<i64>,
+    /// Unix timestamp
+    pub to: Option<i64>,
+    /// Events per page, at most 1000
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Default)]
+#[into_params(parameter_in = Query)]
+pub struct RangeQuery {
+    /// Unix timestamp, inclusive
+    pub from: Option<i64>,
+    /// Unix timestamp, exclusive
+    pub to: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct CandlesQuery {
+    /// Bar length such as `30s`, `5m`, `4h` or `1d`
+    pub interval: String,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Bars over which indicators are computed, 14 by default
+    pub period: Option<usize>,
+    /// Whether intervals without fills get flat bars, true by default; refused
+    /// when the fills span more than `MAX_BARS` bars
+    pub fill_gaps: Option<bool>,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct ApiEvent {
+    pub monitor: String,
+    /// Position of the event in the monitor account
+    pub event_index: u64,
+    /// Slot at which the indexer first saw the event
+    pub slot: u64,
+    #[serde(flatten)]
+    pub event: EventRecord,
+}
+
+impl From<IndexedEvent> for ApiEvent {
+    fn from(event: IndexedEvent) -> Self {
+        ApiEvent {
+            monitor: event.monitor.to_string(),
+            event_index: event.event_index,
+            slot: event.slot,
+            event: event.event,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct EventsPage {
+    pub events: Vec<ApiEvent>,
+    /// Cursor of the next page; absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Statistics of one market's events in a time range
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct MarketStats {
+    pub market_name: String,
+    pub placed: u64,
+    pub filled: u64,
+    pub cancelled: u64,
+    pub bids: u64,
+    pub asks: u64,
+    /// Total filled size, saturating at `u64::MAX`
+    pub volume: u64,
+    pub fill_ratio: Option<f64>,
+    pub cancel_ratio: Option<f64>,
+    pub bid_ask_ratio: Option<f64>,
+    /// Prices of placed and filled orders
+    pub min_price: Option<u64>,
+    pub max_price: Option<u64>,
+    pub mean_price: Option<f64>,
+    pub vwap: Option<f64>,
+    pub realized_volatility: Option<f64>,
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Orderbook monitor API",
+        description = "Events, candles, statistics and snapshots from the local index"
+    ),
+    paths(markets, events, candles, stats, snapshot),
+    components(schemas(
+        ApiEvent,
+        CandleRecord,
+        ErrorBody,
+        EventKind,
+        EventRecord,
+        EventsPage,
+        LevelRecord,
+        MarketStats,
+        MarketSummary,
+        Side,
+        SnapshotRecord
+    ))
+)]
+pub struct ApiDoc;
+
+type Shared = Arc<Mutex<Store>>;
+
+/// Run `f` on the store off the async workers
+async fn query<T: Send + 'static>(
+    store: &Shared,
+    f: impl FnOnce(&Store) -> Result<T, ApiError> + Send + 'static,
+) -> Result<T, ApiError> {
+    let store = store.clone();
+    tokio::task::spawn_blocking(move || f(&store.lock().expect("store lock poisoned")))
+        .await
+        .expect("query task panicked")
+}
+
+fn market_events(store: &Store, market_name: &str, range: &RangeQuery) -> Result<Vec<OrderbookEvent>, ApiError> {
+    let filter = EventFilter { market: Some(market_name.to_string()), from: range.from, to: range.to };
+    let mut events = Vec::new();
+    store.for_each_event(None, &filter, |record| {
+        events.push(record.into());
+        Ok::<_, ApiError>(())
+    })?;
+    Ok(events)
+}
+
+/// Markets with indexed events
+#[utoipa::path(get, path = "/markets", responses((status = 200, body = [MarketSummary])))]
+async fn markets(State(store): State<Shared>) -> Result<Json<Vec<MarketSummary>>, ApiError> {
+    Ok(Json(query(&store, |store| Ok(store.markets()?)).await?))
+}
+
+/// Indexed events in timestamp order, one page at a time
+#[utoipa::path(
+    get,
+    path = "/events",
+    params(EventsQuery),
+    responses((status = 200, body = EventsPage), (status = 400, body = ErrorBody))
+)]
+async fn events(State(store): State<Shared>, Query(params): Query<EventsQuery>) -> Result<Json<EventsPage>, ApiError> {
+    let monitor = params
+        .monitor
+        .map(|m| m.parse::<Pubkey>().map_err(|_| ApiError::BadRequest(format!("Invalid monitor {}", m))))
+        .transpose()?;
+    let cursor = params.cursor.map(|c| c.parse::<EventCursor>()).transpose()?;
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(ApiError::BadRequest(format!("limit must be between 1 and {}", MAX_PAGE_SIZE)));
+    }
+    let filter = EventFilter { market: params.market, from: params.from, to: params.to };
+
+    let page = query(&store, move |store| {
+        Ok(store.events_page(monitor.as_ref(), &filter, cursor.as_ref(), limit)?)
+    })
+    .await?;
+    let next_cursor = page.last().filter(|_| page.len() == limit).map(|e| EventCursor::from(e).to_string());
+    Ok(Json(EventsPage { events: page.into_iter().map(ApiEvent::from).collect(), next_cursor }))
+}
+
+/// OHLCV bars of a market's fills with indicators
+#[utoipa::path(
+    get,
+    path = "/markets/{market}/candles",
+    params(("market" = String, Path, description = "Market name"), CandlesQuery),
+    responses((status = 200, body = [CandleRecord]), (status = 400, body = ErrorBody))
+)]
+async fn candles(
+    State(store): State<Shared>,
+    Path(market): Path<String>,
+    Query(params): Query<CandlesQuery>,
+) -> Result<Json<Vec<CandleRecord>>, ApiError> {
+    let interval = parse_interval(&params.interval)
+        .ok_or_else(|| ApiError::BadRequest(format!("Invalid interval {}", params.interval)))?;
+    let period = params.period.unwrap_or(DEFAULT_PERIOD);
+    let fill_gaps = params.fill_gaps.unwrap_or(true);
+    let range = RangeQuery { from: params.from, to: params.to };
+
+    let events = query(&store, move |store| market_events(store, &market, &range)).await?;
+    let fills = events.iter().filter(|e| e.event_type == OrderbookEventType::OrderFilled).map(Trade::from);
+    let bars = resample(fills, interval, fill_gaps).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    Ok(Json(candle_records(&bars, period)))
+}
+
+/// Statistics of a market's events
+#[utoipa::path(
+    get,
+    path = "/markets/{market}/stats",
+    params(("market" = String, Path, description = "Market name"), RangeQuery),
+    responses((status = 200, body = MarketStats), (status = 404, body = ErrorBody))
+)]
+async fn stats(
+    State(store): State<Shared>,
+    Path(market): Path<String>,
+    Query(range): Query<RangeQuery>,
+) -> Result<Json<MarketStats>, ApiError> {
+    let market_name = market.clone();
+    let events = query(&store, move |store| market_events(store, &market, &range)).await?;
+    if events.is_empty() {
+        return Err(ApiError::NotFound(format!("No events of {}", market_name)));
+    }
+    let (types, sides, prices) = (event_type_counts(&events), side_counts(&events), price_stats(&events));
+    Ok(Json(MarketStats {
+        market_name,
+        placed: types.placed,
+        filled: types.filled,
+        cancelled: types.cancelled,
+        bids: sides.bids,
+        asks: sides.asks,
+        volume: events
+            .iter()
+            .filter(|e| e.event_type == OrderbookEventType::OrderFilled)
+            .fold(0, |volume: u64, e| volume.saturating_add(e.size)),
+        fill_ratio: types.fill_ratio(),
+        cancel_ratio: types.cancel_ratio(),
+        bid_ask_ratio: sides.bid_ask_ratio(),
+        min_price: prices.map(|p| p.min),
+        max_price: prices.map(|p| p.max),
+        mean_price: prices.map(|p| p.mean),
+        vwap: vwap(&events),
+        realized_volatility: realized_volatility(&events),
+    }))
+}
+
+/// The latest book snapshot of a market
+#[utoipa::path(
+    get,
+    path = "/markets/{market}/snapshot",
+    params(("market" = String, Path, description = "Market name")),
+    responses((status = 200, body = SnapshotRecord), (status = 404, body = ErrorBody))
+)]
+async fn snapshot(State(store): State<Shared>, Path(market): Path<String>) -> Result<Json<SnapshotRecord>, ApiError> {
+    let market_name = market.clone();
+    query(&store, move |store| Ok(store.latest_snapshot(&market)?))
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("No snapshot of {}", market_name)))
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// All routes, reading from `store`
+pub fn router(store: Store) -> Router {
+    Router::new()
+        .route("/markets", get(markets))
+        .route("/events", get(events))
+        .route("/markets/:market/candles", get(candles))
+        .route("/markets/:market/stats", get(stats))
+        .route("/markets/:market/snapshot", get(snapshot))
+        .route("/openapi.json", get(openapi))
+        .with_state(Arc::new(Mutex::new(store)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use serde_json::Value;
+    use solana_orderbook_monitor::{BookSnapshot, OrderbookMonitor, PriceLevel};
+    use tower::ServiceExt;
+    use OrderbookEventType::*;
+
+    fn event(timestamp: i64, market_name: &str, price: u64, event_type: OrderbookEventType) -> OrderbookEvent {
+        OrderbookEvent { timestamp, market_name: market_name.to_string(), price, size: 2, is_bid: true, event_type }
+    }
+
+    fn app() -> Router {
+        let mut store = Store::open_in_memory().unwrap();
+        let monitor = OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: 4,
+            events: vec![
+                event(60, "SOL/USDC", 100, OrderPlaced),
+                event(61, "SOL/USDC", 100, OrderFilled),
+                event(125, "SOL/USDC", 110, OrderFilled),
+                event(130, "BTC/USDC", 50_000, OrderPlaced),
+            ],
+            snapshots: vec![BookSnapshot {
+                slot: 3,
+                timestamp: 130,
+                market_name: "SOL/USDC".to_string(),
+                event_index: 3,
+                bids: vec![PriceLevel { price: 99, size: 5 }],
+                asks: vec![PriceLevel { price: 111, size: 1 }],
+            }],
+            paused: false,
+        };
+        store.index(&Pubkey::new_unique(), 5, &monitor).unwrap();
+        router(store)
+    }
+
+    async fn get(app: &Router, uri: &str) -> (StatusCode, Value) {
+        let response = app.clone().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_routes() {
+        let app = app();
+        let (status, markets) = get(&app, "/markets").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(markets[1]["name"], "SOL/USDC");
+        assert_eq!(markets[1]["event_count"], 3);
+
+        let (_, page) = get(&app, "/events?limit=2").await;
+        assert_eq!(page["events"].as_array().unwrap().len(), 2);
+        assert_eq!(page["events"][1]["event_type"], "filled");
+        let cursor = page["next_cursor"].as_str().unwrap().to_string();
+        let (_, page) = get(&app, &format!("/events?limit=2&cursor={}", cursor)).await;
+        let timestamps: Vec<&Value> = page["events"].as_array().unwrap().iter().map(|e| &e["timestamp"]).collect();
+        assert_eq!(timestamps, vec![125, 130]);
+        let (_, page) = get(&app, &format!("/events?limit=2&cursor={}", page["next_cursor"].as_str().unwrap())).await;
+        assert!(page["events"].as_array().unwrap().is_empty());
+        assert!(page["next_cursor"].is_null());
+        let (_, page) = get(&app, "/events?market=BTC%2FUSDC").await;
+        assert_eq!(page["events"][0]["price"], 50_000);
+
+        let (_, candles) = get(&app, "/markets/SOL%2FUSDC/candles?interval=1m&period=2").await;
+        let closes: Vec<&Value> = candles.as_array().unwrap().iter().map(|c| &c["close"]).collect();
+        assert_eq!(closes, vec![100, 110]);
+        assert_eq!(candles[1]["sma"], 105.0);
+
+        let (_, stats) = get(&app, "/markets/SOL%2FUSDC/stats?from=61").await;
+        assert_eq!(stats["filled"], 2);
+        assert_eq!(stats["volume"], 4);
+        assert_eq!(stats["vwap"], 105.0);
+
+        let (_, snapshot) = get(&app, "/markets/SOL%2FUSDC/snapshot").await;
+        assert_eq!(snapshot["asks"][0]["price"], 111);
+
+        let (_, doc) = get(&app, "/openapi.json").await;
+        assert!(doc["paths"]["/markets/{market}/candles"]["get"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let app = app();
+        for (uri, expected) in [
+            ("/events?cursor=bogus", StatusCode::BAD_REQUEST),
+            ("/events?limit=0", StatusCode::BAD_REQUEST),
+            ("/events?monitor=bogus", StatusCode::BAD_REQUEST),
+            ("/markets/SOL%2FUSDC/candles?interval=5w", StatusCode::BAD_REQUEST),
+            ("/markets/ETH%2FUSDC/stats", StatusCode::NOT_FOUND),
+            ("/markets/BTC%2FUSDC/snapshot", StatusCode::NOT_FOUND),
+        ] {
+            let (status, body) = get(&app, uri).await;
+            assert_eq!(status, expected, "{}", uri);
+            assert!(body["error"].is_string(), "{}", uri);
+        }
+
+        // Filling every second between fills a year apart is refused
+        let mut store = Store::open_in_memory().unwrap();
+        let monitor = OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: 2,
+            events: vec![event(0, "SOL/USDC", 100, OrderFilled), event(31_536_000, "SOL/USDC", 110, OrderFilled)],
+            snapshots: vec![],
+            paused: false,
+        };
+        store.index(&Pubkey::new_unique(), 5, &monitor).unwrap();
+        let app = router(store);
+        let (status, body) = get(&app, "/markets/SOL%2FUSDC/candles?interval=1s").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].as_str().unwrap().contains("longer interval"));
+        let (status, candles) = get(&app, "/markets/SOL%2FUSDC/candles?interval=1s&fill_gaps=false").await;
+        assert_eq!((status, candles.as_array().unwrap().len()), (StatusCode::OK, 2));
+    }
+}
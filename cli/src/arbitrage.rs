@@ -0,0 +1,299 @@
+//! Logging of arbitrage gaps between a monitor's markets, run by
+//! `orderbook-arbitrage`.
+//!
+//! Markets are described in TOML, since recorded prices and sizes are in
+//! each venue's native units:
+//!
+//! ```toml
+//! min_gap_bps = 15.0
+//! max_age_secs = 30
+//!
+//! [[market]]
+//! name = "SOL/USDC"
+//! venue = "openbook-v2"
+//! base = "SOL"
+//! quote = "USDC"
+//! price_scale = 0.001
+//! size_scale = 0.001
+//!
+//! [[market]]
+//! name = "SOL-USDC"
+//! venue = "phoenix"
+//! base = "SOL"
+//! quote = "USDC"
+//! price_scale = 0.001
+//! size_scale = 0.001
+//! ```
+//!
+//! A gap is logged when it opens and not again until it has closed.
+
+use serde::{Deserialize, Serialize};
+use solana_orderbook_monitor::arbitrage::{
+    detect, ArbitrageConfig, CrossVenueGap, MarketSpec, Opportunities, TriangularGap,
+};
+use solana_orderbook_monitor::OrderbookMonitor;
+use std::{collections::HashSet, io, path::Path};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ArbitrageError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Invalid arbitrage config: {0}")]
+    Config(#[from] toml::de::Error),
+    #[error("Invalid market {0}: {1}")]
+    InvalidMarket(String, String),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct MarketConfig {
+    /// Market name as recorded on the monitor
+    pub name: String,
+    pub venue: String,
+    pub base: String,
+    pub quote: String,
+    /// Quote units per base unit of one recorded price unit
+    pub price_scale: f64,
+    /// Base units of one recorded size unit
+    pub size_scale: f64,
+}
+
+impl From<&MarketConfig> for MarketSpec {
+    fn from(market: &MarketConfig) -> Self {
+        MarketSpec {
+            market_name: market.name.clone(),
+            venue: market.venue.clone(),
+            base: market.base.clone(),
+            quote: market.quote.clone(),
+            price_scale: market.price_scale,
+            size_scale: market.size_scale,
+        }
+    }
+}
+
+fn default_min_gap_bps() -> f64 {
+    ArbitrageConfig::default().min_gap_bps
+}
+
+fn default_max_age_secs() -> i64 {
+    ArbitrageConfig::default().max_age_secs
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ArbitrageFile {
+    #[serde(default = "default_min_gap_bps")]
+    pub min_gap_bps: f64,
+    #[serde(default = "default_max_age_secs")]
+    pub max_age_secs: i64,
+    #[serde(default, rename = "market")]
+    pub markets: Vec<MarketConfig>,
+}
+
+impl ArbitrageFile {
+    pub fn load(path: &Path) -> Result<Self, ArbitrageError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    pub fn config(&self) -> ArbitrageConfig {
+        ArbitrageConfig { min_gap_bps: self.min_gap_bps, max_age_secs: self.max_age_secs }
+    }
+
+    pub fn specs(&self) -> Vec<MarketSpec> {
+        self.markets.iter().map(MarketSpec::from).collect()
+    }
+}
+
+impl std::str::FromStr for ArbitrageFile {
+    type Err = ArbitrageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file: ArbitrageFile = toml::from_str(s)?;
+        let mut names = HashSet::new();
+        for market in &file.markets {
+            let problem = if !names.insert(market.name.as_str()) {
+                "is listed twice"
+            } else if market.base == market.quote {
+                "base and quote must differ"
+            } else if !(market.price_scale > 0.0 && market.size_scale > 0.0) {
+                "price_scale and size_scale must be positive"
+            } else {
+                continue;
+            };
+            return Err(ArbitrageError::InvalidMarket(market.name.clone(), problem.to_string()));
+        }
+        Ok(file)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LegRecord {
+    pub market_name: String,
+    /// `buy` takes the best ask, `sell` the best bid
+    pub action: String,
+    pub price: f64,
+}
+
+/// One logged line
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OpportunityRecord {
+    CrossVenue {
+        timestamp: i64,
+        base: String,
+        quote: String,
+        buy_market: String,
+        sell_market: String,
+        buy_price: f64,
+        sell_price: f64,
+        gap_bps: f64,
+        /// Base units
+        size: f64,
+        /// Quote units
+        profit: f64,
+    },
+    Triangular {
+        timestamp: i64,
+        assets: Vec<String>,
+        legs: Vec<LegRecord>,
+        gap_bps: f64,
+        /// Units of the first asset
+        size: f64,
+        profit: f64,
+    },
+}
+
+impl From<&CrossVenueGap> for OpportunityRecord {
+    fn from(gap: &CrossVenueGap) -> Self {
+        OpportunityRecord::CrossVenue {
+            timestamp: gap.timestamp,
+            base: gap.base.clone(),
+            quote: gap.quote.clone(),
+            buy_market: gap.buy_market.clone(),
+            sell_market: gap.sell_market.clone(),
+            buy_price: gap.buy_price,
+            sell_price: gap.sell_price,
+            gap_bps: gap.gap_bps,
+            size: gap.size,
+            profit: gap.profit,
+        }
+    }
+}
+
+impl From<&TriangularGap> for OpportunityRecord {
+    fn from(gap: &TriangularGap) -> Self {
+        OpportunityRecord::Triangular {
+            timestamp: gap.timestamp,
+            assets: gap.assets.to_vec(),
+            legs: gap
+                .legs
+                .iter()
+                .map(|leg| LegRecord {
+                    market_name: leg.market_name.clone(),
+                    action: if leg.buy { "buy" } else { "sell" }.to_string(),
+                    price: leg.price,
+                })
+                .collect(),
+            gap_bps: gap.gap_bps,
+            size: gap.size,
+            profit: gap.profit,
+        }
+    }
+}
+
+/// Finds the gaps of each read of a monitor and keeps those already logged
+pub struct ArbitrageWatcher {
+    specs: Vec<MarketSpec>,
+    config: ArbitrageConfig,
+    /// Markets and directions of the gaps open at the last update
+    open: HashSet<String>,
+}
+
+impl ArbitrageWatcher {
+    pub fn new(file: &ArbitrageFile) -> Self {
+        ArbitrageWatcher { specs: file.specs(), config: file.config(), open: HashSet::new() }
+    }
+
+    /// Gaps that opened since the last update
+    pub fn update(&mut self, monitor: &OrderbookMonitor) -> Vec<OpportunityRecord> {
+        let Opportunities { cross_venue, triangular } = detect(monitor, &self.specs, &self.config);
+        let mut found: Vec<(String, OpportunityRecord)> = Vec::new();
+        for gap in &cross_venue {
+            found.push((format!("{}>{}", gap.buy_market, gap.sell_market), gap.into()));
+        }
+        for gap in &triangular {
+            let legs: Vec<String> =
+                gap.legs.iter().map(|l| format!("{}:{}", if l.buy { "buy" } else { "sell" }, l.market_name)).collect();
+            found.push((legs.join(">"), gap.into()));
+        }
+        let previous = std::mem::take(&mut self.open);
+        self.open = found.iter().map(|(key, _)| key.clone()).collect();
+        found.into_iter().filter(|(key, _)| !previous.contains(key)).map(|(_, record)| record).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_orderbook_monitor::{BookSnapshot, PriceLevel};
+
+    const CONFIG: &str = r#"
+        min_gap_bps = 20.0
+
+        [[market]]
+        name = "SOL/USDC"
+        venue = "openbook-v2"
+        base = "SOL"
+        quote = "USDC"
+        price_scale = 0.01
+        size_scale = 1.0
+
+        [[market]]
+        name = "SOL-USDC"
+        venue = "phoenix"
+        base = "SOL"
+        quote = "USDC"
+        price_scale = 0.01
+        size_scale = 1.0
+    "#;
+
+    fn snapshot(market_name: &str, bid: u64, ask: u64) -> BookSnapshot {
+        BookSnapshot {
+            slot: 0,
+            timestamp: 1_000,
+            market_name: market_name.to_string(),
+            event_index: 0,
+            bids: vec![PriceLevel { price: bid, size: 5 }],
+            asks: vec![PriceLevel { price: ask, size: 5 }],
+        }
+    }
+
+    #[test]
+    fn test_watcher_logs_opened_gaps() {
+        let file: ArbitrageFile = CONFIG.parse().unwrap();
+        assert_eq!((file.config().max_age_secs, file.specs().len()), (60, 2));
+        let mut watcher = ArbitrageWatcher::new(&file);
+        let mut monitor = OrderbookMonitor {
+            initialized: true,
+            authority: Default::default(),
+            event_count: 0,
+            events: Vec::new(),
+            snapshots: vec![snapshot("SOL/USDC", 9_990, 10_000), snapshot("SOL-USDC", 10_050, 10_060)],
+            paused: false,
+        };
+        let opened = watcher.update(&monitor);
+        assert_eq!(opened.len(), 1);
+        let json = serde_json::to_value(&opened[0]).unwrap();
+        assert_eq!((json["kind"].as_str(), json["buy_market"].as_str()), (Some("cross_venue"), Some("SOL/USDC")));
+        // Still open: not logged again until it closes
+        assert!(watcher.update(&monitor).is_empty());
+        monitor.snapshots.push(snapshot("SOL-USDC", 10_000, 10_010));
+        assert!(watcher.update(&monitor).is_empty());
+        monitor.snapshots.push(snapshot("SOL-USDC", 10_050, 10_060));
+        assert_eq!(watcher.update(&monitor).len(), 1);
+
+        let duplicate = CONFIG.split("[[market]]").nth(1).unwrap();
+        let twice = format!("{}[[market]]{}", CONFIG, duplicate);
+        let error = twice.parse::<ArbitrageFile>().unwrap_err();
+        assert!(matches!(error, ArbitrageError::InvalidMarket(name, _) if name == "SOL/USDC"));
+    }
+}
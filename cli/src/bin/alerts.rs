@@ -0,0 +1,78 @@
+use clap::Parser;
+use orderbook_monitor_cli::{
+    alerts::{AlertConfig, AlertEngine, Sink},
+    config::ConfigArgs,
+    follow::{follow, unix_now, Mode},
+};
+use solana_orderbook_monitor::OrderbookMonitor;
+use solana_sdk::pubkey::Pubkey;
+use std::{error::Error, path::PathBuf, sync::mpsc::RecvTimeoutError, time::Duration};
+
+/// Raise alerts on the events recorded on a monitor account
+#[derive(Parser, Debug)]
+#[command(name = "orderbook-alerts")]
+struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+    /// Monitor account to watch
+    #[arg(long)]
+    monitor: Pubkey,
+    /// TOML file of rules and sinks
+    #[arg(long)]
+    rules: PathBuf,
+    /// Seconds between polls of the monitor account
+    #[arg(long, default_value_t = 5)]
+    interval: u64,
+    /// Subscribe to account changes over the RPC websocket instead of polling
+    #[arg(long)]
+    subscribe: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let settings = args.config.resolve()?;
+    let config = AlertConfig::load(&args.rules)?;
+    let mut sinks = config.sinks.iter().map(Sink::open).collect::<Result<Vec<_>, _>>()?;
+    if sinks.is_empty() {
+        sinks.push(Sink::Stdout);
+    }
+
+    let monitor = args.monitor;
+    let mode = if args.subscribe { Mode::Subscribe } else { Mode::Poll(Duration::from_secs(args.interval)) };
+    let updates = follow(settings.url, settings.commitment, monitor, mode);
+    let mut engine = AlertEngine::new(config.rules);
+    // What the account already holds only seeds the rules
+    let mut warm = false;
+    loop {
+        let alerts = match updates.recv_timeout(Duration::from_secs(1)) {
+            Ok(Ok((_, data))) => match OrderbookMonitor::unpack(&data) {
+                Ok(state) => {
+                    let mut alerts = engine.update(&state);
+                    alerts.extend(engine.tick(unix_now()));
+                    if !warm {
+                        warm = true;
+                        continue;
+                    }
+                    alerts
+                }
+                Err(_) => {
+                    eprintln!("Account {} does not hold a monitor", monitor);
+                    continue;
+                }
+            },
+            Ok(Err(e)) => {
+                eprintln!("Read of {} failed: {}", monitor, e);
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => engine.tick(unix_now()),
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        for alert in &alerts {
+            for sink in &mut sinks {
+                if let Err(e) = sink.send(alert) {
+                    eprintln!("Alert {} not delivered: {}", alert.rule, e);
+                }
+            }
+        }
+    }
+}
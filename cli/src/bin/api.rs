@@ -0,0 +1,29 @@
+use clap::Parser;
+use orderbook_monitor_cli::{api::router, indexer::Store};
+use std::{error::Error, net::SocketAddr, path::PathBuf};
+
+/// Serve the events of an `orderbook-indexer` database over HTTP as JSON
+#[derive(Parser, Debug)]
+#[command(name = "orderbook-api")]
+struct Args {
+    /// SQLite database written by orderbook-indexer
+    #[arg(long, default_value = "orderbook-index.sqlite")]
+    db: PathBuf,
+    /// Address to serve on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    if !args.db.exists() {
+        return Err(format!("No index at {}; run orderbook-indexer first", args.db.display()).into());
+    }
+    let store = Store::open(&args.db)?;
+
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    println!("Serving http://{} (OpenAPI document at /openapi.json)", listener.local_addr()?);
+    axum::serve(listener, router(store)).await?;
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use clap::Parser;
+use orderbook_monitor_cli::{
+    arbitrage::{ArbitrageFile, ArbitrageWatcher},
+    config::ConfigArgs,
+    follow::{follow, Mode},
+};
+use solana_orderbook_monitor::OrderbookMonitor;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    error::Error,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+/// Log arbitrage gaps between the markets recorded on a monitor account
+#[derive(Parser, Debug)]
+#[command(name = "orderbook-arbitrage")]
+struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+    /// Monitor account to watch
+    #[arg(long)]
+    monitor: Pubkey,
+    /// TOML file describing the markets and thresholds
+    #[arg(long)]
+    markets: PathBuf,
+    /// File the opportunities are appended to as JSON Lines, standard output by default
+    #[arg(long)]
+    log: Option<PathBuf>,
+    /// Seconds between polls of the monitor account
+    #[arg(long, default_value_t = 5)]
+    interval: u64,
+    /// Subscribe to account changes over the RPC websocket instead of polling
+    #[arg(long)]
+    subscribe: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let settings = args.config.resolve()?;
+    let file = ArbitrageFile::load(&args.markets)?;
+    let mut out: Box<dyn Write> = match &args.log {
+        Some(path) => Box::new(OpenOptions::new().create(true).append(true).open(path)?),
+        None => Box::new(io::stdout()),
+    };
+
+    let monitor = args.monitor;
+    let mode = if args.subscribe { Mode::Subscribe } else { Mode::Poll(Duration::from_secs(args.interval)) };
+    let mut watcher = ArbitrageWatcher::new(&file);
+    for update in follow(settings.url, settings.commitment, monitor, mode) {
+        let data = match update {
+            Ok((_, data)) => data,
+            Err(e) => {
+                eprintln!("Read of {} failed: {}", monitor, e);
+                continue;
+            }
+        };
+        let Ok(state) = OrderbookMonitor::unpack(&data) else {
+            eprintln!("Account {} does not hold a monitor", monitor);
+            continue;
+        };
+        for opportunity in watcher.update(&state) {
+            serde_json::to_writer(&mut out, &opportunity)?;
+            writeln!(out)?;
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
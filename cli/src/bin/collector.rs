@@ -0,0 +1,108 @@
+use clap::{Parser, ValueEnum};
+use orderbook_monitor_cli::{
+    chain::{Chain, RpcChain},
+    collector::Collector,
+    follow::unix_now,
+    surveillance::{OwnedFillRecord, TraderEventRecord},
+};
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_orderbook_monitor::adapter::{MarketAdapter, OpenBookV2Adapter, SerumAdapter};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::{
+    error::Error,
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum VenueArg {
+    Serum,
+    OpenbookV2,
+}
+
+/// Collect a market's order lifecycles and owned fills as JSON Lines for the
+/// surveillance commands
+#[derive(Parser, Debug)]
+#[command(name = "orderbook-collector")]
+struct Args {
+    /// DEX market account to watch
+    #[arg(long)]
+    market: Pubkey,
+    /// Market name written with every event. Required for Serum; OpenBook v2
+    /// markets default to the name stored on chain.
+    #[arg(long)]
+    market_name: Option<String>,
+    #[arg(long, value_enum, default_value = "serum")]
+    venue: VenueArg,
+    #[arg(long, default_value = "https://api.devnet.solana.com")]
+    url: String,
+    /// File the trader events are appended to
+    #[arg(long, default_value = "orders.jsonl")]
+    orders: PathBuf,
+    /// File the fills naming both owners are appended to
+    #[arg(long, default_value = "fills.jsonl")]
+    fills: PathBuf,
+    /// Seconds between polls of the market
+    #[arg(long, default_value_t = 2)]
+    interval: u64,
+}
+
+fn append(path: &PathBuf) -> Result<BufWriter<File>, std::io::Error> {
+    Ok(BufWriter::new(OpenOptions::new().create(true).append(true).open(path)?))
+}
+
+fn write_lines<T: Serialize>(out: &mut BufWriter<File>, records: impl IntoIterator<Item = T>) -> Result<(), Box<dyn Error>> {
+    for record in records {
+        writeln!(out, "{}", serde_json::to_string(&record)?)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let client = RpcClient::new_with_commitment(args.url.clone(), CommitmentConfig::confirmed());
+    let mut chain = RpcChain::new(client);
+
+    let market_data = chain.account_data(&args.market).await?;
+    let adapter: Box<dyn MarketAdapter> = match (args.venue, &args.market_name) {
+        (VenueArg::Serum, Some(name)) => Box::new(SerumAdapter::new(args.market, &market_data, name)?),
+        (VenueArg::Serum, None) => return Err("--market-name is required for Serum markets".into()),
+        (VenueArg::OpenbookV2, name) => {
+            let adapter = OpenBookV2Adapter::new(args.market, &market_data)?;
+            Box::new(match name {
+                Some(name) => adapter.with_name(name),
+                None => adapter,
+            })
+        }
+    };
+    println!(
+        "Collecting {} ({}) into {} and {}",
+        adapter.metadata().name,
+        adapter.metadata().venue,
+        args.orders.display(),
+        args.fills.display()
+    );
+
+    let mut orders = append(&args.orders)?;
+    let mut fills = append(&args.fills)?;
+    let mut collector = Collector::new(adapter)?;
+    loop {
+        match collector.poll_once(&mut chain, unix_now()).await {
+            Ok(collected) => {
+                if collected.missed > 0 {
+                    eprintln!("Missed {} events consumed before they were read", collected.missed);
+                }
+                write_lines(&mut orders, collected.events.iter().map(TraderEventRecord::from))?;
+                write_lines(&mut fills, collected.fills.iter().map(OwnedFillRecord::from))?;
+            }
+            Err(e) => eprintln!("Poll failed: {}", e),
+        }
+        tokio::time::sleep(Duration::from_secs(args.interval)).await;
+    }
+}
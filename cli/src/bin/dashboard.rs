@@ -0,0 +1,65 @@
+use clap::Parser;
+use orderbook_monitor_cli::{
+    config::ConfigArgs,
+    dashboard::Dashboard,
+    follow::{follow, unix_now, Mode},
+};
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use solana_orderbook_monitor::OrderbookMonitor;
+use solana_sdk::pubkey::Pubkey;
+use std::{error::Error, time::Duration};
+
+/// Live view of the events recorded on a monitor account
+#[derive(Parser, Debug)]
+#[command(name = "orderbook-dashboard")]
+struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+    /// Monitor account to watch
+    #[arg(long)]
+    monitor: Pubkey,
+    /// Seconds between polls of the monitor account
+    #[arg(long, default_value_t = 2)]
+    interval: u64,
+    /// Subscribe to account changes over the RPC websocket instead of polling
+    #[arg(long)]
+    subscribe: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let settings = args.config.resolve()?;
+
+    let monitor = args.monitor;
+    let mode = if args.subscribe { Mode::Subscribe } else { Mode::Poll(Duration::from_secs(args.interval)) };
+    let updates = follow(settings.url, settings.commitment, monitor, mode);
+
+    let mut dashboard = Dashboard::new(args.monitor);
+    let mut terminal = ratatui::try_init()?;
+    let result = (|| -> Result<(), Box<dyn Error>> {
+        loop {
+            for update in updates.try_iter() {
+                match update.and_then(|(slot, data)| {
+                    OrderbookMonitor::unpack(&data)
+                        .map(|state| (slot, state))
+                        .map_err(|_| format!("Account {} does not hold a monitor", monitor))
+                }) {
+                    Ok((slot, state)) => {
+                        dashboard.update(slot, &state);
+                    }
+                    Err(e) => dashboard.status = Some(e),
+                }
+            }
+            terminal.draw(|frame| dashboard.render(frame, unix_now()))?;
+            if event::poll(Duration::from_millis(200))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !dashboard.handle_key(key.code) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    })();
+    ratatui::try_restore()?;
+    result
+}
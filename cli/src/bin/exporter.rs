@@ -0,0 +1,50 @@
+use clap::Parser;
+use orderbook_monitor_cli::{
+    chain::RpcChain,
+    config::ConfigArgs,
+    follow::unix_now,
+    metrics::{router, Exporter, Metrics},
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{error::Error, net::SocketAddr, time::Duration};
+
+/// Serve Prometheus metrics of monitor accounts
+#[derive(Parser, Debug)]
+#[command(name = "orderbook-exporter")]
+struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+    /// Monitor account to export; repeat for several
+    #[arg(long = "monitor", required = true)]
+    monitors: Vec<Pubkey>,
+    /// Address serving `/metrics`
+    #[arg(long, default_value = "127.0.0.1:9464")]
+    listen: SocketAddr,
+    /// Seconds between polls of the monitor accounts
+    #[arg(long, default_value_t = 15)]
+    interval: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let settings = args.config.resolve()?;
+
+    let metrics = Metrics::new();
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    println!("Serving metrics on http://{}/metrics", listener.local_addr()?);
+    let app = router(metrics.clone());
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let mut chain = RpcChain::new(RpcClient::new_with_commitment(settings.url, settings.commitment));
+    let mut exporter = Exporter::new(metrics, args.monitors);
+    while !server.is_finished() {
+        for (monitor, e) in exporter.poll_once(&mut chain, unix_now()).await {
+            eprintln!("Poll of {} failed: {}", monitor, e);
+        }
+        tokio::time::sleep(Duration::from_secs(args.interval)).await;
+    }
+    server.await??;
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use clap::Parser;
+use orderbook_monitor_cli::{
+    config::ConfigArgs,
+    feed::{router, Feed},
+    follow::{follow, Mode},
+};
+use solana_orderbook_monitor::OrderbookMonitor;
+use solana_sdk::pubkey::Pubkey;
+use std::{error::Error, net::SocketAddr, sync::Arc, time::Duration};
+
+/// Push the events recorded on a monitor account to WebSocket subscribers
+#[derive(Parser, Debug)]
+#[command(name = "orderbook-feed")]
+struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+    /// Monitor account to relay
+    #[arg(long)]
+    monitor: Pubkey,
+    /// Address serving `/ws`
+    #[arg(long, default_value = "127.0.0.1:8765")]
+    listen: SocketAddr,
+    /// Seconds between polls of the monitor account
+    #[arg(long, default_value_t = 1)]
+    interval: u64,
+    /// Subscribe to account changes over the RPC websocket instead of polling
+    #[arg(long)]
+    subscribe: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let settings = args.config.resolve()?;
+
+    let monitor = args.monitor;
+    let mode = if args.subscribe { Mode::Subscribe } else { Mode::Poll(Duration::from_secs(args.interval)) };
+    let updates = follow(settings.url, settings.commitment, monitor, mode);
+    let feed = Arc::new(Feed::new());
+    let source = feed.clone();
+    tokio::task::spawn_blocking(move || {
+        for update in updates {
+            match update {
+                Ok((slot, data)) => match OrderbookMonitor::unpack(&data) {
+                    Ok(state) => {
+                        source.publish(slot, &state);
+                    }
+                    Err(_) => eprintln!("Account {} does not hold a monitor", monitor),
+                },
+                Err(e) => eprintln!("Read of {} failed: {}", monitor, e),
+            }
+        }
+    });
+
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    println!("Serving events on ws://{}/ws", listener.local_addr()?);
+    axum::serve(listener, router(feed)).await?;
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use clap::{Parser, ValueEnum};
+use orderbook_monitor_cli::{
+    chain::{Chain, RpcChain},
+    feeder::{Feeder, DEFAULT_BATCH_SIZE},
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_orderbook_monitor::{
+    adapter::{MarketAdapter, OpenBookV2Adapter, SerumAdapter},
+    validate_market_name,
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Signer},
+};
+use std::{error::Error, path::PathBuf, time::Duration};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum VenueArg {
+    Serum,
+    OpenbookV2,
+}
+
+/// Long-running feeder that records a market's event queue on a monitor account
+#[derive(Parser, Debug)]
+#[command(name = "orderbook-feeder")]
+struct Args {
+    /// Orderbook monitor program id
+    #[arg(long)]
+    program_id: Pubkey,
+    /// Monitor account to record into
+    #[arg(long)]
+    monitor: Pubkey,
+    /// DEX market account to watch
+    #[arg(long)]
+    market: Pubkey,
+    /// Market name recorded with every event. Required for Serum; OpenBook v2
+    /// markets default to the name stored on chain.
+    #[arg(long)]
+    market_name: Option<String>,
+    #[arg(long, value_enum, default_value = "serum")]
+    venue: VenueArg,
+    #[arg(long, default_value = "https://api.devnet.solana.com")]
+    url: String,
+    /// Keypair paying for transactions
+    #[arg(long)]
+    keypair: PathBuf,
+    /// Keypair of the monitor authority, defaults to the payer
+    #[arg(long)]
+    authority: Option<PathBuf>,
+    /// File storing the last recorded sequence number
+    #[arg(long, default_value = "feeder-checkpoint.json")]
+    checkpoint: PathBuf,
+    /// Seconds between polls of the event queue
+    #[arg(long, default_value_t = 2)]
+    interval: u64,
+    #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+    batch_size: usize,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let payer = read_keypair_file(&args.keypair)?;
+    let authority = read_keypair_file(args.authority.as_ref().unwrap_or(&args.keypair))?;
+
+    let client = RpcClient::new_with_commitment(args.url.clone(), CommitmentConfig::confirmed());
+    let mut chain = RpcChain::new(client);
+
+    let market_data = chain.account_data(&args.market).await?;
+    let adapter: Box<dyn MarketAdapter> = match (args.venue, &args.market_name) {
+        (VenueArg::Serum, Some(name)) => Box::new(SerumAdapter::new(args.market, &market_data, name)?),
+        (VenueArg::Serum, None) => return Err("--market-name is required for Serum markets".into()),
+        (VenueArg::OpenbookV2, name) => {
+            let adapter = OpenBookV2Adapter::new(args.market, &market_data)?;
+            Box::new(match name {
+                Some(name) => adapter.with_name(name),
+                None => adapter,
+            })
+        }
+    };
+    validate_market_name(&adapter.metadata().name)
+        .map_err(|e| format!("Cannot record market name {:?}: {}", adapter.metadata().name, e))?;
+
+    println!(
+        "Feeding {} ({}) into monitor {} as {}",
+        adapter.metadata().name,
+        adapter.metadata().venue,
+        args.monitor,
+        authority.pubkey()
+    );
+
+    let mut feeder = Feeder::new(
+        adapter,
+        args.program_id,
+        args.monitor,
+        payer,
+        authority,
+        args.checkpoint,
+    )?
+    .with_batch_size(args.batch_size);
+
+    if let Some(next) = feeder.checkpoint().next_seq {
+        println!("Resuming from sequence number {}", next);
+    }
+
+    loop {
+        match feeder.poll_once(&mut chain).await {
+            Ok(outcome) => {
+                for rejected in &outcome.rejected {
+                    eprintln!("Skipped event {}: {}", rejected.seq_num, rejected.error);
+                }
+                if outcome.missed > 0 {
+                    eprintln!("Missed {} events consumed before they were read", outcome.missed);
+                }
+                if outcome.recorded > 0 {
+                    println!("Recorded {} events", outcome.recorded);
+                }
+            }
+            Err(e) => eprintln!("Poll failed: {}", e),
+        }
+        tokio::time::sleep(Duration::from_secs(args.interval)).await;
+    }
+}
@@ -0,0 +1,61 @@
+use clap::Parser;
+use orderbook_monitor_cli::{
+    chain::RpcChain,
+    config::ConfigArgs,
+    indexer::{Indexer, Store},
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{error::Error, path::PathBuf, time::Duration};
+
+/// Copy the events of monitor accounts into a local SQLite database
+#[derive(Parser, Debug)]
+#[command(name = "orderbook-indexer")]
+struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+    /// Monitor account to index; repeat for several
+    #[arg(long = "monitor", required = true)]
+    monitors: Vec<Pubkey>,
+    /// SQLite database, created if missing
+    #[arg(long, default_value = "orderbook-index.sqlite")]
+    db: PathBuf,
+    /// Seconds between polls of the monitor accounts
+    #[arg(long, default_value_t = 2)]
+    interval: u64,
+    /// Index once and exit instead of polling
+    #[arg(long)]
+    once: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let settings = args.config.resolve()?;
+
+    let store = Store::open(&args.db)?;
+    for monitor in &args.monitors {
+        match store.checkpoint(monitor)? {
+            Some(checkpoint) => println!(
+                "Resuming {} from slot {} at event {}",
+                monitor, checkpoint.slot, checkpoint.next_event_index
+            ),
+            None => println!("Indexing {} from the start", monitor),
+        }
+    }
+
+    let mut chain = RpcChain::new(RpcClient::new_with_commitment(settings.url, settings.commitment));
+    let mut indexer = Indexer::new(store, args.monitors);
+    loop {
+        match indexer.poll_once(&mut chain).await {
+            Ok(0) => {}
+            Ok(indexed) => println!("Indexed {} events", indexed),
+            Err(e) if args.once => return Err(e.into()),
+            Err(e) => eprintln!("Poll failed: {}", e),
+        }
+        if args.once {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(args.interval)).await;
+    }
+}
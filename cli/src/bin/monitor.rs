@@ -0,0 +1,496 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use orderbook_monitor_cli::{
+    client::MonitorClient,
+    config::{ConfigArgs, Settings},
+    export::{
+        candle_records, flow_records, write_candles, write_flow, write_json, write_summary, EventFilter, EventKind,
+        EventWriter, Format, RowFormat, Side,
+    },
+    feeder::DEFAULT_BATCH_SIZE,
+    replay::{flow, for_each_event, trades, ReplaySource},
+    surveillance::{
+        compliance_reports, obligation_days, read_owned_fills, read_trader_events, SpoofingReport, WashTradingReport,
+    },
+};
+use serde::Serialize;
+use solana_client::rpc_client::RpcClient;
+use solana_orderbook_monitor::{
+    candles::{parse_interval, resample},
+    surveillance::{obligations::ObligationConfig, spoofing::SpoofingConfig, wash::WashConfig},
+    NewEvent, OrderbookMonitor, MAX_MARKET_NAME_LEN, MAX_SNAPSHOT_LEVELS,
+};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+};
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Events `init` makes room for when no size is given
+const DEFAULT_EVENTS: usize = 200;
+
+/// Size of a monitor account, in bytes or as a number of events and snapshots
+#[derive(Args, Debug)]
+struct SpaceArgs {
+    /// Account size in bytes
+    #[arg(long, conflicts_with_all = ["events", "snapshots"])]
+    space: Option<usize>,
+    /// Make room for this many events
+    #[arg(long)]
+    events: Option<usize>,
+    /// Make room for this many book snapshots
+    #[arg(long, default_value_t = 0)]
+    snapshots: usize,
+    /// Most price levels per side of the recorded snapshots
+    #[arg(long, default_value_t = MAX_SNAPSHOT_LEVELS)]
+    levels: usize,
+    /// Longest market name of the recorded events, in bytes
+    #[arg(long, default_value_t = MAX_MARKET_NAME_LEN)]
+    max_market_name_len: usize,
+}
+
+impl SpaceArgs {
+    fn bytes(&self) -> Option<usize> {
+        self.space.or_else(|| {
+            (self.events.is_some() || self.snapshots > 0).then(|| {
+                OrderbookMonitor::space_with_snapshots(
+                    self.events.unwrap_or_default(),
+                    self.snapshots,
+                    self.levels,
+                    self.max_market_name_len,
+                )
+            })
+        })
+    }
+}
+
+/// Manage orderbook monitor accounts
+#[derive(Parser, Debug)]
+#[command(name = "orderbook-monitor")]
+struct Cli {
+    #[command(flatten)]
+    config: ConfigArgs,
+    /// Keypair of the monitor authority, defaults to the payer
+    #[arg(long, global = true)]
+    authority: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create and initialize a monitor account
+    Init {
+        /// Keypair of the new account, a fresh one by default
+        #[arg(long)]
+        monitor_keypair: Option<PathBuf>,
+        #[command(flatten)]
+        space: SpaceArgs,
+    },
+    /// Record one event
+    Record {
+        monitor: Pubkey,
+        /// DEX market the event happened on
+        #[arg(long)]
+        market: Pubkey,
+        #[arg(long)]
+        market_name: String,
+        #[arg(long)]
+        price: u64,
+        #[arg(long)]
+        size: u64,
+        #[arg(long, value_enum)]
+        side: Side,
+        #[arg(long = "type", value_enum)]
+        event_type: EventKind,
+    },
+    /// Record the events of a JSON file, in batches
+    RecordBatch {
+        monitor: Pubkey,
+        #[arg(long)]
+        market: Pubkey,
+        /// Events to record, as written by `export` or `export-events --format jsonl`
+        #[arg(long)]
+        file: PathBuf,
+        #[arg(long, value_enum, default_value = "export")]
+        format: BatchFormat,
+        #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+        batch_size: usize,
+    },
+    /// Summarize a monitor account
+    Inspect {
+        monitor: Pubkey,
+        /// Number of recent events to show
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Write the events and snapshots of a monitor as JSON
+    Export {
+        monitor: Pubkey,
+        /// Output file, standard output by default
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Write recorded events as CSV, JSON Lines or Parquet
+    ExportEvents {
+        /// Monitor account to read; with --db, only this monitor's indexed events
+        #[arg(required_unless_present = "db")]
+        monitor: Option<Pubkey>,
+        #[arg(long, value_enum, default_value = "csv")]
+        format: Format,
+        /// Output file, standard output by default
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Only events of this market
+        #[arg(long)]
+        market: Option<String>,
+        /// Only events at or after this unix timestamp
+        #[arg(long)]
+        from: Option<i64>,
+        /// Only events before this unix timestamp
+        #[arg(long)]
+        to: Option<i64>,
+        /// Read from an orderbook-indexer database instead of the account
+        #[arg(long)]
+        db: Option<PathBuf>,
+    },
+    /// Resample fills into OHLCV bars with indicators
+    Candles {
+        /// Monitor account to read; with --db, only this monitor's indexed events
+        #[arg(required_unless_present = "db")]
+        monitor: Option<Pubkey>,
+        #[arg(long)]
+        market: String,
+        /// Bar length, e.g. 30s, 5m, 1h or 1d
+        #[arg(long, default_value = "1m", value_parser = interval)]
+        interval: i64,
+        /// Bars the indicators are computed over
+        #[arg(long, default_value_t = 14)]
+        period: usize,
+        /// Leave intervals without fills out instead of repeating the last close
+        #[arg(long)]
+        no_gap_fill: bool,
+        #[arg(long, value_enum, default_value = "csv")]
+        format: RowFormat,
+        /// Output file, standard output by default
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Read from an orderbook-indexer database instead of the account
+        #[arg(long)]
+        db: Option<PathBuf>,
+    },
+    /// Classify trades and measure order flow per market and interval
+    Microstructure {
+        /// Monitor account to read; with --db, only this monitor's indexed events
+        #[arg(required_unless_present = "db")]
+        monitor: Option<Pubkey>,
+        /// Only this market, every recorded market by default
+        #[arg(long)]
+        market: Option<String>,
+        /// Bar length, e.g. 30s, 5m, 1h or 1d
+        #[arg(long, default_value = "1m", value_parser = interval)]
+        interval: i64,
+        #[arg(long, value_enum, default_value = "csv")]
+        format: RowFormat,
+        /// Output file, standard output by default
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Read from an orderbook-indexer database instead of the account; books start empty
+        #[arg(long)]
+        db: Option<PathBuf>,
+    },
+    /// Score traders for spoofing and layering from their order lifecycles
+    Spoofing {
+        /// JSON Lines of trader events: placements, fills and cancellations
+        input: PathBuf,
+        /// Output file, standard output by default
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Smallest spoof order, as a multiple of the market's mean placement size
+        #[arg(long, default_value_t = SpoofingConfig::default().min_size_ratio)]
+        min_size_ratio: f64,
+        /// Smallest distance of a spoof order from the touch, in basis points
+        #[arg(long, default_value_t = SpoofingConfig::default().min_distance_bps)]
+        min_distance_bps: f64,
+        /// Longest life of an order cancelled quickly, in seconds
+        #[arg(long, default_value_t = SpoofingConfig::default().max_lifetime_secs)]
+        max_lifetime: i64,
+        /// Fewest orders at distinct prices making up a layering pattern
+        #[arg(long, default_value_t = SpoofingConfig::default().min_layers)]
+        min_layers: usize,
+        /// Leave out incidents scoring lower, from 0 to 1
+        #[arg(long, default_value_t = SpoofingConfig::default().min_score)]
+        min_score: f64,
+    },
+    /// Measure suspect wash trading from fills carrying both owners
+    WashTrading {
+        /// JSON Lines of fills with their maker and taker
+        input: PathBuf,
+        /// Output file, standard output by default
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Wallets known to be related, comma separated; repeat for several groups
+        #[arg(long, value_parser = wallets)]
+        related: Vec<Wallets>,
+        /// Longest time for traded size to come back to its first seller, in seconds
+        #[arg(long, default_value_t = WashConfig::default().window_secs)]
+        window: i64,
+        /// Most wallets in one round trip
+        #[arg(long, default_value_t = WashConfig::default().max_cycle_len)]
+        max_cycle_len: usize,
+        /// Round trips two wallets share before they are treated as related
+        #[arg(long, default_value_t = WashConfig::default().min_round_trips)]
+        min_round_trips: usize,
+    },
+    /// Report makers' daily quoting against their uptime and spread obligations
+    Obligations {
+        /// JSON Lines of trader events: placements, fills and cancellations
+        input: PathBuf,
+        /// Output file, standard output by default
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Maker to report; repeat for several, every trader placing orders by default
+        #[arg(long)]
+        maker: Vec<Pubkey>,
+        /// Distance from the market mid within which a maker's size counts as depth
+        #[arg(long, default_value_t = ObligationConfig::default().depth_bps)]
+        depth_bps: f64,
+        /// Widest own spread meeting the obligation, in basis points
+        #[arg(long, default_value_t = ObligationConfig::default().max_spread_bps)]
+        max_spread_bps: f64,
+        /// Smallest size on each side within --depth-bps of the mid
+        #[arg(long, default_value_t = ObligationConfig::default().min_depth)]
+        min_depth: u64,
+        /// Smallest share of the day quoted within obligation, in percent
+        #[arg(long, default_value_t = ObligationConfig::default().min_uptime_pct)]
+        min_uptime: f64,
+        /// Unix timestamp the last quotes are held until, the last event by default
+        #[arg(long)]
+        until: Option<i64>,
+    },
+    /// Close a monitor account and reclaim its lamports
+    Close {
+        monitor: Pubkey,
+        /// Account receiving the lamports, the payer by default
+        #[arg(long)]
+        destination: Option<Pubkey>,
+    },
+    /// Hand a monitor over to a new authority
+    SetAuthority {
+        monitor: Pubkey,
+        /// Keypair file of the new authority, which signs to accept the monitor
+        new_authority: PathBuf,
+    },
+    /// Grow or shrink a monitor account
+    Resize {
+        monitor: Pubkey,
+        #[command(flatten)]
+        space: SpaceArgs,
+    },
+    /// Stop recording into a monitor
+    Pause { monitor: Pubkey },
+    /// Resume recording into a paused monitor
+    Resume { monitor: Pubkey },
+}
+
+/// Input of `record-batch`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum BatchFormat {
+    /// A monitor written by `export`
+    Export,
+    /// One event per line, as written by `export-events --format jsonl`
+    Jsonl,
+}
+
+fn interval(value: &str) -> Result<i64, String> {
+    parse_interval(value).ok_or_else(|| format!("invalid interval {}; use e.g. 30s, 5m, 1h or 1d", value))
+}
+
+/// A comma-separated group of wallets
+#[derive(Debug, Clone)]
+struct Wallets(Vec<Pubkey>);
+
+fn wallets(value: &str) -> Result<Wallets, String> {
+    value
+        .split(',')
+        .map(|w| w.trim().parse().map_err(|_| format!("invalid wallet {}", w)))
+        .collect::<Result<_, _>>()
+        .map(Wallets)
+}
+
+fn read_keypair(path: &Path) -> Result<Keypair, Box<dyn Error>> {
+    read_keypair_file(path).map_err(|e| format!("Cannot read keypair {}: {}", path.display(), e).into())
+}
+
+/// Client signing with the configured keypairs, built only by commands that send transactions
+fn monitor_client(settings: &Settings, authority: Option<&Path>) -> Result<MonitorClient, Box<dyn Error>> {
+    let rpc = RpcClient::new_with_commitment(settings.url.clone(), settings.commitment);
+    let payer = read_keypair(&settings.keypair_path)?;
+    let client = MonitorClient::new(rpc, settings.program_id()?, payer);
+    Ok(match authority {
+        Some(path) => client.with_authority(read_keypair(path)?),
+        None => client,
+    })
+}
+
+/// The file at `path`, or standard output without one
+fn open_output(path: Option<PathBuf>) -> io::Result<Box<dyn Write + Send>> {
+    Ok(match path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    })
+}
+
+/// Write `report` as pretty-printed JSON to the file at `path` or standard output
+fn write_report<T: Serialize>(path: Option<PathBuf>, report: &T) -> Result<(), Box<dyn Error>> {
+    let mut out = open_output(path)?;
+    serde_json::to_writer_pretty(&mut out, report)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run(Cli::parse()) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let settings = cli.config.resolve()?;
+    let client = || monitor_client(&settings, cli.authority.as_deref());
+    let rpc = || RpcClient::new_with_commitment(settings.url.clone(), settings.commitment);
+    // Recorded events from the index, or from the monitor account over RPC
+    let source = |monitor: Option<Pubkey>, db: Option<PathBuf>| -> Result<ReplaySource, Box<dyn Error>> {
+        Ok(match (db, monitor) {
+            (Some(path), monitor) => ReplaySource::Index { path, monitor },
+            (None, Some(monitor)) => ReplaySource::AccountData(rpc().get_account_data(&monitor)?),
+            (None, None) => unreachable!("clap requires a monitor without --db"),
+        })
+    };
+
+    match cli.command {
+        Command::Init { monitor_keypair, space } => {
+            let client = client()?;
+            let monitor = match monitor_keypair {
+                Some(path) => read_keypair(&path)?,
+                None => Keypair::new(),
+            };
+            let space = space.bytes().unwrap_or_else(|| OrderbookMonitor::space(DEFAULT_EVENTS, MAX_MARKET_NAME_LEN));
+            let signature = client.create(&monitor, space)?;
+            println!("Monitor: {}", monitor.pubkey());
+            println!("Authority: {}", client.authority());
+            println!("Signature: {}", signature);
+        }
+        Command::Record { monitor, market, market_name, price, size, side, event_type } => {
+            let event = NewEvent {
+                market_name,
+                price,
+                size,
+                is_bid: side == Side::Bid,
+                event_type: event_type.into(),
+            };
+            println!("Signature: {}", client()?.record_event(&monitor, &market, event)?);
+        }
+        Command::RecordBatch { monitor, market, file, format, batch_size } => {
+            let client = client()?;
+            let source = match format {
+                BatchFormat::Export => ReplaySource::Export(file),
+                BatchFormat::Jsonl => ReplaySource::Jsonl(file),
+            };
+            let mut events = Vec::new();
+            for_each_event(&source, &EventFilter::default(), |record| {
+                events.push(NewEvent::from(record));
+                Ok::<_, Box<dyn Error>>(())
+            })?;
+            for batch in events.chunks(batch_size.max(1)) {
+                let signature = client.record_events(&monitor, &market, batch.to_vec())?;
+                println!("Recorded {} events: {}", batch.len(), signature);
+            }
+        }
+        Command::Inspect { monitor: address, limit } => {
+            let account = rpc().get_account(&address)?;
+            let monitor = OrderbookMonitor::unpack(&account.data)?;
+            write_summary(&address, &account, &monitor, limit, io::stdout().lock())?;
+        }
+        Command::Export { monitor, output } => {
+            let monitor = OrderbookMonitor::unpack(&rpc().get_account_data(&monitor)?)?;
+            let mut out = open_output(output)?;
+            write_json(&monitor, &mut out)?;
+            out.flush()?;
+        }
+        Command::ExportEvents { monitor, format, output, market, from, to, db } => {
+            let mut writer = EventWriter::new(format, open_output(output)?)?;
+            let filter = EventFilter { market, from, to };
+            for_each_event(&source(monitor, db)?, &filter, |event| writer.write(&event).map_err(Box::<dyn Error>::from))?;
+            eprintln!("Exported {} events", writer.finish()?);
+        }
+        Command::Candles { monitor, market, interval, period, no_gap_fill, format, output, db } => {
+            let fills = trades(&source(monitor, db)?, &market)?;
+            let records = candle_records(&resample(fills, interval, !no_gap_fill)?, period);
+            write_candles(format, &records, open_output(output)?)?;
+        }
+        Command::Microstructure { monitor, market, interval, format, output, db } => {
+            let analyzers = flow(&source(monitor, db)?, market.as_deref(), interval)?;
+            write_flow(format, &flow_records(&analyzers), open_output(output)?)?;
+        }
+        Command::Spoofing { input, output, min_size_ratio, min_distance_bps, max_lifetime, min_layers, min_score } => {
+            let events = read_trader_events(BufReader::new(File::open(input)?))?;
+            let config = SpoofingConfig {
+                min_size_ratio,
+                min_distance_bps,
+                max_lifetime_secs: max_lifetime,
+                min_layers,
+                min_score,
+                ..SpoofingConfig::default()
+            };
+            let report = SpoofingReport::detect(&events, &config);
+            write_report(output, &report)?;
+            eprintln!("{} incidents over {} events", report.incidents.len(), events.len());
+        }
+        Command::WashTrading { input, output, related, window, max_cycle_len, min_round_trips } => {
+            let fills = read_owned_fills(BufReader::new(File::open(input)?))?;
+            let config =
+                WashConfig { window_secs: window, max_cycle_len, min_round_trips, ..WashConfig::default() };
+            let related: Vec<Vec<Pubkey>> = related.into_iter().map(|group| group.0).collect();
+            let report = WashTradingReport::detect(&fills, &related, &config);
+            write_report(output, &report)?;
+            for market in &report.markets {
+                eprintln!("{}: {:.1}% of volume suspect", market.market_name, market.suspect_share * 100.0);
+            }
+        }
+        Command::Obligations { input, output, maker, depth_bps, max_spread_bps, min_depth, min_uptime, until } => {
+            let events = read_trader_events(BufReader::new(File::open(input)?))?;
+            let config =
+                ObligationConfig { makers: maker, depth_bps, max_spread_bps, min_depth, min_uptime_pct: min_uptime };
+            let days = obligation_days(&events, config, until);
+            write_report(output, &compliance_reports(&days))?;
+            let missed = days.iter().filter(|d| !d.compliant).count();
+            eprintln!("{} of {} maker days missed the uptime obligation", missed, days.len());
+        }
+        Command::Close { monitor, destination } => {
+            let client = client()?;
+            let destination = destination.unwrap_or(client.payer());
+            println!("Signature: {}", client.close(&monitor, &destination)?);
+        }
+        Command::SetAuthority { monitor, new_authority } => {
+            println!("Signature: {}", client()?.set_authority(&monitor, &read_keypair(&new_authority)?)?);
+        }
+        Command::Resize { monitor, space } => {
+            let space = space.bytes().ok_or("Pass --space or --events")?;
+            println!("Signature: {}", client()?.resize(&monitor, space)?);
+        }
+        Command::Pause { monitor } => {
+            println!("Signature: {}", client()?.set_paused(&monitor, true)?);
+        }
+        Command::Resume { monitor } => {
+            println!("Signature: {}", client()?.set_paused(&monitor, false)?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! The cluster operations the off-chain tools need, so they can run against
+//! an RPC node or an in-process test bank.
+
+use solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{Transaction, TransactionError},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ChainError {
+    #[error("RPC error: {0}")]
+    Rpc(Box<ClientError>),
+    #[error("Account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("Transaction failed: {0}")]
+    Transaction(#[from] TransactionError),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<ClientError> for ChainError {
+    fn from(e: ClientError) -> Self {
+        match e.get_transaction_error() {
+            Some(e) => ChainError::Transaction(e),
+            None => ChainError::Rpc(Box::new(e)),
+        }
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait Chain {
+    async fn account_data(&mut self, address: &Pubkey) -> Result<Vec<u8>, ChainError>;
+
+    /// Account data and the slot it was read at
+    async fn account_at_slot(&mut self, address: &Pubkey) -> Result<(u64, Vec<u8>), ChainError>;
+
+    /// Latest blockhash and the last block height at which it is valid
+    async fn latest_blockhash(&mut self) -> Result<(Hash, u64), ChainError>;
+
+    async fn block_height(&mut self) -> Result<u64, ChainError>;
+
+    /// `None` if the cluster has not seen the transaction
+    async fn signature_status(
+        &mut self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), TransactionError>>, ChainError>;
+
+    async fn send_and_confirm(&mut self, transaction: &Transaction) -> Result<(), ChainError>;
+}
+
+/// A `Chain` backed by a nonblocking RPC client
+pub struct RpcChain {
+    client: RpcClient,
+}
+
+impl RpcChain {
+    pub fn new(client: RpcClient) -> Self {
+        RpcChain { client }
+    }
+
+    pub fn client(&self) -> &RpcClient {
+        &self.client
+    }
+}
+
+impl Chain for RpcChain {
+    async fn account_data(&mut self, address: &Pubkey) -> Result<Vec<u8>, ChainError> {
+        Ok(self.client.get_account_data(address).await?)
+    }
+
+    async fn account_at_slot(&mut self, address: &Pubkey) -> Result<(u64, Vec<u8>), ChainError> {
+        let response = self
+            .client
+            .get_account_with_commitment(address, self.client.commitment())
+            .await?;
+        let account = response.value.ok_or(ChainError::AccountNotFound(*address))?;
+        Ok((response.context.slot, account.data))
+    }
+
+    async fn latest_blockhash(&mut self) -> Result<(Hash, u64), ChainError> {
+        let commitment = self.client.commitment();
+        Ok(self.client.get_latest_blockhash_with_commitment(commitment).await?)
+    }
+
+    async fn block_height(&mut self) -> Result<u64, ChainError> {
+        Ok(self.client.get_block_height().await?)
+    }
+
+    async fn signature_status(
+        &mut self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), TransactionError>>, ChainError> {
+        Ok(self
+            .client
+            .get_signature_status_with_commitment(signature, CommitmentConfig::confirmed())
+            .await?)
+    }
+
+    async fn send_and_confirm(&mut self, transaction: &Transaction) -> Result<(), ChainError> {
+        self.client.send_and_confirm_transaction(transaction).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use super::{unpack, ClientError, Signers};
+use solana_client::rpc_client::RpcClient;
+use solana_orderbook_monitor::{NewEvent, OrderbookMonitor};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+
+/// Monitor client over a blocking RPC client
+pub struct MonitorClient {
+    rpc: RpcClient,
+    signers: Signers,
+}
+
+impl MonitorClient {
+    /// Create a client whose payer is also the monitor authority
+    pub fn new(rpc: RpcClient, program_id: Pubkey, payer: Keypair) -> Self {
+        MonitorClient { rpc, signers: Signers::new(program_id, payer) }
+    }
+
+    /// Sign for the monitor with `authority` instead of the payer
+    pub fn with_authority(mut self, authority: Keypair) -> Self {
+        self.signers.authority = authority;
+        self
+    }
+
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.signers.program_id
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.signers.payer.pubkey()
+    }
+
+    pub fn authority(&self) -> Pubkey {
+        self.signers.authority.pubkey()
+    }
+
+    /// Create a rent-exempt monitor account of `space` bytes and initialize it
+    pub fn create(&self, monitor: &Keypair, space: usize) -> Result<Signature, ClientError> {
+        let lamports = self.rpc.get_minimum_balance_for_rent_exemption(space)?;
+        let instructions = self.signers.create(&monitor.pubkey(), space, lamports)?;
+        self.send(&instructions, &[monitor])
+    }
+
+    pub fn record_event(
+        &self,
+        monitor: &Pubkey,
+        market: &Pubkey,
+        event: NewEvent,
+    ) -> Result<Signature, ClientError> {
+        self.send(&[self.signers.record_event(monitor, market, event)?], &[])
+    }
+
+    /// Record `events` in one instruction; keep batches small enough to fit a transaction
+    pub fn record_events(
+        &self,
+        monitor: &Pubkey,
+        market: &Pubkey,
+        events: Vec<NewEvent>,
+    ) -> Result<Signature, ClientError> {
+        self.send(&[self.signers.record_events(monitor, market, events)?], &[])
+    }
+
+    pub fn fetch(&self, monitor: &Pubkey) -> Result<OrderbookMonitor, ClientError> {
+        let account = self
+            .rpc
+            .get_account_with_commitment(monitor, self.rpc.commitment())?
+            .value
+            .ok_or(ClientError::AccountNotFound(*monitor))?;
+        unpack(monitor, &account.data)
+    }
+
+    /// Hand the monitor over to `new_authority`, which signs to accept it
+    pub fn set_authority(&self, monitor: &Pubkey, new_authority: &Keypair) -> Result<Signature, ClientError> {
+        self.send(&[self.signers.set_authority(monitor, &new_authority.pubkey())], &[new_authority])
+    }
+
+    /// Resize the monitor account to `space` bytes; the payer covers or receives the rent difference
+    pub fn resize(&self, monitor: &Pubkey, space: usize) -> Result<Signature, ClientError> {
+        let current = self
+            .rpc
+            .get_account_with_commitment(monitor, self.rpc.commitment())?
+            .value
+            .ok_or(ClientError::AccountNotFound(*monitor))?
+            .data
+            .len();
+        self.send(&self.signers.resize(monitor, current, space), &[])
+    }
+
+    /// Stop or resume recording into the monitor
+    pub fn set_paused(&self, monitor: &Pubkey, paused: bool) -> Result<Signature, ClientError> {
+        self.send(&[self.signers.set_paused(monitor, paused)], &[])
+    }
+
+    /// Close the monitor account, sending its lamports to `destination`
+    pub fn close(&self, monitor: &Pubkey, destination: &Pubkey) -> Result<Signature, ClientError> {
+        self.send(&[self.signers.close(monitor, destination)], &[])
+    }
+
+    /// Sign `instructions` with the payer, the authority and `extra`, then send and confirm them
+    pub fn send(&self, instructions: &[Instruction], extra: &[&Keypair]) -> Result<Signature, ClientError> {
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.signers.payer.pubkey()),
+            &self.signers.all(extra),
+            self.rpc.get_latest_blockhash()?,
+        );
+        self.rpc
+            .send_and_confirm_transaction(&transaction)
+            .map_err(|e| ClientError::from_transaction(e.into(), instructions, &self.signers.program_id))
+    }
+}
@@ -0,0 +1,238 @@
+//! Typed clients for monitor accounts.
+//!
+//! `MonitorClient` wraps a blocking RPC client and `nonblocking::MonitorClient`
+//! an async one. Both sign with a payer and an authority keypair (the same
+//! keypair unless `with_authority` is used), create accounts rent exempt and
+//! report every failure as a `ClientError`. Custom errors returned by the
+//! monitor program come back as `ClientError::Program`.
+
+use solana_client::client_error::ClientError as RpcError;
+use solana_orderbook_monitor::{
+    client::{
+        close, initialize_with_authority, record_event, record_events, resize, set_authority, set_paused,
+    },
+    NewEvent, OrderbookError, OrderbookMonitor,
+};
+use solana_sdk::{
+    entrypoint::MAX_PERMITTED_DATA_INCREASE,
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction::create_account,
+    transaction::TransactionError,
+};
+use thiserror::Error;
+
+mod blocking;
+pub mod nonblocking;
+
+pub use self::blocking::MonitorClient;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("RPC error: {0}")]
+    Rpc(Box<RpcError>),
+    #[error("Transaction failed: {0}")]
+    Transaction(#[from] TransactionError),
+    #[error("Account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("Account {0} does not hold a monitor")]
+    InvalidAccountData(Pubkey),
+    #[error("{space} bytes cannot hold the monitor, at least {required} are needed")]
+    SpaceTooSmall { space: usize, required: usize },
+    #[error("Invalid input: {0}")]
+    InvalidInput(#[from] OrderbookError),
+    #[error("Instruction {index} failed: {error} (error code {})", error.code())]
+    Program { index: u8, error: OrderbookError },
+}
+
+impl ClientError {
+    /// Turn a failed transaction of `instructions` into `ClientError::Program`
+    /// when the failing instruction is one of `program_id`'s
+    fn from_transaction(
+        e: ClientError,
+        instructions: &[Instruction],
+        program_id: &Pubkey,
+    ) -> ClientError {
+        match e {
+            ClientError::Transaction(ref error) => match decode_error(error, instructions, program_id) {
+                Some((index, error)) => ClientError::Program { index, error },
+                None => e,
+            },
+            e => e,
+        }
+    }
+}
+
+impl From<RpcError> for ClientError {
+    fn from(e: RpcError) -> Self {
+        match e.get_transaction_error() {
+            Some(e) => ClientError::Transaction(e),
+            None => ClientError::Rpc(Box::new(e)),
+        }
+    }
+}
+
+/// The monitor error behind a failed transaction, with the index of the
+/// instruction that raised it.
+///
+/// Custom codes are only decoded for instructions addressed to `program_id`;
+/// other programs reuse the same numbers for their own errors.
+pub fn decode_error(
+    error: &TransactionError,
+    instructions: &[Instruction],
+    program_id: &Pubkey,
+) -> Option<(u8, OrderbookError)> {
+    match error {
+        TransactionError::InstructionError(index, InstructionError::Custom(code))
+            if instructions.get(*index as usize)?.program_id == *program_id =>
+        {
+            OrderbookError::from_code(*code).map(|e| (*index, e))
+        }
+        _ => None,
+    }
+}
+
+fn unpack(address: &Pubkey, data: &[u8]) -> Result<OrderbookMonitor, ClientError> {
+    OrderbookMonitor::unpack(data).map_err(|_| ClientError::InvalidAccountData(*address))
+}
+
+/// Sizes to pass to successive `Resize` instructions to get from `current` to
+/// `target` bytes, as an account grows by at most `MAX_PERMITTED_DATA_INCREASE`
+/// per instruction
+pub fn resize_steps(current: usize, target: usize) -> Vec<usize> {
+    let mut steps = Vec::new();
+    let mut len = current;
+    while len + MAX_PERMITTED_DATA_INCREASE < target {
+        len += MAX_PERMITTED_DATA_INCREASE;
+        steps.push(len);
+    }
+    steps.push(target);
+    steps
+}
+
+/// State and instruction building shared by the blocking and async clients
+struct Signers {
+    program_id: Pubkey,
+    payer: Keypair,
+    authority: Keypair,
+}
+
+impl Signers {
+    fn new(program_id: Pubkey, payer: Keypair) -> Self {
+        let authority = payer.insecure_clone();
+        Signers { program_id, payer, authority }
+    }
+
+    /// Payer first, then the authority if it is another keypair, then `extra`
+    fn all<'a>(&'a self, extra: &[&'a Keypair]) -> Vec<&'a Keypair> {
+        let mut signers = vec![&self.payer];
+        if self.authority.pubkey() != self.payer.pubkey() {
+            signers.push(&self.authority);
+        }
+        signers.extend_from_slice(extra);
+        signers
+    }
+
+    fn create(&self, monitor: &Pubkey, space: usize, lamports: u64) -> Result<Vec<Instruction>, ClientError> {
+        let required = OrderbookMonitor::EMPTY_LEN;
+        if space < required {
+            return Err(ClientError::SpaceTooSmall { space, required });
+        }
+        Ok(vec![
+            create_account(&self.payer.pubkey(), monitor, lamports, space as u64, &self.program_id),
+            initialize_with_authority(&self.program_id, monitor, &self.authority.pubkey()),
+        ])
+    }
+
+    fn record_event(&self, monitor: &Pubkey, market: &Pubkey, event: NewEvent) -> Result<Instruction, ClientError> {
+        event.validate()?;
+        Ok(record_event(
+            &self.program_id,
+            monitor,
+            market,
+            &self.authority.pubkey(),
+            event.market_name,
+            event.price,
+            event.size,
+            event.is_bid,
+            event.event_type,
+        ))
+    }
+
+    fn record_events(
+        &self,
+        monitor: &Pubkey,
+        market: &Pubkey,
+        events: Vec<NewEvent>,
+    ) -> Result<Instruction, ClientError> {
+        if events.is_empty() {
+            return Err(OrderbookError::EmptyBatch.into());
+        }
+        for event in &events {
+            event.validate()?;
+        }
+        Ok(record_events(&self.program_id, monitor, market, &self.authority.pubkey(), events))
+    }
+
+    fn set_authority(&self, monitor: &Pubkey, new_authority: &Pubkey) -> Instruction {
+        set_authority(&self.program_id, monitor, &self.authority.pubkey(), new_authority)
+    }
+
+    fn resize(&self, monitor: &Pubkey, current: usize, space: usize) -> Vec<Instruction> {
+        resize_steps(current, space)
+            .into_iter()
+            .map(|len| {
+                resize(&self.program_id, monitor, &self.authority.pubkey(), &self.payer.pubkey(), len as u64)
+            })
+            .collect()
+    }
+
+    fn close(&self, monitor: &Pubkey, destination: &Pubkey) -> Instruction {
+        close(&self.program_id, monitor, &self.authority.pubkey(), destination)
+    }
+
+    fn set_paused(&self, monitor: &Pubkey, paused: bool) -> Instruction {
+        set_paused(&self.program_id, monitor, &self.authority.pubkey(), paused)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize_steps() {
+        assert_eq!(resize_steps(1000, 500), vec![500]);
+        assert_eq!(resize_steps(1000, 11_240), vec![11_240]);
+        assert_eq!(resize_steps(1000, 25_000), vec![11_240, 21_480, 25_000]);
+    }
+
+    #[test]
+    fn test_decode_error() {
+        let program_id = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let instructions = [
+            create_account(&payer, &Pubkey::new_unique(), 1, 100, &program_id),
+            set_paused(&program_id, &Pubkey::new_unique(), &payer, true),
+        ];
+        let failed = |index, code| TransactionError::InstructionError(index, InstructionError::Custom(code));
+
+        assert_eq!(
+            decode_error(&failed(1, 4), &instructions, &program_id),
+            Some((1, OrderbookError::Unauthorized))
+        );
+        // The same code from the system program is not a monitor error
+        assert_eq!(decode_error(&failed(0, 4), &instructions, &program_id), None);
+        // Unknown codes and out of range instructions are left alone
+        assert_eq!(decode_error(&failed(1, 999), &instructions, &program_id), None);
+        assert_eq!(decode_error(&failed(2, 4), &instructions, &program_id), None);
+        assert_eq!(decode_error(&TransactionError::AccountInUse, &instructions, &program_id), None);
+
+        let e = ClientError::from_transaction(failed(1, 13).into(), &instructions, &program_id);
+        assert_eq!(
+            e.to_string(),
+            "Instruction 1 failed: Monitor is paused (error code 13)"
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! The async counterpart of `MonitorClient`.
+
+use super::{unpack, ClientError, Signers};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_orderbook_monitor::{NewEvent, OrderbookMonitor};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+
+/// Monitor client over a nonblocking RPC client
+pub struct MonitorClient {
+    rpc: RpcClient,
+    signers: Signers,
+}
+
+impl MonitorClient {
+    /// Create a client whose payer is also the monitor authority
+    pub fn new(rpc: RpcClient, program_id: Pubkey, payer: Keypair) -> Self {
+        MonitorClient { rpc, signers: Signers::new(program_id, payer) }
+    }
+
+    /// Sign for the monitor with `authority` instead of the payer
+    pub fn with_authority(mut self, authority: Keypair) -> Self {
+        self.signers.authority = authority;
+        self
+    }
+
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.signers.program_id
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.signers.payer.pubkey()
+    }
+
+    pub fn authority(&self) -> Pubkey {
+        self.signers.authority.pubkey()
+    }
+
+    /// Create a rent-exempt monitor account of `space` bytes and initialize it
+    pub async fn create(&self, monitor: &Keypair, space: usize) -> Result<Signature, ClientError> {
+        let lamports = self.rpc.get_minimum_balance_for_rent_exemption(space).await?;
+        let instructions = self.signers.create(&monitor.pubkey(), space, lamports)?;
+        self.send(&instructions, &[monitor]).await
+    }
+
+    pub async fn record_event(
+        &self,
+        monitor: &Pubkey,
+        market: &Pubkey,
+        event: NewEvent,
+    ) -> Result<Signature, ClientError> {
+        self.send(&[self.signers.record_event(monitor, market, event)?], &[]).await
+    }
+
+    /// Record `events` in one instruction; keep batches small enough to fit a transaction
+    pub async fn record_events(
+        &self,
+        monitor: &Pubkey,
+        market: &Pubkey,
+        events: Vec<NewEvent>,
+    ) -> Result<Signature, ClientError> {
+        self.send(&[self.signers.record_events(monitor, market, events)?], &[]).await
+    }
+
+    pub async fn fetch(&self, monitor: &Pubkey) -> Result<OrderbookMonitor, ClientError> {
+        let account = self
+            .rpc
+            .get_account_with_commitment(monitor, self.rpc.commitment())
+            .await?
+            .value
+            .ok_or(ClientError::AccountNotFound(*monitor))?;
+        unpack(monitor, &account.data)
+    }
+
+    /// Hand the monitor over to `new_authority`, which signs to accept it
+    pub async fn set_authority(
+        &self,
+        monitor: &Pubkey,
+        new_authority: &Keypair,
+    ) -> Result<Signature, ClientError> {
+        self.send(&[self.signers.set_authority(monitor, &new_authority.pubkey())], &[new_authority]).await
+    }
+
+    /// Resize the monitor account to `space` bytes; the payer covers or receives the rent difference
+    pub async fn resize(&self, monitor: &Pubkey, space: usize) -> Result<Signature, ClientError> {
+        let current = self
+            .rpc
+            .get_account_with_commitment(monitor, self.rpc.commitment())
+            .await?
+            .value
+            .ok_or(ClientError::AccountNotFound(*monitor))?
+            .data
+            .len();
+        self.send(&self.signers.resize(monitor, current, space), &[]).await
+    }
+
+    /// Stop or resume recording into the monitor
+    pub async fn set_paused(&self, monitor: &Pubkey, paused: bool) -> Result<Signature, ClientError> {
+        self.send(&[self.signers.set_paused(monitor, paused)], &[]).await
+    }
+
+    /// Close the monitor account, sending its lamports to `destination`
+    pub async fn close(&self, monitor: &Pubkey, destination: &Pubkey) -> Result<Signature, ClientError> {
+        self.send(&[self.signers.close(monitor, destination)], &[]).await
+    }
+
+    /// Sign `instructions` with the payer, the authority and `extra`, then send and confirm them
+    pub async fn send(
+        &self,
+        instructions: &[Instruction],
+        extra: &[&Keypair],
+    ) -> Result<Signature, ClientError> {
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.signers.payer.pubkey()),
+            &self.signers.all(extra),
+            self.rpc.get_latest_blockhash().await?,
+        );
+        self.rpc
+            .send_and_confirm_transaction(&transaction).await
+            .map_err(|e| ClientError::from_transaction(e.into(), instructions, &self.signers.program_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshSerialize;
+    use serde_json::json;
+    use solana_account_decoder::{UiAccount, UiAccountEncoding};
+    use solana_client::rpc_request::RpcRequest;
+    use solana_sdk::account::Account;
+    use std::collections::HashMap;
+
+    fn account_info(data: Vec<u8>) -> serde_json::Value {
+        let account = Account { lamports: 1, data, owner: Pubkey::new_unique(), ..Account::default() };
+        let ui = UiAccount::encode(&Pubkey::new_unique(), &account, UiAccountEncoding::Base64, None, None);
+        json!({ "context": { "slot": 1 }, "value": ui })
+    }
+
+    #[tokio::test]
+    async fn test_fetch_and_create() {
+        let monitor = OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: 0,
+            events: vec![],
+            snapshots: vec![],
+            paused: false,
+        };
+        let mut mocks = HashMap::new();
+        mocks.insert(RpcRequest::GetAccountInfo, account_info(monitor.try_to_vec().unwrap()));
+        let rpc = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        let client = MonitorClient::new(rpc, Pubkey::new_unique(), Keypair::new());
+
+        let address = Pubkey::new_unique();
+        assert_eq!(client.fetch(&address).await.unwrap().authority, monitor.authority);
+
+        // Too little space is caught before anything is sent
+        let result = client.create(&Keypair::new(), 10).await;
+        assert!(matches!(result, Err(ClientError::SpaceTooSmall { space: 10, required: OrderbookMonitor::EMPTY_LEN })));
+        client.create(&Keypair::new(), 1000).await.unwrap();
+
+        // Account data that is not a monitor is a typed error, not a panic
+        let mut mocks = HashMap::new();
+        mocks.insert(RpcRequest::GetAccountInfo, account_info(vec![7; 3]));
+        let rpc = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        let client = MonitorClient::new(rpc, Pubkey::new_unique(), Keypair::new());
+        assert!(matches!(client.fetch(&address).await, Err(ClientError::InvalidAccountData(a)) if a == address));
+    }
+}
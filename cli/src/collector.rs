@@ -0,0 +1,94 @@
+//! Collector of the surveillance inputs: polls a DEX market's accounts and
+//! turns them into the `TraderEvent`s and `OwnedFill`s that `orderbook-monitor
+//! spoofing`, `wash-trading` and `obligations` read, written as JSON Lines by
+//! `orderbook-collector`.
+//!
+//! Fills and cancellations come from the event queue with the owner of each
+//! order. Serum v1 queues carry no time, so their events are stamped with the
+//! time of the read. Placements are found by comparing each read of a Serum
+//! market's bids and asks with the previous one; orders resting at the first
+//! read, or placed and gone between two reads, are not seen. OpenBook v2 books
+//! are not compared, but its fills name both owners and give the owned fills
+//! wash-trading needs.
+
+use crate::chain::{Chain, ChainError};
+use solana_orderbook_monitor::{
+    adapter::{AdapterError, MarketAdapter, Venue},
+    serum::Slab,
+    surveillance::{placements, wash::OwnedFill, TraderEvent},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CollectorError {
+    #[error(transparent)]
+    Adapter(#[from] AdapterError),
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+    #[error("Market has no event queue account")]
+    NoEventQueue,
+}
+
+/// What one poll of the market found
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Collected {
+    /// Placements, fills and cancellations, placements first
+    pub events: Vec<TraderEvent>,
+    /// Fills whose venue reported both owners
+    pub fills: Vec<OwnedFill>,
+    /// Events consumed from the queue before the collector saw them
+    pub missed: u64,
+}
+
+pub struct Collector {
+    adapter: Box<dyn MarketAdapter>,
+    /// Sequence number of the next queue event to collect; `None` before the first poll
+    next_seq: Option<u64>,
+    /// Bids and asks of the previous read, Serum markets only
+    books: Option<(Slab, Slab)>,
+}
+
+impl Collector {
+    pub fn new(adapter: Box<dyn MarketAdapter>) -> Result<Self, CollectorError> {
+        if adapter.metadata().event_queue.is_none() {
+            return Err(CollectorError::NoEventQueue);
+        }
+        Ok(Collector { adapter, next_seq: None, books: None })
+    }
+
+    pub fn next_seq(&self) -> Option<u64> {
+        self.next_seq
+    }
+
+    /// Read the market once, at unix time `now`, and return what is new
+    pub async fn poll_once<C: Chain>(&mut self, chain: &mut C, now: i64) -> Result<Collected, CollectorError> {
+        let metadata = self.adapter.metadata();
+        let mut collected = Collected::default();
+
+        if metadata.venue == Venue::SerumV1 {
+            let bids = Slab::decode(&chain.account_data(&metadata.bids).await?, true).map_err(AdapterError::from)?;
+            let asks = Slab::decode(&chain.account_data(&metadata.asks).await?, false).map_err(AdapterError::from)?;
+            if let Some((previous_bids, previous_asks)) = &self.books {
+                collected.events.extend(placements(previous_bids, &bids, true, &metadata.name, now));
+                collected.events.extend(placements(previous_asks, &asks, false, &metadata.name, now));
+            }
+            self.books = Some((bids, asks));
+        }
+
+        let queue = metadata.event_queue.ok_or(CollectorError::NoEventQueue)?;
+        let data = chain.account_data(&queue).await?;
+        let range = self.adapter.seq_range(&data)?;
+        if let Some(next) = self.next_seq {
+            collected.missed = range.start.saturating_sub(next);
+        }
+        for event in self.adapter.decode_events(&data)? {
+            if self.next_seq.is_some_and(|next| event.seq_num < next) {
+                continue;
+            }
+            collected.events.extend(TraderEvent::from_sequenced(&event, now));
+            collected.fills.extend(OwnedFill::from_sequenced(&event));
+        }
+        self.next_seq = Some(self.next_seq.map_or(range.end, |next| next.max(range.end)));
+        Ok(collected)
+    }
+}
@@ -0,0 +1,130 @@
+//! Connection settings shared by the command line tools.
+//!
+//! Each setting is taken from its command line flag when given and otherwise
+//! from the Solana CLI config file (`solana config get`), so the tools follow
+//! whatever cluster and keypair `solana` itself uses.
+
+use clap::Args;
+use solana_cli_config::{Config, CONFIG_FILE};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::{path::PathBuf, str::FromStr};
+use thiserror::Error;
+
+/// Environment variable holding the default program id
+pub const PROGRAM_ID_ENV: &str = "ORDERBOOK_MONITOR_PROGRAM_ID";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("Cannot read Solana CLI config {0}")]
+    Unreadable(String),
+    #[error("Invalid commitment level {0}")]
+    InvalidCommitment(String),
+    #[error("No program id: pass --program-id or set {}", PROGRAM_ID_ENV)]
+    MissingProgramId,
+}
+
+/// Flags overriding the Solana CLI config
+#[derive(Args, Debug, Clone, Default)]
+pub struct ConfigArgs {
+    /// Solana CLI config file, defaults to ~/.config/solana/cli/config.yml
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+    /// RPC URL of the cluster
+    #[arg(long, short = 'u', global = true)]
+    pub url: Option<String>,
+    /// Commitment level: processed, confirmed or finalized
+    #[arg(long, global = true)]
+    pub commitment: Option<String>,
+    /// Keypair paying for transactions
+    #[arg(long, short = 'k', global = true)]
+    pub keypair: Option<PathBuf>,
+    /// Orderbook monitor program id
+    #[arg(long, global = true, env = PROGRAM_ID_ENV)]
+    pub program_id: Option<Pubkey>,
+}
+
+/// Settings after merging flags and the config file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub url: String,
+    pub commitment: CommitmentConfig,
+    pub keypair_path: PathBuf,
+    pub program_id: Option<Pubkey>,
+}
+
+impl Settings {
+    /// The program id, required by every command that sends instructions
+    pub fn program_id(&self) -> Result<Pubkey, ConfigError> {
+        self.program_id.ok_or(ConfigError::MissingProgramId)
+    }
+}
+
+impl ConfigArgs {
+    /// Merge the flags with the config file they point to, or the default one.
+    ///
+    /// A missing default config file is not an error; `solana` itself falls
+    /// back to its defaults in that case.
+    pub fn resolve(&self) -> Result<Settings, ConfigError> {
+        let config = match (&self.config, CONFIG_FILE.as_ref()) {
+            (Some(path), _) => {
+                Config::load(path).map_err(|_| ConfigError::Unreadable(path.clone()))?
+            }
+            (None, Some(path)) => Config::load(path).unwrap_or_default(),
+            (None, None) => Config::default(),
+        };
+        self.resolve_with(&config)
+    }
+
+    pub fn resolve_with(&self, config: &Config) -> Result<Settings, ConfigError> {
+        let commitment = self.commitment.as_ref().unwrap_or(&config.commitment);
+        Ok(Settings {
+            url: self.url.clone().unwrap_or_else(|| config.json_rpc_url.clone()),
+            commitment: CommitmentConfig::from_str(commitment)
+                .map_err(|_| ConfigError::InvalidCommitment(commitment.clone()))?,
+            keypair_path: self
+                .keypair
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(&config.keypair_path)),
+            program_id: self.program_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_override_config() {
+        let config = Config {
+            json_rpc_url: "http://localhost:8899".to_string(),
+            keypair_path: "/tmp/id.json".to_string(),
+            commitment: "finalized".to_string(),
+            ..Config::default()
+        };
+        let program_id = Pubkey::new_unique();
+
+        let mut args = ConfigArgs { program_id: Some(program_id), ..ConfigArgs::default() };
+        let settings = args.resolve_with(&config).unwrap();
+        assert_eq!(settings.url, "http://localhost:8899");
+        assert_eq!(settings.commitment, CommitmentConfig::finalized());
+        assert_eq!(settings.keypair_path, PathBuf::from("/tmp/id.json"));
+        assert_eq!(settings.program_id(), Ok(program_id));
+
+        args.url = Some("https://api.devnet.solana.com".to_string());
+        args.commitment = Some("processed".to_string());
+        let settings = args.resolve_with(&config).unwrap();
+        assert_eq!(settings.url, "https://api.devnet.solana.com");
+        assert_eq!(settings.commitment, CommitmentConfig::processed());
+
+        args.commitment = Some("eventually".to_string());
+        assert_eq!(
+            args.resolve_with(&config),
+            Err(ConfigError::InvalidCommitment("eventually".to_string()))
+        );
+        args.commitment = None;
+        args.program_id = None;
+        let settings = args.resolve_with(&config).unwrap();
+        assert_eq!(settings.program_id(), Err(ConfigError::MissingProgramId));
+    }
+}
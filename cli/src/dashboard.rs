@@ -0,0 +1,420 @@
+//! State and rendering of the `orderbook-dashboard` terminal UI.
+//!
+//! `Dashboard` takes in successive reads of one monitor account and keeps
+//! the newest events, per-market totals and the spread of each market's
+//! latest snapshot. Event rates and the volume sparkline are computed against
+//! the wall clock passed to `render`, so they fall to zero while a market is
+//! quiet. The filter narrows the tape, rates, sparkline and bid/ask ratios to
+//! one market and to the event types shown; the markets table always lists
+//! every market.
+
+use crate::{
+    export::{EventKind, EventRecord, Side},
+    follow::SeenEvents,
+};
+use ratatui::{
+    crossterm::event::KeyCode,
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, Paragraph, Row, Sparkline, Table},
+    Frame,
+};
+use solana_orderbook_monitor::{
+    analytics::{spread, Spread},
+    OrderbookEvent, OrderbookEventType, OrderbookMonitor,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, VecDeque};
+
+/// Events always kept for the tape
+pub const TAPE_LEN: usize = 500;
+/// Seconds over which event rates are averaged
+pub const RATE_WINDOW: i64 = 60;
+/// Seconds of filled volume per sparkline bar
+pub const SPARKLINE_BUCKET: i64 = 10;
+pub const SPARKLINE_BARS: usize = 60;
+
+const HISTORY_SECS: i64 = SPARKLINE_BUCKET * SPARKLINE_BARS as i64;
+const KINDS: [EventKind; 3] = [EventKind::Placed, EventKind::Filled, EventKind::Cancelled];
+
+fn kind_index(event_type: &OrderbookEventType) -> usize {
+    match event_type {
+        OrderbookEventType::OrderPlaced => 0,
+        OrderbookEventType::OrderFilled => 1,
+        OrderbookEventType::OrderCancelled => 2,
+    }
+}
+
+/// Which market and event types are shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    /// `None` shows every market
+    pub market: Option<String>,
+    /// Whether placed, filled and cancelled events are shown, in that order
+    pub kinds: [bool; 3],
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter { market: None, kinds: [true; 3] }
+    }
+}
+
+impl Filter {
+    pub fn shows_market(&self, market_name: &str) -> bool {
+        self.market.as_deref().is_none_or(|m| m == market_name)
+    }
+
+    pub fn shows(&self, event: &OrderbookEvent) -> bool {
+        self.shows_market(&event.market_name) && self.kinds[kind_index(&event.event_type)]
+    }
+
+    fn describe(&self) -> String {
+        let kinds: Vec<&str> =
+            KINDS.iter().zip(self.kinds).filter(|(_, shown)| *shown).map(|(kind, _)| kind.as_str()).collect();
+        format!(
+            "{} / {}",
+            self.market.as_deref().unwrap_or("all markets"),
+            if kinds.is_empty() { "no events".to_string() } else { kinds.join(", ") }
+        )
+    }
+}
+
+/// Totals of one market since the dashboard started
+#[derive(Debug, Clone, Default)]
+struct MarketState {
+    last_price: Option<u64>,
+    /// Events per type (placed, filled, cancelled) and side (bid, ask)
+    counts: [[u64; 2]; 3],
+}
+
+/// One line of the markets table
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketRow {
+    pub market_name: String,
+    /// Price of the last fill
+    pub last_price: Option<u64>,
+    pub spread: Option<Spread>,
+    pub bid_ask_ratio: Option<f64>,
+    /// Events per minute over the last `RATE_WINDOW` seconds
+    pub rate: f64,
+}
+
+pub struct Dashboard {
+    monitor: Pubkey,
+    slot: u64,
+    event_count: u64,
+    paused: bool,
+    /// Events of the account already taken in
+    seen: SeenEvents,
+    history: VecDeque<OrderbookEvent>,
+    markets: BTreeMap<String, MarketState>,
+    spreads: BTreeMap<String, Spread>,
+    pub filter: Filter,
+    /// Last error of the account source, shown until the next update
+    pub status: Option<String>,
+}
+
+impl Dashboard {
+    pub fn new(monitor: Pubkey) -> Self {
+        Dashboard {
+            monitor,
+            slot: 0,
+            event_count: 0,
+            paused: false,
+            seen: SeenEvents::default(),
+            history: VecDeque::new(),
+            markets: BTreeMap::new(),
+            spreads: BTreeMap::new(),
+            filter: Filter::default(),
+            status: None,
+        }
+    }
+
+    /// Take in the account read at `slot`; returns the number of new events.
+    /// Reads not newer than the last one are ignored, and a recreated account
+    /// starts over.
+    pub fn update(&mut self, slot: u64, monitor: &OrderbookMonitor) -> usize {
+        if slot <= self.slot {
+            return 0;
+        }
+        let (recreated, new_events) = self.seen.advance(monitor);
+        if recreated {
+            let seen = std::mem::take(&mut self.seen);
+            *self = Dashboard { filter: self.filter.clone(), seen, ..Dashboard::new(self.monitor) };
+        }
+        self.slot = slot;
+        self.event_count = monitor.event_count;
+        self.paused = monitor.paused;
+        self.status = None;
+
+        for event in new_events {
+            let market = self.markets.entry(event.market_name.clone()).or_default();
+            if event.event_type == OrderbookEventType::OrderFilled {
+                market.last_price = Some(event.price);
+            }
+            market.counts[kind_index(&event.event_type)][usize::from(!event.is_bid)] += 1;
+            self.history.push_back(event.clone());
+        }
+        let newest = self.history.back().map_or(i64::MIN, |e| e.timestamp);
+        while self.history.len() > TAPE_LEN
+            && self.history.front().is_some_and(|e| e.timestamp < newest.saturating_sub(HISTORY_SECS))
+        {
+            self.history.pop_front();
+        }
+
+        for snapshot in &monitor.snapshots {
+            if let Some(spread) = spread(snapshot) {
+                self.spreads.insert(snapshot.market_name.clone(), spread);
+            }
+        }
+        new_events.len()
+    }
+
+    /// Shown events, newest first
+    pub fn tape(&self) -> impl Iterator<Item = &OrderbookEvent> {
+        self.history.iter().rev().filter(|e| self.filter.shows(e))
+    }
+
+    pub fn market_rows(&self, now: i64) -> Vec<MarketRow> {
+        self.markets
+            .iter()
+            .map(|(name, market)| {
+                let (bids, asks) = market
+                    .counts
+                    .iter()
+                    .zip(self.filter.kinds)
+                    .filter(|(_, shown)| *shown)
+                    .fold((0, 0), |(bids, asks), (counts, _)| (bids + counts[0], asks + counts[1]));
+                let recent = self
+                    .history
+                    .iter()
+                    .filter(|e| &e.market_name == name && self.filter.kinds[kind_index(&e.event_type)])
+                    .filter(|e| e.timestamp > now - RATE_WINDOW && e.timestamp <= now)
+                    .count();
+                MarketRow {
+                    market_name: name.clone(),
+                    last_price: market.last_price,
+                    spread: self.spreads.get(name).copied(),
+                    bid_ask_ratio: (asks != 0).then(|| bids as f64 / asks as f64),
+                    rate: recent as f64 * 60.0 / RATE_WINDOW as f64,
+                }
+            })
+            .collect()
+    }
+
+    /// Filled size of the shown markets per `SPARKLINE_BUCKET` seconds, oldest first
+    pub fn volume_bars(&self, now: i64) -> Vec<u64> {
+        let mut bars = vec![0u64; SPARKLINE_BARS];
+        let end = now.div_euclid(SPARKLINE_BUCKET);
+        for event in &self.history {
+            if event.event_type != OrderbookEventType::OrderFilled || !self.filter.shows_market(&event.market_name) {
+                continue;
+            }
+            let age = end - event.timestamp.div_euclid(SPARKLINE_BUCKET);
+            if (0..SPARKLINE_BARS as i64).contains(&age) {
+                let bar = &mut bars[SPARKLINE_BARS - 1 - age as usize];
+                *bar = bar.saturating_add(event.size);
+            }
+        }
+        bars
+    }
+
+    /// Apply a key press; returns false when the dashboard should quit.
+    ///
+    /// Tab and the arrow keys step through the markets, `p`, `f` and `c`
+    /// toggle placed, filled and cancelled events and `a` shows everything.
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Tab | KeyCode::Right => self.step_market(true),
+            KeyCode::BackTab | KeyCode::Left => self.step_market(false),
+            KeyCode::Char('p') => self.filter.kinds[0] ^= true,
+            KeyCode::Char('f') => self.filter.kinds[1] ^= true,
+            KeyCode::Char('c') => self.filter.kinds[2] ^= true,
+            KeyCode::Char('a') => self.filter = Filter::default(),
+            _ => {}
+        }
+        true
+    }
+
+    /// Cycle through all markets, then every market at once
+    fn step_market(&mut self, forward: bool) {
+        let names: Vec<&String> = self.markets.keys().collect();
+        let position = self.filter.market.as_ref().and_then(|m| names.iter().position(|n| *n == m));
+        let next = match (position, forward) {
+            (None, true) => names.first(),
+            (None, false) => names.last(),
+            (Some(i), true) => names.get(i + 1),
+            (Some(i), false) => i.checked_sub(1).and_then(|i| names.get(i)),
+        };
+        self.filter.market = next.map(|n| n.to_string());
+    }
+
+    pub fn render(&self, frame: &mut Frame, now: i64) {
+        let [header, body, sparkline] =
+            Layout::vertical([Constraint::Length(6), Constraint::Min(5), Constraint::Length(6)]).areas(frame.area());
+        let [markets, tape] = Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(body);
+
+        let mut lines = vec![
+            Line::from(format!(
+                "Monitor {}  slot {}  {} events{}",
+                self.monitor,
+                self.slot,
+                self.event_count,
+                if self.paused { "  PAUSED" } else { "" }
+            )),
+            Line::from(format!("Showing {}", self.filter.describe())),
+            Line::from("tab/←/→ market  p/f/c placed/filled/cancelled  a all  q quit"),
+        ];
+        if let Some(error) = &self.status {
+            lines.insert(1, Line::from(format!("error: {}", error)).style(Style::default().fg(Color::Red)));
+        }
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title("Orderbook monitor")), header);
+
+        let selected = Style::default().add_modifier(Modifier::REVERSED);
+        let rows = self.market_rows(now).into_iter().map(|row| {
+            let style =
+                if self.filter.market.as_ref() == Some(&row.market_name) { selected } else { Style::default() };
+            Row::new(vec![
+                row.market_name,
+                row.last_price.map_or("-".to_string(), |p| p.to_string()),
+                row.spread.map_or("-".to_string(), |s| format!("{} ({:.1} bps)", s.absolute, s.bps)),
+                row.bid_ask_ratio.map_or("-".to_string(), |r| format!("{:.2}", r)),
+                format!("{:.1}", row.rate),
+            ])
+            .style(style)
+        });
+        let widths = [
+            Constraint::Length(17),
+            Constraint::Length(12),
+            Constraint::Min(16),
+            Constraint::Length(8),
+            Constraint::Length(8),
+        ];
+        frame.render_widget(
+            Table::new(rows, widths)
+                .header(Row::new(vec!["Market", "Last", "Spread", "Bid/ask", "Ev/min"]).style(Style::default().add_modifier(Modifier::BOLD)))
+                .block(Block::bordered().title("Markets")),
+            markets,
+        );
+
+        let items = self.tape().take(tape.height as usize).map(|event| {
+            let record = EventRecord::from(event);
+            ListItem::new(format!(
+                "{} {:<10} {:<9} {:<3} {} @ {}",
+                record.timestamp,
+                record.market_name,
+                record.event_type.as_str(),
+                if record.side == Side::Bid { "BID" } else { "ASK" },
+                record.size,
+                record.price
+            ))
+        });
+        frame.render_widget(List::new(items).block(Block::bordered().title("Events")), tape);
+
+        let bars = self.volume_bars(now);
+        frame.render_widget(
+            Sparkline::default()
+                .data(&bars)
+                .block(Block::bordered().title(format!("Filled volume per {}s", SPARKLINE_BUCKET))),
+            sparkline,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{backend::TestBackend, Terminal};
+    use solana_orderbook_monitor::{BookSnapshot, PriceLevel};
+    use OrderbookEventType::*;
+
+    fn event(timestamp: i64, market_name: &str, price: u64, is_bid: bool, event_type: OrderbookEventType) -> OrderbookEvent {
+        OrderbookEvent { timestamp, market_name: market_name.to_string(), price, size: 2, is_bid, event_type }
+    }
+
+    fn monitor(events: Vec<OrderbookEvent>) -> OrderbookMonitor {
+        let snapshot = BookSnapshot {
+            slot: 1,
+            timestamp: 100,
+            market_name: "SOL/USDC".to_string(),
+            event_index: 0,
+            bids: vec![PriceLevel { price: 99, size: 1 }],
+            asks: vec![PriceLevel { price: 101, size: 1 }],
+        };
+        OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: events.len() as u64,
+            events,
+            snapshots: vec![snapshot],
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn test_updates_and_filters() {
+        let mut events = vec![
+            event(100, "SOL/USDC", 99, true, OrderPlaced),
+            event(100, "SOL/USDC", 101, false, OrderPlaced),
+            event(130, "BTC/USDC", 50_000, false, OrderFilled),
+        ];
+        let mut dashboard = Dashboard::new(Pubkey::new_unique());
+        assert_eq!(dashboard.update(5, &monitor(events.clone())), 3);
+        // A lagging read is ignored
+        assert_eq!(dashboard.update(4, &monitor(vec![])), 0);
+        events.push(event(150, "SOL/USDC", 101, false, OrderFilled));
+        assert_eq!(dashboard.update(6, &monitor(events.clone())), 1);
+
+        let rows = dashboard.market_rows(165);
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[0].market_name.as_str(), rows[0].last_price), ("BTC/USDC", Some(50_000)));
+        let sol = &rows[1];
+        assert_eq!(sol.last_price, Some(101));
+        assert_eq!(sol.spread.map(|s| s.absolute), Some(2));
+        assert_eq!(sol.bid_ask_ratio, Some(0.5));
+        // The placements at 100 fell out of the last minute
+        assert_eq!(sol.rate, 1.0);
+
+        let bars = dashboard.volume_bars(165);
+        assert_eq!(bars[SPARKLINE_BARS - 2], 2);
+        assert_eq!(bars[SPARKLINE_BARS - 4], 2);
+        assert_eq!(bars.iter().sum::<u64>(), 4);
+
+        assert!(dashboard.handle_key(KeyCode::Tab));
+        assert!(dashboard.handle_key(KeyCode::Tab));
+        assert_eq!(dashboard.filter.market.as_deref(), Some("SOL/USDC"));
+        dashboard.handle_key(KeyCode::Char('p'));
+        let tape: Vec<i64> = dashboard.tape().map(|e| e.timestamp).collect();
+        assert_eq!(tape, vec![150]);
+        assert_eq!(dashboard.market_rows(165)[1].bid_ask_ratio, Some(0.0));
+        assert_eq!(dashboard.volume_bars(165).iter().sum::<u64>(), 2);
+        dashboard.handle_key(KeyCode::Tab);
+        assert_eq!(dashboard.filter.market, None);
+        dashboard.handle_key(KeyCode::Char('a'));
+        assert_eq!(dashboard.filter, Filter::default());
+        assert!(!dashboard.handle_key(KeyCode::Char('q')));
+
+        // A recreated account starts over but keeps the filter
+        dashboard.handle_key(KeyCode::Left);
+        assert_eq!(dashboard.update(7, &monitor(events[..1].to_vec())), 1);
+        assert_eq!(dashboard.tape().count(), 1);
+        assert_eq!(dashboard.filter.market.as_deref(), Some("SOL/USDC"));
+    }
+
+    #[test]
+    fn test_render() {
+        let mut dashboard = Dashboard::new(Pubkey::new_unique());
+        dashboard.update(1, &monitor(vec![event(100, "SOL/USDC", 101, false, OrderFilled)]));
+        dashboard.status = Some("RPC unavailable".to_string());
+
+        let mut terminal = Terminal::new(TestBackend::new(140, 20)).unwrap();
+        terminal.draw(|frame| dashboard.render(frame, 100)).unwrap();
+        let screen: String = terminal.backend().buffer().content.iter().map(|cell| cell.symbol()).collect();
+        for text in ["error: RPC unavailable", "SOL/USDC", "2 (200.0 bps)", "filled", "101"] {
+            assert!(screen.contains(text), "{:?} not rendered", text);
+        }
+    }
+}
@@ -0,0 +1,647 @@
+//! Serializable views of a monitor account.
+//!
+//! `EventRecord` is also the input format of `orderbook-monitor record-batch`,
+//! so exported events can be recorded again on another monitor. The
+//! timestamp is ignored on input; the program stamps events itself.
+//!
+//! `EventWriter` writes events one at a time as CSV, JSON Lines or Parquet,
+//! so long histories can be streamed from the indexer database without
+//! holding them in memory. Parquet output is buffered one row group at a time.
+
+use borsh::BorshSerialize;
+use clap::ValueEnum;
+use parquet::{
+    basic::Compression,
+    data_type::{ByteArray, ByteArrayType, Int64Type},
+    errors::ParquetError,
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+use serde::{Deserialize, Serialize};
+use solana_orderbook_monitor::{
+    candles::{atr, bollinger, closes, ema, rsi, sma, Candle},
+    microstructure::{FlowAnalyzer, FlowBar},
+    BookSnapshot, NewEvent, OrderbookEvent, OrderbookEventType, OrderbookMonitor,
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    sync::Arc,
+};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+/// Events per Parquet row group, and so the most events held in memory
+pub const PARQUET_ROW_GROUP_SIZE: usize = 65_536;
+
+const PARQUET_SCHEMA: &str = "
+message event {
+    REQUIRED INT64 timestamp;
+    REQUIRED BYTE_ARRAY market_name (UTF8);
+    REQUIRED INT64 price (INTEGER(64, false));
+    REQUIRED INT64 size (INTEGER(64, false));
+    REQUIRED BYTE_ARRAY side (UTF8);
+    REQUIRED BYTE_ARRAY event_type (UTF8);
+}
+";
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] ParquetError),
+}
+
+#[derive(Serialize, Deserialize, ValueEnum, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+#[derive(Serialize, Deserialize, ValueEnum, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Placed,
+    Filled,
+    Cancelled,
+}
+
+impl Side {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Bid => "bid",
+            Side::Ask => "ask",
+        }
+    }
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Placed => "placed",
+            EventKind::Filled => "filled",
+            EventKind::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl From<&OrderbookEventType> for EventKind {
+    fn from(event_type: &OrderbookEventType) -> Self {
+        match event_type {
+            OrderbookEventType::OrderPlaced => EventKind::Placed,
+            OrderbookEventType::OrderFilled => EventKind::Filled,
+            OrderbookEventType::OrderCancelled => EventKind::Cancelled,
+        }
+    }
+}
+
+impl From<EventKind> for OrderbookEventType {
+    fn from(kind: EventKind) -> Self {
+        match kind {
+            EventKind::Placed => OrderbookEventType::OrderPlaced,
+            EventKind::Filled => OrderbookEventType::OrderFilled,
+            EventKind::Cancelled => OrderbookEventType::OrderCancelled,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct EventRecord {
+    #[serde(default)]
+    pub timestamp: i64,
+    pub market_name: String,
+    pub price: u64,
+    pub size: u64,
+    pub side: Side,
+    pub event_type: EventKind,
+}
+
+impl From<&OrderbookEvent> for EventRecord {
+    fn from(event: &OrderbookEvent) -> Self {
+        EventRecord {
+            timestamp: event.timestamp,
+            market_name: event.market_name.clone(),
+            price: event.price,
+            size: event.size,
+            side: if event.is_bid { Side::Bid } else { Side::Ask },
+            event_type: (&event.event_type).into(),
+        }
+    }
+}
+
+impl From<EventRecord> for OrderbookEvent {
+    fn from(record: EventRecord) -> Self {
+        OrderbookEvent {
+            timestamp: record.timestamp,
+            market_name: record.market_name,
+            price: record.price,
+            size: record.size,
+            is_bid: record.side == Side::Bid,
+            event_type: record.event_type.into(),
+        }
+    }
+}
+
+impl From<EventRecord> for NewEvent {
+    fn from(record: EventRecord) -> Self {
+        NewEvent {
+            market_name: record.market_name,
+            price: record.price,
+            size: record.size,
+            is_bid: record.side == Side::Bid,
+            event_type: record.event_type.into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct LevelRecord {
+    pub price: u64,
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotRecord {
+    pub slot: u64,
+    pub timestamp: i64,
+    pub market_name: String,
+    pub event_index: u64,
+    pub bids: Vec<LevelRecord>,
+    pub asks: Vec<LevelRecord>,
+}
+
+impl From<&BookSnapshot> for SnapshotRecord {
+    fn from(snapshot: &BookSnapshot) -> Self {
+        let levels = |levels: &[solana_orderbook_monitor::PriceLevel]| {
+            levels.iter().map(|l| LevelRecord { price: l.price, size: l.size }).collect()
+        };
+        SnapshotRecord {
+            slot: snapshot.slot,
+            timestamp: snapshot.timestamp,
+            market_name: snapshot.market_name.clone(),
+            event_index: snapshot.event_index,
+            bids: levels(&snapshot.bids),
+            asks: levels(&snapshot.asks),
+        }
+    }
+}
+
+/// Everything recorded in one monitor account
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MonitorExport {
+    pub authority: String,
+    pub event_count: u64,
+    pub events: Vec<EventRecord>,
+    pub snapshots: Vec<SnapshotRecord>,
+}
+
+impl From<&OrderbookMonitor> for MonitorExport {
+    fn from(monitor: &OrderbookMonitor) -> Self {
+        MonitorExport {
+            authority: monitor.authority.to_string(),
+            event_count: monitor.event_count,
+            events: monitor.events.iter().map(EventRecord::from).collect(),
+            snapshots: monitor.snapshots.iter().map(SnapshotRecord::from).collect(),
+        }
+    }
+}
+
+/// Output formats of `EventWriter`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Jsonl,
+    Parquet,
+}
+
+/// Output formats of candle and microstructure rows, which have no Parquet schema
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowFormat {
+    Csv,
+    Jsonl,
+}
+
+/// Which events to export; `from` is inclusive and `to` exclusive, both unix timestamps
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    pub market: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &EventRecord) -> bool {
+        self.market.as_ref().is_none_or(|market| *market == event.market_name)
+            && self.from.is_none_or(|from| event.timestamp >= from)
+            && self.to.is_none_or(|to| event.timestamp < to)
+    }
+}
+
+enum Sink<W: Write + Send> {
+    Csv(csv::Writer<W>),
+    Jsonl(W),
+    Parquet { writer: SerializedFileWriter<W>, rows: Vec<EventRecord> },
+}
+
+/// Streams events to `W` in one of the export formats
+pub struct EventWriter<W: Write + Send> {
+    sink: Sink<W>,
+    written: u64,
+}
+
+impl<W: Write + Send> EventWriter<W> {
+    pub fn new(format: Format, out: W) -> Result<Self, ExportError> {
+        let sink = match format {
+            Format::Csv => Sink::Csv(csv::Writer::from_writer(out)),
+            Format::Jsonl => Sink::Jsonl(out),
+            Format::Parquet => {
+                let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                Sink::Parquet {
+                    writer: SerializedFileWriter::new(out, schema, Arc::new(properties))?,
+                    rows: Vec::new(),
+                }
+            }
+        };
+        Ok(EventWriter { sink, written: 0 })
+    }
+
+    pub fn write(&mut self, event: &EventRecord) -> Result<(), ExportError> {
+        match &mut self.sink {
+            Sink::Csv(writer) => writer.serialize(event)?,
+            Sink::Jsonl(out) => {
+                serde_json::to_writer(&mut *out, event)?;
+                writeln!(out)?;
+            }
+            Sink::Parquet { writer, rows } => {
+                rows.push(event.clone());
+                if rows.len() == PARQUET_ROW_GROUP_SIZE {
+                    write_row_group(writer, rows)?;
+                }
+            }
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Flush everything still buffered; returns the number of events written
+    pub fn finish(self) -> Result<u64, ExportError> {
+        match self.sink {
+            Sink::Csv(mut writer) => writer.flush()?,
+            Sink::Jsonl(mut out) => out.flush()?,
+            Sink::Parquet { mut writer, mut rows } => {
+                if !rows.is_empty() {
+                    write_row_group(&mut writer, &mut rows)?;
+                }
+                writer.into_inner()?.flush()?;
+            }
+        }
+        Ok(self.written)
+    }
+}
+
+/// Write `rows` as one row group, in `PARQUET_SCHEMA` column order, and clear them
+fn write_row_group<W: Write + Send>(
+    writer: &mut SerializedFileWriter<W>,
+    rows: &mut Vec<EventRecord>,
+) -> Result<(), ExportError> {
+    let int64 = |f: fn(&EventRecord) -> i64| rows.iter().map(f).collect::<Vec<_>>();
+    let text = |f: fn(&EventRecord) -> &str| rows.iter().map(|r| ByteArray::from(f(r))).collect::<Vec<_>>();
+
+    let mut group = writer.next_row_group()?;
+    let mut column = 0;
+    while let Some(mut writer) = group.next_column()? {
+        match column {
+            0 => writer.typed::<Int64Type>().write_batch(&int64(|r| r.timestamp), None, None)?,
+            1 => writer.typed::<ByteArrayType>().write_batch(&text(|r| &r.market_name), None, None)?,
+            // Unsigned 64-bit columns are stored in INT64 with the same bits
+            2 => writer.typed::<Int64Type>().write_batch(&int64(|r| r.price as i64), None, None)?,
+            3 => writer.typed::<Int64Type>().write_batch(&int64(|r| r.size as i64), None, None)?,
+            4 => writer.typed::<ByteArrayType>().write_batch(&text(|r| r.side.as_str()), None, None)?,
+            _ => writer.typed::<ByteArrayType>().write_batch(&text(|r| r.event_type.as_str()), None, None)?,
+        };
+        writer.close()?;
+        column += 1;
+    }
+    group.close()?;
+    rows.clear();
+    Ok(())
+}
+
+/// A bar with the indicators computed up to it
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct CandleRecord {
+    pub start: i64,
+    pub open: u64,
+    pub high: u64,
+    pub low: u64,
+    pub close: u64,
+    pub volume: u64,
+    pub trades: u64,
+    pub sma: Option<f64>,
+    pub ema: Option<f64>,
+    pub rsi: Option<f64>,
+    pub bollinger_lower: Option<f64>,
+    pub bollinger_upper: Option<f64>,
+    pub atr: Option<f64>,
+}
+
+/// Standard deviations between the middle and the outer Bollinger bands
+pub const BOLLINGER_WIDTH: f64 = 2.0;
+
+/// Attach every indicator, computed over `period` bars, to `candles`
+pub fn candle_records(candles: &[Candle], period: usize) -> Vec<CandleRecord> {
+    let closes = closes(candles);
+    let (sma, ema, rsi) = (sma(&closes, period), ema(&closes, period), rsi(&closes, period));
+    let bands = bollinger(&closes, period, BOLLINGER_WIDTH);
+    let atr = atr(candles, period);
+    candles
+        .iter()
+        .enumerate()
+        .map(|(i, c)| CandleRecord {
+            start: c.start,
+            open: c.open,
+            high: c.high,
+            low: c.low,
+            close: c.close,
+            volume: c.volume,
+            trades: c.trades,
+            sma: sma[i],
+            ema: ema[i],
+            rsi: rsi[i],
+            bollinger_lower: bands[i].map(|b| b.lower),
+            bollinger_upper: bands[i].map(|b| b.upper),
+            atr: atr[i],
+        })
+        .collect()
+}
+
+/// Write candle rows as CSV or JSON Lines
+pub fn write_candles(format: RowFormat, records: &[CandleRecord], out: impl Write) -> Result<(), ExportError> {
+    write_rows(format, records, out)
+}
+
+/// Microstructure metrics of one market and bar, flattened for CSV
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FlowRecord {
+    pub market_name: String,
+    pub start: i64,
+    pub trades: u64,
+    pub buy_volume: u64,
+    pub sell_volume: u64,
+    pub unclassified_volume: u64,
+    pub signed_volume: i64,
+    pub tick_signed_volume: i64,
+    pub agreement: Option<f64>,
+    pub ofi: i64,
+    pub kyle_lambda: Option<f64>,
+    pub bid_filled: u64,
+    pub bid_cancelled: u64,
+    pub bid_depletion_rate: f64,
+    pub ask_filled: u64,
+    pub ask_cancelled: u64,
+    pub ask_depletion_rate: f64,
+}
+
+impl FlowRecord {
+    /// Signed sums saturate at the bounds of an `i64`
+    pub fn new(market_name: &str, bar: &FlowBar) -> Self {
+        let clamp = |value: i128| value.clamp(i64::MIN.into(), i64::MAX.into()) as i64;
+        FlowRecord {
+            market_name: market_name.to_string(),
+            start: bar.start,
+            trades: bar.trades,
+            buy_volume: bar.buy_volume,
+            sell_volume: bar.sell_volume,
+            unclassified_volume: bar.unclassified_volume,
+            signed_volume: clamp(bar.signed_volume),
+            tick_signed_volume: clamp(bar.tick_signed_volume),
+            agreement: bar.agreement,
+            ofi: clamp(bar.ofi),
+            kyle_lambda: bar.kyle_lambda,
+            bid_filled: bar.bid_depletion.filled,
+            bid_cancelled: bar.bid_depletion.cancelled,
+            bid_depletion_rate: bar.bid_depletion.rate,
+            ask_filled: bar.ask_depletion.filled,
+            ask_cancelled: bar.ask_depletion.cancelled,
+            ask_depletion_rate: bar.ask_depletion.rate,
+        }
+    }
+}
+
+/// Write microstructure rows as CSV or JSON Lines
+pub fn write_flow(format: RowFormat, records: &[FlowRecord], out: impl Write) -> Result<(), ExportError> {
+    write_rows(format, records, out)
+}
+
+fn write_rows<T: Serialize>(format: RowFormat, records: &[T], mut out: impl Write) -> Result<(), ExportError> {
+    match format {
+        RowFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for record in records {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+        RowFormat::Jsonl => {
+            for record in records {
+                serde_json::to_writer(&mut out, record)?;
+                writeln!(out)?;
+            }
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the monitor as pretty-printed JSON
+pub fn write_json(monitor: &OrderbookMonitor, mut out: impl Write) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut out, &MonitorExport::from(monitor))?;
+    writeln!(out)
+}
+
+/// Write the summary `orderbook-monitor inspect` prints: the account, its
+/// events per market and the `limit` most recent events
+pub fn write_summary(
+    address: &Pubkey,
+    account: &Account,
+    monitor: &OrderbookMonitor,
+    limit: usize,
+    mut out: impl Write,
+) -> io::Result<()> {
+    writeln!(out, "Monitor: {}", address)?;
+    writeln!(out, "Authority: {}", monitor.authority)?;
+    writeln!(out, "Paused: {}", monitor.paused)?;
+    writeln!(out, "Account size: {} bytes, {} lamports", account.data.len(), account.lamports)?;
+    writeln!(out, "Used: {} bytes", monitor.try_to_vec()?.len())?;
+    writeln!(out, "Events: {}", monitor.event_count)?;
+    writeln!(out, "Snapshots: {}", monitor.snapshots.len())?;
+
+    let mut markets = BTreeMap::new();
+    for event in &monitor.events {
+        *markets.entry(event.market_name.as_str()).or_insert(0) += 1;
+    }
+    for (market, count) in markets {
+        writeln!(out, "  {}: {} events", market, count)?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "Recent events:")?;
+    for event in monitor.events.iter().rev().take(limit) {
+        let record = EventRecord::from(event);
+        writeln!(
+            out,
+            "  {} {} {:?} {:?} {} @ {}",
+            record.timestamp, record.market_name, record.side, record.event_type, record.size, record.price
+        )?;
+    }
+    Ok(())
+}
+
+/// Microstructure rows of every market's bars, by market and then time
+pub fn flow_records(analyzers: &BTreeMap<String, FlowAnalyzer>) -> Vec<FlowRecord> {
+    analyzers
+        .iter()
+        .flat_map(|(market, analyzer)| analyzer.bars().into_iter().map(move |bar| FlowRecord::new(market, &bar)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    #[test]
+    fn test_exported_events_record_again() {
+        let monitor = OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: 1,
+            events: vec![OrderbookEvent {
+                timestamp: 1_700_000_000,
+                market_name: "SOL/USDC".to_string(),
+                price: 25_000,
+                size: 10,
+                is_bid: false,
+                event_type: OrderbookEventType::OrderFilled,
+            }],
+            snapshots: vec![],
+            paused: false,
+        };
+        let mut json = Vec::new();
+        write_json(&monitor, &mut json).unwrap();
+
+        let export: MonitorExport = serde_json::from_slice(&json).unwrap();
+        assert_eq!(export.events[0].side, Side::Ask);
+        assert_eq!(export.events[0].event_type, EventKind::Filled);
+
+        // Events without a timestamp are accepted as batch input
+        let input = r#"[{"market_name":"SOL/USDC","price":25000,"size":10,"side":"ask","event_type":"filled"}]"#;
+        let records: Vec<EventRecord> = serde_json::from_str(input).unwrap();
+        let event = NewEvent::from(records[0].clone());
+        let exported = NewEvent::from(export.events[0].clone());
+        assert_eq!(event, exported);
+    }
+
+    #[test]
+    fn test_event_writer_formats() {
+        let record = |timestamp, market_name: &str, side| EventRecord {
+            timestamp,
+            market_name: market_name.to_string(),
+            price: 25_000,
+            size: 10,
+            side,
+            event_type: EventKind::Placed,
+        };
+        let events = [
+            record(100, "SOL/USDC", Side::Bid),
+            record(150, "BTC/USDC", Side::Ask),
+            record(200, "SOL/USDC", Side::Ask),
+        ];
+        let filter = EventFilter { market: Some("SOL/USDC".to_string()), from: Some(100), to: Some(200) };
+        let selected: Vec<_> = events.iter().filter(|e| filter.matches(e)).collect();
+        assert_eq!(selected, vec![&events[0]]);
+
+        let write = |format| {
+            let mut out = Vec::new();
+            let mut writer = EventWriter::new(format, &mut out).unwrap();
+            for event in &events {
+                writer.write(event).unwrap();
+            }
+            assert_eq!(writer.finish().unwrap(), 3);
+            out
+        };
+
+        let csv = String::from_utf8(write(Format::Csv)).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("timestamp,market_name,price,size,side,event_type"));
+        assert_eq!(lines.next(), Some("100,SOL/USDC,25000,10,bid,placed"));
+
+        let jsonl = String::from_utf8(write(Format::Jsonl)).unwrap();
+        let parsed: Vec<EventRecord> = jsonl.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(parsed, events);
+
+        let reader = SerializedFileReader::new(bytes::Bytes::from(write(Format::Parquet))).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 3);
+        assert_eq!(reader.metadata().file_metadata().schema_descr().column(1).name(), "market_name");
+    }
+
+    #[test]
+    fn test_candle_rows() {
+        let candle = |start, close| Candle { start, open: close, high: close, low: close, close, volume: 1, trades: 1 };
+        let records = candle_records(&[candle(0, 10), candle(60, 12), candle(120, 14)], 2);
+        assert_eq!(records[0].sma, None);
+        assert_eq!(records[1].sma, Some(11.0));
+        assert_eq!(records[2].rsi, Some(100.0));
+
+        let mut csv = Vec::new();
+        write_candles(RowFormat::Csv, &records[..2], &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "start,open,high,low,close,volume,trades,sma,ema,rsi,bollinger_lower,bollinger_upper,atr"
+        );
+        assert_eq!(lines[1], "0,10,10,10,10,1,1,,,,,,");
+        assert_eq!(lines[2], "60,12,12,12,12,1,1,11.0,11.0,,9.0,13.0,1.0");
+
+        // Rows have no Parquet schema, so the format is not offered
+        assert!(RowFormat::from_str("parquet", true).is_err());
+    }
+
+    #[test]
+    fn test_flow_rows() {
+        use solana_orderbook_monitor::microstructure::Depletion;
+        let bar = FlowBar {
+            start: 60,
+            trades: 2,
+            buy_volume: 5,
+            sell_volume: 3,
+            unclassified_volume: 0,
+            signed_volume: 2,
+            tick_signed_volume: i128::MIN,
+            agreement: Some(0.5),
+            ofi: -4,
+            kyle_lambda: None,
+            bid_depletion: Depletion { filled: 3, cancelled: 0, rate: 0.05 },
+            ask_depletion: Depletion::default(),
+        };
+        let records = [FlowRecord::new("SOL/USDC", &bar)];
+        assert_eq!(records[0].tick_signed_volume, i64::MIN);
+
+        let mut csv = Vec::new();
+        write_flow(RowFormat::Csv, &records, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.lines().next().unwrap().starts_with("market_name,start,trades,buy_volume,sell_volume"));
+        assert!(csv.lines().nth(1).unwrap().starts_with("SOL/USDC,60,2,5,3,0,2,"));
+    }
+}
@@ -0,0 +1,340 @@
+//! WebSocket push feed of recorded events, served by `orderbook-feed`.
+//!
+//! The feed follows the monitor account rather than the program logs: a
+//! batch is logged only as a count, so the account is the one place every
+//! event can be decoded from. Each event's sequence number is its index in
+//! the account's event list, the same `event_index` the indexer stores.
+//!
+//! Clients send JSON requests on `/ws`:
+//!
+//! ```json
+//! {"op": "subscribe", "markets": ["SOL/USDC"], "event_types": ["filled"], "from_seq": 120}
+//! {"op": "unsubscribe"}
+//! ```
+//!
+//! Empty `markets` or `event_types` match everything. Without `from_seq`
+//! only events landing after the subscription are sent; with it, matching
+//! events from that sequence number on are replayed first, so a client
+//! reconnects by passing one past the last `seq` it saw. Subscribing again
+//! replaces the subscription. The server answers with `subscribed` (carrying
+//! the `next_seq` to land), `event` (carrying `seq` and the `event`), `reset`
+//! when the account was recreated and sequence numbers restart at 0, and
+//! `error` for requests it could not read.
+
+use crate::{
+    export::{EventKind, EventRecord},
+    follow::SeenEvents,
+};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+    routing::get,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use solana_orderbook_monitor::OrderbookMonitor;
+use std::sync::{Arc, RwLock};
+use tokio::sync::watch;
+
+/// What a client receives
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FeedMessage {
+    Subscribed { next_seq: u64 },
+    Event { seq: u64, event: EventRecord },
+    Reset,
+    Error { message: String },
+}
+
+/// What a client sends
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum FeedRequest {
+    Subscribe(Subscription),
+    Unsubscribe,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Subscription {
+    #[serde(default)]
+    pub markets: Vec<String>,
+    #[serde(default)]
+    pub event_types: Vec<EventKind>,
+    #[serde(default)]
+    pub from_seq: Option<u64>,
+}
+
+impl Subscription {
+    pub fn matches(&self, event: &EventRecord) -> bool {
+        (self.markets.is_empty() || self.markets.contains(&event.market_name))
+            && (self.event_types.is_empty() || self.event_types.contains(&event.event_type))
+    }
+}
+
+/// Every event seen on the account, in sequence order. `epoch` counts the
+/// times the account was found recreated.
+#[derive(Default)]
+struct Log {
+    epoch: u64,
+    /// Slot of the newest read published
+    slot: u64,
+    events: Vec<EventRecord>,
+    seen: SeenEvents,
+}
+
+/// The events shared between the account source and the subscribers
+pub struct Feed {
+    log: RwLock<Log>,
+    /// Bumped on every publish that changed the log, to wake subscribers
+    changes: watch::Sender<u64>,
+}
+
+impl Default for Feed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Feed {
+    pub fn new() -> Self {
+        Feed { log: RwLock::new(Log::default()), changes: watch::channel(0).0 }
+    }
+
+    /// Sequence number the next event will get
+    pub fn next_seq(&self) -> u64 {
+        self.log.read().unwrap().events.len() as u64
+    }
+
+    /// Take the events of `monitor`, read at `slot`, not yet published and
+    /// wake the subscribers. Returns how many were new. Reads not newer than
+    /// the last published one are ignored, so a lagging RPC node cannot pass
+    /// for a recreated account. A recreated account restarts the log from its
+    /// events.
+    pub fn publish(&self, slot: u64, monitor: &OrderbookMonitor) -> usize {
+        let mut log = self.log.write().unwrap();
+        if slot <= log.slot {
+            return 0;
+        }
+        log.slot = slot;
+        let (recreated, new_events) = log.seen.advance(monitor);
+        if recreated {
+            log.epoch += 1;
+            log.events.clear();
+        }
+        log.events.extend(new_events.iter().map(EventRecord::from));
+        let added = new_events.len();
+        drop(log);
+        if recreated || added > 0 {
+            self.changes.send_modify(|version| *version += 1);
+        }
+        added
+    }
+
+    /// The epoch and the sequence number the next event will get
+    fn head(&self) -> (u64, u64) {
+        let log = self.log.read().unwrap();
+        (log.epoch, log.events.len() as u64)
+    }
+
+    /// The epoch and the events from `from` on
+    fn read_from(&self, from: u64) -> (u64, Vec<EventRecord>) {
+        let log = self.log.read().unwrap();
+        (log.epoch, log.events.get(from as usize..).map(<[_]>::to_vec).unwrap_or_default())
+    }
+}
+
+/// A client's subscription and where it has got to
+struct Cursor {
+    subscription: Subscription,
+    epoch: u64,
+    next_seq: u64,
+}
+
+/// Routes of the feed: `/ws` upgrades to a subscriber connection
+pub fn router(feed: Arc<Feed>) -> Router {
+    Router::new().route("/ws", get(upgrade)).with_state(feed)
+}
+
+async fn upgrade(State(feed): State<Arc<Feed>>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| session(socket, feed))
+}
+
+async fn send(socket: &mut WebSocket, message: &FeedMessage) -> bool {
+    let text = serde_json::to_string(message).expect("feed messages serialize");
+    socket.send(Message::Text(text)).await.is_ok()
+}
+
+/// Send `cursor` everything it has not seen yet
+async fn flush(socket: &mut WebSocket, feed: &Feed, cursor: &mut Cursor) -> bool {
+    let (epoch, _) = feed.head();
+    if epoch != cursor.epoch {
+        cursor.epoch = epoch;
+        cursor.next_seq = 0;
+        if !send(socket, &FeedMessage::Reset).await {
+            return false;
+        }
+    }
+    let (epoch, events) = feed.read_from(cursor.next_seq);
+    if epoch != cursor.epoch {
+        // Recreated again since; the next wake-up sends the reset
+        return true;
+    }
+    for (seq, event) in (cursor.next_seq..).zip(events) {
+        cursor.next_seq = seq + 1;
+        if cursor.subscription.matches(&event) && !send(socket, &FeedMessage::Event { seq, event }).await {
+            return false;
+        }
+    }
+    true
+}
+
+async fn session(mut socket: WebSocket, feed: Arc<Feed>) {
+    let mut changes = feed.changes.subscribe();
+    let mut cursor: Option<Cursor> = None;
+    loop {
+        changes.borrow_and_update();
+        if let Some(cursor) = &mut cursor {
+            if !flush(&mut socket, &feed, cursor).await {
+                return;
+            }
+        }
+        tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<FeedRequest>(&text) {
+                    Ok(FeedRequest::Subscribe(subscription)) => {
+                        let (epoch, next_seq) = feed.head();
+                        let from_seq = subscription.from_seq.unwrap_or(next_seq);
+                        cursor = Some(Cursor { subscription, epoch, next_seq: from_seq });
+                        if !send(&mut socket, &FeedMessage::Subscribed { next_seq }).await {
+                            return;
+                        }
+                    }
+                    Ok(FeedRequest::Unsubscribe) => cursor = None,
+                    Err(e) => {
+                        if !send(&mut socket, &FeedMessage::Error { message: e.to_string() }).await {
+                            return;
+                        }
+                    }
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+            changed = changes.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::Side;
+    use futures_util::{SinkExt, StreamExt};
+    use solana_orderbook_monitor::{OrderbookEvent, OrderbookEventType};
+    use solana_sdk::pubkey::Pubkey;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::{connect_async, tungstenite, MaybeTlsStream, WebSocketStream};
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    fn event(market_name: &str, price: u64, event_type: OrderbookEventType) -> OrderbookEvent {
+        OrderbookEvent { timestamp: 1_000, market_name: market_name.to_string(), price, size: 1, is_bid: true, event_type }
+    }
+
+    fn monitor(events: Vec<OrderbookEvent>) -> OrderbookMonitor {
+        OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: events.len() as u64,
+            events,
+            snapshots: vec![],
+            paused: false,
+        }
+    }
+
+    async fn connect(feed: Arc<Feed>) -> Client {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(feed)).await.unwrap() });
+        connect_async(format!("ws://{}/ws", address)).await.unwrap().0
+    }
+
+    async fn request(client: &mut Client, request: &str) {
+        client.send(tungstenite::Message::Text(request.to_string())).await.unwrap();
+    }
+
+    async fn receive(client: &mut Client) -> FeedMessage {
+        match client.next().await.unwrap().unwrap() {
+            tungstenite::Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected frame {:?}", other),
+        }
+    }
+
+    fn seq_of(message: FeedMessage) -> (u64, String, EventKind) {
+        match message {
+            FeedMessage::Event { seq, event } => {
+                assert_eq!(event.side, Side::Bid);
+                (seq, event.market_name, event.event_type)
+            }
+            other => panic!("expected an event, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_and_live_with_filters() {
+        use OrderbookEventType::*;
+        let mut events =
+            vec![event("SOL/USDC", 100, OrderPlaced), event("BTC/USDC", 50_000, OrderPlaced), event("SOL/USDC", 100, OrderFilled)];
+        let feed = Arc::new(Feed::new());
+        assert_eq!(feed.publish(1, &monitor(events.clone())), 3);
+        assert_eq!(feed.publish(2, &monitor(events.clone())), 0);
+
+        let mut replaying = connect(feed.clone()).await;
+        request(&mut replaying, r#"{"op": "subscribe", "markets": ["SOL/USDC"], "from_seq": 0}"#).await;
+        assert_eq!(receive(&mut replaying).await, FeedMessage::Subscribed { next_seq: 3 });
+        assert_eq!(seq_of(receive(&mut replaying).await), (0, "SOL/USDC".to_string(), EventKind::Placed));
+        assert_eq!(seq_of(receive(&mut replaying).await), (2, "SOL/USDC".to_string(), EventKind::Filled));
+
+        let mut live = connect(feed.clone()).await;
+        request(&mut live, r#"{"op": "subscribe", "event_types": ["filled"]}"#).await;
+        assert_eq!(receive(&mut live).await, FeedMessage::Subscribed { next_seq: 3 });
+
+        events.push(event("BTC/USDC", 50_010, OrderFilled));
+        events.push(event("SOL/USDC", 100, OrderCancelled));
+        assert_eq!(feed.publish(3, &monitor(events)), 2);
+        assert_eq!(seq_of(receive(&mut replaying).await), (4, "SOL/USDC".to_string(), EventKind::Cancelled));
+        assert_eq!(seq_of(receive(&mut live).await), (3, "BTC/USDC".to_string(), EventKind::Filled));
+    }
+
+    #[tokio::test]
+    async fn test_bad_request_and_recreated_account() {
+        use OrderbookEventType::*;
+        let feed = Arc::new(Feed::new());
+        let events = vec![event("SOL/USDC", 100, OrderPlaced), event("SOL/USDC", 101, OrderPlaced)];
+        feed.publish(5, &monitor(events.clone()));
+
+        let mut client = connect(feed.clone()).await;
+        request(&mut client, r#"{"op": "subscribe", "from_seq": "latest"}"#).await;
+        assert!(matches!(receive(&mut client).await, FeedMessage::Error { .. }));
+        request(&mut client, r#"{"op": "subscribe", "from_seq": 1}"#).await;
+        assert_eq!(receive(&mut client).await, FeedMessage::Subscribed { next_seq: 2 });
+        assert_eq!(seq_of(receive(&mut client).await).0, 1);
+
+        // A lagging read holding fewer events is not a recreated account
+        assert_eq!(feed.publish(4, &monitor(events[..1].to_vec())), 0);
+        assert_eq!(feed.publish(5, &monitor(vec![])), 0);
+        assert_eq!(feed.next_seq(), 2);
+
+        // A new account with a different first event restarts the sequence
+        assert_eq!(feed.publish(6, &monitor(vec![event("SOL/USDC", 90, OrderFilled)])), 1);
+        assert_eq!(feed.next_seq(), 1);
+        assert_eq!(receive(&mut client).await, FeedMessage::Reset);
+        assert_eq!(seq_of(receive(&mut client).await), (0, "SOL/USDC".to_string(), EventKind::Filled));
+    }
+}
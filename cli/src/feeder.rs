@@ -0,0 +1,311 @@
+//! Event-queue feeder: turns new events of a DEX market into `RecordEvents`
+//! instructions on a monitor account.
+//!
+//! The feeder remembers the next sequence number to record in a checkpoint
+//! file. Before a batch is sent, its signature is written to the checkpoint
+//! as pending; after a restart the pending signature is looked up on chain
+//! so a batch that landed is not recorded twice and one that expired is sent
+//! again.
+//!
+//! Events the program would reject are skipped rather than retried: each one
+//! is validated before batching, and a batch that still fails with a program
+//! error it would hit on every retry is stepped over.
+
+use crate::chain::{Chain, ChainError};
+use serde::{Deserialize, Serialize};
+use solana_orderbook_monitor::{
+    adapter::{AdapterError, MarketAdapter, SequencedEvent},
+    client::record_events,
+    NewEvent, OrderbookError,
+};
+use solana_sdk::{
+    pubkey::Pubkey,
+    instruction::InstructionError,
+    signature::{Keypair, Signature, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use thiserror::Error;
+
+/// Events per `RecordEvents` instruction, small enough to fit a transaction
+pub const DEFAULT_BATCH_SIZE: usize = 8;
+
+#[derive(Error, Debug)]
+pub enum FeederError {
+    #[error(transparent)]
+    Adapter(#[from] AdapterError),
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+    #[error("Checkpoint I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid checkpoint: {0}")]
+    InvalidCheckpoint(String),
+    #[error("Market has no event queue account")]
+    NoEventQueue,
+}
+
+/// A batch that was sent but not yet known to have landed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PendingBatch {
+    pub signature: String,
+    pub last_seq: u64,
+    pub last_valid_block_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Sequence number of the next event to record; `None` before the first batch
+    pub next_seq: Option<u64>,
+    pub pending: Option<PendingBatch>,
+}
+
+impl Checkpoint {
+    /// Load a checkpoint, starting fresh if the file does not exist
+    pub fn load(path: &Path) -> Result<Self, FeederError> {
+        match fs::read(path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(|e| FeederError::InvalidCheckpoint(e.to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Checkpoint::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the checkpoint through a temporary file so a crash never leaves it truncated
+    pub fn save(&self, path: &Path) -> Result<(), FeederError> {
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|e| FeederError::InvalidCheckpoint(e.to_string()))?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// An event the program refused, which the feeder skipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rejected {
+    pub seq_num: u64,
+    pub error: OrderbookError,
+}
+
+/// What one poll of the event queue did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PollOutcome {
+    /// Events recorded on the monitor
+    pub recorded: usize,
+    /// Events consumed from the queue before the feeder saw them
+    pub missed: u64,
+    /// Events skipped because they fail validation or their batch failed
+    pub rejected: Vec<Rejected>,
+    /// A previously sent batch is still in flight
+    pub waiting: bool,
+}
+
+/// The program error of a failed batch if sending it again cannot succeed.
+///
+/// Errors about the monitor's state, such as a full or paused account, are
+/// not included: the batch lands once the account is resized or resumed.
+fn batch_error(error: &TransactionError) -> Option<OrderbookError> {
+    // A batch transaction holds a single `RecordEvents` instruction
+    let TransactionError::InstructionError(0, InstructionError::Custom(code)) = error else {
+        return None;
+    };
+    OrderbookError::from_code(*code).filter(|e| {
+        matches!(
+            e,
+            OrderbookError::InvalidInstruction
+                | OrderbookError::MarketNameTooLong
+                | OrderbookError::InvalidMarketName
+                | OrderbookError::ZeroSize
+                | OrderbookError::ZeroPrice
+                | OrderbookError::EmptyBatch
+                | OrderbookError::InvalidMarket
+        )
+    })
+}
+
+fn new_event(event: &SequencedEvent) -> NewEvent {
+    NewEvent {
+        market_name: event.event.market_name.clone(),
+        price: event.event.price,
+        size: event.event.size,
+        is_bid: event.event.is_bid,
+        event_type: event.event.event_type.clone(),
+    }
+}
+
+pub struct Feeder {
+    adapter: Box<dyn MarketAdapter>,
+    program_id: Pubkey,
+    monitor: Pubkey,
+    payer: Keypair,
+    authority: Keypair,
+    checkpoint_path: PathBuf,
+    checkpoint: Checkpoint,
+    batch_size: usize,
+}
+
+impl Feeder {
+    /// Create a feeder, resuming from the checkpoint at `checkpoint_path`.
+    ///
+    /// `authority` signs for the monitor account and may be the payer.
+    pub fn new(
+        adapter: Box<dyn MarketAdapter>,
+        program_id: Pubkey,
+        monitor: Pubkey,
+        payer: Keypair,
+        authority: Keypair,
+        checkpoint_path: PathBuf,
+    ) -> Result<Self, FeederError> {
+        if adapter.metadata().event_queue.is_none() {
+            return Err(FeederError::NoEventQueue);
+        }
+        let checkpoint = Checkpoint::load(&checkpoint_path)?;
+        Ok(Feeder {
+            adapter,
+            program_id,
+            monitor,
+            payer,
+            authority,
+            checkpoint_path,
+            checkpoint,
+            batch_size: DEFAULT_BATCH_SIZE,
+        })
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn checkpoint(&self) -> &Checkpoint {
+        &self.checkpoint
+    }
+
+    /// Read the event queue once and record every event not recorded yet
+    pub async fn poll_once<C: Chain>(&mut self, chain: &mut C) -> Result<PollOutcome, FeederError> {
+        let mut outcome = PollOutcome::default();
+        if !self.resolve_pending(chain).await? {
+            outcome.waiting = true;
+            return Ok(outcome);
+        }
+
+        let queue = self.adapter.metadata().event_queue.ok_or(FeederError::NoEventQueue)?;
+        let data = chain.account_data(&queue).await?;
+        let range = self.adapter.seq_range(&data)?;
+        let mut events = Vec::new();
+        for event in self.adapter.decode_events(&data)? {
+            if self.checkpoint.next_seq.is_some_and(|next| event.seq_num < next) {
+                continue;
+            }
+            match new_event(&event).validate() {
+                Ok(()) => events.push(event),
+                Err(error) => outcome.rejected.push(Rejected { seq_num: event.seq_num, error }),
+            }
+        }
+
+        if let Some(next) = self.checkpoint.next_seq {
+            outcome.missed = range.start.saturating_sub(next);
+        }
+
+        for batch in events.chunks(self.batch_size) {
+            match self.send_batch(chain, batch).await? {
+                None => outcome.recorded += batch.len(),
+                Some(error) => {
+                    outcome.rejected.extend(batch.iter().map(|e| Rejected { seq_num: e.seq_num, error }))
+                }
+            }
+        }
+
+        // Move past queue entries the adapter does not turn into events
+        if self.checkpoint.next_seq.is_none_or(|next| next < range.end) {
+            self.checkpoint.next_seq = Some(range.end);
+            self.checkpoint.save(&self.checkpoint_path)?;
+        }
+        Ok(outcome)
+    }
+
+    // Settle a batch left pending by a previous poll or run. Returns false
+    // while the batch may still land.
+    async fn resolve_pending<C: Chain>(&mut self, chain: &mut C) -> Result<bool, FeederError> {
+        let Some(pending) = self.checkpoint.pending.clone() else {
+            return Ok(true);
+        };
+        let signature = Signature::from_str(&pending.signature)
+            .map_err(|e| FeederError::InvalidCheckpoint(e.to_string()))?;
+
+        match chain.signature_status(&signature).await? {
+            Some(Ok(())) => {
+                self.checkpoint.next_seq = Some(pending.last_seq + 1);
+            }
+            // Sending it again would fail the same way
+            Some(Err(e)) if batch_error(&e).is_some() => {
+                self.checkpoint.next_seq = Some(pending.last_seq + 1);
+            }
+            Some(Err(_)) => {}
+            None if chain.block_height().await? <= pending.last_valid_block_height => {
+                return Ok(false);
+            }
+            None => {}
+        }
+        self.checkpoint.pending = None;
+        self.checkpoint.save(&self.checkpoint_path)?;
+        Ok(true)
+    }
+
+    // Send one batch, moving the checkpoint past it once it landed. Returns
+    // the program error of a batch that can never land, which is skipped.
+    async fn send_batch<C: Chain>(
+        &mut self,
+        chain: &mut C,
+        batch: &[SequencedEvent],
+    ) -> Result<Option<OrderbookError>, FeederError> {
+        let last_seq = batch.last().map(|e| e.seq_num).unwrap_or_default();
+        let events = batch.iter().map(new_event).collect();
+        let instruction = record_events(
+            &self.program_id,
+            &self.monitor,
+            &self.adapter.metadata().address,
+            &self.authority.pubkey(),
+            events,
+        );
+
+        let (blockhash, last_valid_block_height) = chain.latest_blockhash().await?;
+        let mut signers: Vec<&Keypair> = vec![&self.payer];
+        if self.authority.pubkey() != self.payer.pubkey() {
+            signers.push(&self.authority);
+        }
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.payer.pubkey()),
+            &signers,
+            blockhash,
+        );
+
+        // Persist the signature first so a crash mid-send can be reconciled
+        self.checkpoint.pending = Some(PendingBatch {
+            signature: transaction.signatures[0].to_string(),
+            last_seq,
+            last_valid_block_height,
+        });
+        self.checkpoint.save(&self.checkpoint_path)?;
+
+        let rejected = match chain.send_and_confirm(&transaction).await {
+            Ok(()) => None,
+            Err(ChainError::Transaction(e)) => match batch_error(&e) {
+                Some(error) => Some(error),
+                None => return Err(ChainError::Transaction(e).into()),
+            },
+            Err(e) => return Err(e.into()),
+        };
+
+        self.checkpoint.next_seq = Some(last_seq + 1);
+        self.checkpoint.pending = None;
+        self.checkpoint.save(&self.checkpoint_path)?;
+        Ok(rejected)
+    }
+}
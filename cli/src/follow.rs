@@ -0,0 +1,203 @@
+//! Following one account's data as it changes, by polling or over the RPC
+//! websocket, for the long-running viewers (`orderbook-dashboard`,
+//! `orderbook-feed`). Reads arrive on a channel from a background thread.
+//!
+//! A dropped subscription is reported on the channel and reopened after a
+//! delay that doubles up to `MAX_BACKOFF`. Each time it opens, the account is
+//! also read once over HTTP, so changes made while it was down are not missed.
+//!
+//! `SeenEvents` tracks how far a follower has taken in a monitor's events
+//! and tells a grown account from one closed and recreated at its address.
+
+use crate::export::EventRecord;
+use solana_account_decoder::UiAccountEncoding;
+use solana_cli_config::Config;
+use solana_client::{pubsub_client::PubsubClient, rpc_client::RpcClient, rpc_config::RpcAccountInfoConfig};
+use solana_orderbook_monitor::{OrderbookEvent, OrderbookMonitor};
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// First delay before reopening a dropped subscription
+pub const MIN_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest delay between attempts to reopen a subscription
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Seconds since the Unix epoch on this machine's clock
+pub fn unix_now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+/// How many of a monitor's events a follower has taken in
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SeenEvents {
+    count: usize,
+    /// The last event taken in, to recognise the account it came from
+    last: Option<EventRecord>,
+}
+
+impl SeenEvents {
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The events of `monitor` not seen yet, and whether the account was
+    /// recreated since the last call: it holds fewer events than seen, or a
+    /// different event where the last seen one was. All the events of a
+    /// recreated account are new.
+    pub fn advance<'a>(&mut self, monitor: &'a OrderbookMonitor) -> (bool, &'a [OrderbookEvent]) {
+        let recreated = match &self.last {
+            Some(last) => monitor.events.get(self.count - 1).is_none_or(|event| EventRecord::from(event) != *last),
+            None => false,
+        };
+        if recreated {
+            *self = SeenEvents::default();
+        }
+        let new_events = &monitor.events[self.count..];
+        self.count = monitor.events.len();
+        self.last = monitor.events.last().map(EventRecord::from).or(self.last.take());
+        (recreated, new_events)
+    }
+}
+
+/// Account data and the slot it was read at, or the error reading it
+pub type Update = Result<(u64, Vec<u8>), String>;
+
+/// How to follow the account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Poll(Duration),
+    Subscribe,
+}
+
+/// Start following `address` on the cluster at `url`. The thread stops once
+/// the receiver is dropped.
+pub fn follow(url: String, commitment: CommitmentConfig, address: Pubkey, mode: Mode) -> Receiver<Update> {
+    let (sender, updates) = mpsc::channel();
+    match mode {
+        Mode::Poll(interval) => {
+            let client = RpcClient::new_with_commitment(url, commitment);
+            thread::spawn(move || poll(client, address, interval, sender));
+        }
+        Mode::Subscribe => {
+            let ws_url = Config::compute_websocket_url(&url);
+            let client = RpcClient::new_with_commitment(url, commitment);
+            thread::spawn(move || subscribe(client, &ws_url, address, sender));
+        }
+    }
+    updates
+}
+
+fn read(client: &RpcClient, address: &Pubkey) -> Update {
+    match client.get_account_with_commitment(address, client.commitment()) {
+        Ok(response) => match response.value {
+            Some(account) => Ok((response.context.slot, account.data)),
+            None => Err(format!("Account {} not found", address)),
+        },
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn poll(client: RpcClient, address: Pubkey, interval: Duration, updates: Sender<Update>) {
+    loop {
+        if updates.send(read(&client, &address)).is_err() {
+            return;
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn subscribe(client: RpcClient, url: &str, address: Pubkey, updates: Sender<Update>) {
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(client.commitment()),
+        ..RpcAccountInfoConfig::default()
+    };
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        let closed = match PubsubClient::account_subscribe(url, &address, Some(config.clone())) {
+            Ok((_subscription, receiver)) => {
+                if updates.send(read(&client, &address)).is_err() {
+                    return;
+                }
+                let mut notified = false;
+                for response in receiver {
+                    notified = true;
+                    let update = response
+                        .value
+                        .decode::<Account>()
+                        .map(|account| (response.context.slot, account.data))
+                        .ok_or_else(|| "Undecodable account notification".to_string());
+                    if updates.send(update).is_err() {
+                        return;
+                    }
+                }
+                // A subscription that delivered something was healthy
+                if notified {
+                    backoff = MIN_BACKOFF;
+                }
+                "Subscription closed".to_string()
+            }
+            Err(e) => e.to_string(),
+        };
+        let message = format!("{}; reconnecting in {}s", closed, backoff.as_secs());
+        if updates.send(Err(message)).is_err() {
+            return;
+        }
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_orderbook_monitor::OrderbookEventType;
+
+    fn monitor(prices: &[u64]) -> OrderbookMonitor {
+        OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: prices.len() as u64,
+            events: prices
+                .iter()
+                .map(|&price| OrderbookEvent {
+                    timestamp: 1_700_000_000,
+                    market_name: "SOL/USDC".to_string(),
+                    price,
+                    size: 1,
+                    is_bid: true,
+                    event_type: OrderbookEventType::OrderPlaced,
+                })
+                .collect(),
+            snapshots: vec![],
+            paused: false,
+        }
+    }
+
+    /// Whether advancing over a monitor with these prices found it
+    /// recreated, and how many events were new
+    fn advance(seen: &mut SeenEvents, prices: &[u64]) -> (bool, usize) {
+        let monitor = monitor(prices);
+        let (recreated, new_events) = seen.advance(&monitor);
+        (recreated, new_events.len())
+    }
+
+    #[test]
+    fn test_seen_events_detects_recreated_account() {
+        let mut seen = SeenEvents::default();
+        assert_eq!(advance(&mut seen, &[1, 2]), (false, 2));
+        assert_eq!(advance(&mut seen, &[1, 2, 3]), (false, 1));
+        assert_eq!(advance(&mut seen, &[1, 2, 3]), (false, 0));
+        assert_eq!(seen.count(), 3);
+
+        // Fewer events, or as many but different ones, mean a new account
+        assert_eq!(advance(&mut seen, &[7]), (true, 1));
+        assert_eq!(advance(&mut seen, &[8, 9]), (true, 2));
+        assert_eq!(advance(&mut seen, &[]), (true, 0));
+        assert_eq!(seen.count(), 0);
+    }
+}
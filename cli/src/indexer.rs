@@ -0,0 +1,620 @@
+//! Indexer keeping a local SQLite copy of the events of monitor accounts.
+//!
+//! Each poll reads the monitor accounts together with the slot they were
+//! read at and appends the events past the monitor's checkpoint. Events, the
+//! per-market totals and the new checkpoint are written in one transaction,
+//! so a restarted indexer resumes exactly where the last committed poll
+//! stopped. A read at or before the checkpoint slot, as returned by a lagging
+//! RPC node, is ignored.
+//!
+//! Book snapshots are indexed in the same transaction, numbered by their
+//! position in the account.
+//!
+//! Checkpoints also keep the monitor's authority and a hash of its first
+//! event. A monitor whose first event changed was closed and recreated at the
+//! same address, and its events no longer continue the indexed ones; before
+//! any event is recorded, a new authority is taken as the sign of that.
+//!
+//! Prices and sizes are stored bit for bit in SQLite's signed integers, so
+//! every `u64` reads back exactly, but values above `i64::MAX` are negative
+//! to SQL comparisons.
+
+use crate::{
+    chain::{Chain, ChainError},
+    export::{EventFilter, EventRecord, LevelRecord, SnapshotRecord},
+};
+use clap::ValueEnum;
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use borsh::BorshSerialize;
+use solana_orderbook_monitor::{OrderbookEvent, OrderbookMonitor};
+use solana_sdk::{hash::hash, pubkey::Pubkey};
+use std::{fmt, path::Path, str::FromStr};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS events (
+    monitor TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    market_name TEXT NOT NULL,
+    price INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    side TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    PRIMARY KEY (monitor, event_index)
+);
+CREATE INDEX IF NOT EXISTS events_by_market ON events (market_name, timestamp);
+CREATE TABLE IF NOT EXISTS markets (
+    name TEXT PRIMARY KEY,
+    event_count INTEGER NOT NULL,
+    first_timestamp INTEGER NOT NULL,
+    last_timestamp INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS snapshots (
+    monitor TEXT NOT NULL,
+    snapshot_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    market_name TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    bids TEXT NOT NULL,
+    asks TEXT NOT NULL,
+    PRIMARY KEY (monitor, snapshot_index)
+);
+CREATE INDEX IF NOT EXISTS snapshots_by_market ON snapshots (market_name, slot);
+CREATE TABLE IF NOT EXISTS checkpoints (
+    monitor TEXT PRIMARY KEY,
+    slot INTEGER NOT NULL,
+    next_event_index INTEGER NOT NULL,
+    authority TEXT NOT NULL,
+    first_event TEXT
+);
+";
+
+#[derive(Error, Debug)]
+pub enum IndexerError {
+    #[error("Database error: {0}")]
+    Db(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+    #[error("Account {0} does not hold a monitor")]
+    InvalidAccountData(Pubkey),
+    #[error("Monitor {monitor} holds {recorded} events but {indexed} are indexed; was it recreated?")]
+    Rewound { monitor: Pubkey, indexed: u64, recorded: u64 },
+    #[error("Monitor {0} was closed and recreated since it was indexed")]
+    Recreated(Pubkey),
+    #[error("Invalid cursor {0}")]
+    InvalidCursor(String),
+}
+
+/// How far a monitor has been indexed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexCheckpoint {
+    /// Slot of the last account read that was indexed
+    pub slot: u64,
+    /// Index in `OrderbookMonitor::events` of the next event to index
+    pub next_event_index: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedEvent {
+    pub monitor: Pubkey,
+    pub event_index: u64,
+    /// Slot at which the indexer first saw the event
+    pub slot: u64,
+    pub event: EventRecord,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct MarketSummary {
+    pub name: String,
+    pub event_count: u64,
+    pub first_timestamp: i64,
+    pub last_timestamp: i64,
+}
+
+/// Position of an event in the timestamp order of `Store::events_page`,
+/// written as `timestamp:monitor:event_index`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventCursor {
+    pub timestamp: i64,
+    pub monitor: Pubkey,
+    pub event_index: u64,
+}
+
+impl From<&IndexedEvent> for EventCursor {
+    fn from(event: &IndexedEvent) -> Self {
+        EventCursor { timestamp: event.event.timestamp, monitor: event.monitor, event_index: event.event_index }
+    }
+}
+
+impl fmt::Display for EventCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.timestamp, self.monitor, self.event_index)
+    }
+}
+
+impl FromStr for EventCursor {
+    type Err = IndexerError;
+
+    fn from_str(cursor: &str) -> Result<Self, Self::Err> {
+        let invalid = || IndexerError::InvalidCursor(cursor.to_string());
+        let mut parts = cursor.split(':');
+        let (Some(timestamp), Some(monitor), Some(event_index), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        Ok(EventCursor {
+            timestamp: timestamp.parse().map_err(|_| invalid())?,
+            monitor: monitor.parse().map_err(|_| invalid())?,
+            event_index: event_index.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Read the event columns starting at column `first`, in `events` table order
+fn event_record(row: &Row, first: usize) -> rusqlite::Result<EventRecord> {
+    let side: String = row.get(first + 4)?;
+    let event_type: String = row.get(first + 5)?;
+    Ok(EventRecord {
+        timestamp: row.get(first)?,
+        market_name: row.get(first + 1)?,
+        price: row.get::<_, i64>(first + 2)? as u64,
+        size: row.get::<_, i64>(first + 3)? as u64,
+        side: parse_column(first + 4, &side)?,
+        event_type: parse_column(first + 5, &event_type)?,
+    })
+}
+
+fn parse_column<T: ValueEnum>(index: usize, value: &str) -> rusqlite::Result<T> {
+    T::from_str(value, false)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, e.into()))
+}
+
+fn fingerprint(event: &OrderbookEvent) -> String {
+    hash(&event.try_to_vec().expect("events serialize")).to_string()
+}
+
+fn conversion_error(index: usize, e: impl std::error::Error + Send + Sync + 'static) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e))
+}
+
+/// The SQLite database written by the indexer
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// Open or create the database at `path`
+    pub fn open(path: &Path) -> Result<Self, IndexerError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, IndexerError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, IndexerError> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Store { conn })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub fn checkpoint(&self, monitor: &Pubkey) -> Result<Option<IndexCheckpoint>, IndexerError> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT slot, next_event_index FROM checkpoints WHERE monitor = ?1",
+                [monitor.to_string()],
+                |row| Ok(IndexCheckpoint { slot: row.get(0)?, next_event_index: row.get(1)? }),
+            )
+            .optional()?)
+    }
+
+    /// Index the events of `state`, read from `monitor` at `slot`, that are
+    /// past the checkpoint. Returns `None` if `slot` is not newer than the
+    /// checkpoint.
+    pub fn index(
+        &mut self,
+        monitor: &Pubkey,
+        slot: u64,
+        state: &OrderbookMonitor,
+    ) -> Result<Option<usize>, IndexerError> {
+        let checkpoint = self.checkpoint(monitor)?;
+        if checkpoint.is_some_and(|c| slot <= c.slot) {
+            return Ok(None);
+        }
+        let next = checkpoint.map_or(0, |c| c.next_event_index);
+        let recorded = state.events.len() as u64;
+        if recorded < next {
+            return Err(IndexerError::Rewound { monitor: *monitor, indexed: next, recorded });
+        }
+
+        let authority = state.authority.to_string();
+        let first_event = state.events.first().map(fingerprint);
+        let identity: Option<(String, Option<String>)> = self
+            .conn
+            .query_row(
+                "SELECT authority, first_event FROM checkpoints WHERE monitor = ?1",
+                [monitor.to_string()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        if let Some((indexed_authority, indexed_first_event)) = identity {
+            let recreated = match (indexed_first_event, &first_event) {
+                (Some(indexed), Some(current)) => indexed != *current,
+                _ => indexed_authority != authority,
+            };
+            if recreated {
+                return Err(IndexerError::Recreated(*monitor));
+            }
+        }
+
+        let new_events = &state.events[next as usize..];
+        let tx = self.conn.transaction()?;
+        {
+            let mut insert_event = tx.prepare(
+                "INSERT INTO events
+                    (monitor, event_index, slot, timestamp, market_name, price, size, side, event_type)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            let mut upsert_market = tx.prepare(
+                "INSERT INTO markets (name, event_count, first_timestamp, last_timestamp)
+                 VALUES (?1, 1, ?2, ?2)
+                 ON CONFLICT (name) DO UPDATE SET
+                    event_count = event_count + 1,
+                    first_timestamp = MIN(first_timestamp, excluded.first_timestamp),
+                    last_timestamp = MAX(last_timestamp, excluded.last_timestamp)",
+            )?;
+            for (i, event) in new_events.iter().enumerate() {
+                let record = EventRecord::from(event);
+                insert_event.execute(params![
+                    monitor.to_string(),
+                    next + i as u64,
+                    slot,
+                    record.timestamp,
+                    record.market_name,
+                    record.price as i64,
+                    record.size as i64,
+                    record.side.as_str(),
+                    record.event_type.as_str(),
+                ])?;
+                upsert_market.execute(params![record.market_name, record.timestamp])?;
+            }
+
+            let stored: u64 =
+                tx.query_row("SELECT COUNT(*) FROM snapshots WHERE monitor = ?1", [monitor.to_string()], |row| {
+                    row.get(0)
+                })?;
+            let mut insert_snapshot = tx.prepare(
+                "INSERT INTO snapshots
+                    (monitor, snapshot_index, slot, timestamp, market_name, event_index, bids, asks)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for (i, snapshot) in state.snapshots.iter().enumerate().skip(stored as usize) {
+                let record = SnapshotRecord::from(snapshot);
+                let levels = |levels: &[LevelRecord]| serde_json::to_string(levels).expect("levels serialize");
+                insert_snapshot.execute(params![
+                    monitor.to_string(),
+                    i as u64,
+                    record.slot,
+                    record.timestamp,
+                    record.market_name,
+                    record.event_index,
+                    levels(&record.bids),
+                    levels(&record.asks),
+                ])?;
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO checkpoints (monitor, slot, next_event_index, authority, first_event)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![monitor.to_string(), slot, recorded, authority, first_event],
+        )?;
+        tx.commit()?;
+        Ok(Some(new_events.len()))
+    }
+
+    /// Indexed events of `monitor` from `from_index` on, in recording order
+    pub fn events(&self, monitor: &Pubkey, from_index: u64) -> Result<Vec<IndexedEvent>, IndexerError> {
+        let mut statement = self.conn.prepare(
+            "SELECT event_index, slot, timestamp, market_name, price, size, side, event_type
+             FROM events WHERE monitor = ?1 AND event_index >= ?2 ORDER BY event_index",
+        )?;
+        let rows = statement.query_map(params![monitor.to_string(), from_index], |row| {
+            Ok(IndexedEvent {
+                monitor: *monitor,
+                event_index: row.get(0)?,
+                slot: row.get(1)?,
+                event: event_record(row, 2)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Call `f` with every indexed event matching `filter`, of `monitor` or of
+    /// all monitors, ordered by timestamp. Rows are read one at a time.
+    pub fn for_each_event<E: From<IndexerError>>(
+        &self,
+        monitor: Option<&Pubkey>,
+        filter: &EventFilter,
+        mut f: impl FnMut(EventRecord) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let query = |e: rusqlite::Error| E::from(e.into());
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT timestamp, market_name, price, size, side, event_type FROM events
+                 WHERE (?1 IS NULL OR monitor = ?1)
+                   AND (?2 IS NULL OR market_name = ?2)
+                   AND (?3 IS NULL OR timestamp >= ?3)
+                   AND (?4 IS NULL OR timestamp < ?4)
+                 ORDER BY timestamp, monitor, event_index",
+            )
+            .map_err(query)?;
+        let mut rows = statement
+            .query(params![monitor.map(|m| m.to_string()), filter.market, filter.from, filter.to])
+            .map_err(query)?;
+        while let Some(row) = rows.next().map_err(query)? {
+            f(event_record(row, 0).map_err(query)?)?;
+        }
+        Ok(())
+    }
+
+    /// Up to `limit` events matching `filter`, of `monitor` or of all
+    /// monitors, that come after `after` in the order of `for_each_event`
+    pub fn events_page(
+        &self,
+        monitor: Option<&Pubkey>,
+        filter: &EventFilter,
+        after: Option<&EventCursor>,
+        limit: usize,
+    ) -> Result<Vec<IndexedEvent>, IndexerError> {
+        let mut statement = self.conn.prepare(
+            "SELECT monitor, event_index, slot, timestamp, market_name, price, size, side, event_type
+             FROM events
+             WHERE (?1 IS NULL OR monitor = ?1)
+               AND (?2 IS NULL OR market_name = ?2)
+               AND (?3 IS NULL OR timestamp >= ?3)
+               AND (?4 IS NULL OR timestamp < ?4)
+               AND (?5 IS NULL OR (timestamp, monitor, event_index) > (?5, ?6, ?7))
+             ORDER BY timestamp, monitor, event_index
+             LIMIT ?8",
+        )?;
+        let rows = statement.query_map(
+            params![
+                monitor.map(|m| m.to_string()),
+                filter.market,
+                filter.from,
+                filter.to,
+                after.map(|c| c.timestamp),
+                after.map(|c| c.monitor.to_string()),
+                after.map(|c| c.event_index),
+                limit as i64,
+            ],
+            |row| {
+                let monitor: String = row.get(0)?;
+                Ok(IndexedEvent {
+                    monitor: monitor.parse().map_err(|e| conversion_error(0, e))?,
+                    event_index: row.get(1)?,
+                    slot: row.get(2)?,
+                    event: event_record(row, 3)?,
+                })
+            },
+        )?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// The latest indexed snapshot of `market_name` over all monitors
+    pub fn latest_snapshot(&self, market_name: &str) -> Result<Option<SnapshotRecord>, IndexerError> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT slot, timestamp, market_name, event_index, bids, asks FROM snapshots
+                 WHERE market_name = ?1 ORDER BY slot DESC, timestamp DESC LIMIT 1",
+                [market_name],
+                |row| {
+                    let levels = |index: usize| -> rusqlite::Result<Vec<LevelRecord>> {
+                        let json: String = row.get(index)?;
+                        serde_json::from_str(&json).map_err(|e| conversion_error(index, e))
+                    };
+                    Ok(SnapshotRecord {
+                        slot: row.get(0)?,
+                        timestamp: row.get(1)?,
+                        market_name: row.get(2)?,
+                        event_index: row.get(3)?,
+                        bids: levels(4)?,
+                        asks: levels(5)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    pub fn markets(&self) -> Result<Vec<MarketSummary>, IndexerError> {
+        let mut statement = self.conn.prepare(
+            "SELECT name, event_count, first_timestamp, last_timestamp FROM markets ORDER BY name",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(MarketSummary {
+                name: row.get(0)?,
+                event_count: row.get(1)?,
+                first_timestamp: row.get(2)?,
+                last_timestamp: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+}
+
+/// Polls a set of monitor accounts into a `Store`
+pub struct Indexer {
+    store: Store,
+    monitors: Vec<Pubkey>,
+}
+
+impl Indexer {
+    pub fn new(store: Store, monitors: Vec<Pubkey>) -> Self {
+        Indexer { store, monitors }
+    }
+
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    pub fn monitors(&self) -> &[Pubkey] {
+        &self.monitors
+    }
+
+    /// Read every monitor once and index its new events; returns how many
+    /// events were indexed
+    pub async fn poll_once(&mut self, chain: &mut impl Chain) -> Result<usize, IndexerError> {
+        let mut indexed = 0;
+        for monitor in &self.monitors {
+            let (slot, data) = chain.account_at_slot(monitor).await?;
+            let state = OrderbookMonitor::unpack(&data)
+                .map_err(|_| IndexerError::InvalidAccountData(*monitor))?;
+            indexed += self.store.index(monitor, slot, &state)?.unwrap_or(0);
+        }
+        Ok(indexed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{EventKind, Side};
+    use solana_orderbook_monitor::{OrderbookEvent, OrderbookEventType};
+
+    fn event(market_name: &str, timestamp: i64, event_type: OrderbookEventType) -> OrderbookEvent {
+        OrderbookEvent {
+            timestamp,
+            market_name: market_name.to_string(),
+            price: 25_000,
+            size: 10,
+            is_bid: event_type == OrderbookEventType::OrderPlaced,
+            event_type,
+        }
+    }
+
+    #[test]
+    fn test_index_appends_past_checkpoint() {
+        let mut store = Store::open_in_memory().unwrap();
+        let address = Pubkey::new_unique();
+        let mut monitor = OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: 2,
+            events: vec![
+                event("SOL/USDC", 100, OrderbookEventType::OrderPlaced),
+                event("BTC/USDC", 101, OrderbookEventType::OrderFilled),
+            ],
+            snapshots: vec![],
+            paused: false,
+        };
+        assert_eq!(store.index(&address, 10, &monitor).unwrap(), Some(2));
+
+        // A read that is not newer than the checkpoint changes nothing
+        monitor.events.push(event("SOL/USDC", 105, OrderbookEventType::OrderCancelled));
+        assert_eq!(store.index(&address, 10, &monitor).unwrap(), None);
+        assert_eq!(store.index(&address, 11, &monitor).unwrap(), Some(1));
+        assert_eq!(
+            store.checkpoint(&address).unwrap(),
+            Some(IndexCheckpoint { slot: 11, next_event_index: 3 })
+        );
+
+        let events = store.events(&address, 1).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].event_index, 2);
+        assert_eq!(events[1].slot, 11);
+        assert_eq!(events[1].event.event_type, EventKind::Cancelled);
+        assert_eq!(events[1].event.side, Side::Ask);
+
+        let markets = store.markets().unwrap();
+        assert_eq!(markets[1].name, "SOL/USDC");
+        assert_eq!((markets[1].event_count, markets[1].first_timestamp, markets[1].last_timestamp), (2, 100, 105));
+
+        let filter = EventFilter { market: Some("SOL/USDC".to_string()), from: Some(101), to: None };
+        let mut streamed = Vec::new();
+        store
+            .for_each_event(None, &filter, |event| {
+                streamed.push(event.timestamp);
+                Ok::<_, IndexerError>(())
+            })
+            .unwrap();
+        assert_eq!(streamed, vec![105]);
+
+        // A monitor with fewer events than indexed was replaced
+        monitor.events.truncate(1);
+        assert!(matches!(store.index(&address, 12, &monitor), Err(IndexerError::Rewound { indexed: 3, .. })));
+    }
+
+    #[test]
+    fn test_index_detects_recreated_monitor() {
+        let mut store = Store::open_in_memory().unwrap();
+        let address = Pubkey::new_unique();
+        let mut monitor = OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: 0,
+            events: vec![],
+            snapshots: vec![],
+            paused: false,
+        };
+
+        // Before any event, a new authority means a new account
+        assert_eq!(store.index(&address, 10, &monitor).unwrap(), Some(0));
+        let first_authority = monitor.authority;
+        monitor.authority = Pubkey::new_unique();
+        assert!(matches!(store.index(&address, 11, &monitor), Err(IndexerError::Recreated(_))));
+        monitor.authority = first_authority;
+
+        monitor.events.push(event("SOL/USDC", 100, OrderbookEventType::OrderPlaced));
+        assert_eq!(store.index(&address, 12, &monitor).unwrap(), Some(1));
+
+        // Once events are indexed, handing the monitor over is not a recreation
+        monitor.authority = Pubkey::new_unique();
+        monitor.events.push(event("SOL/USDC", 101, OrderbookEventType::OrderFilled));
+        assert_eq!(store.index(&address, 13, &monitor).unwrap(), Some(1));
+
+        // A recreated monitor that already recorded as many events is caught
+        // by its first event
+        monitor.events = vec![
+            event("BTC/USDC", 200, OrderbookEventType::OrderPlaced),
+            event("BTC/USDC", 201, OrderbookEventType::OrderPlaced),
+            event("BTC/USDC", 202, OrderbookEventType::OrderPlaced),
+        ];
+        assert!(matches!(store.index(&address, 14, &monitor), Err(IndexerError::Recreated(_))));
+        assert_eq!(store.checkpoint(&address).unwrap().unwrap().next_event_index, 2);
+    }
+
+    #[test]
+    fn test_index_keeps_u64_prices_and_sizes() {
+        let mut store = Store::open_in_memory().unwrap();
+        let address = Pubkey::new_unique();
+        let mut placed = event("SOL/USDC", 100, OrderbookEventType::OrderPlaced);
+        placed.price = u64::MAX;
+        placed.size = i64::MAX as u64 + 1;
+        let monitor = OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: 1,
+            events: vec![placed],
+            snapshots: vec![],
+            paused: false,
+        };
+        assert_eq!(store.index(&address, 10, &monitor).unwrap(), Some(1));
+
+        let events = store.events(&address, 0).unwrap();
+        assert_eq!((events[0].event.price, events[0].event.size), (u64::MAX, i64::MAX as u64 + 1));
+        let page = store.events_page(None, &EventFilter::default(), None, 10).unwrap();
+        assert_eq!(page[0].event.price, u64::MAX);
+    }
+}
@@ -0,0 +1,18 @@
+//! Off-chain tools that feed and read orderbook monitor accounts.
+
+pub mod alerts;
+pub mod api;
+pub mod arbitrage;
+pub mod chain;
+pub mod client;
+pub mod collector;
+pub mod config;
+pub mod dashboard;
+pub mod export;
+pub mod feed;
+pub mod feeder;
+pub mod follow;
+pub mod indexer;
+pub mod metrics;
+pub mod replay;
+pub mod surveillance;
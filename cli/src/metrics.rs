@@ -0,0 +1,229 @@
+//! Prometheus metrics of monitor accounts, served by `orderbook-exporter`.
+//!
+//! Each poll reads every monitor account and the transactions that touched
+//! it since the previous poll:
+//!
+//! - `orderbook_events_total{monitor, market, type}` counts recorded events.
+//! - `orderbook_last_event_age_seconds{monitor, market}` is the time since
+//!   the market's newest event, as of the poll.
+//! - `orderbook_last_price{monitor, market}` is the price of the last fill.
+//! - `orderbook_spread{monitor, market}` is the spread of the latest snapshot.
+//! - `orderbook_account_fullness_percent{monitor}` is the share of the
+//!   account's data taken by recorded data; recording fails at 100.
+//! - `orderbook_recorder_tx_failures_total{monitor}` counts failed
+//!   transactions on the account, such as feeder batches rejected by the
+//!   program. Transactions failing preflight never land and are not seen.
+//!   The first poll looks back over the last `SIGNATURE_PAGE` transactions;
+//!   later polls page back through every transaction since the previous one.
+//! - `orderbook_exporter_poll_errors_total{monitor}` counts failed polls.
+
+use crate::{
+    chain::{Chain, ChainError, RpcChain},
+    export::EventKind,
+    follow::SeenEvents,
+};
+use axum::{extract::State, http::header, response::IntoResponse, routing::get, Router};
+use borsh::BorshSerialize;
+use prometheus::{GaugeVec, IntCounterVec, Opts, Registry, TextEncoder};
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_orderbook_monitor::{analytics::spread, OrderbookEventType, OrderbookMonitor};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use std::{collections::BTreeMap, str::FromStr};
+use thiserror::Error;
+
+/// Most transactions read per account and poll
+pub const SIGNATURE_PAGE: usize = 1_000;
+
+#[derive(Error, Debug)]
+pub enum ExporterError {
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+    #[error("Account {0} does not hold a monitor")]
+    InvalidAccountData(Pubkey),
+}
+
+/// The exported metric families
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    events: IntCounterVec,
+    last_event_age: GaugeVec,
+    last_price: GaugeVec,
+    spread: GaugeVec,
+    fullness: GaugeVec,
+    tx_failures: IntCounterVec,
+    poll_errors: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let counter = |name: &str, help: &str, labels: &[&str]| {
+            let counter = IntCounterVec::new(Opts::new(name, help), labels).expect("valid metric");
+            registry.register(Box::new(counter.clone())).expect("unique metric");
+            counter
+        };
+        let gauge = |name: &str, help: &str, labels: &[&str]| {
+            let gauge = GaugeVec::new(Opts::new(name, help), labels).expect("valid metric");
+            registry.register(Box::new(gauge.clone())).expect("unique metric");
+            gauge
+        };
+        let market = &["monitor", "market"];
+        Metrics {
+            events: counter("orderbook_events_total", "Recorded events", &["monitor", "market", "type"]),
+            last_event_age: gauge("orderbook_last_event_age_seconds", "Seconds since the newest event", market),
+            last_price: gauge("orderbook_last_price", "Price of the last fill", market),
+            spread: gauge("orderbook_spread", "Spread of the latest book snapshot", market),
+            fullness: gauge(
+                "orderbook_account_fullness_percent",
+                "Share of the account data taken by recorded data",
+                &["monitor"],
+            ),
+            tx_failures: counter(
+                "orderbook_recorder_tx_failures_total",
+                "Failed transactions on the monitor account",
+                &["monitor"],
+            ),
+            poll_errors: counter("orderbook_exporter_poll_errors_total", "Failed polls of the monitor", &["monitor"]),
+            registry,
+        }
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// The metrics in the Prometheus text format
+    pub fn encode(&self) -> String {
+        TextEncoder::new().encode_to_string(&self.registry.gather()).expect("metrics encode as text")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Watched {
+    address: Pubkey,
+    /// Events of the account already counted
+    seen: SeenEvents,
+    /// Newest transaction on the account already checked for failure
+    last_signature: Option<Signature>,
+}
+
+/// Polls monitor accounts into `Metrics`
+pub struct Exporter {
+    metrics: Metrics,
+    monitors: Vec<Watched>,
+}
+
+impl Exporter {
+    pub fn new(metrics: Metrics, monitors: Vec<Pubkey>) -> Self {
+        let monitors = monitors.into_iter().map(|address| Watched { address, seen: SeenEvents::default(), last_signature: None }).collect();
+        Exporter { metrics, monitors }
+    }
+
+    /// Poll every monitor once, at unix time `now`. A failed poll of one
+    /// monitor is counted and returned without stopping the others.
+    pub async fn poll_once(&mut self, chain: &mut RpcChain, now: i64) -> Vec<(Pubkey, ExporterError)> {
+        let mut errors = Vec::new();
+        for i in 0..self.monitors.len() {
+            let address = self.monitors[i].address;
+            if let Err(e) = self.poll_monitor(i, chain, now).await {
+                self.metrics.poll_errors.with_label_values(&[&address.to_string()]).inc();
+                errors.push((address, e));
+            }
+        }
+        errors
+    }
+
+    async fn poll_monitor(&mut self, index: usize, chain: &mut RpcChain, now: i64) -> Result<(), ExporterError> {
+        let metrics = &self.metrics;
+        let watched = &mut self.monitors[index];
+        let label = watched.address.to_string();
+
+        let (_, data) = chain.account_at_slot(&watched.address).await?;
+        let monitor =
+            OrderbookMonitor::unpack(&data).map_err(|_| ExporterError::InvalidAccountData(watched.address))?;
+
+        // A recreated account starts counting again
+        for event in watched.seen.advance(&monitor).1 {
+            let kind = EventKind::from(&event.event_type).as_str();
+            metrics.events.with_label_values(&[&label, &event.market_name, kind]).inc();
+        }
+
+        let mut newest: BTreeMap<&str, i64> = BTreeMap::new();
+        let mut last_prices: BTreeMap<&str, u64> = BTreeMap::new();
+        for event in &monitor.events {
+            let timestamp = newest.entry(&event.market_name).or_insert(event.timestamp);
+            *timestamp = (*timestamp).max(event.timestamp);
+            if event.event_type == OrderbookEventType::OrderFilled {
+                last_prices.insert(&event.market_name, event.price);
+            }
+        }
+        for (market, timestamp) in newest {
+            metrics.last_event_age.with_label_values(&[&label, market]).set((now - timestamp).max(0) as f64);
+        }
+        for (market, price) in last_prices {
+            metrics.last_price.with_label_values(&[&label, market]).set(price as f64);
+        }
+        for snapshot in &monitor.snapshots {
+            if let Some(spread) = spread(snapshot) {
+                metrics.spread.with_label_values(&[&label, &snapshot.market_name]).set(spread.absolute as f64);
+            }
+        }
+
+        let used = monitor.try_to_vec().map_err(|_| ExporterError::InvalidAccountData(watched.address))?.len();
+        let fullness = if data.is_empty() { 100.0 } else { used as f64 / data.len() as f64 * 100.0 };
+        metrics.fullness.with_label_values(&[&label]).set(fullness);
+
+        // Pages run from the newest transaction back to the last one checked,
+        // so a busy account does not hide failures past the first page
+        let client = chain.client();
+        // Signature history is not served at processed commitment
+        let commitment = client.commitment();
+        let commitment = if commitment.is_at_least_confirmed() { commitment } else { CommitmentConfig::confirmed() };
+        let until = watched.last_signature;
+        let mut before = None;
+        let mut newest = None;
+        loop {
+            let config = GetConfirmedSignaturesForAddress2Config {
+                before,
+                until,
+                limit: Some(SIGNATURE_PAGE),
+                commitment: Some(commitment),
+            };
+            let signatures =
+                client.get_signatures_for_address_with_config(&watched.address, config).await.map_err(ChainError::from)?;
+            let failures = signatures.iter().filter(|s| s.err.is_some()).count();
+            metrics.tx_failures.with_label_values(&[&label]).inc_by(failures as u64);
+
+            let parse = |signature: &str| Signature::from_str(signature).map_err(|e| ChainError::Other(e.to_string()));
+            if newest.is_none() {
+                newest = signatures.first().map(|s| parse(&s.signature)).transpose()?;
+            }
+            // The first poll only looks back over one page
+            match signatures.last() {
+                Some(oldest) if until.is_some() && signatures.len() == SIGNATURE_PAGE => {
+                    before = Some(parse(&oldest.signature)?);
+                }
+                _ => break,
+            }
+        }
+        if newest.is_some() {
+            watched.last_signature = newest;
+        }
+        Ok(())
+    }
+}
+
+async fn serve_metrics(State(metrics): State<Metrics>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], metrics.encode())
+}
+
+/// HTTP routes serving `metrics` at `/metrics`
+pub fn router(metrics: Metrics) -> Router {
+    Router::new().route("/metrics", get(serve_metrics)).with_state(metrics)
+}
@@ -0,0 +1,248 @@
+//! Recorded events as the input of a backtest or an analysis.
+//!
+//! Events come from the indexer database, a JSON Lines export written by
+//! `orderbook-monitor export-events --format jsonl`, a JSON export written
+//! by `orderbook-monitor export`, the raw data of a monitor account, e.g.
+//! saved with `solana account <ADDRESS> --output-file`, or the data of a
+//! monitor account already read. An account holds every event recorded
+//! since its creation, up to its size (recording then fails with
+//! `AccountFull`), and also its book snapshots.
+
+use crate::{
+    export::{EventFilter, EventKind, EventRecord, MonitorExport},
+    indexer::{IndexerError, Store},
+};
+use solana_orderbook_monitor::{
+    analytics::market_activity,
+    candles::Trade,
+    microstructure::{analyze, FlowAnalyzer},
+    OrderbookEvent, OrderbookMonitor,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::PathBuf,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Line {line}: {source}")]
+    Json { line: usize, source: serde_json::Error },
+    #[error(transparent)]
+    Index(#[from] IndexerError),
+    #[error("Data is not a monitor account")]
+    InvalidAccountData,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplaySource {
+    /// Indexer database, with the events of one monitor or of all of them
+    Index { path: PathBuf, monitor: Option<Pubkey> },
+    /// One `EventRecord` per line
+    Jsonl(PathBuf),
+    /// A monitor written as JSON by `orderbook-monitor export`
+    Export(PathBuf),
+    /// Raw monitor account data
+    Account(PathBuf),
+    /// Monitor account data already read, e.g. over RPC
+    AccountData(Vec<u8>),
+}
+
+impl ReplaySource {
+    /// The monitor account of `Account` and `AccountData` sources
+    fn monitor(&self) -> Result<Option<OrderbookMonitor>, ReplayError> {
+        let data = match self {
+            ReplaySource::Account(path) => Cow::Owned(std::fs::read(path)?),
+            ReplaySource::AccountData(data) => Cow::Borrowed(data),
+            ReplaySource::Index { .. } | ReplaySource::Jsonl(_) | ReplaySource::Export(_) => return Ok(None),
+        };
+        OrderbookMonitor::unpack(&data).map(Some).map_err(|_| ReplayError::InvalidAccountData)
+    }
+}
+
+/// An error of the caller's, passed through `Store::for_each_event`
+struct Caller<E>(E);
+
+impl<E: From<ReplayError>> From<IndexerError> for Caller<E> {
+    fn from(e: IndexerError) -> Self {
+        Caller(ReplayError::from(e).into())
+    }
+}
+
+/// Call `f` with every event of `source` matching `filter`, in the order
+/// they were recorded. The index and JSON Lines are read one event at a time.
+pub fn for_each_event<E: From<ReplayError>>(
+    source: &ReplaySource,
+    filter: &EventFilter,
+    mut f: impl FnMut(EventRecord) -> Result<(), E>,
+) -> Result<(), E> {
+    match source {
+        ReplaySource::Index { path, monitor } => {
+            let store = Store::open(path).map_err(ReplayError::from)?;
+            store.for_each_event(monitor.as_ref(), filter, |record| f(record).map_err(Caller)).map_err(|e| e.0)?;
+        }
+        ReplaySource::Jsonl(path) => {
+            for (i, line) in BufReader::new(File::open(path).map_err(ReplayError::from)?).lines().enumerate() {
+                let line = line.map_err(ReplayError::from)?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record: EventRecord =
+                    serde_json::from_str(&line).map_err(|source| ReplayError::Json { line: i + 1, source })?;
+                if filter.matches(&record) {
+                    f(record)?;
+                }
+            }
+        }
+        ReplaySource::Export(path) => {
+            let file = BufReader::new(File::open(path).map_err(ReplayError::from)?);
+            let export: MonitorExport =
+                serde_json::from_reader(file).map_err(|source| ReplayError::Json { line: source.line(), source })?;
+            for record in export.events.into_iter().filter(|e| filter.matches(e)) {
+                f(record)?;
+            }
+        }
+        ReplaySource::Account(_) | ReplaySource::AccountData(_) => {
+            let monitor = source.monitor()?.expect("account sources hold a monitor");
+            for record in monitor.events.iter().map(EventRecord::from).filter(|e| filter.matches(e)) {
+                f(record)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Events of `source` matching `filter`, in the order they were recorded
+pub fn load_events(source: &ReplaySource, filter: &EventFilter) -> Result<Vec<OrderbookEvent>, ReplayError> {
+    let mut events = Vec::new();
+    for_each_event(source, filter, |record| {
+        events.push(record.into());
+        Ok::<_, ReplayError>(())
+    })?;
+    Ok(events)
+}
+
+/// Fills of `market_name` in `source`, as trades to resample into candles
+pub fn trades(source: &ReplaySource, market_name: &str) -> Result<Vec<Trade>, ReplayError> {
+    let filter = EventFilter { market: Some(market_name.to_string()), ..EventFilter::default() };
+    let mut trades = Vec::new();
+    for_each_event(source, &filter, |record| {
+        if record.event_type == EventKind::Filled {
+            trades.push(Trade { timestamp: record.timestamp, price: record.price, size: record.size });
+        }
+        Ok::<_, ReplayError>(())
+    })?;
+    Ok(trades)
+}
+
+/// Order flow of `market_name`, or of every market, in bars of `interval`
+/// seconds. Books are restarted from the snapshots of a monitor account;
+/// from the index or JSON Lines they start empty.
+pub fn flow(
+    source: &ReplaySource,
+    market_name: Option<&str>,
+    interval: i64,
+) -> Result<BTreeMap<String, FlowAnalyzer>, ReplayError> {
+    let mut analyzers: BTreeMap<String, FlowAnalyzer> = BTreeMap::new();
+    if let Some(monitor) = source.monitor()? {
+        let markets: Vec<String> = match market_name {
+            Some(market) => vec![market.to_string()],
+            None => market_activity(&monitor.events).into_keys().map(str::to_string).collect(),
+        };
+        for market in markets {
+            let analyzer = analyze(&monitor, &market, interval);
+            analyzers.insert(market, analyzer);
+        }
+        return Ok(analyzers);
+    }
+    let filter = EventFilter { market: market_name.map(str::to_string), ..EventFilter::default() };
+    for_each_event(source, &filter, |record| {
+        let analyzer = analyzers.entry(record.market_name.clone()).or_insert_with(|| FlowAnalyzer::new(interval));
+        analyzer.apply(&record.into());
+        Ok::<_, ReplayError>(())
+    })?;
+    Ok(analyzers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{write_json, EventWriter, Format};
+    use solana_orderbook_monitor::OrderbookEventType;
+
+    fn event(timestamp: i64, market_name: &str, price: u64) -> OrderbookEvent {
+        OrderbookEvent {
+            timestamp,
+            market_name: market_name.to_string(),
+            price,
+            size: 1,
+            is_bid: price < 100,
+            event_type: OrderbookEventType::OrderPlaced,
+        }
+    }
+
+    #[test]
+    fn test_sources_agree() {
+        let events = vec![event(1, "SOL/USDC", 99), event(2, "BTC/USDC", 50_000), event(3, "SOL/USDC", 101)];
+        let dir = std::env::temp_dir().join(format!("replay-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let jsonl = dir.join("events.jsonl");
+        let mut writer = EventWriter::new(Format::Jsonl, File::create(&jsonl).unwrap()).unwrap();
+        for event in &events {
+            writer.write(&event.into()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let monitor = OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: events.len() as u64,
+            events: events.clone(),
+            snapshots: vec![],
+            paused: false,
+        };
+        let account = dir.join("monitor.bin");
+        let mut data = vec![0; OrderbookMonitor::space(8, 16)];
+        monitor.pack(&mut data).unwrap();
+        std::fs::write(&account, &data).unwrap();
+
+        let export = dir.join("monitor.json");
+        write_json(&monitor, File::create(&export).unwrap()).unwrap();
+
+        let db = dir.join("index.sqlite");
+        let _ = std::fs::remove_file(&db);
+        let mut store = Store::open(&db).unwrap();
+        store.index(&Pubkey::new_unique(), 1, &monitor).unwrap();
+        drop(store);
+
+        let records = |events: Vec<OrderbookEvent>| events.iter().map(EventRecord::from).collect::<Vec<_>>();
+        let filter = EventFilter { market: Some("SOL/USDC".to_string()), from: Some(2), to: None };
+        for source in [
+            ReplaySource::Jsonl(jsonl),
+            ReplaySource::Account(account),
+            ReplaySource::AccountData(data.clone()),
+            ReplaySource::Export(export),
+            ReplaySource::Index { path: db, monitor: None },
+        ] {
+            let all = load_events(&source, &EventFilter::default()).unwrap();
+            assert_eq!(records(all), records(events.clone()), "{:?}", source);
+            let filtered = load_events(&source, &filter).unwrap();
+            assert_eq!(records(filtered), records(vec![events[2].clone()]), "{:?}", source);
+            let markets: Vec<String> = flow(&source, None, 60).unwrap().into_keys().collect();
+            assert_eq!(markets, vec!["BTC/USDC", "SOL/USDC"], "{:?}", source);
+            assert!(trades(&source, "SOL/USDC").unwrap().is_empty());
+        }
+
+        std::fs::write(dir.join("bad.jsonl"), "\n{}\n").unwrap();
+        let err = load_events(&ReplaySource::Jsonl(dir.join("bad.jsonl")), &filter).unwrap_err();
+        assert!(matches!(err, ReplayError::Json { line: 2, .. }));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
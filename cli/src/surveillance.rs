@@ -0,0 +1,500 @@
+//! JSON views of the market-abuse heuristics and maker obligations in
+//! `solana_orderbook_monitor::surveillance`, used by `orderbook-monitor
+//! spoofing`, `orderbook-monitor wash-trading` and `orderbook-monitor
+//! obligations`.
+//!
+//! The inputs are JSON Lines: `TraderEventRecord`s, one event of an order
+//! lifecycle per line, and `OwnedFillRecord`s, one fill with both owners per
+//! line. Wallets are base58 pubkeys and order ids decimal strings, since
+//! Serum order ids do not fit in a JSON number exactly.
+
+use crate::export::{EventKind, Side};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use solana_orderbook_monitor::surveillance::{
+    obligations::{DailyCompliance, ObligationConfig, ObligationTracker, SECONDS_PER_DAY},
+    spoofing::{self, trader_scores, Incident, IncidentKind, SpoofingConfig, TraderScore},
+    wash::{self, wallet_volumes, OwnedFill, WalletWash, WashConfig, WashReason, WashReport},
+    TraderEvent,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::io::{self, BufRead};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SurveillanceError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Line {line}: {source}")]
+    Json { line: usize, source: serde_json::Error },
+    #[error("Line {line}: invalid {field}")]
+    InvalidField { line: usize, field: &'static str },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TraderEventRecord {
+    pub timestamp: i64,
+    pub trader: String,
+    pub order_id: String,
+    pub market_name: String,
+    pub price: u64,
+    pub size: u64,
+    pub side: Side,
+    pub event_type: EventKind,
+}
+
+impl From<&TraderEvent> for TraderEventRecord {
+    fn from(event: &TraderEvent) -> Self {
+        TraderEventRecord {
+            timestamp: event.timestamp,
+            trader: event.trader.to_string(),
+            order_id: event.order_id.to_string(),
+            market_name: event.market_name.clone(),
+            price: event.price,
+            size: event.size,
+            side: if event.is_bid { Side::Bid } else { Side::Ask },
+            event_type: (&event.event_type).into(),
+        }
+    }
+}
+
+impl TraderEventRecord {
+    /// The event, or the name of the field that does not parse
+    pub fn to_event(&self) -> Result<TraderEvent, &'static str> {
+        Ok(TraderEvent {
+            timestamp: self.timestamp,
+            trader: self.trader.parse().map_err(|_| "trader")?,
+            order_id: self.order_id.parse().map_err(|_| "order_id")?,
+            market_name: self.market_name.clone(),
+            price: self.price,
+            size: self.size,
+            is_bid: self.side == Side::Bid,
+            event_type: self.event_type.into(),
+        })
+    }
+}
+
+/// Read one record per line, skipping blank lines, and convert it with `convert`
+fn read_lines<R: DeserializeOwned, T>(
+    reader: impl BufRead,
+    convert: impl Fn(&R) -> Result<T, &'static str>,
+) -> Result<Vec<T>, SurveillanceError> {
+    let mut items = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: R =
+            serde_json::from_str(&line).map_err(|source| SurveillanceError::Json { line: i + 1, source })?;
+        items.push(convert(&record).map_err(|field| SurveillanceError::InvalidField { line: i + 1, field })?);
+    }
+    Ok(items)
+}
+
+/// Read one `TraderEventRecord` per line
+pub fn read_trader_events(reader: impl BufRead) -> Result<Vec<TraderEvent>, SurveillanceError> {
+    read_lines(reader, TraderEventRecord::to_event)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OwnedFillRecord {
+    pub timestamp: i64,
+    pub market_name: String,
+    pub maker: String,
+    pub taker: String,
+    pub price: u64,
+    pub size: u64,
+    /// Side of the taker
+    pub taker_side: Side,
+}
+
+impl From<&OwnedFill> for OwnedFillRecord {
+    fn from(fill: &OwnedFill) -> Self {
+        OwnedFillRecord {
+            timestamp: fill.timestamp,
+            market_name: fill.market_name.clone(),
+            maker: fill.maker.to_string(),
+            taker: fill.taker.to_string(),
+            price: fill.price,
+            size: fill.size,
+            taker_side: if fill.taker_is_bid { Side::Bid } else { Side::Ask },
+        }
+    }
+}
+
+impl OwnedFillRecord {
+    /// The fill, or the name of the field that does not parse
+    pub fn to_fill(&self) -> Result<OwnedFill, &'static str> {
+        Ok(OwnedFill {
+            timestamp: self.timestamp,
+            market_name: self.market_name.clone(),
+            maker: self.maker.parse().map_err(|_| "maker")?,
+            taker: self.taker.parse().map_err(|_| "taker")?,
+            price: self.price,
+            size: self.size,
+            taker_is_bid: self.taker_side == Side::Bid,
+        })
+    }
+}
+
+/// Read one `OwnedFillRecord` per line
+pub fn read_owned_fills(reader: impl BufRead) -> Result<Vec<OwnedFill>, SurveillanceError> {
+    read_lines(reader, OwnedFillRecord::to_fill)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IncidentKindRecord {
+    Spoofing,
+    Layering,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IncidentRecord {
+    pub kind: IncidentKindRecord,
+    pub trader: String,
+    pub market_name: String,
+    pub side: Side,
+    pub start: i64,
+    pub end: i64,
+    pub order_ids: Vec<String>,
+    pub order_size: u64,
+    pub opposite_fill_size: u64,
+    pub score: f64,
+}
+
+impl From<&Incident> for IncidentRecord {
+    fn from(incident: &Incident) -> Self {
+        IncidentRecord {
+            kind: match incident.kind {
+                IncidentKind::Spoofing => IncidentKindRecord::Spoofing,
+                IncidentKind::Layering => IncidentKindRecord::Layering,
+            },
+            trader: incident.trader.to_string(),
+            market_name: incident.market_name.clone(),
+            side: if incident.is_bid { Side::Bid } else { Side::Ask },
+            start: incident.start,
+            end: incident.end,
+            order_ids: incident.order_ids.iter().map(u128::to_string).collect(),
+            order_size: incident.order_size,
+            opposite_fill_size: incident.opposite_fill_size,
+            score: incident.score,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TraderScoreRecord {
+    pub trader: String,
+    pub market_name: String,
+    pub spoofing: usize,
+    pub layering: usize,
+    pub max_score: f64,
+    pub total_score: f64,
+}
+
+impl From<&TraderScore> for TraderScoreRecord {
+    fn from(score: &TraderScore) -> Self {
+        TraderScoreRecord {
+            trader: score.trader.to_string(),
+            market_name: score.market_name.clone(),
+            spoofing: score.spoofing,
+            layering: score.layering,
+            max_score: score.max_score,
+            total_score: score.total_score,
+        }
+    }
+}
+
+/// What `orderbook-monitor spoofing` writes
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpoofingReport {
+    pub scores: Vec<TraderScoreRecord>,
+    pub incidents: Vec<IncidentRecord>,
+}
+
+impl SpoofingReport {
+    pub fn new(incidents: &[Incident], scores: &[TraderScore]) -> Self {
+        SpoofingReport {
+            scores: scores.iter().map(TraderScoreRecord::from).collect(),
+            incidents: incidents.iter().map(IncidentRecord::from).collect(),
+        }
+    }
+
+    /// Detect the incidents in `events` and score their traders
+    pub fn detect(events: &[TraderEvent], config: &SpoofingConfig) -> Self {
+        let incidents = spoofing::detect(events, config);
+        SpoofingReport::new(&incidents, &trader_scores(&incidents))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MarketWashRecord {
+    pub market_name: String,
+    pub volume: u64,
+    pub suspect_volume: u64,
+    pub self_trade_volume: u64,
+    pub round_trip_volume: u64,
+    pub cluster_volume: u64,
+    pub suspect_share: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WalletWashRecord {
+    pub wallet: String,
+    pub market_name: String,
+    pub suspect_volume: u64,
+    pub suspect_share: f64,
+}
+
+impl From<&WalletWash> for WalletWashRecord {
+    fn from(wallet: &WalletWash) -> Self {
+        WalletWashRecord {
+            wallet: wallet.wallet.to_string(),
+            market_name: wallet.market_name.clone(),
+            suspect_volume: wallet.suspect_volume,
+            suspect_share: wallet.suspect_share,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WashReasonRecord {
+    SelfTrade,
+    RoundTrip,
+    Cluster,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SuspectFillRecord {
+    pub reason: WashReasonRecord,
+    #[serde(flatten)]
+    pub fill: OwnedFillRecord,
+}
+
+/// What `orderbook-monitor wash-trading` writes
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WashTradingReport {
+    pub markets: Vec<MarketWashRecord>,
+    pub wallets: Vec<WalletWashRecord>,
+    pub clusters: Vec<Vec<String>>,
+    pub suspects: Vec<SuspectFillRecord>,
+}
+
+impl WashTradingReport {
+    pub fn new(fills: &[OwnedFill], report: &WashReport, wallets: &[WalletWash]) -> Self {
+        WashTradingReport {
+            markets: report
+                .markets
+                .iter()
+                .map(|m| MarketWashRecord {
+                    market_name: m.market_name.clone(),
+                    volume: m.volume,
+                    suspect_volume: m.suspect_volume(),
+                    self_trade_volume: m.self_trade_volume,
+                    round_trip_volume: m.round_trip_volume,
+                    cluster_volume: m.cluster_volume,
+                    suspect_share: m.suspect_share,
+                })
+                .collect(),
+            wallets: wallets.iter().map(WalletWashRecord::from).collect(),
+            clusters: report.clusters.iter().map(|c| c.iter().map(|w| w.to_string()).collect()).collect(),
+            suspects: report
+                .suspects
+                .iter()
+                .map(|s| SuspectFillRecord {
+                    reason: match s.reason {
+                        WashReason::SelfTrade => WashReasonRecord::SelfTrade,
+                        WashReason::RoundTrip => WashReasonRecord::RoundTrip,
+                        WashReason::Cluster => WashReasonRecord::Cluster,
+                    },
+                    fill: OwnedFillRecord::from(&fills[s.index]),
+                })
+                .collect(),
+        }
+    }
+
+    /// Detect suspect fills, with `related` groups of wallets known in advance
+    pub fn detect(fills: &[OwnedFill], related: &[Vec<Pubkey>], config: &WashConfig) -> Self {
+        let report = wash::detect(fills, related, config);
+        WashTradingReport::new(fills, &report, &wallet_volumes(fills, &report))
+    }
+}
+
+/// `YYYY-MM-DD` of the UTC day holding `timestamp`
+pub fn utc_date(timestamp: i64) -> String {
+    // Days since 0000-03-01, so that leap days end a year
+    let days = timestamp.div_euclid(SECONDS_PER_DAY) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MakerComplianceRecord {
+    pub maker: String,
+    pub observed_secs: i64,
+    pub two_sided_secs: i64,
+    pub compliant_secs: i64,
+    pub at_touch_secs: i64,
+    pub two_sided_pct: f64,
+    pub uptime_pct: f64,
+    pub at_touch_pct: f64,
+    pub avg_spread_bps: Option<f64>,
+    pub avg_bid_depth: f64,
+    pub avg_ask_depth: f64,
+    pub compliant: bool,
+}
+
+impl From<&DailyCompliance> for MakerComplianceRecord {
+    fn from(day: &DailyCompliance) -> Self {
+        MakerComplianceRecord {
+            maker: day.maker.to_string(),
+            observed_secs: day.observed_secs,
+            two_sided_secs: day.two_sided_secs,
+            compliant_secs: day.compliant_secs,
+            at_touch_secs: day.at_touch_secs,
+            two_sided_pct: day.two_sided_pct,
+            uptime_pct: day.uptime_pct,
+            at_touch_pct: day.at_touch_pct,
+            avg_spread_bps: day.avg_spread_bps,
+            avg_bid_depth: day.avg_bid_depth,
+            avg_ask_depth: day.avg_ask_depth,
+            compliant: day.compliant,
+        }
+    }
+}
+
+/// The makers of one market over one UTC day
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DailyComplianceReport {
+    pub date: String,
+    pub market_name: String,
+    pub makers: Vec<MakerComplianceRecord>,
+}
+
+/// Each maker's compliance per market and day over `events`, holding the
+/// last quotes until `until`, the last event by default
+pub fn obligation_days(events: &[TraderEvent], config: ObligationConfig, until: Option<i64>) -> Vec<DailyCompliance> {
+    let mut tracker = ObligationTracker::new(config);
+    for event in events {
+        tracker.apply(event);
+    }
+    if let Some(until) = until.or_else(|| events.iter().map(|e| e.timestamp).max()) {
+        tracker.advance(until);
+    }
+    tracker.report()
+}
+
+/// What `orderbook-monitor obligations` writes: one report per day and
+/// market, in that order, given `DailyCompliance` sorted the same way
+pub fn compliance_reports(days: &[DailyCompliance]) -> Vec<DailyComplianceReport> {
+    let mut reports: Vec<DailyComplianceReport> = Vec::new();
+    for day in days {
+        let date = utc_date(day.day);
+        match reports.last_mut() {
+            Some(report) if report.date == date && report.market_name == day.market_name => {
+                report.makers.push(MakerComplianceRecord::from(day))
+            }
+            _ => reports.push(DailyComplianceReport {
+                date,
+                market_name: day.market_name.clone(),
+                makers: vec![MakerComplianceRecord::from(day)],
+            }),
+        }
+    }
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_orderbook_monitor::OrderbookEventType;
+
+    #[test]
+    fn test_read_trader_events() {
+        let event = TraderEvent {
+            timestamp: 10,
+            trader: Pubkey::new_unique(),
+            order_id: u128::MAX - 1,
+            market_name: "SOL/USDC".to_string(),
+            price: 100,
+            size: 5,
+            is_bid: false,
+            event_type: OrderbookEventType::OrderCancelled,
+        };
+        let line = serde_json::to_string(&TraderEventRecord::from(&event)).unwrap();
+        assert!(line.contains(&format!("\"order_id\":\"{}\"", u128::MAX - 1)));
+        let input = format!("{}\n\n{}\n", line, line);
+        assert_eq!(read_trader_events(input.as_bytes()).unwrap(), vec![event.clone(), event]);
+
+        let bad = line.replace("\"trader\":\"", "\"trader\":\"0");
+        assert!(matches!(
+            read_trader_events(bad.as_bytes()),
+            Err(SurveillanceError::InvalidField { line: 1, field: "trader" })
+        ));
+    }
+
+    #[test]
+    fn test_wash_trading_report() {
+        use solana_orderbook_monitor::surveillance::wash::{detect, wallet_volumes, WashConfig};
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let fill = |maker, taker| OwnedFill {
+            timestamp: 0,
+            market_name: "SOL/USDC".to_string(),
+            maker,
+            taker,
+            price: 100,
+            size: 4,
+            taker_is_bid: true,
+        };
+        let input: String = [fill(a, a), fill(a, b)]
+            .iter()
+            .map(|f| serde_json::to_string(&OwnedFillRecord::from(f)).unwrap() + "\n")
+            .collect();
+        let fills = read_owned_fills(input.as_bytes()).unwrap();
+        let wash = detect(&fills, &[], &WashConfig::default());
+        let report = WashTradingReport::new(&fills, &wash, &wallet_volumes(&fills, &wash));
+        assert_eq!((report.markets[0].volume, report.markets[0].suspect_volume), (8, 4));
+        assert_eq!(report.suspects.len(), 1);
+        let json = serde_json::to_value(&report.suspects[0]).unwrap();
+        assert_eq!((json["reason"].as_str(), json["maker"].as_str()), (Some("self_trade"), Some(a.to_string().as_str())));
+    }
+
+    #[test]
+    fn test_compliance_reports() {
+        assert_eq!(utc_date(0), "1970-01-01");
+        assert_eq!(utc_date(951_782_400), "2000-02-29");
+        assert_eq!(utc_date(1_704_067_199), "2023-12-31");
+        assert_eq!(utc_date(-1), "1969-12-31");
+
+        let day = |day: i64, market_name: &str| DailyCompliance {
+            day: day * SECONDS_PER_DAY,
+            market_name: market_name.to_string(),
+            maker: Pubkey::new_unique(),
+            observed_secs: SECONDS_PER_DAY,
+            two_sided_secs: 0,
+            compliant_secs: 0,
+            at_touch_secs: 0,
+            two_sided_pct: 0.0,
+            uptime_pct: 0.0,
+            at_touch_pct: 0.0,
+            avg_spread_bps: None,
+            avg_bid_depth: 0.0,
+            avg_ask_depth: 0.0,
+            compliant: false,
+        };
+        let days = [day(19_723, "BTC/USDC"), day(19_723, "SOL/USDC"), day(19_723, "SOL/USDC"), day(19_724, "SOL/USDC")];
+        let reports = compliance_reports(&days);
+        let shape: Vec<(&str, &str, usize)> =
+            reports.iter().map(|r| (r.date.as_str(), r.market_name.as_str(), r.makers.len())).collect();
+        assert_eq!(shape, vec![("2024-01-01", "BTC/USDC", 1), ("2024-01-01", "SOL/USDC", 2), ("2024-01-02", "SOL/USDC", 1)]);
+    }
+}
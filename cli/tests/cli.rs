@@ -0,0 +1,161 @@
+use axum::{extract::State, routing::post, Json, Router};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::BorshDeserialize;
+use orderbook_monitor_cli::export::EventRecord;
+use serde_json::{json, Value};
+use solana_orderbook_monitor::{NewEvent, OrderbookEvent, OrderbookEventType, OrderbookInstruction, OrderbookMonitor};
+use solana_sdk::{
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{write_keypair_file, Keypair},
+    transaction::Transaction,
+};
+use std::{
+    process::Command,
+    sync::{Arc, Mutex},
+};
+use tokio::net::TcpListener;
+
+/// What the stand-in RPC node serves and receives: one account, and the
+/// transactions sent to it, each confirmed at once
+#[derive(Default)]
+struct Node {
+    data: Vec<u8>,
+    sent: Vec<Transaction>,
+}
+
+async fn rpc(State(node): State<Arc<Mutex<Node>>>, Json(request): Json<Value>) -> Json<Value> {
+    let mut node = node.lock().unwrap();
+    let params = &request["params"];
+    let result = match request["method"].as_str().unwrap() {
+        "getAccountInfo" => json!({
+            "context": { "slot": 10 },
+            "value": {
+                "data": [STANDARD.encode(&node.data), "base64"],
+                "executable": false,
+                "lamports": 1_000_000,
+                "owner": Pubkey::new_unique().to_string(),
+                "rentEpoch": 0,
+                "space": node.data.len(),
+            },
+        }),
+        "getLatestBlockhash" => json!({
+            "context": { "slot": 10 },
+            "value": { "blockhash": Hash::new_unique().to_string(), "lastValidBlockHeight": 100 },
+        }),
+        "sendTransaction" => {
+            assert_eq!(params[1]["encoding"], "base64");
+            let wire = STANDARD.decode(params[0].as_str().unwrap()).unwrap();
+            let transaction: Transaction = bincode::deserialize(&wire).unwrap();
+            let signature = transaction.signatures[0].to_string();
+            node.sent.push(transaction);
+            json!(signature)
+        }
+        "getSignatureStatuses" => {
+            let status = json!({
+                "slot": 10,
+                "confirmations": null,
+                "err": null,
+                "status": { "Ok": null },
+                "confirmationStatus": "confirmed",
+            });
+            json!({ "context": { "slot": 10 }, "value": vec![status; params[0].as_array().unwrap().len()] })
+        }
+        "getVersion" => json!({ "solana-core": "1.18.26", "feature-set": 0 }),
+        method => panic!("unexpected RPC method {}", method),
+    };
+    Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+}
+
+async fn serve(node: Arc<Mutex<Node>>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = Router::new().route("/", post(rpc)).with_state(node);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}", address)
+}
+
+/// Run `orderbook-monitor` with `args`, failing the test on a non-zero exit
+async fn monitor_cli(args: Vec<String>) {
+    let output = tokio::task::spawn_blocking(move || {
+        Command::new(env!("CARGO_BIN_EXE_orderbook-monitor")).args(&args).output().unwrap()
+    })
+    .await
+    .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+fn event(market_name: &str, price: u64, size: u64, event_type: OrderbookEventType) -> OrderbookEvent {
+    OrderbookEvent { timestamp: 1_000, market_name: market_name.to_string(), price, size, is_bid: false, event_type }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_record_batch_reads_export() {
+    use OrderbookEventType::*;
+    let events = vec![
+        event("SOL/USDC", 100, 5, OrderPlaced),
+        event("SOL/USDC", 100, 2, OrderFilled),
+        event("BTC/USDC", 50_000, 1, OrderCancelled),
+    ];
+    let monitor = OrderbookMonitor {
+        initialized: true,
+        authority: Pubkey::new_unique(),
+        event_count: events.len() as u64,
+        events: events.clone(),
+        snapshots: vec![],
+        paused: false,
+    };
+    let mut data = vec![0; 1_000];
+    monitor.pack(&mut data).unwrap();
+    let node = Arc::new(Mutex::new(Node { data, sent: Vec::new() }));
+    let url = serve(node.clone()).await;
+
+    let dir = std::env::temp_dir().join(format!("monitor-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let keypair = dir.join("payer.json");
+    write_keypair_file(&Keypair::new(), &keypair).unwrap();
+    let program_id = Pubkey::new_unique();
+    let monitor = Pubkey::new_unique();
+    let market = Pubkey::new_unique();
+    let args = |command: &[&str]| -> Vec<String> {
+        let global = ["--url", &url, "--keypair", keypair.to_str().unwrap(), "--program-id", &program_id.to_string()];
+        global.iter().chain(command).map(|arg| arg.to_string()).collect()
+    };
+
+    let export = dir.join("monitor.json");
+    let jsonl = dir.join("events.jsonl");
+    monitor_cli(args(&["export", &monitor.to_string(), "--output", export.to_str().unwrap()])).await;
+    monitor_cli(args(&[
+        "export-events",
+        &monitor.to_string(),
+        "--format",
+        "jsonl",
+        "--output",
+        jsonl.to_str().unwrap(),
+    ]))
+    .await;
+
+    let expected: Vec<NewEvent> = events.iter().map(|e| NewEvent::from(EventRecord::from(e))).collect();
+    for (file, format) in [(&export, "export"), (&jsonl, "jsonl")] {
+        let (monitor, market) = (monitor.to_string(), market.to_string());
+        let file = file.to_str().unwrap();
+        let command = ["record-batch", &monitor, "--market", &market, "--file", file, "--format", format];
+        monitor_cli(args(&[&command[..], &["--batch-size", "2"]].concat())).await;
+
+        // Two transactions of at most two events, in the recorded order
+        let sent = std::mem::take(&mut node.lock().unwrap().sent);
+        assert_eq!(sent.len(), 2, "{}", format);
+        let recorded: Vec<NewEvent> = sent
+            .iter()
+            .flat_map(|transaction| {
+                let data = &transaction.message.instructions[0].data;
+                match OrderbookInstruction::try_from_slice(data).unwrap() {
+                    OrderbookInstruction::RecordEvents { events } => events,
+                    instruction => panic!("unexpected instruction {:?}", instruction),
+                }
+            })
+            .collect();
+        assert_eq!(recorded, expected, "{}", format);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::BanksChain;
+use orderbook_monitor_cli::collector::Collector;
+use solana_orderbook_monitor::{adapter::SerumAdapter, serum::MarketState, OrderbookEventType};
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    pubkey::Pubkey,
+};
+
+const MARKET: &[u8] = include_bytes!("../../tests/fixtures/serum/market.bin");
+const BIDS: &[u8] = include_bytes!("../../tests/fixtures/serum/bids.bin");
+const ASKS: &[u8] = include_bytes!("../../tests/fixtures/serum/asks.bin");
+const EVENT_QUEUE: &[u8] = include_bytes!("../../tests/fixtures/serum/event_queue.bin");
+
+// Serum v1 slab layout: 5 bytes of padding, then the header fields
+const SLAB_LEAF_COUNT_OFFSET: usize = 5 + 32;
+
+#[tokio::test]
+async fn test_collector_finds_placements_between_reads() {
+    let mut program_test = ProgramTest::default();
+    let dex_program = Pubkey::new_unique();
+    let market = Pubkey::new_unique();
+    let state = MarketState::decode(MARKET).unwrap();
+
+    // The bids start out empty: a slab without leaves
+    let mut empty_bids = BIDS.to_vec();
+    empty_bids[SLAB_LEAF_COUNT_OFFSET..SLAB_LEAF_COUNT_OFFSET + 8].copy_from_slice(&0u64.to_le_bytes());
+    for (address, data) in
+        [(market, MARKET), (state.bids, &empty_bids[..]), (state.asks, ASKS), (state.event_queue, EVENT_QUEUE)]
+    {
+        program_test.add_account(
+            address,
+            Account { lamports: 1_000_000_000, data: data.to_vec(), owner: dex_program, ..Account::default() },
+        );
+    }
+    let mut context = program_test.start_with_context().await;
+    let mut chain = BanksChain(context.banks_client.clone());
+
+    // The first read only sees the queue, stamped with the read's time
+    let adapter = SerumAdapter::new(market, MARKET, "SOL/USDC").unwrap();
+    let mut collector = Collector::new(Box::new(adapter)).unwrap();
+    let collected = collector.poll_once(&mut chain, 1_000).await.unwrap();
+    let kinds: Vec<OrderbookEventType> = collected.events.iter().map(|e| e.event_type.clone()).collect();
+    assert_eq!(kinds, vec![OrderbookEventType::OrderFilled, OrderbookEventType::OrderCancelled]);
+    assert!(collected.events.iter().all(|e| e.timestamp == 1_000));
+    // Serum fills do not name the taker
+    assert!(collected.fills.is_empty());
+    assert_eq!(collector.next_seq(), Some(10));
+
+    // Orders appearing on the bids are placements at the next read
+    let mut bids = AccountSharedData::new(1_000_000_000, BIDS.len(), &dex_program);
+    bids.set_data_from_slice(BIDS);
+    context.set_account(&state.bids, &bids);
+    let collected = collector.poll_once(&mut chain, 1_010).await.unwrap();
+    assert_eq!(collected.events.len(), 3);
+    assert!(collected.events.iter().all(|e| e.event_type == OrderbookEventType::OrderPlaced && e.is_bid));
+    assert_eq!(collected.events[0].timestamp, 1_010);
+    assert_eq!(collected.missed, 0);
+
+    assert_eq!(collector.poll_once(&mut chain, 1_020).await.unwrap().events, vec![]);
+}
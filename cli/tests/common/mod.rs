@@ -0,0 +1,61 @@
+//! Helpers shared by the integration tests.
+
+use orderbook_monitor_cli::chain::{Chain, ChainError};
+use solana_program_test::{BanksClient, BanksClientError};
+use solana_sdk::{
+    commitment_config::CommitmentLevel,
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{Transaction, TransactionError},
+};
+
+/// A `Chain` over the test bank
+pub struct BanksChain(pub BanksClient);
+
+fn banks_error(e: BanksClientError) -> ChainError {
+    match e {
+        BanksClientError::TransactionError(e) => ChainError::Transaction(e),
+        e => ChainError::Other(e.to_string()),
+    }
+}
+
+impl Chain for BanksChain {
+    async fn account_data(&mut self, address: &Pubkey) -> Result<Vec<u8>, ChainError> {
+        self.0
+            .get_account(*address)
+            .await
+            .map_err(banks_error)?
+            .map(|account| account.data)
+            .ok_or(ChainError::AccountNotFound(*address))
+    }
+
+    async fn account_at_slot(&mut self, address: &Pubkey) -> Result<(u64, Vec<u8>), ChainError> {
+        let slot = self.0.get_root_slot().await.map_err(banks_error)?;
+        Ok((slot, self.account_data(address).await?))
+    }
+
+    async fn latest_blockhash(&mut self) -> Result<(Hash, u64), ChainError> {
+        self.0
+            .get_latest_blockhash_with_commitment(CommitmentLevel::default())
+            .await
+            .map_err(banks_error)?
+            .ok_or_else(|| ChainError::Other("no blockhash".to_string()))
+    }
+
+    async fn block_height(&mut self) -> Result<u64, ChainError> {
+        self.0.get_root_block_height().await.map_err(banks_error)
+    }
+
+    async fn signature_status(
+        &mut self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), TransactionError>>, ChainError> {
+        let status = self.0.get_transaction_status(*signature).await.map_err(banks_error)?;
+        Ok(status.map(|status| status.err.map_or(Ok(()), Err)))
+    }
+
+    async fn send_and_confirm(&mut self, transaction: &Transaction) -> Result<(), ChainError> {
+        self.0.process_transaction(transaction.clone()).await.map_err(banks_error)
+    }
+}
@@ -0,0 +1,186 @@
+use axum::{extract::State, routing::post, Json, Router};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use orderbook_monitor_cli::{
+    chain::RpcChain,
+    metrics::{router, Exporter, Metrics, SIGNATURE_PAGE},
+};
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_orderbook_monitor::{BookSnapshot, OrderbookEvent, OrderbookEventType, OrderbookMonitor, PriceLevel};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::sync::{Arc, Mutex};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// What the stand-in RPC node serves: one account and its transactions,
+/// newest first, as `(signature, failed)`
+#[derive(Default)]
+struct Node {
+    data: Vec<u8>,
+    signatures: Vec<(Signature, bool)>,
+}
+
+async fn rpc(State(node): State<Arc<Mutex<Node>>>, Json(request): Json<Value>) -> Json<Value> {
+    let node = node.lock().unwrap();
+    let result = match request["method"].as_str().unwrap() {
+        "getAccountInfo" => json!({
+            "context": { "slot": 10 },
+            "value": {
+                "data": [STANDARD.encode(&node.data), "base64"],
+                "executable": false,
+                "lamports": 1_000_000,
+                "owner": Pubkey::new_unique().to_string(),
+                "rentEpoch": 0,
+                "space": node.data.len(),
+            },
+        }),
+        "getSignaturesForAddress" => {
+            let config = &request["params"][1];
+            let until = config["until"].as_str().map(str::to_string);
+            let limit = config["limit"].as_u64().unwrap_or(1_000) as usize;
+            let start = match config["before"].as_str() {
+                Some(before) => node.signatures.iter().position(|(s, _)| s.to_string() == before).unwrap() + 1,
+                None => 0,
+            };
+            let newer = node.signatures[start..].iter().take_while(|(s, _)| Some(s.to_string()) != until).take(limit);
+            Value::Array(
+                newer
+                    .map(|(signature, failed)| {
+                        json!({
+                            "signature": signature.to_string(),
+                            "slot": 10,
+                            "err": if *failed { json!({ "InstructionError": [0, { "Custom": 13 }] }) } else { Value::Null },
+                            "memo": null,
+                            "blockTime": null,
+                            "confirmationStatus": "confirmed",
+                        })
+                    })
+                    .collect(),
+            )
+        }
+        "getVersion" => json!({ "solana-core": "1.18.26", "feature-set": 0 }),
+        method => panic!("unexpected RPC method {}", method),
+    };
+    Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+}
+
+async fn serve(app: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("{}", address)
+}
+
+async fn scrape(address: &str) -> String {
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(format!("GET /metrics HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", address).as_bytes())
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    response
+}
+
+/// Value of the sample of `name` carrying all of `labels`
+fn sample(metrics: &str, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+    metrics
+        .lines()
+        .filter(|line| line.starts_with(&format!("{}{{", name)))
+        .find(|line| labels.iter().all(|(k, v)| line.contains(&format!("{}=\"{}\"", k, v))))
+        .and_then(|line| line.rsplit(' ').next()?.parse().ok())
+}
+
+fn event(timestamp: i64, market_name: &str, price: u64, event_type: OrderbookEventType) -> OrderbookEvent {
+    OrderbookEvent { timestamp, market_name: market_name.to_string(), price, size: 1, is_bid: true, event_type }
+}
+
+fn account(events: Vec<OrderbookEvent>) -> Vec<u8> {
+    let monitor = OrderbookMonitor {
+        initialized: true,
+        authority: Pubkey::new_unique(),
+        event_count: events.len() as u64,
+        events,
+        snapshots: vec![BookSnapshot {
+            slot: 9,
+            timestamp: 1_000,
+            market_name: "SOL/USDC".to_string(),
+            event_index: 0,
+            bids: vec![PriceLevel { price: 99, size: 1 }],
+            asks: vec![PriceLevel { price: 102, size: 1 }],
+        }],
+        paused: false,
+    };
+    let mut data = vec![0; 1_000];
+    monitor.pack(&mut data).unwrap();
+    data
+}
+
+#[tokio::test]
+async fn test_exporter_scrape() {
+    use OrderbookEventType::*;
+    let mut events = vec![
+        event(1_000, "SOL/USDC", 100, OrderPlaced),
+        event(1_010, "SOL/USDC", 101, OrderFilled),
+        event(1_020, "BTC/USDC", 50_000, OrderPlaced),
+    ];
+    let node = Arc::new(Mutex::new(Node {
+        data: account(events.clone()),
+        signatures: vec![(Signature::new_unique(), true), (Signature::new_unique(), false)],
+    }));
+    let rpc_url = format!("http://{}", serve(Router::new().route("/", post(rpc)).with_state(node.clone())).await);
+
+    let monitor = Pubkey::new_unique();
+    let metrics = Metrics::new();
+    let metrics_address = serve(router(metrics.clone())).await;
+    let mut chain = RpcChain::new(RpcClient::new(rpc_url));
+    let mut exporter = Exporter::new(metrics, vec![monitor]);
+    assert!(exporter.poll_once(&mut chain, 1_030).await.is_empty());
+
+    let text = scrape(&metrics_address).await;
+    let m = monitor.to_string();
+    let sol = [("monitor", m.as_str()), ("market", "SOL/USDC")];
+    assert_eq!(sample(&text, "orderbook_events_total", &[sol[0], sol[1], ("type", "placed")]), Some(1.0));
+    assert_eq!(sample(&text, "orderbook_events_total", &[sol[0], sol[1], ("type", "filled")]), Some(1.0));
+    assert_eq!(sample(&text, "orderbook_last_event_age_seconds", &sol), Some(20.0));
+    assert_eq!(sample(&text, "orderbook_last_event_age_seconds", &[sol[0], ("market", "BTC/USDC")]), Some(10.0));
+    assert_eq!(sample(&text, "orderbook_last_price", &sol), Some(101.0));
+    assert_eq!(sample(&text, "orderbook_spread", &sol), Some(3.0));
+    assert_eq!(sample(&text, "orderbook_recorder_tx_failures_total", &sol[..1]), Some(1.0));
+    let fullness = sample(&text, "orderbook_account_fullness_percent", &sol[..1]).unwrap();
+    assert!(fullness > 0.0 && fullness < 100.0, "{}", fullness);
+
+    // Only events and transactions new since the last poll are counted
+    events.push(event(1_040, "SOL/USDC", 100, OrderCancelled));
+    {
+        let mut node = node.lock().unwrap();
+        node.data = account(events);
+        node.signatures.insert(0, (Signature::new_unique(), true));
+    }
+    assert!(exporter.poll_once(&mut chain, 1_040).await.is_empty());
+    let text = scrape(&metrics_address).await;
+    assert_eq!(sample(&text, "orderbook_events_total", &[sol[0], sol[1], ("type", "placed")]), Some(1.0));
+    assert_eq!(sample(&text, "orderbook_events_total", &[sol[0], sol[1], ("type", "cancelled")]), Some(1.0));
+    assert_eq!(sample(&text, "orderbook_last_event_age_seconds", &sol), Some(0.0));
+    assert_eq!(sample(&text, "orderbook_recorder_tx_failures_total", &sol[..1]), Some(2.0));
+
+    // More transactions than one page since the last poll are all checked
+    {
+        let mut node = node.lock().unwrap();
+        let burst = (0..SIGNATURE_PAGE + 5).map(|i| (Signature::new_unique(), i % 100 == 0));
+        node.signatures.splice(0..0, burst);
+    }
+    assert!(exporter.poll_once(&mut chain, 1_045).await.is_empty());
+    let text = scrape(&metrics_address).await;
+    assert_eq!(sample(&text, "orderbook_recorder_tx_failures_total", &sol[..1]), Some(2.0 + 11.0));
+
+    // A broken account is counted as a failed poll
+    node.lock().unwrap().data = vec![7; 4];
+    let errors = exporter.poll_once(&mut chain, 1_050).await;
+    assert_eq!(errors.len(), 1);
+    let text = scrape(&metrics_address).await;
+    assert_eq!(sample(&text, "orderbook_exporter_poll_errors_total", &sol[..1]), Some(1.0));
+}
@@ -0,0 +1,265 @@
+mod common;
+
+use common::BanksChain;
+use orderbook_monitor_cli::{
+    chain::Chain,
+    feeder::{Checkpoint, Feeder, PendingBatch, Rejected},
+};
+use solana_orderbook_monitor::{
+    adapter::SerumAdapter,
+    client::{initialize, record_events},
+    process_instruction,
+    serum::MarketState,
+    NewEvent, OrderbookError, OrderbookEventType, OrderbookMonitor,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use std::path::PathBuf;
+
+const MARKET: &[u8] = include_bytes!("../../tests/fixtures/serum/market.bin");
+const EVENT_QUEUE: &[u8] = include_bytes!("../../tests/fixtures/serum/event_queue.bin");
+
+// Serum v1 event queue layout: 5 bytes of padding, then the header fields
+const QUEUE_COUNT_OFFSET: usize = 5 + 16;
+const QUEUE_SEQ_NUM_OFFSET: usize = 5 + 24;
+const QUEUE_EVENTS_OFFSET: usize = 5 + 32;
+const EVENT_LEN: usize = 88;
+
+struct Setup {
+    context: ProgramTestContext,
+    program_id: Pubkey,
+    market: Pubkey,
+    event_queue: Pubkey,
+    monitor: Keypair,
+    checkpoint: PathBuf,
+}
+
+async fn setup() -> Setup {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "solana_orderbook_monitor",
+        program_id,
+        processor!(process_instruction),
+    );
+    program_test.prefer_bpf(false);
+
+    // A mock Serum market whose event queue lives at the address it points to
+    let dex_program = Pubkey::new_unique();
+    let market = Pubkey::new_unique();
+    let event_queue = MarketState::decode(MARKET).unwrap().event_queue;
+    for (address, data) in [(market, MARKET), (event_queue, EVENT_QUEUE)] {
+        program_test.add_account(
+            address,
+            Account {
+                lamports: 1_000_000_000,
+                data: data.to_vec(),
+                owner: dex_program,
+                ..Account::default()
+            },
+        );
+    }
+
+    let monitor = Keypair::new();
+    program_test.add_account(
+        monitor.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            data: vec![0; 4096],
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+
+    let mut context = program_test.start_with_context().await;
+    let transaction = Transaction::new_signed_with_payer(
+        &[initialize(&program_id, &monitor.pubkey())],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &monitor],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await.unwrap();
+
+    let checkpoint = std::env::temp_dir().join(format!(
+        "feeder-{}-{}.json",
+        std::process::id(),
+        Pubkey::new_unique()
+    ));
+    Setup { context, program_id, market, event_queue, monitor, checkpoint }
+}
+
+fn feeder(setup: &Setup) -> Feeder {
+    feeder_for(setup, setup.market, "SOL/USDC")
+}
+
+fn feeder_for(setup: &Setup, market: Pubkey, market_name: &str) -> Feeder {
+    let adapter = SerumAdapter::new(market, MARKET, market_name).unwrap();
+    Feeder::new(
+        Box::new(adapter),
+        setup.program_id,
+        setup.monitor.pubkey(),
+        Keypair::from_bytes(&setup.context.payer.to_bytes()).unwrap(),
+        Keypair::from_bytes(&setup.monitor.to_bytes()).unwrap(),
+        setup.checkpoint.clone(),
+    )
+    .unwrap()
+}
+
+async fn monitor(setup: &mut Setup) -> OrderbookMonitor {
+    let account = setup
+        .context
+        .banks_client
+        .get_account(setup.monitor.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    OrderbookMonitor::unpack(&account.data).unwrap()
+}
+
+// Append a copy of the queue's maker fill as the next event
+fn push_event(queue: &mut [u8]) -> u64 {
+    let read = |queue: &[u8], offset: usize| {
+        u64::from_le_bytes(queue[offset..offset + 8].try_into().unwrap())
+    };
+    let head = read(queue, 5 + 8);
+    let count = read(queue, QUEUE_COUNT_OFFSET);
+    let seq_num = read(queue, QUEUE_SEQ_NUM_OFFSET);
+    let capacity = (queue.len() - QUEUE_EVENTS_OFFSET - 7) as u64 / EVENT_LEN as u64;
+
+    let slot = |index: u64| QUEUE_EVENTS_OFFSET + ((head + index) % capacity) as usize * EVENT_LEN;
+    let maker_fill = queue[slot(1)..slot(1) + EVENT_LEN].to_vec();
+    let target = slot(count);
+    queue[target..target + EVENT_LEN].copy_from_slice(&maker_fill);
+    queue[QUEUE_COUNT_OFFSET..QUEUE_COUNT_OFFSET + 8].copy_from_slice(&(count + 1).to_le_bytes());
+    queue[QUEUE_SEQ_NUM_OFFSET..QUEUE_SEQ_NUM_OFFSET + 8].copy_from_slice(&(seq_num + 1).to_le_bytes());
+    seq_num
+}
+
+async fn set_queue(setup: &mut Setup, data: &[u8]) {
+    let mut account = AccountSharedData::new(1_000_000_000, data.len(), &Pubkey::new_unique());
+    account.set_data_from_slice(data);
+    setup.context.set_account(&setup.event_queue, &account);
+}
+
+#[tokio::test]
+async fn test_feeder_records_queue_once_across_restarts() {
+    let mut setup = setup().await;
+    let mut chain = BanksChain(setup.context.banks_client.clone());
+
+    // The maker fill and the cancellation are recorded; the taker fill and
+    // the empty out are not
+    let mut feeder = feeder(&setup);
+    let outcome = feeder.poll_once(&mut chain).await.unwrap();
+    assert_eq!(outcome.recorded, 2);
+    assert_eq!(feeder.checkpoint().next_seq, Some(10));
+
+    let recorded = monitor(&mut setup).await;
+    assert_eq!(recorded.event_count, 2);
+    assert_eq!(recorded.events[0].event_type, OrderbookEventType::OrderFilled);
+    assert_eq!(recorded.events[1].event_type, OrderbookEventType::OrderCancelled);
+    assert_eq!(recorded.events[1].market_name, "SOL/USDC");
+
+    // Polling the same queue again records nothing
+    assert_eq!(feeder.poll_once(&mut chain).await.unwrap().recorded, 0);
+
+    // A restarted feeder resumes from the checkpoint
+    let mut feeder = crate::feeder(&setup);
+    assert_eq!(feeder.poll_once(&mut chain).await.unwrap().recorded, 0);
+
+    // Only the new event is recorded once the queue grows
+    let mut queue = EVENT_QUEUE.to_vec();
+    push_event(&mut queue);
+    set_queue(&mut setup, &queue).await;
+    let outcome = feeder.poll_once(&mut chain).await.unwrap();
+    assert_eq!(outcome.recorded, 1);
+    assert_eq!(outcome.missed, 0);
+    assert_eq!(monitor(&mut setup).await.event_count, 3);
+
+    std::fs::remove_file(&setup.checkpoint).unwrap();
+}
+
+#[tokio::test]
+async fn test_feeder_reconciles_pending_batch() {
+    let mut setup = setup().await;
+    let mut chain = BanksChain(setup.context.banks_client.clone());
+
+    // Simulate a feeder that sent a batch for sequence number 10 and crashed
+    // before recording that it landed
+    let mut queue = EVENT_QUEUE.to_vec();
+    let seq = push_event(&mut queue);
+    set_queue(&mut setup, &queue).await;
+
+    let (blockhash, last_valid_block_height) = chain.latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[record_events(
+            &setup.program_id,
+            &setup.monitor.pubkey(),
+            &setup.market,
+            &setup.monitor.pubkey(),
+            vec![NewEvent {
+                market_name: "SOL/USDC".to_string(),
+                price: 25010,
+                size: 10,
+                is_bid: false,
+                event_type: OrderbookEventType::OrderFilled,
+            }],
+        )],
+        Some(&setup.context.payer.pubkey()),
+        &[&setup.context.payer, &setup.monitor],
+        blockhash,
+    );
+    chain.send_and_confirm(&transaction).await.unwrap();
+
+    Checkpoint {
+        next_seq: Some(seq),
+        pending: Some(PendingBatch {
+            signature: transaction.signatures[0].to_string(),
+            last_seq: seq,
+            last_valid_block_height,
+        }),
+    }
+    .save(&setup.checkpoint)
+    .unwrap();
+
+    // The restarted feeder sees the batch landed and does not send it again
+    let mut feeder = feeder(&setup);
+    let outcome = feeder.poll_once(&mut chain).await.unwrap();
+    assert_eq!(outcome.recorded, 0);
+    assert_eq!(feeder.checkpoint().next_seq, Some(seq + 1));
+    assert_eq!(feeder.checkpoint().pending, None);
+    assert_eq!(monitor(&mut setup).await.event_count, 1);
+
+    std::fs::remove_file(&setup.checkpoint).unwrap();
+}
+
+#[tokio::test]
+async fn test_feeder_skips_rejected_events() {
+    let mut setup = setup().await;
+    let mut chain = BanksChain(setup.context.banks_client.clone());
+    let rejected = |error| vec![Rejected { seq_num: 7, error }, Rejected { seq_num: 8, error }];
+
+    // Events failing validation are never sent
+    let mut feeder = feeder_for(&setup, setup.market, "sol/usdc");
+    let outcome = feeder.poll_once(&mut chain).await.unwrap();
+    assert_eq!(outcome.recorded, 0);
+    assert_eq!(outcome.rejected, rejected(OrderbookError::InvalidMarketName));
+    assert_eq!(feeder.checkpoint().next_seq, Some(10));
+    std::fs::remove_file(&setup.checkpoint).unwrap();
+
+    // A batch the program refuses whatever the monitor's state is skipped
+    // instead of being sent again on every poll
+    let mut feeder = feeder_for(&setup, setup.monitor.pubkey(), "SOL/USDC");
+    let outcome = feeder.poll_once(&mut chain).await.unwrap();
+    assert_eq!(outcome.recorded, 0);
+    assert_eq!(outcome.rejected, rejected(OrderbookError::InvalidMarket));
+    assert_eq!(feeder.checkpoint().next_seq, Some(10));
+    assert_eq!(feeder.checkpoint().pending, None);
+    assert_eq!(feeder.poll_once(&mut chain).await.unwrap(), Default::default());
+    assert_eq!(monitor(&mut setup).await.event_count, 0);
+
+    std::fs::remove_file(&setup.checkpoint).unwrap();
+}
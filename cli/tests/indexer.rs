@@ -0,0 +1,108 @@
+mod common;
+
+use common::BanksChain;
+use orderbook_monitor_cli::{
+    export::EventKind,
+    indexer::{IndexCheckpoint, Indexer, Store},
+};
+use solana_orderbook_monitor::{
+    client::{initialize, record_events},
+    process_instruction, NewEvent, OrderbookEventType,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+
+async fn record(context: &mut ProgramTestContext, program_id: &Pubkey, monitor: &Keypair, prices: &[u64]) {
+    let events = prices
+        .iter()
+        .map(|&price| NewEvent {
+            market_name: "SOL/USDC".to_string(),
+            price,
+            size: 1,
+            is_bid: true,
+            event_type: OrderbookEventType::OrderFilled,
+        })
+        .collect();
+    let instruction = record_events(program_id, &monitor.pubkey(), &Pubkey::new_unique(), &monitor.pubkey(), events);
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, monitor],
+        blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_indexer_resumes_from_checkpoint() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "solana_orderbook_monitor",
+        program_id,
+        processor!(process_instruction),
+    );
+    program_test.prefer_bpf(false);
+    let monitor = Keypair::new();
+    program_test.add_account(
+        monitor.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            data: vec![0; 4096],
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    let mut context = program_test.start_with_context().await;
+    let transaction = Transaction::new_signed_with_payer(
+        &[initialize(&program_id, &monitor.pubkey())],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &monitor],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await.unwrap();
+
+    let db = std::env::temp_dir().join(format!("indexer-{}-{}.sqlite", std::process::id(), Pubkey::new_unique()));
+    let mut chain = BanksChain(context.banks_client.clone());
+
+    record(&mut context, &program_id, &monitor, &[100, 101]).await;
+    context.warp_to_slot(10).unwrap();
+    let mut indexer = Indexer::new(Store::open(&db).unwrap(), vec![monitor.pubkey()]);
+    assert_eq!(indexer.poll_once(&mut chain).await.unwrap(), 2);
+    let checkpoint = indexer.store().checkpoint(&monitor.pubkey()).unwrap().unwrap();
+    assert_eq!(checkpoint.next_event_index, 2);
+
+    // Nothing new at the same slot
+    assert_eq!(indexer.poll_once(&mut chain).await.unwrap(), 0);
+    drop(indexer);
+
+    // A restarted indexer picks up only what was recorded since
+    record(&mut context, &program_id, &monitor, &[102, 103, 104]).await;
+    context.warp_to_slot(20).unwrap();
+    let mut indexer = Indexer::new(Store::open(&db).unwrap(), vec![monitor.pubkey()]);
+    assert_eq!(indexer.poll_once(&mut chain).await.unwrap(), 3);
+
+    let store = indexer.store();
+    let slot = store.checkpoint(&monitor.pubkey()).unwrap().unwrap().slot;
+    assert!(slot > checkpoint.slot);
+    assert_eq!(
+        store.checkpoint(&monitor.pubkey()).unwrap(),
+        Some(IndexCheckpoint { slot, next_event_index: 5 })
+    );
+    let events = store.events(&monitor.pubkey(), 0).unwrap();
+    let prices: Vec<u64> = events.iter().map(|e| e.event.price).collect();
+    assert_eq!(prices, vec![100, 101, 102, 103, 104]);
+    assert!(events.iter().all(|e| e.event.event_type == EventKind::Filled));
+    assert_eq!(events[2].slot, slot);
+
+    let markets = store.markets().unwrap();
+    assert_eq!(markets.len(), 1);
+    assert_eq!(markets[0].event_count, 5);
+
+    std::fs::remove_file(&db).unwrap();
+}
@@ -0,0 +1,180 @@
+use solana_orderbook_monitor::{
+    client::{close, initialize_with_authority, record_event, resize, set_authority, set_paused},
+    process_instruction, OrderbookError, OrderbookEventType, OrderbookMonitor,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction::create_account,
+    transaction::{Transaction, TransactionError},
+};
+
+async fn send(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+fn custom_error(result: Result<(), BanksClientError>) -> Option<u32> {
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => Some(code),
+        _ => None,
+    }
+}
+
+async fn start(program_id: Pubkey) -> ProgramTestContext {
+    let mut program_test = ProgramTest::new(
+        "solana_orderbook_monitor",
+        program_id,
+        processor!(process_instruction),
+    );
+    program_test.prefer_bpf(false);
+    program_test.start_with_context().await
+}
+
+#[tokio::test]
+async fn test_authority_resize_and_close() {
+    let program_id = Pubkey::new_unique();
+    let mut context = start(program_id).await;
+    let rent = Rent::default();
+
+    // Create a monitor owned by a separate authority
+    let monitor = Keypair::new();
+    let authority = Keypair::new();
+    let payer = context.payer.pubkey();
+    send(
+        &mut context,
+        &[
+            create_account(&payer, &monitor.pubkey(), rent.minimum_balance(200), 200, &program_id),
+            initialize_with_authority(&program_id, &monitor.pubkey(), &authority.pubkey()),
+        ],
+        &[&monitor, &authority],
+    )
+    .await
+    .unwrap();
+
+    let record = |authority: &Pubkey| {
+        record_event(
+            &program_id,
+            &monitor.pubkey(),
+            &Pubkey::new_unique(),
+            authority,
+            "SOL/USDC".to_string(),
+            100,
+            1,
+            true,
+            OrderbookEventType::OrderPlaced,
+        )
+    };
+
+    // Only the authority may record
+    let result = send(&mut context, &[record(&monitor.pubkey())], &[&monitor]).await;
+    assert_eq!(custom_error(result), Some(OrderbookError::Unauthorized as u32));
+    send(&mut context, &[record(&authority.pubkey())], &[&authority]).await.unwrap();
+
+    // Nothing is recorded while paused
+    let pause = |paused| set_paused(&program_id, &monitor.pubkey(), &authority.pubkey(), paused);
+    send(&mut context, &[pause(true)], &[&authority]).await.unwrap();
+    let result = send(&mut context, &[record(&authority.pubkey())], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(OrderbookError::Paused as u32));
+    send(&mut context, &[pause(false)], &[&authority]).await.unwrap();
+
+    // Hand over to a new authority, which must sign
+    let new_authority = Keypair::new();
+    let hand_over = set_authority(&program_id, &monitor.pubkey(), &authority.pubkey(), &new_authority.pubkey());
+    let mut unsigned = hand_over.clone();
+    unsigned.accounts[2].is_signer = false;
+    let result = send(&mut context, &[unsigned], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(OrderbookError::Unauthorized as u32));
+    send(&mut context, &[hand_over], &[&authority, &new_authority]).await.unwrap();
+    let result = send(&mut context, &[record(&authority.pubkey())], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(OrderbookError::Unauthorized as u32));
+
+    // Grow the account; the payer tops up rent
+    send(
+        &mut context,
+        &[resize(&program_id, &monitor.pubkey(), &new_authority.pubkey(), &payer, 1000)],
+        &[&new_authority],
+    )
+    .await
+    .unwrap();
+    let account = context.banks_client.get_account(monitor.pubkey()).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), 1000);
+    assert_eq!(account.lamports, rent.minimum_balance(1000));
+    assert_eq!(OrderbookMonitor::unpack(&account.data).unwrap().event_count, 1);
+
+    // Shrinking below the recorded data is refused
+    let result = send(
+        &mut context,
+        &[resize(&program_id, &monitor.pubkey(), &new_authority.pubkey(), &payer, 50)],
+        &[&new_authority],
+    )
+    .await;
+    assert_eq!(custom_error(result), Some(OrderbookError::SizeTooSmall as u32));
+
+    // Closing hands every lamport to the destination
+    let destination = Pubkey::new_unique();
+    send(
+        &mut context,
+        &[close(&program_id, &monitor.pubkey(), &new_authority.pubkey(), &destination)],
+        &[&new_authority],
+    )
+    .await
+    .unwrap();
+    assert!(context.banks_client.get_account(monitor.pubkey()).await.unwrap().is_none());
+    let reclaimed = context.banks_client.get_balance(destination).await.unwrap();
+    assert_eq!(reclaimed, rent.minimum_balance(1000));
+}
+
+#[tokio::test]
+async fn test_initialize_requires_signatures() {
+    let program_id = Pubkey::new_unique();
+    let mut context = start(program_id).await;
+    let rent = Rent::default();
+
+    let monitor = Keypair::new();
+    let authority = Keypair::new();
+    let payer = context.payer.pubkey();
+    send(
+        &mut context,
+        &[create_account(&payer, &monitor.pubkey(), rent.minimum_balance(200), 200, &program_id)],
+        &[&monitor],
+    )
+    .await
+    .unwrap();
+
+    let unsigned = |monitor_signs, authority_signs| {
+        let mut instruction = initialize_with_authority(&program_id, &monitor.pubkey(), &authority.pubkey());
+        instruction.accounts[0].is_signer = monitor_signs;
+        instruction.accounts[1].is_signer = authority_signs;
+        instruction
+    };
+
+    // Someone else cannot claim the account, nor name an authority that did not sign
+    let result = send(&mut context, &[unsigned(false, false)], &[]).await;
+    assert_eq!(custom_error(result), Some(OrderbookError::Unauthorized as u32));
+    let result = send(&mut context, &[unsigned(false, true)], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(OrderbookError::Unauthorized as u32));
+    let result = send(&mut context, &[unsigned(true, false)], &[&monitor]).await;
+    assert_eq!(custom_error(result), Some(OrderbookError::Unauthorized as u32));
+    let account = context.banks_client.get_account(monitor.pubkey()).await.unwrap().unwrap();
+    assert!(OrderbookMonitor::unpack(&account.data).is_ok_and(|m| !m.initialized));
+
+    send(&mut context, &[unsigned(true, true)], &[&monitor, &authority]).await.unwrap();
+    let account = context.banks_client.get_account(monitor.pubkey()).await.unwrap().unwrap();
+    assert_eq!(OrderbookMonitor::unpack(&account.data).unwrap().authority, authority.pubkey());
+}
@@ -2,7 +2,6 @@ use solana_client::rpc_client::RpcClient;
 use solana_program::{
     pubkey::Pubkey, 
     system_instruction::create_account, 
-    system_program,
 };
 use solana_sdk::{
     signature::{Keypair, Signer},
@@ -11,11 +10,12 @@ use solana_sdk::{
 };
 use solana_orderbook_monitor::{
     client::{initialize, record_event},
-    OrderbookEventType,
+    OrderbookEventType, OrderbookMonitor, MAX_MARKET_NAME_LEN,
 };
 use std::str::FromStr;
 
 // This example demonstrates how to interact with the orderbook monitor program
+#[allow(clippy::inconsistent_digit_grouping)]
 fn main() {
     // Connect to the Solana cluster
     let rpc_url = "https://api.devnet.solana.com".to_string();
@@ -30,13 +30,8 @@ fn main() {
     // Create a new keypair for the monitor account
     let monitor_account = Keypair::new();
     
-    // Space needed for the monitor account (example estimation)
-    let space = 8 + // Discriminator
-                1 + // initialized: bool
-                32 + // authority: Pubkey
-                8 + // event_count: u64
-                4 + // events.len() prefixed length
-                0; // We start with 0 events
+    // Space for 100 events with market names of any allowed length
+    let space = OrderbookMonitor::space(100, MAX_MARKET_NAME_LEN);
     
     // Calculate rent exemption
     let rent = client.get_minimum_balance_for_rent_exemption(space).unwrap();
@@ -87,6 +82,7 @@ fn main() {
         &program_id,
         &monitor_account.pubkey(),
         &market_account.pubkey(),
+        &monitor_account.pubkey(), // The monitor account is its own authority
         "SOL/USDC".to_string(),
         2500_000_000, // Price in lamports (e.g., $25.00 with 8 decimals)
         10_000_000,   // Size in lamports (e.g., 0.1 SOL)
@@ -97,7 +93,7 @@ fn main() {
     let record_tx = Transaction::new_signed_with_payer(
         &[record_event_ix],
         Some(&payer.pubkey()),
-        &[&payer],
+        &[&payer, &monitor_account],
         client.get_latest_blockhash().unwrap(),
     );
     
@@ -111,6 +107,7 @@ fn main() {
         &program_id,
         &monitor_account.pubkey(),
         &market_account.pubkey(),
+        &monitor_account.pubkey(),
         "SOL/USDC".to_string(),
         2500_000_000, // Price in lamports
         5_000_000,    // Partial fill size
@@ -121,7 +118,7 @@ fn main() {
     let record_filled_tx = Transaction::new_signed_with_payer(
         &[record_filled_ix],
         Some(&payer.pubkey()),
-        &[&payer],
+        &[&payer, &monitor_account],
         client.get_latest_blockhash().unwrap(),
     );
     
@@ -1,11 +1,15 @@
 use solana_client::rpc_client::RpcClient;
-use solana_program::{
-    pubkey::Pubkey,
-    borsh::try_from_slice_unchecked,
-};
-use solana_orderbook_monitor::{OrderbookMonitor, OrderbookEventType};
+use solana_program::pubkey::Pubkey;
+use solana_orderbook_monitor::{analytics, OrderbookMonitor, OrderbookEventType};
 use std::str::FromStr;
-use std::collections::HashMap;
+
+fn percent(share: Option<f64>) -> String {
+    share.map_or("n/a".to_string(), |share| format!("{:.1}%", share * 100.0))
+}
+
+fn number(value: Option<f64>) -> String {
+    value.map_or("n/a".to_string(), |value| format!("{:.4}", value))
+}
 
 /// This example demonstrates how to extract and analyze orderbook data from an
 /// initialized monitor account
@@ -13,93 +17,60 @@ fn main() {
     // Connect to the Solana cluster
     let rpc_url = "https://api.devnet.solana.com".to_string();
     let client = RpcClient::new(rpc_url);
-    
+
     // Replace with your actual monitor account address
     let monitor_address = Pubkey::from_str("YOUR_MONITOR_ACCOUNT_ADDRESS").expect("Invalid monitor address");
-    
+
     // Fetch the account data
     let account = client.get_account(&monitor_address).expect("Failed to fetch monitor account");
-    
+
     // Deserialize the account data
-    let monitor: OrderbookMonitor = try_from_slice_unchecked(&account.data)
+    let monitor = OrderbookMonitor::unpack(&account.data)
         .expect("Failed to deserialize monitor account data");
-    
+    let summary = analytics::summarize(&monitor);
+
     println!("=== Orderbook Monitor Analysis ===");
-    println!("Total events recorded: {}", monitor.event_count);
+    println!("Total events recorded: {}", summary.event_count);
     println!("Authority: {}", monitor.authority);
-    println!("");
-    
-    // Calculate market activity
-    let mut markets = HashMap::new();
-    for event in &monitor.events {
-        let counter = markets.entry(event.market_name.clone()).or_insert(0);
-        *counter += 1;
-    }
-    
+    println!();
+
     println!("=== Market Activity ===");
-    for (market, count) in markets {
-        println!("{}: {} events", market, count);
-    }
-    println!("");
-    
-    // Calculate event type distribution
-    let mut event_types = HashMap::new();
-    for event in &monitor.events {
-        let event_type = match event.event_type {
-            OrderbookEventType::OrderPlaced => "Order Placed",
-            OrderbookEventType::OrderFilled => "Order Filled",
-            OrderbookEventType::OrderCancelled => "Order Cancelled",
-        };
-        let counter = event_types.entry(event_type).or_insert(0);
-        *counter += 1;
+    for market in &summary.markets {
+        println!("{}: {} events", market.market_name, market.event_types.total());
     }
-    
+    println!();
+
     println!("=== Event Type Distribution ===");
-    for (event_type, count) in event_types {
-        println!("{}: {} events", event_type, count);
-    }
-    println!("");
-    
-    // Calculate bid/ask distribution
-    let mut bids = 0;
-    let mut asks = 0;
-    for event in &monitor.events {
-        if event.is_bid {
-            bids += 1;
-        } else {
-            asks += 1;
-        }
-    }
-    
+    println!("Order Placed: {} events", summary.event_types.placed);
+    println!("Order Filled: {} events", summary.event_types.filled);
+    println!("Order Cancelled: {} events", summary.event_types.cancelled);
+    println!("Fill ratio: {}", number(summary.event_types.fill_ratio()));
+    println!("Cancel ratio: {}", number(summary.event_types.cancel_ratio()));
+    println!();
+
     println!("=== Bid/Ask Distribution ===");
-    println!("Bids: {} events ({}%)", bids, (bids as f64 / monitor.event_count as f64) * 100.0);
-    println!("Asks: {} events ({}%)", asks, (asks as f64 / monitor.event_count as f64) * 100.0);
-    println!("");
-    
-    // Calculate price statistics (for a specific market)
-    if !monitor.events.is_empty() {
-        let target_market = &monitor.events[0].market_name;
-        let mut prices = vec![];
-        
-        for event in &monitor.events {
-            if &event.market_name == target_market {
-                prices.push(event.price);
-            }
+    let share = summary.sides.bid_share();
+    println!("Bids: {} events ({})", summary.sides.bids, percent(share));
+    println!("Asks: {} events ({})", summary.sides.asks, percent(share.map(|share| 1.0 - share)));
+    println!("Bid/ask ratio: {}", number(summary.sides.bid_ask_ratio()));
+    println!();
+
+    for market in &summary.markets {
+        println!("=== Statistics for {} ===", market.market_name);
+        if let Some(prices) = market.prices {
+            println!("Min price: {}", prices.min);
+            println!("Max price: {}", prices.max);
+            println!("Avg price: {:.2}", prices.mean);
         }
-        
-        if !prices.is_empty() {
-            let min_price = prices.iter().min().unwrap();
-            let max_price = prices.iter().max().unwrap();
-            let avg_price = prices.iter().sum::<u64>() as f64 / prices.len() as f64;
-            
-            println!("=== Price Statistics for {} ===", target_market);
-            println!("Min price: {}", min_price);
-            println!("Max price: {}", max_price);
-            println!("Avg price: {:.2}", avg_price);
-            println!("");
+        println!("Filled volume: {}", market.volume);
+        println!("VWAP: {}", number(market.vwap));
+        println!("Realized volatility: {}", number(market.realized_volatility));
+        if let Some(spread) = market.spread {
+            println!("Spread: {} ({:.1} bps)", spread.absolute, spread.bps);
         }
+        println!();
     }
-    
+
     // Display recent events (last 5)
     println!("=== Recent Events ===");
     for (i, event) in monitor.events.iter().rev().take(5).enumerate() {
@@ -108,8 +79,8 @@ fn main() {
             OrderbookEventType::OrderFilled => "Order Filled",
             OrderbookEventType::OrderCancelled => "Order Cancelled",
         };
-        
-        println!("Event #{}: {} {} on {} for {} at price {}", 
+
+        println!("Event #{}: {} {} on {} for {} at price {}",
             monitor.event_count - i as u64,
             if event.is_bid { "BID" } else { "ASK" },
             event_type,
@@ -117,4 +88,4 @@ fn main() {
             event.size,
             event.price);
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,77 @@
+use solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use solana_orderbook_monitor::{
+    snapshot::{depth_chart, verify_monitor},
+    OrderbookMonitor,
+};
+use std::str::FromStr;
+
+/// This example reads the book snapshots recorded in a monitor account, prints
+/// the depth chart of the latest snapshot per market and checks every pair of
+/// consecutive snapshots against the events recorded between them
+fn main() {
+    // Connect to the Solana cluster
+    let rpc_url = "https://api.devnet.solana.com".to_string();
+    let client = RpcClient::new(rpc_url);
+
+    // Replace with your actual monitor account address
+    let monitor_address = Pubkey::from_str("YOUR_MONITOR_ACCOUNT_ADDRESS").expect("Invalid monitor address");
+
+    // Fetch and deserialize the account data
+    let account = client.get_account(&monitor_address).expect("Failed to fetch monitor account");
+    let monitor = OrderbookMonitor::unpack(&account.data)
+        .expect("Failed to deserialize monitor account data");
+
+    println!("=== Book Snapshots ===");
+    println!("Total snapshots recorded: {}", monitor.snapshots.len());
+    println!();
+
+    // Depth chart of the most recent snapshot of each market
+    let mut printed = Vec::new();
+    for snapshot in monitor.snapshots.iter().rev() {
+        if printed.contains(&&snapshot.market_name) {
+            continue;
+        }
+        printed.push(&snapshot.market_name);
+
+        let chart = depth_chart(snapshot);
+        println!("=== Depth for {} at slot {} ===", snapshot.market_name, snapshot.slot);
+        for point in chart.asks.iter().rev() {
+            println!("ASK {:>20} {:>16} {:>16}", point.price, point.size, point.cumulative);
+        }
+        for point in &chart.bids {
+            println!("BID {:>20} {:>16} {:>16}", point.price, point.size, point.cumulative);
+        }
+        println!();
+    }
+
+    // Replay events between consecutive snapshots and compare
+    println!("=== Snapshot Consistency ===");
+    for check in verify_monitor(&monitor) {
+        if check.mismatches.is_empty() {
+            println!(
+                "{}: slots {} -> {} consistent ({} events replayed)",
+                check.market_name, check.from_slot, check.to_slot, check.events_replayed
+            );
+            continue;
+        }
+
+        println!(
+            "{}: slots {} -> {} has {} mismatched levels ({} events replayed)",
+            check.market_name,
+            check.from_slot,
+            check.to_slot,
+            check.mismatches.len(),
+            check.events_replayed
+        );
+        for mismatch in check.mismatches {
+            println!(
+                "  {} {}: replayed {} recorded {}",
+                if mismatch.is_bid { "BID" } else { "ASK" },
+                mismatch.price,
+                mismatch.replayed,
+                mismatch.recorded
+            );
+        }
+    }
+}
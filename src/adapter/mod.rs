@@ -0,0 +1,148 @@
+//! Normalized access to the order books of different DEX programs.
+//!
+//! Each venue stores its book and events in its own account layout. A
+//! `MarketAdapter` decodes those accounts into the types this program records:
+//! `OrderbookEvent`s and L2 `PriceLevel`s. Prices and sizes stay in the
+//! venue's native lot (or tick) units; `MarketMetadata` carries the lot sizes
+//! needed to convert them.
+
+use crate::layout::TooShort;
+use crate::serum::SerumError;
+use crate::{OrderbookEvent, PriceLevel};
+use solana_program::pubkey::Pubkey;
+use std::fmt;
+use std::ops::Range;
+use thiserror::Error;
+
+pub mod openbook_v2;
+pub mod phoenix;
+pub mod serum;
+
+pub use self::openbook_v2::OpenBookV2Adapter;
+pub use self::phoenix::PhoenixAdapter;
+pub use self::serum::SerumAdapter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Venue {
+    SerumV1,
+    OpenBookV2,
+    Phoenix,
+}
+
+impl fmt::Display for Venue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Venue::SerumV1 => "Serum/OpenBook v1",
+            Venue::OpenBookV2 => "OpenBook v2",
+            Venue::Phoenix => "Phoenix",
+        })
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AdapterError {
+    #[error(transparent)]
+    Serum(#[from] SerumError),
+    #[error("Account data is too short")]
+    TooShort,
+    #[error("Unexpected account discriminator")]
+    InvalidDiscriminator,
+    #[error("Invalid book node {0}")]
+    InvalidNode(u32),
+    #[error("{0} does not keep its events in an account")]
+    EventsUnsupported(Venue),
+}
+
+impl From<TooShort> for AdapterError {
+    fn from(_: TooShort) -> Self {
+        AdapterError::TooShort
+    }
+}
+
+/// Static description of a market, decoded from its market account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarketMetadata {
+    pub venue: Venue,
+    /// Name recorded with every event of this market, e.g. `SOL/USDC`
+    pub name: String,
+    pub address: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    /// Base atoms per base lot
+    pub base_lot_size: u64,
+    /// Quote atoms per quote lot
+    pub quote_lot_size: u64,
+    /// Account holding the bids, passed to `decode_levels`
+    pub bids: Pubkey,
+    /// Account holding the asks, passed to `decode_levels`
+    pub asks: Pubkey,
+    /// Account holding the event queue, passed to `decode_events`
+    pub event_queue: Option<Pubkey>,
+}
+
+/// A normalized event together with its position in the venue's event stream
+#[derive(Debug, Clone)]
+pub struct SequencedEvent {
+    pub seq_num: u64,
+    /// Id of the resting order the event applies to, if the venue's events carry one
+    pub order_id: Option<u128>,
+    /// Owner of that order, if the venue's events carry one: the open orders
+    /// account on Serum v1 and the open orders account of the maker (fills)
+    /// or of the cancelled order (outs) on OpenBook v2
+    pub owner: Option<Pubkey>,
+    /// Owner of the taking order of a fill, if the venue's fill events carry
+    /// it: the taker's open orders account on OpenBook v2
+    pub taker: Option<Pubkey>,
+    pub event: OrderbookEvent,
+}
+
+/// Decodes one market's accounts into normalized events and book levels.
+///
+/// Fills are reported once per trade, on the side of the resting (maker)
+/// order, so that replaying them against L2 levels reduces the right side.
+pub trait MarketAdapter {
+    fn metadata(&self) -> &MarketMetadata;
+
+    /// Decode the events currently held in the event queue account, oldest first
+    fn decode_events(&self, data: &[u8]) -> Result<Vec<SequencedEvent>, AdapterError>;
+
+    /// Sequence numbers of every entry in the event queue account, including
+    /// entries `decode_events` skips
+    fn seq_range(&self, data: &[u8]) -> Result<Range<u64>, AdapterError>;
+
+    /// Decode one side of the book into at most `depth` levels, best price first
+    fn decode_levels(
+        &self,
+        data: &[u8],
+        is_bid: bool,
+        depth: usize,
+    ) -> Result<Vec<PriceLevel>, AdapterError>;
+}
+
+/// Aggregate `(price, size)` pairs already sorted best price first into levels
+pub(crate) fn aggregate_levels(
+    orders: impl IntoIterator<Item = (u64, u64)>,
+    depth: usize,
+) -> Vec<PriceLevel> {
+    let mut levels: Vec<PriceLevel> = Vec::new();
+    for (price, size) in orders {
+        if let Some(level) = levels.last_mut().filter(|l| l.price == price) {
+            level.size = level.size.saturating_add(size);
+            continue;
+        }
+        if levels.len() == depth {
+            break;
+        }
+        levels.push(PriceLevel { price, size });
+    }
+    levels
+}
+
+/// Check an Anchor account discriminator
+pub(crate) fn check_discriminator(data: &[u8], expected: [u8; 8]) -> Result<(), AdapterError> {
+    match data.get(..8) {
+        Some(discriminator) if discriminator == expected => Ok(()),
+        Some(_) => Err(AdapterError::InvalidDiscriminator),
+        None => Err(AdapterError::TooShort),
+    }
+}
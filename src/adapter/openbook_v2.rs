@@ -0,0 +1,305 @@
+//! OpenBook v2 adapter.
+//!
+//! OpenBook v2 is an Anchor program: every account starts with an 8-byte
+//! discriminator followed by a zero-copy struct. Only the fixed-price order
+//! tree of a book side is decoded; oracle-pegged orders are skipped because
+//! their price depends on the oracle at read time.
+
+use super::{
+    aggregate_levels, check_discriminator, AdapterError, MarketAdapter, MarketMetadata,
+    SequencedEvent, Venue,
+};
+use crate::layout::Reader;
+use crate::{OrderbookEvent, OrderbookEventType, PriceLevel};
+use solana_program::pubkey::Pubkey;
+use std::ops::Range;
+
+const MARKET_DISCRIMINATOR: [u8; 8] = [219, 190, 213, 55, 0, 227, 198, 154];
+const BOOK_SIDE_DISCRIMINATOR: [u8; 8] = [72, 44, 225, 141, 178, 130, 97, 57];
+const EVENT_HEAP_DISCRIMINATOR: [u8; 8] = [119, 59, 61, 19, 165, 84, 57, 175];
+
+// Offsets include the discriminator. `Market` in openbook-v2
+// `state/market.rs`: `name` follows the bump, decimals, padding and five
+// authority keys; `bids`, `asks` and `event_heap` come right after it; the
+// lot sizes follow the two oracles and the 88-byte `OracleConfig`; the mints
+// follow the fee and volume counters
+const MARKET_NAME_OFFSET: usize = 184;
+const MARKET_BOOK_OFFSET: usize = 200;
+const MARKET_LOT_SIZE_OFFSET: usize = 448;
+const MARKET_MINT_OFFSET: usize = 576;
+
+// `BookSide` in `state/orderbook/bookside.rs`: the fixed and oracle-pegged
+// `OrderTreeRoot`s, reserved roots and 256 reserved bytes, then
+// `OrderTreeNodes` whose 88-byte `AnyNode`s follow a 528-byte header
+const BOOK_ROOT_OFFSET: usize = 8;
+const BOOK_TREE_TYPE_OFFSET: usize = 312;
+const BOOK_NODES_OFFSET: usize = 840;
+const BOOK_NODE_LEN: usize = 88;
+
+// `EventHeap` in `state/orderbook/heap.rs`: an `EventHeapHeader` then
+// `EventNode`s of two u16 links, 4 bytes of padding and a 144-byte `AnyEvent`
+const HEAP_HEADER_OFFSET: usize = 8;
+const HEAP_NODES_OFFSET: usize = 24;
+const HEAP_NODE_LEN: usize = 152;
+
+const NODE_INNER: u8 = 1;
+const NODE_LEAF: u8 = 2;
+
+const EVENT_FILL: u8 = 0;
+const EVENT_OUT: u8 = 1;
+
+const SIDE_BID: u8 = 0;
+
+// Used list head, event count and next sequence number of an event heap
+fn heap_header(data: &[u8]) -> Result<(u16, u16, u64), AdapterError> {
+    check_discriminator(data, EVENT_HEAP_DISCRIMINATOR)?;
+    let header = &mut Reader::at(data, HEAP_HEADER_OFFSET, 16)?;
+    let _free_head = header.u16()?;
+    let used_head = header.u16()?;
+    let count = header.u16()?;
+    header.skip(2)?;
+    Ok((used_head, count, header.u64()?))
+}
+
+pub struct OpenBookV2Adapter {
+    metadata: MarketMetadata,
+}
+
+impl OpenBookV2Adapter {
+    pub fn new(address: Pubkey, market_data: &[u8]) -> Result<Self, AdapterError> {
+        check_discriminator(market_data, MARKET_DISCRIMINATOR)?;
+
+        // The market name is stored NUL-padded on chain
+        let name = Reader::at(market_data, MARKET_NAME_OFFSET, 16)?.take(16)?;
+        let name = String::from_utf8_lossy(name).trim_end_matches('\0').to_string();
+
+        let book = &mut Reader::at(market_data, MARKET_BOOK_OFFSET, 96)?;
+        let bids = book.pubkey()?;
+        let asks = book.pubkey()?;
+        let event_heap = book.pubkey()?;
+
+        let lots = &mut Reader::at(market_data, MARKET_LOT_SIZE_OFFSET, 16)?;
+        let quote_lot_size = lots.i64()? as u64;
+        let base_lot_size = lots.i64()? as u64;
+
+        let mints = &mut Reader::at(market_data, MARKET_MINT_OFFSET, 64)?;
+        let base_mint = mints.pubkey()?;
+        let quote_mint = mints.pubkey()?;
+
+        Ok(OpenBookV2Adapter {
+            metadata: MarketMetadata {
+                venue: Venue::OpenBookV2,
+                name,
+                address,
+                base_mint,
+                quote_mint,
+                base_lot_size,
+                quote_lot_size,
+                bids,
+                asks,
+                event_queue: Some(event_heap),
+            },
+        })
+    }
+
+    /// Record events under `name` instead of the name stored on chain
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.metadata.name = name.to_string();
+        self
+    }
+}
+
+impl MarketAdapter for OpenBookV2Adapter {
+    fn metadata(&self) -> &MarketMetadata {
+        &self.metadata
+    }
+
+    fn decode_events(&self, data: &[u8]) -> Result<Vec<SequencedEvent>, AdapterError> {
+        let (used_head, count, seq_num) = heap_header(data)?;
+
+        // The used nodes form a linked list from the oldest event
+        let mut events = Vec::new();
+        let mut index = used_head;
+        for i in 0..count as u64 {
+            let node = &mut Reader::at(
+                data,
+                HEAP_NODES_OFFSET + index as usize * HEAP_NODE_LEN,
+                HEAP_NODE_LEN,
+            )?;
+            let next = node.u16()?;
+            node.skip(6)?;
+
+            let event_type = node.u8()?;
+            let (owner, taker, event) = match event_type {
+                EVENT_FILL => {
+                    let taker_side = node.u8()?;
+                    node.skip(6)?;
+                    let timestamp = node.u64()? as i64;
+                    let _market_seq_num = node.u64()?;
+                    let maker = node.pubkey()?;
+                    let _maker_timestamp = node.u64()?;
+                    let taker = node.pubkey()?;
+                    let _taker_client_order_id = node.u64()?;
+                    let price = node.i64()? as u64;
+                    let _peg_limit = node.i64()?;
+                    let quantity = node.i64()? as u64;
+                    let event = OrderbookEvent {
+                        timestamp,
+                        market_name: self.metadata.name.clone(),
+                        price,
+                        size: quantity,
+                        is_bid: taker_side != SIDE_BID,
+                        event_type: OrderbookEventType::OrderFilled,
+                    };
+                    (maker, Some(taker), Some(event))
+                }
+                EVENT_OUT => {
+                    let side = node.u8()?;
+                    node.skip(6)?;
+                    let timestamp = node.u64()? as i64;
+                    let _seq_num = node.u64()?;
+                    let owner = node.pubkey()?;
+                    let quantity = node.i64()? as u64;
+                    // Out events do not carry the order's price
+                    let event = (quantity > 0).then(|| OrderbookEvent {
+                        timestamp,
+                        market_name: self.metadata.name.clone(),
+                        price: 0,
+                        size: quantity,
+                        is_bid: side == SIDE_BID,
+                        event_type: OrderbookEventType::OrderCancelled,
+                    });
+                    (owner, None, event)
+                }
+                _ => return Err(AdapterError::InvalidNode(index as u32)),
+            };
+
+            if let Some(event) = event {
+                events.push(SequencedEvent {
+                    seq_num: seq_num.wrapping_sub(count as u64).wrapping_add(i),
+                    // v2 events identify the maker by owner and client order id only
+                    order_id: None,
+                    owner: Some(owner),
+                    taker,
+                    event,
+                });
+            }
+            index = next;
+        }
+        Ok(events)
+    }
+
+    fn seq_range(&self, data: &[u8]) -> Result<Range<u64>, AdapterError> {
+        let (_, count, seq_num) = heap_header(data)?;
+        Ok(seq_num.wrapping_sub(count as u64)..seq_num)
+    }
+
+    fn decode_levels(
+        &self,
+        data: &[u8],
+        is_bid: bool,
+        depth: usize,
+    ) -> Result<Vec<PriceLevel>, AdapterError> {
+        check_discriminator(data, BOOK_SIDE_DISCRIMINATOR)?;
+        let tree_type = Reader::at(data, BOOK_TREE_TYPE_OFFSET, 1)?.u8()?;
+        if (tree_type == 0) != is_bid {
+            return Err(AdapterError::InvalidDiscriminator);
+        }
+
+        let root = &mut Reader::at(data, BOOK_ROOT_OFFSET, 8)?;
+        let root_node = root.u32()?;
+        let leaf_count = root.u32()?;
+
+        let node_count = data.len().saturating_sub(BOOK_NODES_OFFSET) / BOOK_NODE_LEN;
+        let mut orders = Vec::with_capacity(leaf_count as usize);
+
+        // In-order walk of the fixed-price tree yields orders by ascending key
+        let mut stack = if leaf_count > 0 { vec![root_node] } else { Vec::new() };
+        let mut visited = 0;
+        while let Some(index) = stack.pop() {
+            visited += 1;
+            if index as usize >= node_count || visited > node_count {
+                return Err(AdapterError::InvalidNode(index));
+            }
+            let node = &mut Reader::at(
+                data,
+                BOOK_NODES_OFFSET + index as usize * BOOK_NODE_LEN,
+                BOOK_NODE_LEN,
+            )?;
+            match node.u8()? {
+                NODE_INNER => {
+                    node.skip(3 + 4 + 16)?;
+                    let left = node.u32()?;
+                    let right = node.u32()?;
+                    stack.push(right);
+                    stack.push(left);
+                }
+                NODE_LEAF => {
+                    node.skip(1 + 2 + 4)?;
+                    let key = node.u128()?;
+                    node.skip(32)?;
+                    let quantity = node.i64()? as u64;
+                    orders.push(((key >> 64) as u64, quantity));
+                }
+                _ => return Err(AdapterError::InvalidNode(index)),
+            }
+        }
+
+        // Bids keep their best order at the highest key
+        if is_bid {
+            orders.reverse();
+        }
+        Ok(aggregate_levels(orders, depth))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKET: &[u8] = include_bytes!("../../tests/fixtures/openbook_v2/market.bin");
+    const BIDS: &[u8] = include_bytes!("../../tests/fixtures/openbook_v2/bids.bin");
+    const ASKS: &[u8] = include_bytes!("../../tests/fixtures/openbook_v2/asks.bin");
+    const EVENT_HEAP: &[u8] = include_bytes!("../../tests/fixtures/openbook_v2/event_heap.bin");
+
+    #[test]
+    fn test_openbook_v2_adapter() {
+        let adapter = OpenBookV2Adapter::new(Pubkey::new_unique(), MARKET).unwrap();
+        let metadata = adapter.metadata();
+        assert_eq!(metadata.venue, Venue::OpenBookV2);
+        assert_eq!(metadata.name, "SOL-USDC");
+        assert_eq!(metadata.base_lot_size, 1_000_000);
+        assert_eq!(metadata.quote_lot_size, 1);
+        assert!(metadata.event_queue.is_some());
+
+        let bids = adapter.decode_levels(BIDS, true, 10).unwrap();
+        assert_eq!(
+            bids,
+            vec![PriceLevel { price: 150_000, size: 7 }, PriceLevel { price: 149_900, size: 4 }]
+        );
+        let asks = adapter.decode_levels(ASKS, false, 10).unwrap();
+        assert_eq!(asks, vec![PriceLevel { price: 150_100, size: 9 }]);
+        assert_eq!(adapter.decode_levels(ASKS, true, 10), Err(AdapterError::InvalidDiscriminator));
+
+        let events = adapter.decode_events(EVENT_HEAP).unwrap();
+        let seqs: Vec<u64> = events.iter().map(|e| e.seq_num).collect();
+        assert_eq!(seqs, vec![40, 41]);
+        assert_eq!(adapter.seq_range(EVENT_HEAP).unwrap(), 39..42);
+
+        // A taker sell fills a resting bid
+        let fill = &events[0].event;
+        assert_eq!(fill.event_type, OrderbookEventType::OrderFilled);
+        assert!(fill.is_bid);
+        assert_eq!((fill.price, fill.size), (150_000, 3));
+        assert_eq!(fill.timestamp, 1_700_000_000);
+        assert!(events[0].owner.is_some() && events[0].taker.is_some());
+
+        let out = &events[1].event;
+        assert_eq!(out.event_type, OrderbookEventType::OrderCancelled);
+        assert!(!out.is_bid);
+        assert_eq!(out.size, 9);
+        assert!(events[1].owner.is_some() && events[1].taker.is_none());
+
+        assert_eq!(adapter.decode_events(MARKET).unwrap_err(), AdapterError::InvalidDiscriminator);
+    }
+}
@@ -0,0 +1,220 @@
+//! Phoenix adapter.
+//!
+//! A Phoenix market is a single account: a `MarketHeader` followed by a FIFO
+//! market holding one red-black tree per book side. Phoenix emits its fills
+//! and cancellations through instruction logs instead of an event queue
+//! account, so only metadata and book levels can be decoded from accounts.
+//! Prices are in ticks and sizes in base lots.
+
+use super::{aggregate_levels, AdapterError, MarketAdapter, MarketMetadata, SequencedEvent, Venue};
+use crate::layout::Reader;
+use crate::PriceLevel;
+use solana_program::pubkey::Pubkey;
+use std::ops::Range;
+
+// `MarketHeader` in phoenix-v1 `program/accounts.rs`: the discriminant and
+// status come before `MarketSizeParams`; each `TokenParams` is two u32s, the
+// mint and the vault, followed by its lot size
+const HEADER_SIZE_PARAMS_OFFSET: usize = 16;
+const HEADER_BASE_MINT_OFFSET: usize = 48;
+const HEADER_BASE_LOT_SIZE_OFFSET: usize = 112;
+const HEADER_QUOTE_MINT_OFFSET: usize = 128;
+const HEADER_QUOTE_LOT_SIZE_OFFSET: usize = 192;
+
+// `FIFOMarket` in phoenix-v1 `state/markets/fifo.rs` follows the 576-byte
+// `MarketHeader`: a `[u64; 32]` padding and six u64 fields come before the
+// bids `RedBlackTree`, with the asks tree right after it
+const BIDS_TREE_OFFSET: usize = 880;
+// A sokoban `RedBlackTree` is a 16-byte header holding the root, then a
+// `NodeAllocator`: `size: u64`, `bump_index: u32`, `free_list_head: u32` and
+// the nodes. A node is four u32 registers (left, right, parent, color), the
+// `FIFOOrderId` key (price in ticks, sequence number) and the
+// `FIFORestingOrder` value (trader index, base lots, two expiry fields)
+const ALLOCATOR_OFFSET: usize = 16;
+const TREE_HEADER_LEN: usize = 32;
+const TREE_NODE_LEN: usize = 64;
+
+// Sokoban trees address nodes from 1; 0 is the sentinel
+const SENTINEL: u32 = 0;
+
+pub struct PhoenixAdapter {
+    metadata: MarketMetadata,
+    bids_size: u64,
+    asks_size: u64,
+}
+
+impl PhoenixAdapter {
+    pub fn new(address: Pubkey, market_data: &[u8], name: &str) -> Result<Self, AdapterError> {
+        let sizes = &mut Reader::at(market_data, HEADER_SIZE_PARAMS_OFFSET, 16)?;
+        let bids_size = sizes.u64()?;
+        let asks_size = sizes.u64()?;
+
+        let base_mint = Reader::at(market_data, HEADER_BASE_MINT_OFFSET, 32)?.pubkey()?;
+        let base_lot_size = Reader::at(market_data, HEADER_BASE_LOT_SIZE_OFFSET, 8)?.u64()?;
+        let quote_mint = Reader::at(market_data, HEADER_QUOTE_MINT_OFFSET, 32)?.pubkey()?;
+        let quote_lot_size = Reader::at(market_data, HEADER_QUOTE_LOT_SIZE_OFFSET, 8)?.u64()?;
+
+        let adapter = PhoenixAdapter {
+            metadata: MarketMetadata {
+                venue: Venue::Phoenix,
+                name: name.to_string(),
+                address,
+                base_mint,
+                quote_mint,
+                base_lot_size,
+                quote_lot_size,
+                bids: address,
+                asks: address,
+                event_queue: None,
+            },
+            bids_size,
+            asks_size,
+        };
+
+        // Both trees must fit in the account
+        let end = adapter.tree_offset(false).saturating_add(adapter.tree_len(asks_size));
+        if market_data.len() < end {
+            return Err(AdapterError::TooShort);
+        }
+        Ok(adapter)
+    }
+
+    fn tree_len(&self, size: u64) -> usize {
+        TREE_HEADER_LEN.saturating_add((size as usize).saturating_mul(TREE_NODE_LEN))
+    }
+
+    fn tree_offset(&self, is_bid: bool) -> usize {
+        if is_bid {
+            BIDS_TREE_OFFSET
+        } else {
+            BIDS_TREE_OFFSET.saturating_add(self.tree_len(self.bids_size))
+        }
+    }
+}
+
+impl MarketAdapter for PhoenixAdapter {
+    fn metadata(&self) -> &MarketMetadata {
+        &self.metadata
+    }
+
+    fn decode_events(&self, _data: &[u8]) -> Result<Vec<SequencedEvent>, AdapterError> {
+        Err(AdapterError::EventsUnsupported(Venue::Phoenix))
+    }
+
+    fn seq_range(&self, _data: &[u8]) -> Result<Range<u64>, AdapterError> {
+        Err(AdapterError::EventsUnsupported(Venue::Phoenix))
+    }
+
+    fn decode_levels(
+        &self,
+        data: &[u8],
+        is_bid: bool,
+        depth: usize,
+    ) -> Result<Vec<PriceLevel>, AdapterError> {
+        let size = if is_bid { self.bids_size } else { self.asks_size };
+        let offset = self.tree_offset(is_bid);
+        let tree = data
+            .get(offset..offset.saturating_add(self.tree_len(size)))
+            .ok_or(AdapterError::TooShort)?;
+
+        // Nodes are read from the allocator rather than walked from the tree
+        // root: addresses 1 up to the bump index have been handed out, and
+        // those on the free list, linked through their first register, are
+        // not in the book
+        let allocator = &mut Reader::at(tree, ALLOCATOR_OFFSET, 16)?;
+        let live = allocator.u64()?;
+        let bump_index = allocator.u32()?;
+        let mut free_head = allocator.u32()?;
+        if bump_index == SENTINEL || bump_index as u64 > size.saturating_add(1) {
+            return Err(AdapterError::InvalidNode(bump_index));
+        }
+        let node = |address: u32| -> Result<Reader, AdapterError> {
+            if address == SENTINEL || address >= bump_index {
+                return Err(AdapterError::InvalidNode(address));
+            }
+            let offset = TREE_HEADER_LEN + (address as usize - 1) * TREE_NODE_LEN;
+            Ok(Reader::at(tree, offset, TREE_NODE_LEN)?)
+        };
+
+        let mut free = vec![false; bump_index as usize];
+        while free_head != bump_index {
+            let next = node(free_head)?.u32()?;
+            if free[free_head as usize] {
+                return Err(AdapterError::InvalidNode(free_head));
+            }
+            free[free_head as usize] = true;
+            free_head = next;
+        }
+
+        let mut orders = Vec::new();
+        for address in (1..bump_index).filter(|&address| !free[address as usize]) {
+            let r = &mut node(address)?;
+            r.skip(16)?;
+            let price_in_ticks = r.u64()?;
+            let _order_sequence_number = r.u64()?;
+            let _trader_index = r.u64()?;
+            let num_base_lots = r.u64()?;
+            orders.push((price_in_ticks, num_base_lots));
+        }
+        if orders.len() as u64 != live {
+            return Err(AdapterError::InvalidNode(bump_index));
+        }
+
+        // Levels only sum sizes, so the order within a price does not matter
+        if is_bid {
+            orders.sort_by_key(|&(price, _)| std::cmp::Reverse(price));
+        } else {
+            orders.sort_by_key(|&(price, _)| price);
+        }
+        Ok(aggregate_levels(orders, depth))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKET: &[u8] = include_bytes!("../../tests/fixtures/phoenix/market.bin");
+
+    #[test]
+    fn test_phoenix_adapter() {
+        let address = Pubkey::new_unique();
+        let adapter = PhoenixAdapter::new(address, MARKET, "SOL/USDC").unwrap();
+        let metadata = adapter.metadata();
+        assert_eq!(metadata.venue, Venue::Phoenix);
+        assert_eq!(metadata.bids, address);
+        assert_eq!(metadata.base_lot_size, 1_000);
+        assert_eq!(metadata.quote_lot_size, 1);
+        assert_eq!(metadata.event_queue, None);
+
+        let bids = adapter.decode_levels(MARKET, true, 10).unwrap();
+        assert_eq!(
+            bids,
+            vec![PriceLevel { price: 2_001, size: 15 }, PriceLevel { price: 1_999, size: 40 }]
+        );
+        let asks = adapter.decode_levels(MARKET, false, 1).unwrap();
+        assert_eq!(asks, vec![PriceLevel { price: 2_003, size: 25 }]);
+
+        // The freed third ask node still holds 99 lots at 2004
+        let asks = adapter.decode_levels(MARKET, false, 10).unwrap();
+        assert_eq!(
+            asks,
+            vec![PriceLevel { price: 2_003, size: 25 }, PriceLevel { price: 2_005, size: 10 }]
+        );
+
+        // A free list pointing back at itself is rejected instead of looping
+        let mut cyclic = MARKET.to_vec();
+        let freed_node = 1168 + TREE_HEADER_LEN + 2 * TREE_NODE_LEN;
+        cyclic[freed_node..freed_node + 4].copy_from_slice(&3u32.to_le_bytes());
+        assert_eq!(adapter.decode_levels(&cyclic, false, 10), Err(AdapterError::InvalidNode(3)));
+
+        assert_eq!(
+            adapter.decode_events(MARKET).unwrap_err(),
+            AdapterError::EventsUnsupported(Venue::Phoenix)
+        );
+        assert_eq!(
+            PhoenixAdapter::new(address, &MARKET[..1000], "SOL/USDC").err(),
+            Some(AdapterError::TooShort)
+        );
+    }
+}
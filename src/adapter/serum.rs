@@ -0,0 +1,119 @@
+//! Serum / OpenBook v1 adapter built on the decoders in `crate::serum`.
+
+use super::{AdapterError, MarketAdapter, MarketMetadata, SequencedEvent, Venue};
+use crate::serum::{EventQueue, MarketState, Slab};
+use crate::{OrderbookEvent, OrderbookInstruction, PriceLevel};
+use solana_program::pubkey::Pubkey;
+use std::ops::Range;
+
+pub struct SerumAdapter {
+    market: MarketState,
+    metadata: MarketMetadata,
+}
+
+impl SerumAdapter {
+    pub fn new(address: Pubkey, market_data: &[u8], name: &str) -> Result<Self, AdapterError> {
+        let market = MarketState::decode(market_data)?;
+        let metadata = MarketMetadata {
+            venue: Venue::SerumV1,
+            name: name.to_string(),
+            address,
+            base_mint: market.coin_mint,
+            quote_mint: market.pc_mint,
+            base_lot_size: market.coin_lot_size,
+            quote_lot_size: market.pc_lot_size,
+            bids: market.bids,
+            asks: market.asks,
+            event_queue: Some(market.event_queue),
+        };
+        Ok(SerumAdapter { market, metadata })
+    }
+
+    pub fn market(&self) -> &MarketState {
+        &self.market
+    }
+}
+
+impl MarketAdapter for SerumAdapter {
+    fn metadata(&self) -> &MarketMetadata {
+        &self.metadata
+    }
+
+    fn decode_events(&self, data: &[u8]) -> Result<Vec<SequencedEvent>, AdapterError> {
+        let queue = EventQueue::decode(data)?;
+        Ok(queue
+            .events
+            .iter()
+            .filter_map(|e| {
+                let payload = e.record_event_payload(&self.market, &self.metadata.name)?;
+                let OrderbookInstruction::RecordEvent { market_name, price, size, is_bid, event_type } = payload
+                else {
+                    return None;
+                };
+                // v1 events carry no time: `timestamp` stays 0 for the reader to stamp
+                Some(SequencedEvent {
+                    seq_num: e.seq_num,
+                    order_id: Some(e.order_id),
+                    owner: Some(e.owner),
+                    // v1 reports the taker in its own, aggregated fill event
+                    taker: None,
+                    event: OrderbookEvent {
+                        timestamp: 0,
+                        market_name,
+                        price,
+                        size,
+                        is_bid,
+                        event_type,
+                    },
+                })
+            })
+            .collect())
+    }
+
+    fn seq_range(&self, data: &[u8]) -> Result<Range<u64>, AdapterError> {
+        let queue = EventQueue::decode(data)?;
+        Ok(queue.seq_num.wrapping_sub(queue.count)..queue.seq_num)
+    }
+
+    fn decode_levels(
+        &self,
+        data: &[u8],
+        is_bid: bool,
+        depth: usize,
+    ) -> Result<Vec<PriceLevel>, AdapterError> {
+        Ok(Slab::decode(data, is_bid)?.levels(depth))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderbookEventType;
+
+    const MARKET: &[u8] = include_bytes!("../../tests/fixtures/serum/market.bin");
+    const BIDS: &[u8] = include_bytes!("../../tests/fixtures/serum/bids.bin");
+    const EVENT_QUEUE: &[u8] = include_bytes!("../../tests/fixtures/serum/event_queue.bin");
+
+    #[test]
+    fn test_serum_adapter() {
+        let adapter = SerumAdapter::new(Pubkey::new_unique(), MARKET, "SOL/USDC").unwrap();
+        let metadata = adapter.metadata();
+        assert_eq!(metadata.venue, Venue::SerumV1);
+        assert_eq!(metadata.base_lot_size, 1_000_000);
+        assert_eq!(metadata.event_queue, Some(adapter.market().event_queue));
+
+        // The taker fill and the empty out are dropped
+        let events = adapter.decode_events(EVENT_QUEUE).unwrap();
+        let seqs: Vec<u64> = events.iter().map(|e| e.seq_num).collect();
+        assert_eq!(seqs, vec![7, 8]);
+        assert_eq!(adapter.seq_range(EVENT_QUEUE).unwrap(), 6..10);
+        assert_eq!(events[0].event.event_type, OrderbookEventType::OrderFilled);
+        assert!(!events[0].event.is_bid);
+        assert_eq!(events[0].event.size, 10);
+        // The maker's order id carries the price
+        assert_eq!(events[0].order_id.map(crate::serum::order_id_price), Some(events[0].event.price));
+
+        let bids = adapter.decode_levels(BIDS, true, 1).unwrap();
+        assert_eq!(bids, vec![PriceLevel { price: 25000, size: 120 }]);
+    }
+}
@@ -0,0 +1,317 @@
+//! Off-chain statistics over recorded events and snapshots.
+//!
+//! Every statistic that needs a denominator returns `None` when it has
+//! nothing to divide by, e.g. a bid share over zero events or a VWAP over a
+//! market without fills, instead of `NaN` or a panic. Prices and sizes are
+//! in the native units they were recorded in.
+
+use crate::{BookSnapshot, OrderbookEvent, OrderbookEventType, OrderbookMonitor};
+use std::collections::BTreeMap;
+
+/// Number of events of each type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventTypeCounts {
+    pub placed: u64,
+    pub filled: u64,
+    pub cancelled: u64,
+}
+
+impl EventTypeCounts {
+    pub fn total(&self) -> u64 {
+        self.placed + self.filled + self.cancelled
+    }
+
+    /// Fills per placed order
+    pub fn fill_ratio(&self) -> Option<f64> {
+        ratio(self.filled, self.placed)
+    }
+
+    /// Cancellations per placed order
+    pub fn cancel_ratio(&self) -> Option<f64> {
+        ratio(self.cancelled, self.placed)
+    }
+}
+
+/// Number of bid and ask events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SideCounts {
+    pub bids: u64,
+    pub asks: u64,
+}
+
+impl SideCounts {
+    /// Bids per ask
+    pub fn bid_ask_ratio(&self) -> Option<f64> {
+        ratio(self.bids, self.asks)
+    }
+
+    /// Fraction of events on the bid side
+    pub fn bid_share(&self) -> Option<f64> {
+        ratio(self.bids, self.bids + self.asks)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceStats {
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub count: u64,
+}
+
+/// Top-of-book spread of a snapshot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spread {
+    pub best_bid: u64,
+    pub best_ask: u64,
+    /// `best_ask - best_bid`; negative when the book is crossed
+    pub absolute: i128,
+    pub mid: f64,
+    /// Spread in basis points of the mid price
+    pub bps: f64,
+}
+
+/// Statistics of one market
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketStats {
+    pub market_name: String,
+    pub event_types: EventTypeCounts,
+    pub sides: SideCounts,
+    /// Total filled size, saturating at `u64::MAX`
+    pub volume: u64,
+    /// Statistics of the prices of placed and filled orders
+    pub prices: Option<PriceStats>,
+    pub vwap: Option<f64>,
+    pub realized_volatility: Option<f64>,
+    /// Spread of the market's latest snapshot
+    pub spread: Option<Spread>,
+}
+
+/// Statistics of a whole monitor account
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub event_count: u64,
+    pub event_types: EventTypeCounts,
+    pub sides: SideCounts,
+    /// Per market, ordered by market name
+    pub markets: Vec<MarketStats>,
+}
+
+fn ratio(numerator: u64, denominator: u64) -> Option<f64> {
+    (denominator != 0).then(|| numerator as f64 / denominator as f64)
+}
+
+fn fills(events: &[OrderbookEvent]) -> impl Iterator<Item = &OrderbookEvent> {
+    events.iter().filter(|e| e.event_type == OrderbookEventType::OrderFilled)
+}
+
+pub fn event_type_counts(events: &[OrderbookEvent]) -> EventTypeCounts {
+    let mut counts = EventTypeCounts::default();
+    for event in events {
+        match event.event_type {
+            OrderbookEventType::OrderPlaced => counts.placed += 1,
+            OrderbookEventType::OrderFilled => counts.filled += 1,
+            OrderbookEventType::OrderCancelled => counts.cancelled += 1,
+        }
+    }
+    counts
+}
+
+pub fn side_counts(events: &[OrderbookEvent]) -> SideCounts {
+    let bids = events.iter().filter(|e| e.is_bid).count() as u64;
+    SideCounts { bids, asks: events.len() as u64 - bids }
+}
+
+/// Number of events per market, ordered by market name
+pub fn market_activity(events: &[OrderbookEvent]) -> BTreeMap<&str, u64> {
+    let mut markets = BTreeMap::new();
+    for event in events {
+        *markets.entry(event.market_name.as_str()).or_insert(0) += 1;
+    }
+    markets
+}
+
+/// Min, max and mean price of placed and filled orders; cancellations may
+/// carry no price and are left out
+pub fn price_stats(events: &[OrderbookEvent]) -> Option<PriceStats> {
+    let prices: Vec<u64> = events
+        .iter()
+        .filter(|e| e.event_type != OrderbookEventType::OrderCancelled)
+        .map(|e| e.price)
+        .collect();
+    let count = prices.len() as u64;
+    let sum: u128 = prices.iter().map(|&p| p as u128).sum();
+    Some(PriceStats {
+        min: *prices.iter().min()?,
+        max: *prices.iter().max()?,
+        mean: sum as f64 / count as f64,
+        count,
+    })
+}
+
+/// Volume-weighted average price of the fills
+pub fn vwap(events: &[OrderbookEvent]) -> Option<f64> {
+    let (notional, volume) = fills(events).fold((0u128, 0u128), |(notional, volume), e| {
+        (notional + e.price as u128 * e.size as u128, volume + e.size as u128)
+    });
+    (volume != 0).then(|| notional as f64 / volume as f64)
+}
+
+/// Square root of the sum of squared log returns between consecutive fill
+/// prices; needs at least two fills
+pub fn realized_volatility(events: &[OrderbookEvent]) -> Option<f64> {
+    let prices: Vec<f64> = fills(events).filter(|e| e.price > 0).map(|e| e.price as f64).collect();
+    if prices.len() < 2 {
+        return None;
+    }
+    let sum: f64 = prices.windows(2).map(|w| (w[1] / w[0]).ln().powi(2)).sum();
+    Some(sum.sqrt())
+}
+
+/// Spread between the best levels of a snapshot; `None` if a side is empty
+pub fn spread(snapshot: &BookSnapshot) -> Option<Spread> {
+    let best_bid = snapshot.bids.first()?.price;
+    let best_ask = snapshot.asks.first()?.price;
+    let absolute = best_ask as i128 - best_bid as i128;
+    let mid = (best_bid as f64 + best_ask as f64) / 2.0;
+    let bps = if mid > 0.0 { absolute as f64 / mid * 10_000.0 } else { 0.0 };
+    Some(Spread { best_bid, best_ask, absolute, mid, bps })
+}
+
+/// Statistics of the events and latest snapshot of `market_name`
+pub fn market_stats(monitor: &OrderbookMonitor, market_name: &str) -> MarketStats {
+    let events: Vec<OrderbookEvent> = monitor
+        .events
+        .iter()
+        .filter(|e| e.market_name == market_name)
+        .cloned()
+        .collect();
+    MarketStats {
+        market_name: market_name.to_string(),
+        event_types: event_type_counts(&events),
+        sides: side_counts(&events),
+        volume: fills(&events).fold(0, |volume: u64, e| volume.saturating_add(e.size)),
+        prices: price_stats(&events),
+        vwap: vwap(&events),
+        realized_volatility: realized_volatility(&events),
+        spread: monitor
+            .snapshots
+            .iter()
+            .rev()
+            .find(|s| s.market_name == market_name)
+            .and_then(spread),
+    }
+}
+
+pub fn summarize(monitor: &OrderbookMonitor) -> Summary {
+    Summary {
+        event_count: monitor.event_count,
+        event_types: event_type_counts(&monitor.events),
+        sides: side_counts(&monitor.events),
+        markets: market_activity(&monitor.events)
+            .into_keys()
+            .map(|market_name| market_stats(monitor, market_name))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PriceLevel;
+    use solana_program::pubkey::Pubkey;
+
+    fn event(
+        market_name: &str,
+        price: u64,
+        size: u64,
+        is_bid: bool,
+        event_type: OrderbookEventType,
+    ) -> OrderbookEvent {
+        OrderbookEvent {
+            timestamp: 1_700_000_000,
+            market_name: market_name.to_string(),
+            price,
+            size,
+            is_bid,
+            event_type,
+        }
+    }
+
+    fn monitor(events: Vec<OrderbookEvent>, snapshots: Vec<BookSnapshot>) -> OrderbookMonitor {
+        OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: events.len() as u64,
+            events,
+            snapshots,
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn test_empty_monitor() {
+        let summary = summarize(&monitor(vec![], vec![]));
+        assert_eq!(summary.event_count, 0);
+        assert!(summary.markets.is_empty());
+        assert_eq!(summary.sides.bid_share(), None);
+        assert_eq!(summary.sides.bid_ask_ratio(), None);
+        assert_eq!(summary.event_types.fill_ratio(), None);
+        assert_eq!(summary.event_types.cancel_ratio(), None);
+        assert_eq!(price_stats(&[]), None);
+        assert_eq!(vwap(&[]), None);
+        assert_eq!(realized_volatility(&[]), None);
+    }
+
+    #[test]
+    fn test_market_stats() {
+        use OrderbookEventType::*;
+        let events = vec![
+            event("SOL/USDC", 100, 4, true, OrderPlaced),
+            event("SOL/USDC", 110, 2, false, OrderPlaced),
+            event("SOL/USDC", 100, 1, true, OrderFilled),
+            event("SOL/USDC", 110, 3, false, OrderFilled),
+            event("SOL/USDC", 0, 1, true, OrderCancelled),
+            event("BTC/USDC", 50_000, 1, false, OrderPlaced),
+        ];
+        let snapshot = BookSnapshot {
+            slot: 1,
+            timestamp: 1_700_000_000,
+            market_name: "SOL/USDC".to_string(),
+            event_index: 6,
+            bids: vec![PriceLevel { price: 99, size: 3 }],
+            asks: vec![PriceLevel { price: 101, size: 2 }],
+        };
+        let summary = summarize(&monitor(events, vec![snapshot]));
+
+        assert_eq!(summary.event_types, EventTypeCounts { placed: 3, filled: 2, cancelled: 1 });
+        assert_eq!(summary.sides.bid_share(), Some(0.5));
+        assert_eq!(summary.markets.len(), 2);
+        assert_eq!(summary.markets[0].market_name, "BTC/USDC");
+        assert_eq!(summary.markets[0].vwap, None);
+        assert_eq!(summary.markets[0].spread, None);
+
+        let sol = &summary.markets[1];
+        assert_eq!(sol.event_types.fill_ratio(), Some(1.0));
+        assert_eq!(sol.event_types.cancel_ratio(), Some(0.5));
+        assert_eq!(sol.sides.bid_ask_ratio(), Some(1.5));
+        assert_eq!(sol.volume, 4);
+        // The cancellation's zero price is left out
+        assert_eq!(sol.prices, Some(PriceStats { min: 100, max: 110, mean: 105.0, count: 4 }));
+        assert_eq!(sol.vwap, Some(107.5));
+        let volatility = sol.realized_volatility.unwrap();
+        assert!((volatility - (1.1f64).ln()).abs() < 1e-12);
+
+        let spread = sol.spread.unwrap();
+        assert_eq!((spread.best_bid, spread.best_ask, spread.absolute), (99, 101, 2));
+        assert_eq!(spread.mid, 100.0);
+        assert_eq!(spread.bps, 200.0);
+
+        // Volume saturates instead of overflowing
+        let huge = vec![
+            event("SOL/USDC", 100, u64::MAX, true, OrderFilled),
+            event("SOL/USDC", 100, 1, true, OrderFilled),
+        ];
+        assert_eq!(market_stats(&monitor(huge, vec![]), "SOL/USDC").volume, u64::MAX);
+    }
+}
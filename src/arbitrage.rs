@@ -0,0 +1,334 @@
+//! Off-chain detection of arbitrage between the markets of one monitor, from
+//! the latest recorded snapshot of each.
+//!
+//! Recorded prices and sizes are in each venue's native units, so every
+//! market is described by a `MarketSpec`: its base and quote assets, the
+//! venue it was fed from and the scales turning its prices into quote units
+//! per base unit and its sizes into base units. Two kinds of gaps are found:
+//!
+//! - a cross-venue gap, where the best bid of a pair on one venue is above its
+//!   best ask on another. Its size walks both books down to the last level
+//!   still clearing `min_gap_bps`;
+//! - a triangular gap, where converting an asset through two others at the
+//!   best prices returns more of it than went in. Each leg uses the best
+//!   market for its direction, and the size is what fits in the top level of
+//!   every leg.
+//!
+//! Snapshots more than `max_age_secs` apart are not compared.
+
+use crate::{BookSnapshot, OrderbookMonitor, PriceLevel};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How the prices and sizes of one recorded market read in common units
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketSpec {
+    pub market_name: String,
+    /// Adapter or program the market was fed from
+    pub venue: String,
+    pub base: String,
+    pub quote: String,
+    /// Quote units per base unit of one recorded price unit
+    pub price_scale: f64,
+    /// Base units of one recorded size unit
+    pub size_scale: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArbitrageConfig {
+    /// Smallest gap reported, in basis points, e.g. to cover fees
+    pub min_gap_bps: f64,
+    /// Widest spread of snapshot times within one opportunity
+    pub max_age_secs: i64,
+}
+
+impl Default for ArbitrageConfig {
+    fn default() -> Self {
+        ArbitrageConfig { min_gap_bps: 10.0, max_age_secs: 60 }
+    }
+}
+
+/// Buying a pair where it is cheap and selling it where it is dear
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossVenueGap {
+    pub base: String,
+    pub quote: String,
+    /// Market whose asks are bought
+    pub buy_market: String,
+    /// Market whose bids are sold to
+    pub sell_market: String,
+    pub buy_price: f64,
+    pub sell_price: f64,
+    /// Best bid over best ask, in basis points of the ask
+    pub gap_bps: f64,
+    /// Base units tradable while the gap stays above `min_gap_bps`
+    pub size: f64,
+    /// Quote units earned on `size`, before fees
+    pub profit: f64,
+    /// Time of the newer snapshot
+    pub timestamp: i64,
+}
+
+/// One conversion of a triangular cycle
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leg {
+    pub market_name: String,
+    /// Buying the market's base with its quote, at the best ask; otherwise
+    /// selling the base at the best bid
+    pub buy: bool,
+    pub price: f64,
+}
+
+/// Converting `assets[0]` through the other two assets and back
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriangularGap {
+    pub assets: [String; 3],
+    pub legs: [Leg; 3],
+    /// Return of the cycle above one, in basis points
+    pub gap_bps: f64,
+    /// Units of `assets[0]` fitting in the top level of every leg
+    pub size: f64,
+    /// Units of `assets[0]` earned on `size`, before fees
+    pub profit: f64,
+    /// Time of the newest snapshot
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Opportunities {
+    pub cross_venue: Vec<CrossVenueGap>,
+    pub triangular: Vec<TriangularGap>,
+}
+
+/// A snapshot in common units, levels as `(price, size)`, best first
+#[derive(Debug, Clone)]
+struct Book<'a> {
+    spec: &'a MarketSpec,
+    timestamp: i64,
+    bids: Vec<(f64, f64)>,
+    asks: Vec<(f64, f64)>,
+}
+
+impl<'a> Book<'a> {
+    fn new(spec: &'a MarketSpec, snapshot: &BookSnapshot) -> Self {
+        let scale = |levels: &[PriceLevel]| {
+            levels.iter().map(|l| (l.price as f64 * spec.price_scale, l.size as f64 * spec.size_scale)).collect()
+        };
+        Book { spec, timestamp: snapshot.timestamp, bids: scale(&snapshot.bids), asks: scale(&snapshot.asks) }
+    }
+
+    /// Rate from `from` to the other asset of the market, how much of `from`
+    /// the top level takes and the leg; `None` if the side is empty
+    fn convert(&self, from: &str) -> Option<(f64, f64, Leg)> {
+        let leg = |buy, price| Leg { market_name: self.spec.market_name.clone(), buy, price };
+        if from == self.spec.base {
+            let &(price, size) = self.bids.first()?;
+            Some((price, size, leg(false, price)))
+        } else {
+            let &(price, size) = self.asks.first().filter(|(price, _)| *price > 0.0)?;
+            Some((1.0 / price, size * price, leg(true, price)))
+        }
+    }
+}
+
+/// Time of the newest snapshot, if none is more than `max_age_secs` older
+fn fresh(timestamps: &[i64], config: &ArbitrageConfig) -> Option<i64> {
+    let newest = *timestamps.iter().max()?;
+    let oldest = *timestamps.iter().min()?;
+    (newest - oldest <= config.max_age_secs).then_some(newest)
+}
+
+fn cross_venue(buy: &Book, sell: &Book, config: &ArbitrageConfig) -> Option<CrossVenueGap> {
+    let (&(ask, _), &(bid, _)) = (buy.asks.first()?, sell.bids.first()?);
+    let gap_bps = |bid: f64, ask: f64| (bid - ask) / ask * 10_000.0;
+    if ask <= 0.0 || gap_bps(bid, ask) < config.min_gap_bps {
+        return None;
+    }
+    let timestamp = fresh(&[buy.timestamp, sell.timestamp], config)?;
+
+    let (mut asks, mut bids) = (buy.asks.clone(), sell.bids.clone());
+    let (mut i, mut j, mut size, mut profit) = (0, 0, 0.0, 0.0);
+    while i < asks.len() && j < bids.len() && gap_bps(bids[j].0, asks[i].0) >= config.min_gap_bps {
+        let traded = asks[i].1.min(bids[j].1);
+        size += traded;
+        profit += traded * (bids[j].0 - asks[i].0);
+        asks[i].1 -= traded;
+        bids[j].1 -= traded;
+        if asks[i].1 <= 0.0 {
+            i += 1;
+        }
+        if bids[j].1 <= 0.0 {
+            j += 1;
+        }
+    }
+    Some(CrossVenueGap {
+        base: buy.spec.base.clone(),
+        quote: buy.spec.quote.clone(),
+        buy_market: buy.spec.market_name.clone(),
+        sell_market: sell.spec.market_name.clone(),
+        buy_price: ask,
+        sell_price: bid,
+        gap_bps: gap_bps(bid, ask),
+        size,
+        profit,
+        timestamp,
+    })
+}
+
+/// The best conversion from `from` to `to` among `books`, with its book's time
+fn best_leg(books: &[Book], from: &str, to: &str) -> Option<(f64, f64, Leg, i64)> {
+    books
+        .iter()
+        .filter(|b| (b.spec.base == from && b.spec.quote == to) || (b.spec.base == to && b.spec.quote == from))
+        .filter_map(|b| b.convert(from).map(|(rate, capacity, leg)| (rate, capacity, leg, b.timestamp)))
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+fn triangular(books: &[Book], assets: [&str; 3], config: &ArbitrageConfig) -> Option<TriangularGap> {
+    let mut rate = 1.0;
+    let mut size = f64::INFINITY;
+    let mut legs = Vec::with_capacity(3);
+    let mut timestamps = Vec::with_capacity(3);
+    for k in 0..3 {
+        let (leg_rate, capacity, leg, timestamp) = best_leg(books, assets[k], assets[(k + 1) % 3])?;
+        // Capacity is in units of the leg's input; bring it back to the first asset
+        size = f64::min(size, capacity / rate);
+        rate *= leg_rate;
+        legs.push(leg);
+        timestamps.push(timestamp);
+    }
+    let gap_bps = (rate - 1.0) * 10_000.0;
+    if gap_bps < config.min_gap_bps {
+        return None;
+    }
+    let timestamp = fresh(&timestamps, config)?;
+    Some(TriangularGap {
+        assets: assets.map(str::to_string),
+        legs: legs.try_into().ok()?,
+        gap_bps,
+        size,
+        profit: size * (rate - 1.0),
+        timestamp,
+    })
+}
+
+/// Gaps between `snapshots`, each read through the spec of its market and
+/// largest first. Snapshots of markets without a spec are left out.
+pub fn find(snapshots: &[&BookSnapshot], specs: &[MarketSpec], config: &ArbitrageConfig) -> Opportunities {
+    let books: Vec<Book> = snapshots
+        .iter()
+        .filter_map(|s| specs.iter().find(|spec| spec.market_name == s.market_name).map(|spec| Book::new(spec, s)))
+        .collect();
+    let mut opportunities = Opportunities::default();
+
+    for buy in &books {
+        for sell in &books {
+            let same_pair = buy.spec.base == sell.spec.base && buy.spec.quote == sell.spec.quote;
+            if same_pair && buy.spec.venue != sell.spec.venue {
+                opportunities.cross_venue.extend(cross_venue(buy, sell, config));
+            }
+        }
+    }
+
+    let assets: BTreeSet<&str> = books.iter().flat_map(|b| [b.spec.base.as_str(), b.spec.quote.as_str()]).collect();
+    let assets: Vec<&str> = assets.into_iter().collect();
+    for (a, &first) in assets.iter().enumerate() {
+        for (b, &second) in assets.iter().enumerate().skip(a + 1) {
+            for &third in assets.iter().skip(b + 1) {
+                // Both directions around the triangle, starting from its first asset
+                for cycle in [[first, second, third], [first, third, second]] {
+                    opportunities.triangular.extend(triangular(&books, cycle, config));
+                }
+            }
+        }
+    }
+
+    opportunities.cross_venue.sort_by(|a, b| b.gap_bps.total_cmp(&a.gap_bps));
+    opportunities.triangular.sort_by(|a, b| b.gap_bps.total_cmp(&a.gap_bps));
+    opportunities
+}
+
+/// Gaps between the latest snapshots of the markets in `monitor`
+pub fn detect(monitor: &OrderbookMonitor, specs: &[MarketSpec], config: &ArbitrageConfig) -> Opportunities {
+    let mut latest: BTreeMap<&str, &BookSnapshot> = BTreeMap::new();
+    for snapshot in &monitor.snapshots {
+        latest.insert(&snapshot.market_name, snapshot);
+    }
+    find(&latest.into_values().collect::<Vec<_>>(), specs, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(market_name: &str, venue: &str, base: &str, quote: &str) -> MarketSpec {
+        MarketSpec {
+            market_name: market_name.to_string(),
+            venue: venue.to_string(),
+            base: base.to_string(),
+            quote: quote.to_string(),
+            price_scale: 0.01,
+            size_scale: 1.0,
+        }
+    }
+
+    fn snapshot(market_name: &str, timestamp: i64, bids: &[(u64, u64)], asks: &[(u64, u64)]) -> BookSnapshot {
+        let levels = |levels: &[(u64, u64)]| levels.iter().map(|&(price, size)| PriceLevel { price, size }).collect();
+        BookSnapshot {
+            slot: 0,
+            timestamp,
+            market_name: market_name.to_string(),
+            event_index: 0,
+            bids: levels(bids),
+            asks: levels(asks),
+        }
+    }
+
+    #[test]
+    fn test_cross_venue_gap() {
+        let specs = [spec("SOL/USDC", "openbook-v2", "SOL", "USDC"), spec("SOL-USDC", "phoenix", "SOL", "USDC")];
+        // Phoenix bids 100.50 and 100.20 over OpenBook asks of 100.00 and 100.15
+        let openbook = snapshot("SOL/USDC", 100, &[(9_990, 50)], &[(10_000, 3), (10_015, 4), (10_100, 10)]);
+        let phoenix = snapshot("SOL-USDC", 110, &[(10_050, 5), (10_020, 10)], &[(10_060, 5)]);
+        let config = ArbitrageConfig { min_gap_bps: 3.0, ..ArbitrageConfig::default() };
+        let found = find(&[&openbook, &phoenix], &specs, &config);
+        assert!(found.triangular.is_empty());
+        assert_eq!(found.cross_venue.len(), 1);
+        let gap = &found.cross_venue[0];
+        assert_eq!((gap.buy_market.as_str(), gap.sell_market.as_str(), gap.timestamp), ("SOL/USDC", "SOL-USDC", 110));
+        assert!((gap.gap_bps - 50.0).abs() < 1e-9, "{}", gap.gap_bps);
+        // 3 at 100.00 and 2 at 100.15 sold at 100.50, then 2 at 100.15 sold at 100.20 (5 bps)
+        assert!((gap.size - 7.0).abs() < 1e-9, "{}", gap.size);
+        assert!((gap.profit - (3.0 * 0.5 + 2.0 * 0.35 + 2.0 * 0.05)).abs() < 1e-9, "{}", gap.profit);
+
+        // Too far apart in time, or on the same venue
+        let stale = snapshot("SOL-USDC", 200, &[(10_050, 5)], &[]);
+        assert!(find(&[&openbook, &stale], &specs, &config).cross_venue.is_empty());
+        let same_venue = [specs[0].clone(), MarketSpec { venue: "openbook-v2".to_string(), ..specs[1].clone() }];
+        assert!(find(&[&openbook, &phoenix], &same_venue, &config).cross_venue.is_empty());
+    }
+
+    #[test]
+    fn test_triangular_gap() {
+        let specs = [
+            spec("SOL/USDC", "openbook-v2", "SOL", "USDC"),
+            spec("BTC/USDC", "openbook-v2", "BTC", "USDC"),
+            MarketSpec { price_scale: 0.000_01, ..spec("SOL/BTC", "openbook-v2", "SOL", "BTC") },
+        ];
+        // SOL at 100 USDC and BTC at 50 000 USDC, but SOL bid at 0.00202 BTC (101 USDC)
+        let sol = snapshot("SOL/USDC", 0, &[(9_999, 100)], &[(10_000, 100)]);
+        let btc = snapshot("BTC/USDC", 0, &[(5_000_000, 1)], &[(5_000_100, 1)]);
+        let sol_btc = snapshot("SOL/BTC", 0, &[(202, 10)], &[(203, 10)]);
+        let found = find(&[&sol, &btc, &sol_btc], &specs, &ArbitrageConfig::default());
+        assert!(found.cross_venue.is_empty());
+        assert_eq!(found.triangular.len(), 1);
+        let gap = &found.triangular[0];
+        // Sell BTC for USDC, buy SOL with it, sell the SOL for BTC
+        assert_eq!(gap.assets, ["BTC".to_string(), "USDC".to_string(), "SOL".to_string()]);
+        let legs: Vec<(&str, bool)> = gap.legs.iter().map(|l| (l.market_name.as_str(), l.buy)).collect();
+        assert_eq!(legs, vec![("BTC/USDC", false), ("SOL/USDC", true), ("SOL/BTC", false)]);
+        assert!((gap.gap_bps - 100.0).abs() < 1e-6, "{}", gap.gap_bps);
+        // The SOL/BTC bid takes 10 SOL, bought with 0.02 BTC; the other top levels take more
+        assert!((gap.size - 0.02).abs() < 1e-9, "{}", gap.size);
+        assert!((gap.profit - 0.0002).abs() < 1e-9, "{}", gap.profit);
+    }
+}
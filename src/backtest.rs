@@ -0,0 +1,476 @@
+//! Deterministic replay of recorded events into a trading `Strategy`.
+//!
+//! The engine rebuilds the aggregated book of one market from its events and
+//! simulates the strategy's orders against it:
+//!
+//! - Submissions and cancellations take effect `latency_events` events and
+//!   `latency_secs` seconds after the strategy makes them.
+//! - A market order, or a limit order crossing the book when it arrives,
+//!   takes the displayed levels of the other side at the taker fee. Recorded
+//!   events keep applying to the book as they were recorded, but the size
+//!   the strategy took from a level stays taken until the next recorded
+//!   event at that level, so it cannot be taken twice.
+//! - A resting limit order fills at its own price, at the maker fee, when a
+//!   recorded fill trades through it (at a strictly worse price for the
+//!   resting side), since the queue position at equal prices is unknown.
+//!
+//! Equity is cash plus position marked at the mid price, or the last
+//! recorded fill price while a side of the book is empty.
+
+use crate::snapshot::L2Book;
+use crate::{OrderbookEvent, OrderbookEventType};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Fees in basis points of the notional; negative values are rebates
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Fees {
+    pub maker_bps: f64,
+    pub taker_bps: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestConfig {
+    /// Market whose events are replayed; events of other markets are skipped
+    pub market_name: String,
+    /// Recorded events that must pass before an action takes effect
+    pub latency_events: usize,
+    /// Seconds that must pass before an action takes effect
+    pub latency_secs: i64,
+    pub fees: Fees,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderKind {
+    /// Rest at `price` after taking any liquidity at or better than it
+    Limit { price: u64 },
+    /// Take liquidity at any price; the unfilled rest is dropped
+    Market,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderRequest {
+    pub is_bid: bool,
+    pub size: u64,
+    pub kind: OrderKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fill {
+    pub order_id: u64,
+    pub timestamp: i64,
+    pub is_bid: bool,
+    pub price: u64,
+    pub size: u64,
+    pub is_maker: bool,
+    /// Fee paid in quote units; negative for a rebate
+    pub fee: f64,
+}
+
+/// A strategy order resting in the simulated book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenOrder {
+    pub order_id: u64,
+    pub is_bid: bool,
+    pub price: u64,
+    pub remaining: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Submit(u64, OrderRequest),
+    Cancel(u64),
+}
+
+/// What a strategy sees and does on every event
+pub struct Context<'a> {
+    pub book: &'a L2Book,
+    pub timestamp: i64,
+    /// Net base position, positive when long
+    pub position: i128,
+    /// Quote balance, starting at zero
+    pub cash: f64,
+    pub open_orders: &'a BTreeMap<u64, OpenOrder>,
+    actions: Vec<Action>,
+    next_order_id: &'a mut u64,
+}
+
+impl Context<'_> {
+    /// Submit an order; it reaches the book after the configured latency
+    pub fn submit(&mut self, request: OrderRequest) -> u64 {
+        let order_id = *self.next_order_id;
+        *self.next_order_id += 1;
+        self.actions.push(Action::Submit(order_id, request));
+        order_id
+    }
+
+    /// Cancel an order after the configured latency
+    pub fn cancel(&mut self, order_id: u64) {
+        self.actions.push(Action::Cancel(order_id));
+    }
+}
+
+pub trait Strategy {
+    /// Called after each recorded event of the market has been applied to the book
+    fn on_event(&mut self, context: &mut Context, event: &OrderbookEvent);
+
+    /// Called for every simulated fill, before the event that caused it
+    fn on_fill(&mut self, _context: &mut Context, _fill: &Fill) {}
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub events: usize,
+    pub orders: usize,
+    /// Orders that filled at least partly
+    pub filled_orders: usize,
+    pub fills: Vec<Fill>,
+    pub maker_volume: u64,
+    pub taker_volume: u64,
+    pub fees: f64,
+    pub position: i128,
+    pub cash: f64,
+    /// Final equity; the backtest starts flat at zero
+    pub pnl: f64,
+    /// Largest drop of equity from a previous peak
+    pub max_drawdown: f64,
+}
+
+impl Report {
+    /// Fraction of submitted orders that filled at least partly
+    pub fn fill_ratio(&self) -> Option<f64> {
+        (self.orders != 0).then(|| self.filled_orders as f64 / self.orders as f64)
+    }
+}
+
+struct Pending {
+    action: Action,
+    due_event: usize,
+    due_timestamp: i64,
+}
+
+/// Runs one strategy over one market's events
+pub struct Backtest {
+    config: BacktestConfig,
+    book: L2Book,
+    open_orders: BTreeMap<u64, OpenOrder>,
+    pending: Vec<Pending>,
+    next_order_id: u64,
+    /// Size taken by simulated orders from each `(is_bid, price)` level
+    /// since it was last updated by a recorded event
+    consumed: HashMap<(bool, u64), u64>,
+    filled_order_ids: HashSet<u64>,
+    position: i128,
+    cash: f64,
+    last_price: Option<u64>,
+    peak: f64,
+    report: Report,
+}
+
+impl Backtest {
+    pub fn new(config: BacktestConfig) -> Self {
+        Backtest {
+            config,
+            book: L2Book::default(),
+            open_orders: BTreeMap::new(),
+            pending: Vec::new(),
+            next_order_id: 0,
+            consumed: HashMap::new(),
+            filled_order_ids: HashSet::new(),
+            position: 0,
+            cash: 0.0,
+            last_price: None,
+            peak: 0.0,
+            report: Report::default(),
+        }
+    }
+
+    /// Replay `events` in order and report the result
+    pub fn run<'e>(
+        mut self,
+        strategy: &mut impl Strategy,
+        events: impl IntoIterator<Item = &'e OrderbookEvent>,
+    ) -> Report {
+        for event in events {
+            if event.market_name == self.config.market_name {
+                self.step(strategy, event);
+            }
+        }
+        self.report.position = self.position;
+        self.report.cash = self.cash;
+        self.report.pnl = self.equity();
+        self.report
+    }
+
+    fn step(&mut self, strategy: &mut impl Strategy, event: &OrderbookEvent) {
+        let index = self.report.events;
+        self.report.events += 1;
+
+        let mut fills = Vec::new();
+        let (due, waiting): (Vec<Pending>, Vec<Pending>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|p| index >= p.due_event && event.timestamp >= p.due_timestamp);
+        self.pending = waiting;
+        for pending in due {
+            match pending.action {
+                Action::Submit(order_id, request) => self.arrive(order_id, request, event.timestamp, &mut fills),
+                Action::Cancel(order_id) => {
+                    self.open_orders.remove(&order_id);
+                }
+            }
+        }
+
+        self.book.apply(event);
+        self.consumed.remove(&(event.is_bid, event.price));
+        if event.event_type == OrderbookEventType::OrderFilled {
+            self.last_price = Some(event.price);
+            self.trade_through(event, &mut fills);
+        }
+
+        for fill in &fills {
+            self.with_context(strategy, event.timestamp, |strategy, context| strategy.on_fill(context, fill));
+        }
+        self.with_context(strategy, event.timestamp, |strategy, context| strategy.on_event(context, event));
+
+        let equity = self.equity();
+        self.peak = self.peak.max(equity);
+        self.report.max_drawdown = self.report.max_drawdown.max(self.peak - equity);
+    }
+
+    /// Let the strategy act and queue its actions behind the latency
+    fn with_context<S: Strategy>(&mut self, strategy: &mut S, timestamp: i64, f: impl FnOnce(&mut S, &mut Context)) {
+        let mut context = Context {
+            book: &self.book,
+            timestamp,
+            position: self.position,
+            cash: self.cash,
+            open_orders: &self.open_orders,
+            actions: Vec::new(),
+            next_order_id: &mut self.next_order_id,
+        };
+        f(strategy, &mut context);
+        let actions = context.actions;
+        for action in actions {
+            if let Action::Submit(..) = action {
+                self.report.orders += 1;
+            }
+            self.pending.push(Pending {
+                action,
+                due_event: self.report.events + self.config.latency_events,
+                due_timestamp: timestamp + self.config.latency_secs,
+            });
+        }
+    }
+
+    /// An order reaches the book: take what crosses, rest the remainder of a limit order
+    fn arrive(&mut self, order_id: u64, request: OrderRequest, timestamp: i64, fills: &mut Vec<Fill>) {
+        let limit = match request.kind {
+            OrderKind::Limit { price } => Some(price),
+            OrderKind::Market => None,
+        };
+        let levels = if request.is_bid { self.book.asks(usize::MAX) } else { self.book.bids(usize::MAX) };
+        let mut remaining = request.size;
+        for level in levels {
+            let crosses = limit.is_none_or(|limit| {
+                if request.is_bid { level.price <= limit } else { level.price >= limit }
+            });
+            if remaining == 0 || !crosses {
+                break;
+            }
+            let consumed = self.consumed.entry((!request.is_bid, level.price)).or_default();
+            let size = remaining.min(level.size.saturating_sub(*consumed));
+            if size == 0 {
+                continue;
+            }
+            *consumed += size;
+            remaining -= size;
+            fills.push(self.fill(order_id, timestamp, request.is_bid, level.price, size, false));
+        }
+        if let (Some(price), true) = (limit, remaining > 0) {
+            self.open_orders.insert(order_id, OpenOrder { order_id, is_bid: request.is_bid, price, remaining });
+        }
+    }
+
+    /// Fill resting orders that a recorded trade went through
+    fn trade_through(&mut self, event: &OrderbookEvent, fills: &mut Vec<Fill>) {
+        // The recorded fill is on the maker's side: a bid fill is a sell into bids
+        let mut trade_left = event.size;
+        let candidates: Vec<OpenOrder> = self
+            .open_orders
+            .values()
+            .filter(|o| o.is_bid == event.is_bid)
+            .filter(|o| if o.is_bid { event.price < o.price } else { event.price > o.price })
+            .copied()
+            .collect();
+        for order in candidates {
+            if trade_left == 0 {
+                break;
+            }
+            let size = trade_left.min(order.remaining);
+            trade_left -= size;
+            fills.push(self.fill(order.order_id, event.timestamp, order.is_bid, order.price, size, true));
+            let open = self.open_orders.get_mut(&order.order_id).expect("candidate is open");
+            open.remaining -= size;
+            if open.remaining == 0 {
+                self.open_orders.remove(&order.order_id);
+            }
+        }
+    }
+
+    fn fill(&mut self, order_id: u64, timestamp: i64, is_bid: bool, price: u64, size: u64, is_maker: bool) -> Fill {
+        let notional = price as f64 * size as f64;
+        let bps = if is_maker { self.config.fees.maker_bps } else { self.config.fees.taker_bps };
+        let fee = notional * bps / 10_000.0;
+        if is_bid {
+            self.position += size as i128;
+            self.cash -= notional + fee;
+        } else {
+            self.position -= size as i128;
+            self.cash += notional - fee;
+        }
+        if self.filled_order_ids.insert(order_id) {
+            self.report.filled_orders += 1;
+        }
+        if is_maker {
+            self.report.maker_volume += size;
+        } else {
+            self.report.taker_volume += size;
+        }
+        self.report.fees += fee;
+        let fill = Fill { order_id, timestamp, is_bid, price, size, is_maker, fee };
+        self.report.fills.push(fill);
+        fill
+    }
+
+    fn equity(&self) -> f64 {
+        let best = |levels: Vec<crate::PriceLevel>| levels.first().map(|l| l.price as f64);
+        let mark = match (best(self.book.bids(1)), best(self.book.asks(1))) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / 2.0),
+            _ => self.last_price.map(|p| p as f64),
+        };
+        self.cash + mark.map_or(0.0, |mark| self.position as f64 * mark)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use OrderbookEventType::*;
+
+    fn event(timestamp: i64, price: u64, size: u64, is_bid: bool, event_type: OrderbookEventType) -> OrderbookEvent {
+        OrderbookEvent { timestamp, market_name: "SOL/USDC".to_string(), price, size, is_bid, event_type }
+    }
+
+    fn config(latency_events: usize) -> BacktestConfig {
+        BacktestConfig {
+            market_name: "SOL/USDC".to_string(),
+            latency_events,
+            latency_secs: 0,
+            fees: Fees { maker_bps: -1.0, taker_bps: 10.0 },
+        }
+    }
+
+    /// Buys at market on the first event, then bids 98 until filled
+    #[derive(Default)]
+    struct BuyThenBid {
+        submitted: bool,
+        fills: Vec<Fill>,
+    }
+
+    impl Strategy for BuyThenBid {
+        fn on_event(&mut self, context: &mut Context, _event: &OrderbookEvent) {
+            if !self.submitted {
+                self.submitted = true;
+                context.submit(OrderRequest { is_bid: true, size: 3, kind: OrderKind::Market });
+                context.submit(OrderRequest { is_bid: true, size: 2, kind: OrderKind::Limit { price: 98 } });
+            }
+        }
+
+        fn on_fill(&mut self, _context: &mut Context, fill: &Fill) {
+            self.fills.push(*fill);
+        }
+    }
+
+    fn events() -> Vec<OrderbookEvent> {
+        vec![
+            event(1, 99, 10, true, OrderPlaced),
+            event(1, 101, 2, false, OrderPlaced),
+            event(2, 102, 5, false, OrderPlaced),
+            event(3, 97, 4, true, OrderFilled),
+            event(4, 101, 2, false, OrderCancelled),
+        ]
+    }
+
+    #[test]
+    fn test_latency_and_fills() {
+        let mut events = events();
+        let mut other = event(2, 1, 1, true, OrderPlaced);
+        other.market_name = "BTC/USDC".to_string();
+        events.insert(3, other);
+
+        let mut strategy = BuyThenBid::default();
+        let report = Backtest::new(config(2)).run(&mut strategy, &events);
+        assert_eq!(report.events, 5);
+        assert_eq!(report.orders, 2);
+
+        // Submitted after event 0 and arriving before event 2: the market
+        // order takes both 101s and one 102
+        let taker: Vec<(u64, u64)> = report.fills.iter().filter(|f| !f.is_maker).map(|f| (f.price, f.size)).collect();
+        assert_eq!(taker, vec![(101, 2), (102, 1)]);
+        // The bid at 98 rests and a sale at 97 trades through it
+        let maker: Vec<&Fill> = report.fills.iter().filter(|f| f.is_maker).collect();
+        assert_eq!(maker.len(), 1);
+        assert_eq!((maker[0].price, maker[0].size, maker[0].timestamp), (98, 2, 3));
+        assert_eq!(strategy.fills, report.fills);
+
+        assert_eq!(report.filled_orders, 2);
+        assert_eq!(report.fill_ratio(), Some(1.0));
+        assert_eq!((report.taker_volume, report.maker_volume, report.position), (3, 2, 5));
+        let fees = (304.0 * 10.0 - 196.0) / 10_000.0;
+        assert!((report.fees - fees).abs() < 1e-9);
+        assert!((report.cash - -(304.0 + 196.0 + fees)).abs() < 1e-9);
+        // Marked at the 99/102 mid after the 101 ask was cancelled
+        assert!((report.pnl - (report.cash + 5.0 * 100.5)).abs() < 1e-9);
+        assert!(report.max_drawdown > 0.0);
+    }
+
+    #[test]
+    fn test_taken_liquidity_stays_taken() {
+        /// Buys 2 at market on each of the first two events
+        struct TwoBuys(usize);
+
+        impl Strategy for TwoBuys {
+            fn on_event(&mut self, context: &mut Context, _event: &OrderbookEvent) {
+                if self.0 < 2 {
+                    self.0 += 1;
+                    context.submit(OrderRequest { is_bid: true, size: 2, kind: OrderKind::Market });
+                }
+            }
+        }
+        let taker = |events: &[OrderbookEvent]| {
+            let report = Backtest::new(config(1)).run(&mut TwoBuys(0), events);
+            report.fills.iter().map(|f| (f.order_id, f.price, f.size)).collect::<Vec<_>>()
+        };
+
+        // The first order takes both 101s before event 2; the second arrives
+        // after it and must go to 102
+        assert_eq!(taker(&events()), vec![(0, 101, 2), (1, 102, 2)]);
+
+        // A recorded update of the 101 level in between replenishes it
+        let mut events = events();
+        events[2] = event(2, 101, 3, false, OrderPlaced);
+        assert_eq!(taker(&events), vec![(0, 101, 2), (1, 101, 2)]);
+    }
+
+    #[test]
+    fn test_replay_is_deterministic() {
+        let run = |latency| Backtest::new(config(latency)).run(&mut BuyThenBid::default(), &events());
+        assert_eq!(run(1), run(1));
+        // With no latency the market order arrives before the asks were placed
+        let report = run(0);
+        assert!(report.fills.iter().all(|f| f.is_maker));
+
+        let report = Backtest::new(config(0)).run(&mut BuyThenBid::default(), &[]);
+        assert_eq!(report, Report::default());
+        assert_eq!(report.fill_ratio(), None);
+    }
+}
@@ -0,0 +1,327 @@
+//! Off-chain OHLCV bars resampled from recorded fills, and indicators over them.
+//!
+//! Bars start at multiples of the interval in unix time. With gap filling,
+//! intervals without fills between the first and last bar get a flat bar at
+//! the previous close with zero volume, so indicators see evenly spaced bars.
+//! Gap filling is refused when the fills span more than `MAX_BARS` bars.
+//!
+//! Indicators return one value per bar, `None` until enough bars have been
+//! seen; a period of zero yields no values at all.
+
+use crate::{OrderbookEvent, OrderbookEventType};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Most bars `resample` fills gaps across
+pub const MAX_BARS: u64 = 100_000;
+
+/// Fills spanning more bars than `MAX_BARS`, e.g. months of fills at a
+/// one-second interval
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Fills span {bars} bars of {interval}s, more than the {} allowed; use a longer interval", MAX_BARS)]
+pub struct TooManyBars {
+    pub bars: u64,
+    pub interval: i64,
+}
+
+/// One fill, the input of `resample`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trade {
+    pub timestamp: i64,
+    pub price: u64,
+    pub size: u64,
+}
+
+impl From<&OrderbookEvent> for Trade {
+    fn from(event: &OrderbookEvent) -> Self {
+        Trade { timestamp: event.timestamp, price: event.price, size: event.size }
+    }
+}
+
+/// Fills of `market_name` among `events`
+pub fn trades<'a>(events: &'a [OrderbookEvent], market_name: &'a str) -> impl Iterator<Item = Trade> + 'a {
+    events
+        .iter()
+        .filter(move |e| e.event_type == OrderbookEventType::OrderFilled && e.market_name == market_name)
+        .map(Trade::from)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candle {
+    /// Unix timestamp of the start of the bar
+    pub start: i64,
+    pub open: u64,
+    pub high: u64,
+    pub low: u64,
+    pub close: u64,
+    pub volume: u64,
+    pub trades: u64,
+}
+
+/// Parse an interval such as `30s`, `5m`, `4h` or `1d` into seconds; a bare
+/// number is taken as seconds
+pub fn parse_interval(interval: &str) -> Option<i64> {
+    let (digits, unit) = match interval.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => interval.split_at(i),
+        None => (interval, "s"),
+    };
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        _ => return None,
+    };
+    let seconds = digits.parse::<i64>().ok()?.checked_mul(multiplier)?;
+    (seconds > 0).then_some(seconds)
+}
+
+/// Bucket `trades` into bars of `interval` seconds, in time order.
+///
+/// Trades within a bar are taken in the order given; recorded events are
+/// already in time order.
+pub fn resample(
+    trades: impl IntoIterator<Item = Trade>,
+    interval: i64,
+    fill_gaps: bool,
+) -> Result<Vec<Candle>, TooManyBars> {
+    assert!(interval > 0, "interval must be positive");
+    let mut bars: BTreeMap<i64, Candle> = BTreeMap::new();
+    for trade in trades {
+        let start = trade.timestamp.div_euclid(interval) * interval;
+        let bar = bars.entry(start).or_insert(Candle {
+            start,
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            volume: 0,
+            trades: 0,
+        });
+        bar.high = bar.high.max(trade.price);
+        bar.low = bar.low.min(trade.price);
+        bar.close = trade.price;
+        bar.volume = bar.volume.saturating_add(trade.size);
+        bar.trades += 1;
+    }
+
+    if let (true, Some(first), Some(last)) = (fill_gaps, bars.keys().next(), bars.keys().next_back()) {
+        let span = (*last as i128 - *first as i128) / interval as i128 + 1;
+        let bars = u64::try_from(span).unwrap_or(u64::MAX);
+        if bars > MAX_BARS {
+            return Err(TooManyBars { bars, interval });
+        }
+    }
+
+    let mut candles: Vec<Candle> = Vec::with_capacity(bars.len());
+    for bar in bars.into_values() {
+        if let Some(prev) = candles.last().copied().filter(|_| fill_gaps) {
+            let mut start = prev.start + interval;
+            while start < bar.start {
+                let close = prev.close;
+                candles.push(Candle { start, open: close, high: close, low: close, close, volume: 0, trades: 0 });
+                start += interval;
+            }
+        }
+        candles.push(bar);
+    }
+    Ok(candles)
+}
+
+/// Closing prices of `candles`, the usual input of the indicators
+pub fn closes(candles: &[Candle]) -> Vec<f64> {
+    candles.iter().map(|c| c.close as f64).collect()
+}
+
+/// Simple moving average over `period` values
+pub fn sma(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period == 0 {
+        return out;
+    }
+    let mut sum = 0.0;
+    for (i, value) in values.iter().enumerate() {
+        sum += value;
+        if i >= period {
+            sum -= values[i - period];
+        }
+        if i + 1 >= period {
+            out[i] = Some(sum / period as f64);
+        }
+    }
+    out
+}
+
+/// Exponential moving average with smoothing `2 / (period + 1)`, seeded with
+/// the simple average of the first `period` values
+pub fn ema(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period == 0 || values.len() < period {
+        return out;
+    }
+    let alpha = 2.0 / (period as f64 + 1.0);
+    let mut average = values[..period].iter().sum::<f64>() / period as f64;
+    out[period - 1] = Some(average);
+    for (value, out) in values.iter().zip(out.iter_mut()).skip(period) {
+        average += alpha * (value - average);
+        *out = Some(average);
+    }
+    out
+}
+
+/// Relative strength index with Wilder's smoothing; 100 when there were no
+/// losses and 50 when prices did not move at all
+pub fn rsi(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period == 0 || values.len() <= period {
+        return out;
+    }
+    let change = |i: usize| values[i] - values[i - 1];
+    let index = |gain: f64, loss: f64| {
+        if loss == 0.0 {
+            if gain == 0.0 { 50.0 } else { 100.0 }
+        } else {
+            100.0 - 100.0 / (1.0 + gain / loss)
+        }
+    };
+
+    let (mut gain, mut loss) = (1..=period).fold((0.0, 0.0), |(gain, loss), i| {
+        let c = change(i);
+        (gain + c.max(0.0), loss + (-c).max(0.0))
+    });
+    gain /= period as f64;
+    loss /= period as f64;
+    out[period] = Some(index(gain, loss));
+    for (i, value) in out.iter_mut().enumerate().skip(period + 1) {
+        let c = change(i);
+        gain = (gain * (period - 1) as f64 + c.max(0.0)) / period as f64;
+        loss = (loss * (period - 1) as f64 + (-c).max(0.0)) / period as f64;
+        *value = Some(index(gain, loss));
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Band {
+    pub lower: f64,
+    pub middle: f64,
+    pub upper: f64,
+}
+
+/// Bollinger bands: the simple average of `period` values plus and minus
+/// `width` population standard deviations
+pub fn bollinger(values: &[f64], period: usize, width: f64) -> Vec<Option<Band>> {
+    sma(values, period)
+        .into_iter()
+        .enumerate()
+        .map(|(i, middle)| {
+            let middle = middle?;
+            let window = &values[i + 1 - period..=i];
+            let variance = window.iter().map(|v| (v - middle).powi(2)).sum::<f64>() / period as f64;
+            let deviation = width * variance.sqrt();
+            Some(Band { lower: middle - deviation, middle, upper: middle + deviation })
+        })
+        .collect()
+}
+
+/// Average true range with Wilder's smoothing, seeded with the simple average
+/// of the first `period` true ranges
+pub fn atr(candles: &[Candle], period: usize) -> Vec<Option<f64>> {
+    let true_ranges: Vec<f64> = candles
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let (high, low) = (c.high as f64, c.low as f64);
+            match i.checked_sub(1).map(|p| candles[p].close as f64) {
+                Some(prev) => (high - low).max((high - prev).abs()).max((low - prev).abs()),
+                None => high - low,
+            }
+        })
+        .collect();
+
+    let mut out = vec![None; candles.len()];
+    if period == 0 || candles.len() < period {
+        return out;
+    }
+    let mut average = true_ranges[..period].iter().sum::<f64>() / period as f64;
+    out[period - 1] = Some(average);
+    for (true_range, out) in true_ranges.iter().zip(out.iter_mut()).skip(period) {
+        average = (average * (period - 1) as f64 + true_range) / period as f64;
+        *out = Some(average);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(timestamp: i64, price: u64, size: u64) -> Trade {
+        Trade { timestamp, price, size }
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("5m"), Some(300));
+        assert_eq!(parse_interval("1d"), Some(86_400));
+        assert_eq!(parse_interval("90"), Some(90));
+        assert_eq!(parse_interval("0m"), None);
+        assert_eq!(parse_interval("5w"), None);
+        assert_eq!(parse_interval("m"), None);
+    }
+
+    #[test]
+    fn test_resample_with_gaps() {
+        let trades = [trade(600, 10, 1), trade(630, 12, 2), trade(659, 9, 1), trade(790, 11, 5)];
+        let candles = resample(trades, 60, false).unwrap();
+        assert_eq!(candles.len(), 2);
+        assert_eq!(
+            candles[0],
+            Candle { start: 600, open: 10, high: 12, low: 9, close: 9, volume: 4, trades: 3 }
+        );
+
+        let candles = resample(trades, 60, true).unwrap();
+        assert_eq!(candles.iter().map(|c| c.start).collect::<Vec<_>>(), vec![600, 660, 720, 780]);
+        assert_eq!(candles[1], Candle { start: 660, open: 9, high: 9, low: 9, close: 9, volume: 0, trades: 0 });
+        assert_eq!(candles[3].close, 11);
+
+        assert!(resample([], 60, true).unwrap().is_empty());
+
+        // Filling every bar between two distant fills is refused
+        let distant = [trade(0, 10, 1), trade(MAX_BARS as i64 * 60, 11, 1)];
+        assert_eq!(resample(distant, 60, true), Err(TooManyBars { bars: MAX_BARS + 1, interval: 60 }));
+        assert_eq!(resample(distant, 60, false).unwrap().len(), 2);
+        let far_apart = [trade(i64::MIN, 10, 1), trade(i64::MAX, 11, 1)];
+        assert_eq!(resample(far_apart, 1, true), Err(TooManyBars { bars: u64::MAX, interval: 1 }));
+    }
+
+    #[test]
+    fn test_indicators() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(sma(&values, 3), vec![None, None, Some(2.0), Some(3.0), Some(4.0)]);
+        assert_eq!(sma(&values, 0), vec![None; 5]);
+        assert_eq!(ema(&values, 3), vec![None, None, Some(2.0), Some(3.0), Some(4.0)]);
+        assert_eq!(ema(&values, 6), vec![None; 5]);
+
+        // Only gains, then an even split of gains and losses
+        assert_eq!(rsi(&values, 2)[2], Some(100.0));
+        assert_close(rsi(&[1.0, 2.0, 1.0], 2)[2], 50.0);
+        assert_eq!(rsi(&[3.0, 3.0, 3.0], 2)[2], Some(50.0));
+
+        let band = bollinger(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0], 8, 2.0)[7].unwrap();
+        assert_eq!((band.lower, band.middle, band.upper), (1.0, 5.0, 9.0));
+
+        let candle = |high, low, close| Candle { start: 0, open: close, high, low, close, volume: 1, trades: 1 };
+        let candles = [candle(10, 8, 9), candle(12, 9, 11), candle(11, 10, 10)];
+        // True ranges 2, 3 and 1; the second reaches back to the previous close
+        let atr = atr(&candles, 2);
+        assert_eq!(atr[0], None);
+        assert_close(atr[1], 2.5);
+        assert_close(atr[2], 1.75);
+    }
+}
@@ -0,0 +1,471 @@
+//! Off-chain per-order (L3) books rebuilt from an event stream with order ids.
+//!
+//! Recorded `OrderbookEvent`s carry no order ids, so the input is `L3Event`,
+//! built from venue events that do (`SequencedEvent::order_id`, set by the
+//! Serum adapter) and optionally seeded with the resting orders of a Serum
+//! bids/asks slab. Placements add an order, fills reduce it and cancellations
+//! remove what is left of it.
+//!
+//! `L3Replay` keeps a copy of the book every `checkpoint_interval` events, so
+//! the book at any historical sequence number is rebuilt by replaying at most
+//! that many events. A stream usually starts while orders placed before it
+//! are resting; their fills and cancellations are skipped and counted.
+
+use crate::adapter::SequencedEvent;
+use crate::serum::Slab;
+use crate::snapshot::DepthPoint;
+use crate::{OrderbookEventType, PriceLevel};
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+
+/// Events between two copies of the book kept by `L3Replay`
+pub const DEFAULT_CHECKPOINT_INTERVAL: usize = 65_536;
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum L3Error {
+    #[error("Event {seq_num} follows event {last}")]
+    OutOfOrder { seq_num: u64, last: u64 },
+    #[error("Order {0:#x} is already resting")]
+    DuplicateOrder(u128),
+    #[error("Order {0:#x} is not in the book")]
+    UnknownOrder(u128),
+}
+
+/// One event of a market's stream, identifying the order it applies to
+#[derive(Debug, Clone, PartialEq)]
+pub struct L3Event {
+    pub seq_num: u64,
+    pub order_id: u128,
+    pub market_name: String,
+    pub price: u64,
+    pub size: u64,
+    pub is_bid: bool,
+    pub event_type: OrderbookEventType,
+}
+
+impl L3Event {
+    /// `None` if the venue did not report an order id
+    pub fn from_sequenced(event: &SequencedEvent) -> Option<Self> {
+        Some(L3Event {
+            seq_num: event.seq_num,
+            order_id: event.order_id?,
+            market_name: event.event.market_name.clone(),
+            price: event.event.price,
+            size: event.event.size,
+            is_bid: event.event.is_bid,
+            event_type: event.event.event_type.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestingOrder {
+    pub order_id: u128,
+    pub price: u64,
+    pub size: u64,
+    pub is_bid: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Resting {
+    order: RestingOrder,
+    /// Position in the level's time priority
+    arrival: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Level {
+    size: u64,
+    orders: BTreeMap<u64, u128>,
+}
+
+/// The resting orders of one market
+#[derive(Debug, Clone, Default)]
+pub struct L3Book {
+    orders: HashMap<u128, Resting>,
+    bids: BTreeMap<u64, Level>,
+    asks: BTreeMap<u64, Level>,
+    arrivals: u64,
+    seq_num: Option<u64>,
+}
+
+impl L3Book {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seed a book with the resting orders of a Serum bids and asks slab
+    pub fn from_slabs(bids: &Slab, asks: &Slab) -> Result<Self, L3Error> {
+        let mut book = L3Book::new();
+        for (slab, is_bid) in [(bids, true), (asks, false)] {
+            for order in &slab.orders {
+                book.insert(RestingOrder {
+                    order_id: order.order_id,
+                    price: order.price,
+                    size: order.quantity,
+                    is_bid,
+                })?;
+            }
+        }
+        Ok(book)
+    }
+
+    /// Sequence number of the last applied event
+    pub fn seq_num(&self) -> Option<u64> {
+        self.seq_num
+    }
+
+    /// Number of resting orders
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    fn side(&self, is_bid: bool) -> &BTreeMap<u64, Level> {
+        if is_bid { &self.bids } else { &self.asks }
+    }
+
+    /// Add an order behind every order already resting at its price
+    pub fn insert(&mut self, order: RestingOrder) -> Result<(), L3Error> {
+        if self.orders.contains_key(&order.order_id) {
+            return Err(L3Error::DuplicateOrder(order.order_id));
+        }
+        let arrival = self.arrivals;
+        self.arrivals += 1;
+        let side = if order.is_bid { &mut self.bids } else { &mut self.asks };
+        let level = side.entry(order.price).or_default();
+        level.size = level.size.saturating_add(order.size);
+        level.orders.insert(arrival, order.order_id);
+        self.orders.insert(order.order_id, Resting { order, arrival });
+        Ok(())
+    }
+
+    /// Take up to `size` from an order, removing it once nothing is left;
+    /// `None` takes all of it
+    fn reduce(&mut self, order_id: u128, size: Option<u64>) -> Result<(), L3Error> {
+        let resting = self.orders.get_mut(&order_id).ok_or(L3Error::UnknownOrder(order_id))?;
+        let order = resting.order;
+        let taken = size.unwrap_or(order.size).min(order.size);
+        resting.order.size -= taken;
+        let (remaining, arrival) = (resting.order.size, resting.arrival);
+
+        let side = if order.is_bid { &mut self.bids } else { &mut self.asks };
+        let level = side.get_mut(&order.price).expect("resting order has a level");
+        level.size -= taken;
+        if remaining == 0 {
+            level.orders.remove(&arrival);
+            if level.orders.is_empty() {
+                side.remove(&order.price);
+            }
+            self.orders.remove(&order_id);
+        }
+        Ok(())
+    }
+
+    /// Apply the next event of the stream.
+    ///
+    /// Events must come in increasing sequence order. The sequence number
+    /// advances even when the event is rejected, so a stream that starts
+    /// after some orders were placed can skip the `UnknownOrder` errors of
+    /// those orders.
+    pub fn apply(&mut self, event: &L3Event) -> Result<(), L3Error> {
+        if let Some(last) = self.seq_num.filter(|&last| event.seq_num <= last) {
+            return Err(L3Error::OutOfOrder { seq_num: event.seq_num, last });
+        }
+        self.seq_num = Some(event.seq_num);
+        match event.event_type {
+            OrderbookEventType::OrderPlaced => self.insert(RestingOrder {
+                order_id: event.order_id,
+                price: event.price,
+                size: event.size,
+                is_bid: event.is_bid,
+            }),
+            OrderbookEventType::OrderFilled => self.reduce(event.order_id, Some(event.size)),
+            OrderbookEventType::OrderCancelled => self.reduce(event.order_id, None),
+        }
+    }
+
+    pub fn order(&self, order_id: u128) -> Option<&RestingOrder> {
+        self.orders.get(&order_id).map(|r| &r.order)
+    }
+
+    /// Orders resting at a price, in time priority
+    pub fn orders_at(&self, is_bid: bool, price: u64) -> Vec<&RestingOrder> {
+        self.side(is_bid).get(&price).map_or(Vec::new(), |level| {
+            level.orders.values().map(|id| &self.orders[id].order).collect()
+        })
+    }
+
+    /// Top `depth` levels of one side, best price first
+    pub fn levels(&self, is_bid: bool, depth: usize) -> Vec<PriceLevel> {
+        let level = |(&price, level): (&u64, &Level)| PriceLevel { price, size: level.size };
+        if is_bid {
+            self.bids.iter().rev().take(depth).map(level).collect()
+        } else {
+            self.asks.iter().take(depth).map(level).collect()
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<PriceLevel> {
+        self.levels(true, 1).pop()
+    }
+
+    pub fn best_ask(&self) -> Option<PriceLevel> {
+        self.levels(false, 1).pop()
+    }
+
+    /// Total size resting at a price on one side
+    pub fn depth_at(&self, is_bid: bool, price: u64) -> u64 {
+        self.side(is_bid).get(&price).map_or(0, |level| level.size)
+    }
+
+    /// Cumulative depth of the top `depth` levels of one side, best price first
+    pub fn depth_curve(&self, is_bid: bool, depth: usize) -> Vec<DepthPoint> {
+        let mut cumulative = 0u64;
+        self.levels(is_bid, depth)
+            .into_iter()
+            .map(|level| {
+                cumulative = cumulative.saturating_add(level.size);
+                DepthPoint { price: level.price, size: level.size, cumulative }
+            })
+            .collect()
+    }
+
+    /// `(bids - asks) / (bids + asks)` over the top `depth` levels of each
+    /// side, from -1 (only asks) to 1 (only bids); `None` for an empty book
+    pub fn imbalance(&self, depth: usize) -> Option<f64> {
+        let total = |is_bid| self.levels(is_bid, depth).iter().map(|l| l.size as f64).sum::<f64>();
+        let (bids, asks) = (total(true), total(false));
+        (bids + asks > 0.0).then(|| (bids - asks) / (bids + asks))
+    }
+}
+
+/// A market's event stream with periodic copies of its book
+pub struct L3Replay {
+    events: Vec<L3Event>,
+    /// `checkpoints[i]` is the book after `i * checkpoint_interval` events
+    checkpoints: Vec<L3Book>,
+    checkpoint_interval: usize,
+    unknown_orders: usize,
+}
+
+// Apply an event of a replay, skipping those of orders placed before the
+// stream started. Returns whether the event was skipped.
+fn replay_event(book: &mut L3Book, event: &L3Event) -> Result<bool, L3Error> {
+    match book.apply(event) {
+        Ok(()) => Ok(false),
+        Err(L3Error::UnknownOrder(_)) => Ok(true),
+        Err(e) => Err(e),
+    }
+}
+
+impl L3Replay {
+    /// Replay `events` of one market on top of `initial`, keeping a copy of
+    /// the book every `checkpoint_interval` events.
+    ///
+    /// Fills and cancellations of orders missing from the book are skipped;
+    /// out of order events and duplicate placements are errors.
+    pub fn new(initial: L3Book, events: Vec<L3Event>, checkpoint_interval: usize) -> Result<Self, L3Error> {
+        let checkpoint_interval = checkpoint_interval.max(1);
+        let mut book = initial;
+        let mut checkpoints = vec![book.clone()];
+        let mut unknown_orders = 0;
+        for (i, event) in events.iter().enumerate() {
+            if replay_event(&mut book, event)? {
+                unknown_orders += 1;
+            }
+            if (i + 1) % checkpoint_interval == 0 {
+                checkpoints.push(book.clone());
+            }
+        }
+        Ok(L3Replay { events, checkpoints, checkpoint_interval, unknown_orders })
+    }
+
+    /// Split a stream of several markets and replay each from an empty book
+    pub fn by_market(
+        events: impl IntoIterator<Item = L3Event>,
+        checkpoint_interval: usize,
+    ) -> Result<BTreeMap<String, L3Replay>, L3Error> {
+        let mut markets: BTreeMap<String, Vec<L3Event>> = BTreeMap::new();
+        for event in events {
+            markets.entry(event.market_name.clone()).or_default().push(event);
+        }
+        markets
+            .into_iter()
+            .map(|(market, events)| Ok((market, L3Replay::new(L3Book::new(), events, checkpoint_interval)?)))
+            .collect()
+    }
+
+    pub fn events(&self) -> &[L3Event] {
+        &self.events
+    }
+
+    /// Number of skipped events of orders that were not in the book
+    pub fn unknown_orders(&self) -> usize {
+        self.unknown_orders
+    }
+
+    /// The book after every event up to and including `seq_num`
+    pub fn book_at(&self, seq_num: u64) -> L3Book {
+        let applied = self.events.partition_point(|e| e.seq_num <= seq_num);
+        let checkpoint = applied / self.checkpoint_interval;
+        let mut book = self.checkpoints[checkpoint].clone();
+        for event in &self.events[checkpoint * self.checkpoint_interval..applied] {
+            replay_event(&mut book, event).expect("events were checked when the replay was built");
+        }
+        book
+    }
+
+    /// The book after the whole stream
+    pub fn book(&self) -> L3Book {
+        self.book_at(u64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::{MarketAdapter, SerumAdapter};
+    use solana_program::pubkey::Pubkey;
+
+    const MARKET: &[u8] = include_bytes!("../tests/fixtures/serum/market.bin");
+    const BIDS: &[u8] = include_bytes!("../tests/fixtures/serum/bids.bin");
+    const ASKS: &[u8] = include_bytes!("../tests/fixtures/serum/asks.bin");
+    const EVENT_QUEUE: &[u8] = include_bytes!("../tests/fixtures/serum/event_queue.bin");
+
+    fn event(
+        seq_num: u64,
+        order_id: u128,
+        price: u64,
+        size: u64,
+        is_bid: bool,
+        event_type: OrderbookEventType,
+    ) -> L3Event {
+        L3Event { seq_num, order_id, market_name: "SOL/USDC".to_string(), price, size, is_bid, event_type }
+    }
+
+    #[test]
+    fn test_book_queries() {
+        use OrderbookEventType::*;
+        let mut book = L3Book::new();
+        for e in [
+            event(1, 1, 100, 5, true, OrderPlaced),
+            event(2, 2, 100, 3, true, OrderPlaced),
+            event(3, 3, 99, 4, true, OrderPlaced),
+            event(4, 4, 102, 6, false, OrderPlaced),
+            event(5, 1, 100, 2, true, OrderFilled),
+            event(6, 2, 100, 0, true, OrderCancelled),
+        ] {
+            book.apply(&e).unwrap();
+        }
+        assert_eq!(book.len(), 3);
+        assert_eq!(book.best_bid(), Some(PriceLevel { price: 100, size: 3 }));
+        assert_eq!(book.best_ask(), Some(PriceLevel { price: 102, size: 6 }));
+        assert_eq!(book.depth_at(true, 99), 4);
+        assert_eq!(book.order(1).unwrap().size, 3);
+        assert_eq!(book.order(2), None);
+        assert_eq!(book.depth_curve(true, 2).last().unwrap().cumulative, 7);
+        assert_eq!(book.imbalance(1), Some(-1.0 / 3.0));
+
+        // Later orders queue behind earlier ones at the same price
+        book.apply(&event(7, 5, 99, 1, true, OrderPlaced)).unwrap();
+        let queue: Vec<u128> = book.orders_at(true, 99).iter().map(|o| o.order_id).collect();
+        assert_eq!(queue, vec![3, 5]);
+
+        assert_eq!(
+            book.apply(&event(7, 6, 99, 1, true, OrderPlaced)),
+            Err(L3Error::OutOfOrder { seq_num: 7, last: 7 })
+        );
+        assert_eq!(book.apply(&event(8, 3, 99, 1, true, OrderPlaced)), Err(L3Error::DuplicateOrder(3)));
+        assert_eq!(book.apply(&event(9, 42, 99, 1, true, OrderFilled)), Err(L3Error::UnknownOrder(42)));
+        assert_eq!(L3Book::new().imbalance(5), None);
+    }
+
+    #[test]
+    fn test_serum_book_and_queue() {
+        let bids = Slab::decode(BIDS, true).unwrap();
+        let asks = Slab::decode(ASKS, false).unwrap();
+        let mut book = L3Book::from_slabs(&bids, &asks).unwrap();
+        assert_eq!(book.len(), bids.orders.len() + asks.orders.len());
+        let adapter = SerumAdapter::new(Pubkey::new_unique(), MARKET, "SOL/USDC").unwrap();
+        assert_eq!(book.levels(true, 1), adapter.decode_levels(BIDS, true, 1).unwrap());
+
+        let events: Vec<L3Event> = adapter
+            .decode_events(EVENT_QUEUE)
+            .unwrap()
+            .iter()
+            .filter_map(L3Event::from_sequenced)
+            .collect();
+        assert_eq!(events.len(), 2);
+        // The maker fill reduces a resting ask and the out removes another
+        let (fill, out) = (&events[0], &events[1]);
+        let filled = book.order(fill.order_id).unwrap().size;
+        assert!(book.order(out.order_id).is_some());
+        book.apply(fill).unwrap();
+        book.apply(out).unwrap();
+        assert_eq!(book.order(fill.order_id).map_or(0, |o| o.size), filled - fill.size);
+        assert_eq!(book.order(out.order_id), None);
+        assert_eq!(book.seq_num(), Some(8));
+    }
+
+    #[test]
+    fn test_replay_at_sequence_numbers() {
+        // Orders are placed, half filled and then cancelled, ten at a time
+        let mut events = Vec::new();
+        let mut seq_num = 0;
+        let mut next = |order_id: u128, size, event_type| {
+            seq_num += 1;
+            let price = 100 + (order_id % 10) as u64;
+            event(seq_num, order_id, price, size, order_id.is_multiple_of(2), event_type)
+        };
+        for batch in 0..10_000u128 {
+            let ids = batch * 10..batch * 10 + 10;
+            events.extend(ids.clone().map(|id| next(id, 4, OrderbookEventType::OrderPlaced)));
+            events.extend(ids.clone().map(|id| next(id, 2, OrderbookEventType::OrderFilled)));
+            events.extend(ids.map(|id| next(id, 0, OrderbookEventType::OrderCancelled)));
+        }
+
+        let replay = L3Replay::new(L3Book::new(), events, 1_000).unwrap();
+        assert!(replay.book().is_empty());
+        // After the placements and fills of batch 500, its ten orders rest with size 2
+        let book = replay.book_at(500 * 30 + 20);
+        assert_eq!(book.len(), 10);
+        assert_eq!(book.depth_at(true, 100), 2);
+        assert_eq!(book.best_ask(), Some(PriceLevel { price: 101, size: 2 }));
+        assert_eq!(book.seq_num(), Some(500 * 30 + 20));
+        assert!(replay.book_at(0).is_empty());
+
+        // Each market is replayed on its own
+        let mut mixed = replay.events()[..30].to_vec();
+        for event in mixed.iter_mut().filter(|e| e.order_id == 0) {
+            event.market_name = "BTC/USDC".to_string();
+        }
+        let markets = L3Replay::by_market(mixed, 10).unwrap();
+        assert_eq!(markets.len(), 2);
+        assert_eq!(markets["BTC/USDC"].events().len(), 3);
+        assert_eq!(markets["BTC/USDC"].book_at(11).order(0).map(|o| o.size), Some(2));
+        assert_eq!(markets["SOL/USDC"].book_at(20).len(), 9);
+    }
+
+    #[test]
+    fn test_replay_starting_mid_life() {
+        use OrderbookEventType::*;
+        // Order 1 was placed before the stream started
+        let events = vec![
+            event(10, 1, 100, 2, true, OrderFilled),
+            event(11, 2, 101, 5, false, OrderPlaced),
+            event(12, 1, 100, 0, true, OrderCancelled),
+            event(13, 2, 101, 3, false, OrderFilled),
+        ];
+        let replay = L3Replay::new(L3Book::new(), events, 2).unwrap();
+        assert_eq!(replay.unknown_orders(), 2);
+        assert_eq!(replay.book().len(), 1);
+        assert_eq!(replay.book_at(12).best_ask(), Some(PriceLevel { price: 101, size: 5 }));
+        assert_eq!(replay.book().order(2).map(|o| o.size), Some(2));
+        assert_eq!(replay.book().seq_num(), Some(13));
+        assert_eq!(L3Replay::by_market(replay.events().to_vec(), 2).unwrap()["SOL/USDC"].unknown_orders(), 2);
+    }
+}
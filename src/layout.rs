@@ -0,0 +1,65 @@
+//! Little-endian field reader shared by the DEX account decoders.
+
+use solana_program::pubkey::Pubkey;
+
+/// The account data ended before the field being read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TooShort;
+
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Reader { data }
+    }
+
+    /// Reader over `len` bytes starting at `offset`
+    pub(crate) fn at(data: &'a [u8], offset: usize, len: usize) -> Result<Self, TooShort> {
+        data.get(offset..offset.checked_add(len).ok_or(TooShort)?)
+            .map(Reader::new)
+            .ok_or(TooShort)
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], TooShort> {
+        if self.data.len() < len {
+            return Err(TooShort);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    pub(crate) fn skip(&mut self, len: usize) -> Result<(), TooShort> {
+        self.take(len).map(|_| ())
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, TooShort> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, TooShort> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, TooShort> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, TooShort> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn i64(&mut self) -> Result<i64, TooShort> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u128(&mut self) -> Result<u128, TooShort> {
+        Ok(u128::from_le_bytes(self.take(16)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn pubkey(&mut self) -> Result<Pubkey, TooShort> {
+        Ok(Pubkey::new_from_array(self.take(32)?.try_into().unwrap()))
+    }
+}
@@ -4,27 +4,127 @@ use solana_program::{
     entrypoint,
     entrypoint::ProgramResult,
     msg,
+    program::invoke,
     program_error::ProgramError,
     pubkey::Pubkey,
     clock::Clock,
+    rent::Rent,
+    system_instruction, system_program,
     sysvar::Sysvar,
 };
 use thiserror::Error;
 
-// Define program errors
-#[derive(Error, Debug, Copy, Clone)]
+pub mod adapter;
+#[cfg(not(target_os = "solana"))]
+pub mod analytics;
+#[cfg(not(target_os = "solana"))]
+pub mod arbitrage;
+#[cfg(not(target_os = "solana"))]
+pub mod backtest;
+#[cfg(not(target_os = "solana"))]
+pub mod candles;
+#[cfg(not(target_os = "solana"))]
+pub mod l3;
+mod layout;
+#[cfg(not(target_os = "solana"))]
+pub mod microstructure;
+pub mod serum;
+pub mod snapshot;
+#[cfg(not(target_os = "solana"))]
+pub mod surveillance;
+
+/// Maximum number of price levels recorded per side in a book snapshot
+pub const MAX_SNAPSHOT_LEVELS: usize = 32;
+
+/// Maximum length of a market name in bytes, the size of OpenBook's name field
+pub const MAX_MARKET_NAME_LEN: usize = 16;
+
+/// Errors returned by the program as `ProgramError::Custom(code)`.
+///
+/// The codes are part of the program's interface: never renumber a variant,
+/// only append new ones.
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum OrderbookError {
     #[error("Invalid instruction")]
-    InvalidInstruction,
+    InvalidInstruction = 0,
     #[error("Invalid account owner")]
-    InvalidOwner,
+    InvalidOwner = 1,
     #[error("Account already initialized")]
-    AlreadyInitialized,
+    AlreadyInitialized = 2,
+    #[error("Invalid book snapshot")]
+    InvalidSnapshot = 3,
+    #[error("Signer is not the monitor authority")]
+    Unauthorized = 4,
+    #[error("Account size is smaller than the recorded data")]
+    SizeTooSmall = 5,
+    #[error("Market name is longer than {} bytes", MAX_MARKET_NAME_LEN)]
+    MarketNameTooLong = 6,
+    #[error("Market name must be non-empty and use only A-Z, 0-9, '/' and '-'")]
+    InvalidMarketName = 7,
+    #[error("Event size must be greater than zero")]
+    ZeroSize = 8,
+    #[error("Placed and filled orders must have a non-zero price")]
+    ZeroPrice = 9,
+    #[error("Event batch is empty")]
+    EmptyBatch = 10,
+    #[error("Monitor account is not initialized")]
+    NotInitialized = 11,
+    #[error("Monitor account is full; resize it to record more")]
+    AccountFull = 12,
+    #[error("Monitor is paused")]
+    Paused = 13,
+    #[error("Market account cannot be a monitor account")]
+    InvalidMarket = 14,
+    #[error("Clock is behind the last recorded timestamp")]
+    StaleTimestamp = 15,
+}
+
+impl OrderbookError {
+    /// Every error, indexed by its code
+    pub const ALL: [OrderbookError; 16] = [
+        OrderbookError::InvalidInstruction,
+        OrderbookError::InvalidOwner,
+        OrderbookError::AlreadyInitialized,
+        OrderbookError::InvalidSnapshot,
+        OrderbookError::Unauthorized,
+        OrderbookError::SizeTooSmall,
+        OrderbookError::MarketNameTooLong,
+        OrderbookError::InvalidMarketName,
+        OrderbookError::ZeroSize,
+        OrderbookError::ZeroPrice,
+        OrderbookError::EmptyBatch,
+        OrderbookError::NotInitialized,
+        OrderbookError::AccountFull,
+        OrderbookError::Paused,
+        OrderbookError::InvalidMarket,
+        OrderbookError::StaleTimestamp,
+    ];
+
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    /// The error behind a `ProgramError::Custom` code, if the code is one of ours
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.get(code as usize).copied()
+    }
+}
+
+/// Check a market name against the length limit and the allowed characters
+pub fn validate_market_name(market_name: &str) -> Result<(), OrderbookError> {
+    if market_name.len() > MAX_MARKET_NAME_LEN {
+        return Err(OrderbookError::MarketNameTooLong);
+    }
+    let allowed = |c: char| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '/' || c == '-';
+    if market_name.is_empty() || !market_name.chars().all(allowed) {
+        return Err(OrderbookError::InvalidMarketName);
+    }
+    Ok(())
 }
 
 impl From<OrderbookError> for ProgramError {
     fn from(e: OrderbookError) -> Self {
-        ProgramError::Custom(e as u32)
+        ProgramError::Custom(e.code())
     }
 }
 
@@ -33,13 +133,15 @@ impl From<OrderbookError> for ProgramError {
 pub enum OrderbookInstruction {
     /// Initialize a new orderbook monitor
     /// Accounts expected:
-    /// 0. `[writable]` The orderbook monitor account to initialize
+    /// 0. `[writable, signer]` The orderbook monitor account to initialize
+    /// 1. `[signer]` (optional) The authority; defaults to the monitor account
     Initialize,
     
     /// Record a new orderbook event
     /// Accounts expected:
     /// 0. `[writable]` The orderbook monitor account
     /// 1. `[]` Market account or other relevant account to monitor
+    /// 2. `[signer]` The monitor authority
     RecordEvent {
         market_name: String,
         price: u64,
@@ -47,6 +149,87 @@ pub enum OrderbookInstruction {
         is_bid: bool,
         event_type: OrderbookEventType,
     },
+
+    /// Record a snapshot of the top price levels of a market's book
+    /// Accounts expected:
+    /// 0. `[writable]` The orderbook monitor account
+    /// 1. `[]` Market account the snapshot was taken from
+    /// 2. `[signer]` The monitor authority
+    RecordSnapshot {
+        market_name: String,
+        bids: Vec<PriceLevel>,
+        asks: Vec<PriceLevel>,
+    },
+
+    /// Record several orderbook events in one instruction
+    /// Accounts expected:
+    /// 0. `[writable]` The orderbook monitor account
+    /// 1. `[]` Market account or other relevant account to monitor
+    /// 2. `[signer]` The monitor authority
+    RecordEvents {
+        events: Vec<NewEvent>,
+    },
+
+    /// Hand the monitor over to a new authority
+    /// Accounts expected:
+    /// 0. `[writable]` The orderbook monitor account
+    /// 1. `[signer]` The current monitor authority
+    /// 2. `[signer]` The new monitor authority
+    SetAuthority {
+        new_authority: Pubkey,
+    },
+
+    /// Grow or shrink the monitor account, keeping it rent exempt
+    /// Accounts expected:
+    /// 0. `[writable]` The orderbook monitor account
+    /// 1. `[signer]` The monitor authority
+    /// 2. `[writable, signer]` Payer of extra rent, refunded when shrinking
+    /// 3. `[]` The system program
+    Resize {
+        new_len: u64,
+    },
+
+    /// Close the monitor account and reclaim its lamports
+    /// Accounts expected:
+    /// 0. `[writable]` The orderbook monitor account
+    /// 1. `[signer]` The monitor authority
+    /// 2. `[writable]` Account receiving the lamports
+    Close,
+
+    /// Stop or resume recording events and snapshots
+    /// Accounts expected:
+    /// 0. `[writable]` The orderbook monitor account
+    /// 1. `[signer]` The monitor authority
+    SetPaused {
+        paused: bool,
+    },
+}
+
+/// One event of a `RecordEvents` batch, with the same fields as `RecordEvent`
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone)]
+pub struct NewEvent {
+    pub market_name: String,
+    pub price: u64,
+    pub size: u64,
+    pub is_bid: bool,
+    pub event_type: OrderbookEventType,
+}
+
+impl NewEvent {
+    /// Check the event the way the program does before recording it.
+    ///
+    /// Cancellations may carry a zero price: some venues do not report the
+    /// price of a cancelled order.
+    pub fn validate(&self) -> Result<(), OrderbookError> {
+        validate_market_name(&self.market_name)?;
+        if self.size == 0 {
+            return Err(OrderbookError::ZeroSize);
+        }
+        if self.price == 0 && self.event_type != OrderbookEventType::OrderCancelled {
+            return Err(OrderbookError::ZeroPrice);
+        }
+        Ok(())
+    }
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone)]
@@ -63,6 +246,12 @@ pub struct OrderbookMonitor {
     pub authority: Pubkey,
     pub event_count: u64,
     pub events: Vec<OrderbookEvent>,
+    /// Book snapshots and the paused flag follow the events: an account
+    /// written when only events were stored reads as holding no snapshots and
+    /// not paused from the zeroed spare space after its events.
+    pub snapshots: Vec<BookSnapshot>,
+    /// Whether recording is stopped
+    pub paused: bool,
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
@@ -75,6 +264,88 @@ pub struct OrderbookEvent {
     pub event_type: OrderbookEventType,
 }
 
+impl OrderbookEvent {
+    /// Serialized size of an event without its market name bytes
+    pub const FIXED_LEN: usize = 8 + 4 + 8 + 8 + 1 + 1;
+
+    /// Serialized size of an event whose market name is `market_name_len` bytes long
+    pub fn space(market_name_len: usize) -> usize {
+        Self::FIXED_LEN + market_name_len
+    }
+}
+
+/// A single price level of an L2 book: price and the aggregated size resting at it
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PriceLevel {
+    pub price: u64,
+    pub size: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct BookSnapshot {
+    pub slot: u64,
+    pub timestamp: i64,
+    pub market_name: String,
+    /// Number of events recorded before this snapshot was taken
+    pub event_index: u64,
+    /// Bid levels, best (highest) price first
+    pub bids: Vec<PriceLevel>,
+    /// Ask levels, best (lowest) price first
+    pub asks: Vec<PriceLevel>,
+}
+
+impl BookSnapshot {
+    /// Serialized size of a snapshot with `levels` price levels per side,
+    /// recorded for a market name `market_name_len` bytes long
+    pub fn space(market_name_len: usize, levels: usize) -> usize {
+        8 + 8 + 4 + market_name_len + 8 + 2 * (4 + levels * 16)
+    }
+}
+
+impl OrderbookMonitor {
+    /// Serialized size of a monitor with nothing recorded: `initialized`,
+    /// `authority`, `event_count`, the length prefixes of both vectors and
+    /// `paused`
+    pub const EMPTY_LEN: usize = 1 + 32 + 8 + 4 + 4 + 1;
+
+    /// Exact account size needed to record `max_events` events whose market
+    /// names are at most `max_market_name_len` bytes long. Leaves no room for
+    /// snapshots; see `space_with_snapshots`.
+    pub fn space(max_events: usize, max_market_name_len: usize) -> usize {
+        Self::EMPTY_LEN + max_events * OrderbookEvent::space(max_market_name_len)
+    }
+
+    /// Exact account size needed to also record `max_snapshots` snapshots of
+    /// at most `max_levels` levels per side
+    pub fn space_with_snapshots(
+        max_events: usize,
+        max_snapshots: usize,
+        max_levels: usize,
+        max_market_name_len: usize,
+    ) -> usize {
+        Self::space(max_events, max_market_name_len)
+            + max_snapshots * BookSnapshot::space(max_market_name_len, max_levels)
+    }
+
+    /// Deserialize the monitor from account data, ignoring any unused trailing space
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Serialize the monitor into the start of the account data
+    pub fn pack(&self, dst: &mut [u8]) -> ProgramResult {
+        self.serialize(&mut &mut dst[..])
+            .map_err(|_| OrderbookError::AccountFull.into())
+    }
+
+    /// Timestamp of the latest recorded event or snapshot
+    pub fn last_timestamp(&self) -> Option<i64> {
+        let event = self.events.last().map(|e| e.timestamp);
+        let snapshot = self.snapshots.last().map(|s| s.timestamp);
+        event.max(snapshot)
+    }
+}
+
 // Program entrypoint
 entrypoint!(process_instruction);
 
@@ -94,7 +365,61 @@ pub fn process_instruction(
         OrderbookInstruction::RecordEvent { market_name, price, size, is_bid, event_type } => {
             process_record_event(program_id, accounts, market_name, price, size, is_bid, event_type)
         },
+        OrderbookInstruction::RecordSnapshot { market_name, bids, asks } => {
+            process_record_snapshot(program_id, accounts, market_name, bids, asks)
+        },
+        OrderbookInstruction::RecordEvents { events } => {
+            process_record_events(program_id, accounts, events)
+        },
+        OrderbookInstruction::SetAuthority { new_authority } => {
+            process_set_authority(program_id, accounts, new_authority)
+        },
+        OrderbookInstruction::Resize { new_len } => {
+            process_resize(program_id, accounts, new_len)
+        },
+        OrderbookInstruction::Close => {
+            process_close(program_id, accounts)
+        },
+        OrderbookInstruction::SetPaused { paused } => {
+            process_set_paused(program_id, accounts, paused)
+        },
+    }
+}
+
+/// Check that `monitor` is initialized and that `authority` signed and is its authority
+fn check_authority(monitor: &OrderbookMonitor, authority: &AccountInfo) -> ProgramResult {
+    if !monitor.initialized {
+        msg!("Monitor account is not initialized");
+        return Err(OrderbookError::NotInitialized.into());
+    }
+    if !authority.is_signer || *authority.key != monitor.authority {
+        msg!("Missing signature of the monitor authority");
+        return Err(OrderbookError::Unauthorized.into());
+    }
+    Ok(())
+}
+
+/// Check that recording into `monitor` is allowed at `clock` for `market_account`
+fn check_recording(
+    program_id: &Pubkey,
+    monitor: &OrderbookMonitor,
+    market_account: &AccountInfo,
+    clock: &Clock,
+) -> ProgramResult {
+    if monitor.paused {
+        msg!("Monitor is paused");
+        return Err(OrderbookError::Paused.into());
+    }
+    if market_account.owner == program_id {
+        msg!("Market account {} is a monitor account", market_account.key);
+        return Err(OrderbookError::InvalidMarket.into());
+    }
+    // Keep recorded timestamps in order for anything replaying them
+    if monitor.last_timestamp().is_some_and(|last| clock.unix_timestamp < last) {
+        msg!("Clock timestamp {} is behind the last recorded one", clock.unix_timestamp);
+        return Err(OrderbookError::StaleTimestamp.into());
     }
+    Ok(())
 }
 
 fn process_initialize(
@@ -110,23 +435,47 @@ fn process_initialize(
         return Err(OrderbookError::InvalidOwner.into());
     }
 
-    // Get authority (the first account is also the authority in this simple case)
-    let authority = monitor_account.key;
+    // Initializing again would let anyone take over the monitor
+    if OrderbookMonitor::unpack(&monitor_account.data.borrow()).is_ok_and(|m| m.initialized) {
+        msg!("Monitor account is already initialized");
+        return Err(OrderbookError::AlreadyInitialized.into());
+    }
+
+    // Otherwise anyone could claim an account created for the program
+    // between its creation and its owner's initialization
+    if !monitor_account.is_signer {
+        msg!("Missing signature of the monitor account");
+        return Err(OrderbookError::Unauthorized.into());
+    }
+
+    // The authority defaults to the monitor account itself, and must sign
+    // when given so a monitor cannot be handed to a mistyped key
+    let authority = match next_account_info(account_info_iter) {
+        Ok(authority) if !authority.is_signer => {
+            msg!("Missing signature of the authority");
+            return Err(OrderbookError::Unauthorized.into());
+        }
+        Ok(authority) => *authority.key,
+        Err(_) => *monitor_account.key,
+    };
 
     // Initialize the monitor account
     let monitor = OrderbookMonitor {
         initialized: true,
-        authority: *authority,
+        authority,
         event_count: 0,
         events: Vec::new(),
+        snapshots: Vec::new(),
+        paused: false,
     };
 
-    monitor.serialize(&mut *monitor_account.data.borrow_mut())?;
+    monitor.pack(&mut monitor_account.data.borrow_mut())?;
     
     msg!("Orderbook monitor initialized");
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn process_record_event(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
@@ -138,7 +487,8 @@ fn process_record_event(
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
     let monitor_account = next_account_info(account_info_iter)?;
-    let _market_account = next_account_info(account_info_iter)?;
+    let market_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
 
     // Check account ownership
     if monitor_account.owner != program_id {
@@ -146,20 +496,28 @@ fn process_record_event(
         return Err(OrderbookError::InvalidOwner.into());
     }
 
+    let new_event = NewEvent { market_name, price, size, is_bid, event_type };
+    if let Err(e) = new_event.validate() {
+        msg!("Rejected event: {}", e);
+        return Err(e.into());
+    }
+
     // Get the current clock for timestamp
     let clock = Clock::get()?;
     
     // Load the monitor account data
-    let mut monitor = OrderbookMonitor::try_from_slice(&monitor_account.data.borrow())?;
+    let mut monitor = OrderbookMonitor::unpack(&monitor_account.data.borrow())?;
+    check_authority(&monitor, authority)?;
+    check_recording(program_id, &monitor, market_account, &clock)?;
 
     // Create a new event
     let event = OrderbookEvent {
         timestamp: clock.unix_timestamp,
-        market_name,
-        price,
-        size,
-        is_bid,
-        event_type,
+        market_name: new_event.market_name,
+        price: new_event.price,
+        size: new_event.size,
+        is_bid: new_event.is_bid,
+        event_type: new_event.event_type,
     };
 
     // Record the event
@@ -167,13 +525,259 @@ fn process_record_event(
     monitor.event_count += 1;
 
     // Save the updated monitor account
-    monitor.serialize(&mut *monitor_account.data.borrow_mut())?;
+    monitor.pack(&mut monitor_account.data.borrow_mut())?;
 
     // Log the event
     msg!("Orderbook event recorded: {:?}", event);
     Ok(())
 }
 
+fn process_record_events(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    events: Vec<NewEvent>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let monitor_account = next_account_info(account_info_iter)?;
+    let market_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+
+    // Check account ownership
+    if monitor_account.owner != program_id {
+        msg!("Monitor account does not have the correct program id");
+        return Err(OrderbookError::InvalidOwner.into());
+    }
+
+    if events.is_empty() {
+        msg!("Rejected empty event batch");
+        return Err(OrderbookError::EmptyBatch.into());
+    }
+    for (i, event) in events.iter().enumerate() {
+        if let Err(e) = event.validate() {
+            msg!("Rejected event {}: {}", i, e);
+            return Err(e.into());
+        }
+    }
+
+    let clock = Clock::get()?;
+
+    let mut monitor = OrderbookMonitor::unpack(&monitor_account.data.borrow())?;
+    check_authority(&monitor, authority)?;
+    check_recording(program_id, &monitor, market_account, &clock)?;
+
+    // Every event of the batch shares the transaction's timestamp
+    let count = events.len();
+    for new_event in events {
+        monitor.events.push(OrderbookEvent {
+            timestamp: clock.unix_timestamp,
+            market_name: new_event.market_name,
+            price: new_event.price,
+            size: new_event.size,
+            is_bid: new_event.is_bid,
+            event_type: new_event.event_type,
+        });
+    }
+    monitor.event_count += count as u64;
+
+    monitor.pack(&mut monitor_account.data.borrow_mut())?;
+
+    msg!("Orderbook events recorded: {}", count);
+    Ok(())
+}
+
+fn process_record_snapshot(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    market_name: String,
+    bids: Vec<PriceLevel>,
+    asks: Vec<PriceLevel>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let monitor_account = next_account_info(account_info_iter)?;
+    let market_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+
+    // Check account ownership
+    if monitor_account.owner != program_id {
+        msg!("Monitor account does not have the correct program id");
+        return Err(OrderbookError::InvalidOwner.into());
+    }
+
+    if let Err(e) = validate_market_name(&market_name) {
+        msg!("Rejected snapshot: {}", e);
+        return Err(e.into());
+    }
+
+    // Each side must fit the level limit and be ordered best price first
+    if bids.len() > MAX_SNAPSHOT_LEVELS || asks.len() > MAX_SNAPSHOT_LEVELS {
+        msg!("Snapshot exceeds {} levels per side", MAX_SNAPSHOT_LEVELS);
+        return Err(OrderbookError::InvalidSnapshot.into());
+    }
+    if !bids.windows(2).all(|w| w[0].price > w[1].price)
+        || !asks.windows(2).all(|w| w[0].price < w[1].price)
+    {
+        msg!("Snapshot levels must be sorted best price first without duplicates");
+        return Err(OrderbookError::InvalidSnapshot.into());
+    }
+
+    let clock = Clock::get()?;
+
+    let mut monitor = OrderbookMonitor::unpack(&monitor_account.data.borrow())?;
+    check_authority(&monitor, authority)?;
+    check_recording(program_id, &monitor, market_account, &clock)?;
+
+    let snapshot = BookSnapshot {
+        slot: clock.slot,
+        timestamp: clock.unix_timestamp,
+        market_name,
+        event_index: monitor.event_count,
+        bids,
+        asks,
+    };
+
+    msg!(
+        "Orderbook snapshot recorded: {} at slot {} ({} bids, {} asks)",
+        snapshot.market_name,
+        snapshot.slot,
+        snapshot.bids.len(),
+        snapshot.asks.len()
+    );
+    monitor.snapshots.push(snapshot);
+
+    monitor.pack(&mut monitor_account.data.borrow_mut())?;
+    Ok(())
+}
+
+fn process_set_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_authority: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let monitor_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let new_authority_account = next_account_info(account_info_iter)?;
+
+    if monitor_account.owner != program_id {
+        msg!("Monitor account does not have the correct program id");
+        return Err(OrderbookError::InvalidOwner.into());
+    }
+
+    let mut monitor = OrderbookMonitor::unpack(&monitor_account.data.borrow())?;
+    check_authority(&monitor, authority)?;
+
+    // As on initialization, the new authority signs so a monitor cannot be
+    // handed to a mistyped key
+    if *new_authority_account.key != new_authority || !new_authority_account.is_signer {
+        msg!("Missing signature of the new authority");
+        return Err(OrderbookError::Unauthorized.into());
+    }
+
+    monitor.authority = new_authority;
+    monitor.pack(&mut monitor_account.data.borrow_mut())?;
+
+    msg!("Monitor authority set to {}", new_authority);
+    Ok(())
+}
+
+fn process_resize(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_len: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let monitor_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program_account = next_account_info(account_info_iter)?;
+
+    if monitor_account.owner != program_id {
+        msg!("Monitor account does not have the correct program id");
+        return Err(OrderbookError::InvalidOwner.into());
+    }
+    if !system_program::check_id(system_program_account.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let monitor = OrderbookMonitor::unpack(&monitor_account.data.borrow())?;
+    check_authority(&monitor, authority)?;
+
+    // Never cut off recorded data
+    let new_len = new_len as usize;
+    let used = monitor.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?.len();
+    if new_len < used {
+        msg!("Monitor data needs {} bytes, {} requested", used, new_len);
+        return Err(OrderbookError::SizeTooSmall.into());
+    }
+
+    let required = Rent::get()?.minimum_balance(new_len);
+    let current = monitor_account.lamports();
+    if required > current {
+        invoke(
+            &system_instruction::transfer(payer.key, monitor_account.key, required - current),
+            &[payer.clone(), monitor_account.clone(), system_program_account.clone()],
+        )?;
+    } else if current > required {
+        **monitor_account.try_borrow_mut_lamports()? -= current - required;
+        **payer.try_borrow_mut_lamports()? += current - required;
+    }
+    monitor_account.realloc(new_len, false)?;
+
+    msg!("Monitor account resized to {} bytes", new_len);
+    Ok(())
+}
+
+fn process_close(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let monitor_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let destination = next_account_info(account_info_iter)?;
+
+    if monitor_account.owner != program_id {
+        msg!("Monitor account does not have the correct program id");
+        return Err(OrderbookError::InvalidOwner.into());
+    }
+
+    let monitor = OrderbookMonitor::unpack(&monitor_account.data.borrow())?;
+    check_authority(&monitor, authority)?;
+
+    // The runtime purges the account once it holds no lamports
+    let lamports = monitor_account.lamports();
+    **monitor_account.try_borrow_mut_lamports()? = 0;
+    **destination.try_borrow_mut_lamports()? += lamports;
+    monitor_account.data.borrow_mut().fill(0);
+
+    msg!("Monitor account closed, {} lamports reclaimed", lamports);
+    Ok(())
+}
+
+fn process_set_paused(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    paused: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let monitor_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+
+    if monitor_account.owner != program_id {
+        msg!("Monitor account does not have the correct program id");
+        return Err(OrderbookError::InvalidOwner.into());
+    }
+
+    let mut monitor = OrderbookMonitor::unpack(&monitor_account.data.borrow())?;
+    check_authority(&monitor, authority)?;
+
+    monitor.paused = paused;
+    monitor.pack(&mut monitor_account.data.borrow_mut())?;
+
+    msg!("Monitor {}", if paused { "paused" } else { "resumed" });
+    Ok(())
+}
+
 // Client-side helpers
 #[cfg(not(feature = "no-entrypoint"))]
 pub mod client {
@@ -183,20 +787,31 @@ pub mod client {
     pub fn initialize(
         program_id: &Pubkey,
         monitor_account: &Pubkey,
+    ) -> Instruction {
+        initialize_with_authority(program_id, monitor_account, monitor_account)
+    }
+
+    pub fn initialize_with_authority(
+        program_id: &Pubkey,
+        monitor_account: &Pubkey,
+        authority: &Pubkey,
     ) -> Instruction {
         Instruction {
             program_id: *program_id,
             accounts: vec![
                 AccountMeta::new(*monitor_account, true),
+                AccountMeta::new_readonly(*authority, true),
             ],
             data: OrderbookInstruction::Initialize.try_to_vec().unwrap(),
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn record_event(
         program_id: &Pubkey,
         monitor_account: &Pubkey,
         market_account: &Pubkey,
+        authority: &Pubkey,
         market_name: String,
         price: u64,
         size: u64,
@@ -206,8 +821,9 @@ pub mod client {
         Instruction {
             program_id: *program_id,
             accounts: vec![
-                AccountMeta::new(*monitor_account, true),
+                AccountMeta::new(*monitor_account, false),
                 AccountMeta::new_readonly(*market_account, false),
+                AccountMeta::new_readonly(*authority, true),
             ],
             data: OrderbookInstruction::RecordEvent {
                 market_name,
@@ -220,66 +836,162 @@ pub mod client {
             .unwrap(),
         }
     }
+
+    pub fn record_events(
+        program_id: &Pubkey,
+        monitor_account: &Pubkey,
+        market_account: &Pubkey,
+        authority: &Pubkey,
+        events: Vec<NewEvent>,
+    ) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(*monitor_account, false),
+                AccountMeta::new_readonly(*market_account, false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+            data: OrderbookInstruction::RecordEvents { events }
+                .try_to_vec()
+                .unwrap(),
+        }
+    }
+
+    pub fn record_snapshot(
+        program_id: &Pubkey,
+        monitor_account: &Pubkey,
+        market_account: &Pubkey,
+        authority: &Pubkey,
+        market_name: String,
+        bids: Vec<PriceLevel>,
+        asks: Vec<PriceLevel>,
+    ) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(*monitor_account, false),
+                AccountMeta::new_readonly(*market_account, false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+            data: OrderbookInstruction::RecordSnapshot {
+                market_name,
+                bids,
+                asks,
+            }
+            .try_to_vec()
+            .unwrap(),
+        }
+    }
+
+    pub fn set_authority(
+        program_id: &Pubkey,
+        monitor_account: &Pubkey,
+        authority: &Pubkey,
+        new_authority: &Pubkey,
+    ) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(*monitor_account, false),
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new_readonly(*new_authority, true),
+            ],
+            data: OrderbookInstruction::SetAuthority {
+                new_authority: *new_authority,
+            }
+            .try_to_vec()
+            .unwrap(),
+        }
+    }
+
+    pub fn resize(
+        program_id: &Pubkey,
+        monitor_account: &Pubkey,
+        authority: &Pubkey,
+        payer: &Pubkey,
+        new_len: u64,
+    ) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(*monitor_account, false),
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new(*payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+            data: OrderbookInstruction::Resize { new_len }.try_to_vec().unwrap(),
+        }
+    }
+
+    pub fn close(
+        program_id: &Pubkey,
+        monitor_account: &Pubkey,
+        authority: &Pubkey,
+        destination: &Pubkey,
+    ) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(*monitor_account, false),
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new(*destination, false),
+            ],
+            data: OrderbookInstruction::Close.try_to_vec().unwrap(),
+        }
+    }
+
+    pub fn set_paused(
+        program_id: &Pubkey,
+        monitor_account: &Pubkey,
+        authority: &Pubkey,
+        paused: bool,
+    ) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(*monitor_account, false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+            data: OrderbookInstruction::SetPaused { paused }.try_to_vec().unwrap(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use solana_program::{
-        program_pack::Pack,
-        pubkey::Pubkey,
-        rent::Rent,
         account_info::AccountInfo,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        pubkey::Pubkey,
     };
-    use solana_program::signer::keypair::Keypair;
     use std::mem::size_of;
 
-    // A test helper function that creates a monitor account for testing
-    fn create_monitor_account(lamports: u64, data_len: usize) -> (Keypair, AccountInfo) {
-        let owner = Pubkey::new_unique();
-        let key = Keypair::new();
-        let mut lamports_ref = lamports;
-        let mut data = vec![0; data_len];
-        
-        let account_info = AccountInfo::new(
-            &key.pubkey(),
-            false,
-            true,
-            &mut lamports_ref,
-            &mut data,
-            &owner,
-            false,
-            Rent::default().last_slot_of_epoch(0),
-        );
-        
-        (key, account_info)
+    // Serve a fixed clock to `Clock::get()` outside of the runtime
+    struct TestSyscallStubs;
+
+    impl SyscallStubs for TestSyscallStubs {
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            let clock = Clock {
+                slot: 42,
+                unix_timestamp: 1_700_000_000,
+                ..Clock::default()
+            };
+            unsafe { *(var_addr as *mut Clock) = clock };
+            solana_program::entrypoint::SUCCESS
+        }
     }
 
     #[test]
     fn test_initialize() {
         let program_id = Pubkey::new_unique();
-        
-        // Create a monitor account
-        let (_, monitor_account) = create_monitor_account(
-            Rent::default().minimum_balance(size_of::<OrderbookMonitor>()),
-            size_of::<OrderbookMonitor>(),
-        );
-        
-        // Set the owner to the program id for this test
-        // Note: This is a bit of a hack for testing, as we can't easily 
-        // modify the account_info's owner field directly
-        let mut owner = program_id;
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = vec![0; size_of::<OrderbookMonitor>()];
+
         let monitor_account = AccountInfo::new(
-            monitor_account.key,
-            monitor_account.is_signer,
-            monitor_account.is_writable,
-            monitor_account.lamports,
-            monitor_account.data,
-            &owner,
-            monitor_account.executable,
-            monitor_account.rent_epoch,
+            &key, true, true, &mut lamports, &mut data, &program_id, false, 0,
         );
-        
         let accounts = vec![monitor_account];
         
         // Test the initialize function
@@ -289,55 +1001,42 @@ mod tests {
         assert!(result.is_ok());
         
         // Verify the account was initialized properly
-        let monitor = OrderbookMonitor::try_from_slice(&accounts[0].data.borrow()).unwrap();
+        let monitor = OrderbookMonitor::unpack(&accounts[0].data.borrow()).unwrap();
         assert!(monitor.initialized);
         assert_eq!(monitor.authority, *accounts[0].key);
         assert_eq!(monitor.event_count, 0);
         assert!(monitor.events.is_empty());
+        assert!(monitor.snapshots.is_empty());
     }
 
     #[test]
     fn test_record_event() {
+        set_syscall_stubs(Box::new(TestSyscallStubs));
         let program_id = Pubkey::new_unique();
-        
-        // Create monitor account with pre-initialized data
-        let (_, monitor_account) = create_monitor_account(
-            Rent::default().minimum_balance(1000), // Larger to accommodate events
-            1000, // Larger data size to accommodate events
+
+        // Larger data size to accommodate events
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = vec![0; 1000];
+        let monitor_account = AccountInfo::new(
+            &key, true, true, &mut lamports, &mut data, &program_id, false, 0,
         );
-        
+
         // Create market account
         let market_key = Pubkey::new_unique();
+        let market_owner = Pubkey::new_unique();
+        let mut market_lamports = 100000;
+        let mut market_data = vec![0; 10];
         let market_account = AccountInfo::new(
-            &market_key,
-            false,
-            false,
-            &mut 100000,
-            &mut vec![0; 10],
-            &Pubkey::new_unique(),
-            false,
-            Rent::default().last_slot_of_epoch(0),
-        );
-        
-        // Set the owner to the program id for this test
-        let mut owner = program_id;
-        let monitor_account = AccountInfo::new(
-            monitor_account.key,
-            monitor_account.is_signer,
-            monitor_account.is_writable,
-            monitor_account.lamports,
-            monitor_account.data,
-            &owner,
-            monitor_account.executable,
-            monitor_account.rent_epoch,
+            &market_key, false, false, &mut market_lamports, &mut market_data, &market_owner, false, 0,
         );
         
         // Initialize the monitor first
         let accounts = vec![monitor_account.clone()];
-        let _ = process_initialize(&program_id, &accounts);
+        process_initialize(&program_id, &accounts).unwrap();
         
-        // Now test recording an event
-        let accounts = vec![monitor_account, market_account];
+        // Now test recording an event, signed by the monitor as its own authority
+        let accounts = vec![monitor_account.clone(), market_account, monitor_account];
         let result = process_record_event(
             &program_id,
             &accounts,
@@ -352,13 +1051,301 @@ mod tests {
         assert!(result.is_ok());
         
         // Verify the event was recorded
-        let monitor = OrderbookMonitor::try_from_slice(&accounts[0].data.borrow()).unwrap();
+        let monitor = OrderbookMonitor::unpack(&accounts[0].data.borrow()).unwrap();
         assert_eq!(monitor.event_count, 1);
         assert_eq!(monitor.events.len(), 1);
+        assert_eq!(monitor.events[0].timestamp, 1_700_000_000);
         assert_eq!(monitor.events[0].market_name, "BTC/USDC");
         assert_eq!(monitor.events[0].price, 50000_00000000);
         assert_eq!(monitor.events[0].size, 1_00000000);
         assert!(monitor.events[0].is_bid);
         assert!(matches!(monitor.events[0].event_type, OrderbookEventType::OrderPlaced));
+
+        // Names outside the allowed charset are rejected
+        let result = process_record_event(
+            &program_id,
+            &accounts,
+            "btc/usdc".to_string(),
+            50000_00000000,
+            1_00000000,
+            true,
+            OrderbookEventType::OrderPlaced,
+        );
+        assert_eq!(result, Err(OrderbookError::InvalidMarketName.into()));
+    }
+
+    #[test]
+    fn test_record_snapshot() {
+        set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = Pubkey::new_unique();
+
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = vec![0; 1000];
+        let monitor_account = AccountInfo::new(
+            &key, true, true, &mut lamports, &mut data, &program_id, false, 0,
+        );
+
+        let market_key = Pubkey::new_unique();
+        let market_owner = Pubkey::new_unique();
+        let mut market_lamports = 100000;
+        let mut market_data = vec![0; 10];
+        let market_account = AccountInfo::new(
+            &market_key, false, false, &mut market_lamports, &mut market_data, &market_owner, false, 0,
+        );
+
+        process_initialize(&program_id, std::slice::from_ref(&monitor_account)).unwrap();
+        let accounts = vec![monitor_account.clone(), market_account, monitor_account];
+
+        let bids = vec![PriceLevel { price: 100, size: 5 }, PriceLevel { price: 99, size: 3 }];
+        let asks = vec![PriceLevel { price: 101, size: 2 }];
+        process_record_snapshot(&program_id, &accounts, "SOL/USDC".to_string(), bids.clone(), asks.clone())
+            .unwrap();
+
+        let monitor = OrderbookMonitor::unpack(&accounts[0].data.borrow()).unwrap();
+        assert_eq!(monitor.snapshots.len(), 1);
+        assert_eq!(monitor.snapshots[0].slot, 42);
+        assert_eq!(monitor.snapshots[0].event_index, 0);
+        assert_eq!(monitor.snapshots[0].bids, bids);
+        assert_eq!(monitor.snapshots[0].asks, asks);
+
+        // Bids out of order are rejected
+        let unsorted = vec![PriceLevel { price: 99, size: 3 }, PriceLevel { price: 100, size: 5 }];
+        let result = process_record_snapshot(&program_id, &accounts, "SOL/USDC".to_string(), unsorted, asks);
+        assert_eq!(result, Err(OrderbookError::InvalidSnapshot.into()));
+    }
+
+    #[test]
+    fn test_set_authority() {
+        set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = Pubkey::new_unique();
+
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = vec![0; 1000];
+        let monitor_account = AccountInfo::new(
+            &key, true, true, &mut lamports, &mut data, &program_id, false, 0,
+        );
+
+        let new_key = Pubkey::new_unique();
+        let wallet_owner = Pubkey::new_unique();
+        let mut new_lamports = 0;
+        let mut new_data = vec![];
+        let new_authority = AccountInfo::new(
+            &new_key, true, false, &mut new_lamports, &mut new_data, &wallet_owner, false, 0,
+        );
+
+        process_initialize(&program_id, std::slice::from_ref(&monitor_account)).unwrap();
+
+        // Initializing again is rejected
+        let result = process_initialize(&program_id, &[monitor_account.clone(), new_authority.clone()]);
+        assert_eq!(result, Err(OrderbookError::AlreadyInitialized.into()));
+
+        // The new authority must sign
+        let unsigned = AccountInfo { is_signer: false, ..new_authority.clone() };
+        let accounts = [monitor_account.clone(), monitor_account.clone(), unsigned];
+        let result = process_set_authority(&program_id, &accounts, new_key);
+        assert_eq!(result, Err(OrderbookError::Unauthorized.into()));
+        let accounts = [monitor_account.clone(), monitor_account.clone(), new_authority.clone()];
+        let result = process_set_authority(&program_id, &accounts, Pubkey::new_unique());
+        assert_eq!(result, Err(OrderbookError::Unauthorized.into()));
+
+        let accounts = [monitor_account.clone(), monitor_account.clone(), new_authority.clone()];
+        process_set_authority(&program_id, &accounts, new_key).unwrap();
+        let monitor = OrderbookMonitor::unpack(&monitor_account.data.borrow()).unwrap();
+        assert_eq!(monitor.authority, new_key);
+
+        // The monitor account no longer signs for itself
+        let accounts = vec![monitor_account.clone(), new_authority.clone(), monitor_account.clone()];
+        let result = process_record_event(
+            &program_id,
+            &accounts,
+            "SOL/USDC".to_string(),
+            100,
+            1,
+            true,
+            OrderbookEventType::OrderPlaced,
+        );
+        assert_eq!(result, Err(OrderbookError::Unauthorized.into()));
+
+        let accounts = vec![monitor_account.clone(), new_authority.clone(), new_authority];
+        process_record_event(
+            &program_id,
+            &accounts,
+            "SOL/USDC".to_string(),
+            100,
+            1,
+            true,
+            OrderbookEventType::OrderPlaced,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_space_matches_layout() {
+        let event = |name: &str| OrderbookEvent {
+            timestamp: 1_700_000_000,
+            market_name: name.to_string(),
+            price: 100,
+            size: 1,
+            is_bid: true,
+            event_type: OrderbookEventType::OrderCancelled,
+        };
+        let mut monitor = OrderbookMonitor {
+            initialized: true,
+            authority: Pubkey::new_unique(),
+            event_count: 3,
+            events: vec![event("SOL/USDC"), event("BTC/USDC"), event("ETH/USDC")],
+            snapshots: vec![],
+            paused: false,
+        };
+        assert_eq!(monitor.try_to_vec().unwrap().len(), OrderbookMonitor::space(3, 8));
+
+        // Shorter names fit in the space computed for the longest one
+        monitor.events.push(event("SOL"));
+        assert!(monitor.try_to_vec().unwrap().len() < OrderbookMonitor::space(4, 8));
+
+        let level = PriceLevel { price: 100, size: 1 };
+        let snapshot = BookSnapshot {
+            slot: 42,
+            timestamp: 1_700_000_000,
+            market_name: "SOL/USDC".to_string(),
+            event_index: 3,
+            bids: vec![level; 5],
+            asks: vec![level; 5],
+        };
+        assert_eq!(snapshot.try_to_vec().unwrap().len(), BookSnapshot::space(8, 5));
+
+        monitor.events.pop();
+        monitor.snapshots = vec![snapshot.clone(), snapshot];
+        assert_eq!(monitor.try_to_vec().unwrap().len(), OrderbookMonitor::space_with_snapshots(3, 2, 5, 8));
+    }
+
+    #[test]
+    fn test_unpack_events_only_account() {
+        // Layout of the accounts written when only events were stored
+        let authority = Pubkey::new_unique();
+        let event = OrderbookEvent {
+            timestamp: 1_700_000_000,
+            market_name: "SOL/USDC".to_string(),
+            price: 100,
+            size: 1,
+            is_bid: true,
+            event_type: OrderbookEventType::OrderPlaced,
+        };
+        let mut data = (true, authority, 1u64, vec![event]).try_to_vec().unwrap();
+        let full = data.len();
+        data.extend_from_slice(&[0; 5]);
+
+        let monitor = OrderbookMonitor::unpack(&data).unwrap();
+        assert_eq!((monitor.authority, monitor.event_count, monitor.events.len()), (authority, 1, 1));
+        assert!(monitor.snapshots.is_empty());
+        assert!(!monitor.paused);
+        assert!(OrderbookMonitor::unpack(&data[..full]).is_err());
+    }
+
+    #[test]
+    fn test_event_validation() {
+        let event = NewEvent {
+            market_name: "SOL/USDC".to_string(),
+            price: 100,
+            size: 1,
+            is_bid: true,
+            event_type: OrderbookEventType::OrderPlaced,
+        };
+        assert_eq!(event.validate(), Ok(()));
+
+        let with = |f: fn(&mut NewEvent)| {
+            let mut event = event.clone();
+            f(&mut event);
+            event.validate()
+        };
+        assert_eq!(with(|e| e.market_name = "SOL-USDC".to_string()), Ok(()));
+        assert_eq!(
+            with(|e| e.market_name = "A".repeat(MAX_MARKET_NAME_LEN + 1)),
+            Err(OrderbookError::MarketNameTooLong)
+        );
+        assert_eq!(with(|e| e.market_name = "sol/usdc".to_string()), Err(OrderbookError::InvalidMarketName));
+        assert_eq!(with(|e| e.market_name = String::new()), Err(OrderbookError::InvalidMarketName));
+        assert_eq!(with(|e| e.size = 0), Err(OrderbookError::ZeroSize));
+        assert_eq!(with(|e| e.price = 0), Err(OrderbookError::ZeroPrice));
+        assert_eq!(
+            with(|e| {
+                e.price = 0;
+                e.event_type = OrderbookEventType::OrderCancelled;
+            }),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_error_codes_are_stable() {
+        for (code, error) in OrderbookError::ALL.iter().enumerate() {
+            assert_eq!(error.code(), code as u32);
+            assert_eq!(OrderbookError::from_code(code as u32), Some(*error));
+        }
+        assert_eq!(OrderbookError::from_code(OrderbookError::ALL.len() as u32), None);
+        assert_eq!(ProgramError::from(OrderbookError::Unauthorized), ProgramError::Custom(4));
+        assert_eq!(ProgramError::from(OrderbookError::StaleTimestamp), ProgramError::Custom(15));
+    }
+
+    #[test]
+    fn test_recording_rules() {
+        set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = Pubkey::new_unique();
+
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = vec![0; 200];
+        let monitor_account = AccountInfo::new(
+            &key, true, true, &mut lamports, &mut data, &program_id, false, 0,
+        );
+
+        let market_key = Pubkey::new_unique();
+        let market_owner = Pubkey::new_unique();
+        let mut market_lamports = 100000;
+        let mut market_data = vec![0; 10];
+        let market_account = AccountInfo::new(
+            &market_key, false, false, &mut market_lamports, &mut market_data, &market_owner, false, 0,
+        );
+
+        let markets = [market_account.clone(), monitor_account.clone()];
+        let record = |market: usize| {
+            let accounts = vec![monitor_account.clone(), markets[market].clone(), monitor_account.clone()];
+            process_record_event(
+                &program_id,
+                &accounts,
+                "SOL/USDC".to_string(),
+                100,
+                1,
+                true,
+                OrderbookEventType::OrderPlaced,
+            )
+        };
+
+        // Nothing can be recorded before initialization
+        assert_eq!(record(0), Err(OrderbookError::NotInitialized.into()));
+        process_initialize(&program_id, std::slice::from_ref(&monitor_account)).unwrap();
+
+        // A monitor account is not a market
+        assert_eq!(record(1), Err(OrderbookError::InvalidMarket.into()));
+
+        let authority = [monitor_account.clone(), monitor_account.clone()];
+        process_set_paused(&program_id, &authority, true).unwrap();
+        assert_eq!(record(0), Err(OrderbookError::Paused.into()));
+        process_set_paused(&program_id, &authority, false).unwrap();
+
+        // 200 bytes hold three events before the account is full
+        for _ in 0..3 {
+            record(0).unwrap();
+        }
+        let mut monitor = OrderbookMonitor::unpack(&monitor_account.data.borrow()).unwrap();
+        assert_eq!(record(0), Err(OrderbookError::AccountFull.into()));
+
+        // Timestamps may not go backwards
+        monitor.events.truncate(1);
+        monitor.events[0].timestamp = 1_700_000_001;
+        monitor.pack(&mut monitor_account.data.borrow_mut()).unwrap();
+        assert_eq!(record(0), Err(OrderbookError::StaleTimestamp.into()));
     }
 }
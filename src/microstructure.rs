@@ -0,0 +1,365 @@
+//! Off-chain market microstructure metrics over recorded events: trade
+//! classification, signed volume, order flow imbalance (OFI), Kyle's lambda
+//! and queue depletion at the touch.
+//!
+//! A market's book is rebuilt with `L2Book`, from its recorded snapshots when
+//! there are any, so every fill is seen together with the quotes prevailing
+//! just before it. Fills fed through the adapters sit on the maker's side, so
+//! their aggressor is the other side; events recorded by other means carry no
+//! such guarantee, and the classification rules here infer the side from
+//! prices alone:
+//!
+//! - the tick rule calls a trade a buy above the last different trade price
+//!   and a sell below it;
+//! - the Lee-Ready rule calls a trade a buy above the prevailing mid and a
+//!   sell below it, falling back to the tick rule at the mid or without quotes.
+//!
+//! OFI follows Cont, Kukanov and Stoikov: each book change adds the size
+//! joining the best bid and leaving the best ask, and subtracts the reverse.
+//! Kyle's lambda is the least-squares slope of trade-to-trade price changes
+//! on the signed size of each trade. Metrics are bucketed into bars starting
+//! at multiples of the interval in unix time, like `candles`.
+
+use crate::snapshot::L2Book;
+use crate::{BookSnapshot, OrderbookEvent, OrderbookEventType, OrderbookMonitor, PriceLevel};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+impl TradeSide {
+    fn sign(self) -> i128 {
+        match self {
+            TradeSide::Buy => 1,
+            TradeSide::Sell => -1,
+        }
+    }
+}
+
+/// A fill with the quotes before it and both classifications
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassifiedTrade {
+    pub timestamp: i64,
+    pub price: u64,
+    pub size: u64,
+    /// Mid of the best bid and ask before the fill; `None` if a side was empty
+    pub mid: Option<f64>,
+    /// `None` until the price first changes
+    pub tick_rule: Option<TradeSide>,
+    pub lee_ready: Option<TradeSide>,
+}
+
+/// Size taken from the best level of one side by fills and cancellations
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Depletion {
+    pub filled: u64,
+    pub cancelled: u64,
+    /// Filled and cancelled size per second of the bar
+    pub rate: f64,
+}
+
+/// Microstructure metrics of one market over one interval
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowBar {
+    /// Unix timestamp of the start of the bar
+    pub start: i64,
+    pub trades: u64,
+    /// Volume classified by the Lee-Ready rule
+    pub buy_volume: u64,
+    pub sell_volume: u64,
+    pub unclassified_volume: u64,
+    /// `buy_volume - sell_volume`
+    pub signed_volume: i128,
+    /// Signed volume by the tick rule alone
+    pub tick_signed_volume: i128,
+    /// Share of the trades classified by both rules on which they agree
+    pub agreement: Option<f64>,
+    pub ofi: i128,
+    /// Price change per unit of signed size; needs two trades of different signed sizes
+    pub kyle_lambda: Option<f64>,
+    pub bid_depletion: Depletion,
+    pub ask_depletion: Depletion,
+}
+
+/// Side of a trade at `price` after a trade at `previous`, carrying the last
+/// side over a zero tick
+pub fn tick_rule(price: u64, previous: Option<u64>, last_side: Option<TradeSide>) -> Option<TradeSide> {
+    match previous.map(|previous| price.cmp(&previous)) {
+        Some(std::cmp::Ordering::Greater) => Some(TradeSide::Buy),
+        Some(std::cmp::Ordering::Less) => Some(TradeSide::Sell),
+        _ => last_side,
+    }
+}
+
+/// Side of a trade at `price` against the prevailing `mid`, or `tick` at the mid
+pub fn lee_ready(price: u64, mid: Option<f64>, tick: Option<TradeSide>) -> Option<TradeSide> {
+    match mid {
+        Some(mid) if price as f64 > mid => Some(TradeSide::Buy),
+        Some(mid) if (price as f64) < mid => Some(TradeSide::Sell),
+        _ => tick,
+    }
+}
+
+/// Order flow imbalance of one book change, from the best levels before and
+/// after it. A side empty before or after adds nothing.
+pub fn order_flow_imbalance(
+    bid: Option<PriceLevel>,
+    ask: Option<PriceLevel>,
+    next_bid: Option<PriceLevel>,
+    next_ask: Option<PriceLevel>,
+) -> i128 {
+    let mut ofi = 0i128;
+    if let (Some(bid), Some(next)) = (bid, next_bid) {
+        if next.price >= bid.price {
+            ofi += i128::from(next.size);
+        }
+        if next.price <= bid.price {
+            ofi -= i128::from(bid.size);
+        }
+    }
+    if let (Some(ask), Some(next)) = (ask, next_ask) {
+        if next.price <= ask.price {
+            ofi -= i128::from(next.size);
+        }
+        if next.price >= ask.price {
+            ofi += i128::from(ask.size);
+        }
+    }
+    ofi
+}
+
+/// Least-squares slope of `y` on `x` over `(x, y)` points; `None` with fewer
+/// than two points or no spread in `x`
+pub fn kyle_lambda(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    (variance > 0.0).then(|| covariance / variance)
+}
+
+fn best(book: &L2Book) -> (Option<PriceLevel>, Option<PriceLevel>) {
+    (book.bids(1).pop(), book.asks(1).pop())
+}
+
+#[derive(Debug, Clone, Default)]
+struct Bar {
+    trades: u64,
+    buy_volume: u64,
+    sell_volume: u64,
+    unclassified_volume: u64,
+    tick_signed_volume: i128,
+    compared: u64,
+    agreed: u64,
+    ofi: i128,
+    impact: Vec<(f64, f64)>,
+    bid_depletion: Depletion,
+    ask_depletion: Depletion,
+}
+
+/// Replays one market's events and buckets their metrics into bars
+#[derive(Debug, Clone)]
+pub struct FlowAnalyzer {
+    interval: i64,
+    book: L2Book,
+    last_price: Option<u64>,
+    last_side: Option<TradeSide>,
+    trades: Vec<ClassifiedTrade>,
+    bars: BTreeMap<i64, Bar>,
+}
+
+impl FlowAnalyzer {
+    /// Bars of `interval` seconds, starting from an empty book
+    pub fn new(interval: i64) -> Self {
+        assert!(interval > 0, "interval must be positive");
+        FlowAnalyzer {
+            interval,
+            book: L2Book::default(),
+            last_price: None,
+            last_side: None,
+            trades: Vec::new(),
+            bars: BTreeMap::new(),
+        }
+    }
+
+    /// Replace the rebuilt book with a recorded snapshot
+    pub fn reset(&mut self, snapshot: &BookSnapshot) {
+        self.book = L2Book::from_snapshot(snapshot);
+    }
+
+    /// Apply the next event of the market, in recording order
+    pub fn apply(&mut self, event: &OrderbookEvent) {
+        let (bid, ask) = best(&self.book);
+        let start = event.timestamp.div_euclid(self.interval) * self.interval;
+        let bar = self.bars.entry(start).or_default();
+
+        if event.event_type != OrderbookEventType::OrderPlaced {
+            let touch = if event.is_bid { bid } else { ask };
+            if touch.is_some_and(|touch| touch.price == event.price) {
+                let depletion = if event.is_bid { &mut bar.bid_depletion } else { &mut bar.ask_depletion };
+                if event.event_type == OrderbookEventType::OrderFilled {
+                    depletion.filled = depletion.filled.saturating_add(event.size);
+                } else {
+                    depletion.cancelled = depletion.cancelled.saturating_add(event.size);
+                }
+            }
+        }
+
+        if event.event_type == OrderbookEventType::OrderFilled {
+            let mid = bid.zip(ask).map(|(bid, ask)| (bid.price as f64 + ask.price as f64) / 2.0);
+            let tick = tick_rule(event.price, self.last_price, self.last_side);
+            let side = lee_ready(event.price, mid, tick);
+            bar.trades += 1;
+            match side {
+                Some(TradeSide::Buy) => bar.buy_volume = bar.buy_volume.saturating_add(event.size),
+                Some(TradeSide::Sell) => bar.sell_volume = bar.sell_volume.saturating_add(event.size),
+                None => bar.unclassified_volume = bar.unclassified_volume.saturating_add(event.size),
+            }
+            if let Some(tick) = tick {
+                bar.tick_signed_volume += tick.sign() * i128::from(event.size);
+            }
+            if let (Some(tick), Some(side)) = (tick, side) {
+                bar.compared += 1;
+                bar.agreed += u64::from(tick == side);
+            }
+            if let (Some(side), Some(last_price)) = (side, self.last_price) {
+                let signed_size = (side.sign() * i128::from(event.size)) as f64;
+                bar.impact.push((signed_size, event.price as f64 - last_price as f64));
+            }
+            self.trades.push(ClassifiedTrade {
+                timestamp: event.timestamp,
+                price: event.price,
+                size: event.size,
+                mid,
+                tick_rule: tick,
+                lee_ready: side,
+            });
+            self.last_price = Some(event.price);
+            self.last_side = tick;
+        }
+
+        self.book.apply(event);
+        let (next_bid, next_ask) = best(&self.book);
+        bar.ofi += order_flow_imbalance(bid, ask, next_bid, next_ask);
+    }
+
+    /// Every fill seen so far, classified
+    pub fn trades(&self) -> &[ClassifiedTrade] {
+        &self.trades
+    }
+
+    /// Bars with at least one event, in time order
+    pub fn bars(&self) -> Vec<FlowBar> {
+        let rate = |depletion: Depletion| Depletion {
+            rate: depletion.filled.saturating_add(depletion.cancelled) as f64 / self.interval as f64,
+            ..depletion
+        };
+        self.bars
+            .iter()
+            .map(|(&start, bar)| FlowBar {
+                start,
+                trades: bar.trades,
+                buy_volume: bar.buy_volume,
+                sell_volume: bar.sell_volume,
+                unclassified_volume: bar.unclassified_volume,
+                signed_volume: i128::from(bar.buy_volume) - i128::from(bar.sell_volume),
+                tick_signed_volume: bar.tick_signed_volume,
+                agreement: (bar.compared > 0).then(|| bar.agreed as f64 / bar.compared as f64),
+                ofi: bar.ofi,
+                kyle_lambda: kyle_lambda(&bar.impact),
+                bid_depletion: rate(bar.bid_depletion),
+                ask_depletion: rate(bar.ask_depletion),
+            })
+            .collect()
+    }
+}
+
+/// Replay the events of `market_name` in `monitor`, restarting the book from
+/// each of its snapshots at the event it was taken before
+pub fn analyze(monitor: &OrderbookMonitor, market_name: &str, interval: i64) -> FlowAnalyzer {
+    let mut analyzer = FlowAnalyzer::new(interval);
+    let mut snapshots = monitor.snapshots.iter().filter(|s| s.market_name == market_name).peekable();
+    for (index, event) in monitor.events.iter().enumerate() {
+        while let Some(snapshot) = snapshots.next_if(|s| s.event_index <= index as u64) {
+            analyzer.reset(snapshot);
+        }
+        if event.market_name == market_name {
+            analyzer.apply(event);
+        }
+    }
+    analyzer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use OrderbookEventType::*;
+
+    fn event(timestamp: i64, price: u64, size: u64, is_bid: bool, event_type: OrderbookEventType) -> OrderbookEvent {
+        OrderbookEvent { timestamp, market_name: "SOL/USDC".to_string(), price, size, is_bid, event_type }
+    }
+
+    #[test]
+    fn test_classification_and_bars() {
+        let events = vec![
+            event(0, 99, 10, true, OrderPlaced),
+            event(0, 101, 10, false, OrderPlaced),
+            // A buyer lifts the ask, then a seller hits the bid twice
+            event(10, 101, 4, false, OrderFilled),
+            event(20, 99, 2, true, OrderFilled),
+            event(30, 99, 8, true, OrderFilled),
+            // Next bar: the bid is rebuilt and a trade prints at the mid
+            event(60, 99, 5, true, OrderPlaced),
+            event(61, 100, 1, true, OrderFilled),
+            event(62, 101, 6, false, OrderCancelled),
+        ];
+        let mut analyzer = FlowAnalyzer::new(60);
+        for event in &events {
+            analyzer.apply(event);
+        }
+        let sides: Vec<(Option<TradeSide>, Option<TradeSide>)> =
+            analyzer.trades().iter().map(|t| (t.tick_rule, t.lee_ready)).collect();
+        use TradeSide::*;
+        assert_eq!(
+            sides,
+            vec![(None, Some(Buy)), (Some(Sell), Some(Sell)), (Some(Sell), Some(Sell)), (Some(Buy), Some(Buy))]
+        );
+        // At the mid, Lee-Ready falls back on the tick rule
+        assert_eq!(analyzer.trades()[3].mid, Some(100.0));
+
+        let bars = analyzer.bars();
+        assert_eq!(bars.len(), 2);
+        let first = &bars[0];
+        assert_eq!((first.trades, first.buy_volume, first.sell_volume, first.signed_volume), (3, 4, 10, -6));
+        assert_eq!((first.tick_signed_volume, first.agreement), (-10, Some(1.0)));
+        // Lifting the ask adds 4 and the first hit takes 2 off the bid; emptying the bid adds nothing
+        assert_eq!(first.ofi, 4 - 2);
+        assert_eq!((first.bid_depletion.filled, first.ask_depletion.filled), (10, 4));
+        // Price changes -2 and 0 for signed sizes -2 and -8
+        assert_eq!(first.kyle_lambda, Some(-1.0 / 3.0));
+
+        let second = &bars[1];
+        assert_eq!((second.trades, second.signed_volume, second.kyle_lambda), (1, 1, None));
+        assert_eq!(second.ask_depletion, Depletion { filled: 0, cancelled: 6, rate: 0.1 });
+    }
+
+    #[test]
+    fn test_order_flow_imbalance() {
+        let level = |price, size| Some(PriceLevel { price, size });
+        // Bid grows at the same price, ask unchanged
+        assert_eq!(order_flow_imbalance(level(99, 5), level(101, 5), level(99, 8), level(101, 5)), 3);
+        // Bid improves, ask retreats
+        assert_eq!(order_flow_imbalance(level(99, 5), level(101, 5), level(100, 2), level(102, 7)), 2 + 5);
+        // A side missing before or after adds nothing
+        assert_eq!(order_flow_imbalance(None, level(101, 5), level(99, 8), level(101, 2)), 3);
+        assert_eq!(kyle_lambda(&[(1.0, 2.0)]), None);
+        assert_eq!(kyle_lambda(&[(1.0, 2.0), (1.0, 3.0)]), None);
+    }
+}
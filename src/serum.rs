@@ -0,0 +1,481 @@
+//! Decoding of Serum / OpenBook v1 market accounts from raw account bytes.
+//!
+//! Every v1 account is wrapped in a 5-byte `serum` head padding and a 7-byte
+//! `padding` tail, with little-endian fields in between. Prices and sizes are
+//! kept in the market's lot units: a price is quote lots per base lot and a
+//! size is a number of base lots.
+
+use crate::adapter::aggregate_levels;
+use crate::layout::{Reader, TooShort};
+use crate::{OrderbookEventType, OrderbookInstruction, PriceLevel};
+use solana_program::pubkey::Pubkey;
+use thiserror::Error;
+
+const HEAD_PADDING: &[u8] = b"serum";
+const TAIL_PADDING: &[u8] = b"padding";
+
+// Lengths inside the padding, as defined in serum-dex: `MarketState`,
+// `EventQueueHeader` and `Event` in `dex/src/state.rs`; the account flags
+// followed by `SlabHeader`, and the `AnyNode` slab nodes, in
+// `dex/src/critbit.rs`
+const MARKET_STATE_LEN: usize = 376;
+const SLAB_HEADER_LEN: usize = 40;
+const SLAB_NODE_LEN: usize = 72;
+const QUEUE_HEADER_LEN: usize = 32;
+const EVENT_LEN: usize = 88;
+
+/// Account flag bits shared by every v1 account
+pub mod account_flag {
+    pub const INITIALIZED: u64 = 1 << 0;
+    pub const MARKET: u64 = 1 << 1;
+    pub const OPEN_ORDERS: u64 = 1 << 2;
+    pub const REQUEST_QUEUE: u64 = 1 << 3;
+    pub const EVENT_QUEUE: u64 = 1 << 4;
+    pub const BIDS: u64 = 1 << 5;
+    pub const ASKS: u64 = 1 << 6;
+    pub const DISABLED: u64 = 1 << 7;
+}
+
+/// Event flag bits of an event queue entry
+pub mod event_flag {
+    pub const FILL: u8 = 1 << 0;
+    pub const OUT: u8 = 1 << 1;
+    pub const BID: u8 = 1 << 2;
+    pub const MAKER: u8 = 1 << 3;
+    pub const RELEASE_FUNDS: u8 = 1 << 4;
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SerumError {
+    #[error("Account data is too short")]
+    TooShort,
+    #[error("Account data is missing the serum padding")]
+    InvalidPadding,
+    #[error("Unexpected account flags {0:#x}")]
+    InvalidAccountFlags(u64),
+    #[error("Invalid slab node {0}")]
+    InvalidNode(u32),
+}
+
+impl From<TooShort> for SerumError {
+    fn from(_: TooShort) -> Self {
+        SerumError::TooShort
+    }
+}
+
+// Strip the head and tail padding and return the inner account data
+fn unwrap_padding(data: &[u8]) -> Result<&[u8], SerumError> {
+    if data.len() < HEAD_PADDING.len() + TAIL_PADDING.len() {
+        return Err(SerumError::TooShort);
+    }
+    let (head, rest) = data.split_at(HEAD_PADDING.len());
+    let (inner, tail) = rest.split_at(rest.len() - TAIL_PADDING.len());
+    if head != HEAD_PADDING || tail != TAIL_PADDING {
+        return Err(SerumError::InvalidPadding);
+    }
+    Ok(inner)
+}
+
+fn check_flags(flags: u64, required: u64) -> Result<(), SerumError> {
+    if flags & (account_flag::INITIALIZED | required) != account_flag::INITIALIZED | required {
+        return Err(SerumError::InvalidAccountFlags(flags));
+    }
+    Ok(())
+}
+
+/// The v1 `MarketState` account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarketState {
+    pub account_flags: u64,
+    pub own_address: Pubkey,
+    pub vault_signer_nonce: u64,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub coin_vault: Pubkey,
+    pub coin_deposits_total: u64,
+    pub coin_fees_accrued: u64,
+    pub pc_vault: Pubkey,
+    pub pc_deposits_total: u64,
+    pub pc_fees_accrued: u64,
+    pub pc_dust_threshold: u64,
+    pub request_queue: Pubkey,
+    pub event_queue: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub coin_lot_size: u64,
+    pub pc_lot_size: u64,
+    pub fee_rate_bps: u64,
+    pub referrer_rebates_accrued: u64,
+}
+
+impl MarketState {
+    pub fn decode(data: &[u8]) -> Result<Self, SerumError> {
+        let inner = unwrap_padding(data)?;
+        if inner.len() < MARKET_STATE_LEN {
+            return Err(SerumError::TooShort);
+        }
+        let r = &mut Reader::new(inner);
+
+        let market = MarketState {
+            account_flags: r.u64()?,
+            own_address: r.pubkey()?,
+            vault_signer_nonce: r.u64()?,
+            coin_mint: r.pubkey()?,
+            pc_mint: r.pubkey()?,
+            coin_vault: r.pubkey()?,
+            coin_deposits_total: r.u64()?,
+            coin_fees_accrued: r.u64()?,
+            pc_vault: r.pubkey()?,
+            pc_deposits_total: r.u64()?,
+            pc_fees_accrued: r.u64()?,
+            pc_dust_threshold: r.u64()?,
+            request_queue: r.pubkey()?,
+            event_queue: r.pubkey()?,
+            bids: r.pubkey()?,
+            asks: r.pubkey()?,
+            coin_lot_size: r.u64()?,
+            pc_lot_size: r.u64()?,
+            fee_rate_bps: r.u64()?,
+            referrer_rebates_accrued: r.u64()?,
+        };
+        check_flags(market.account_flags, account_flag::MARKET)?;
+        Ok(market)
+    }
+}
+
+/// Price in quote lots encoded in the upper half of an order id
+pub fn order_id_price(order_id: u128) -> u64 {
+    (order_id >> 64) as u64
+}
+
+/// A resting order decoded from a leaf of the bids or asks slab
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Order {
+    pub order_id: u128,
+    pub price: u64,
+    pub quantity: u64,
+    pub owner: Pubkey,
+    pub owner_slot: u8,
+    pub fee_tier: u8,
+    pub client_order_id: u64,
+}
+
+/// One side of the book, decoded from a bids or asks slab account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slab {
+    pub account_flags: u64,
+    /// Resting orders in priority order, best price first
+    pub orders: Vec<Order>,
+}
+
+const NODE_INNER: u32 = 1;
+const NODE_LEAF: u32 = 2;
+
+impl Slab {
+    /// Decode a bids (`is_bid`) or asks slab
+    pub fn decode(data: &[u8], is_bid: bool) -> Result<Self, SerumError> {
+        let inner = unwrap_padding(data)?;
+        if inner.len() < SLAB_HEADER_LEN {
+            return Err(SerumError::TooShort);
+        }
+        let header = &mut Reader::new(inner);
+        let account_flags = header.u64()?;
+        check_flags(
+            account_flags,
+            if is_bid { account_flag::BIDS } else { account_flag::ASKS },
+        )?;
+        let _bump_index = header.u64()?;
+        let _free_list_len = header.u64()?;
+        let _free_list_head = header.u32()?;
+        let root = header.u32()?;
+        let leaf_count = header.u64()?;
+
+        let nodes = &inner[SLAB_HEADER_LEN..];
+        let node_count = nodes.len() / SLAB_NODE_LEN;
+        let mut orders = Vec::with_capacity(leaf_count as usize);
+
+        // In-order walk of the crit-bit tree yields orders by ascending key
+        let mut stack = if leaf_count > 0 { vec![root] } else { Vec::new() };
+        let mut visited = 0;
+        while let Some(index) = stack.pop() {
+            visited += 1;
+            if index as usize >= node_count || visited > node_count {
+                return Err(SerumError::InvalidNode(index));
+            }
+            let offset = index as usize * SLAB_NODE_LEN;
+            let r = &mut Reader::new(&nodes[offset..offset + SLAB_NODE_LEN]);
+            match r.u32()? {
+                NODE_INNER => {
+                    let _prefix_len = r.u32()?;
+                    let _key = r.u128()?;
+                    let left = r.u32()?;
+                    let right = r.u32()?;
+                    stack.push(right);
+                    stack.push(left);
+                }
+                NODE_LEAF => {
+                    let owner_slot = r.u8()?;
+                    let fee_tier = r.u8()?;
+                    r.take(2)?;
+                    let order_id = r.u128()?;
+                    orders.push(Order {
+                        order_id,
+                        price: order_id_price(order_id),
+                        owner: r.pubkey()?,
+                        quantity: r.u64()?,
+                        client_order_id: r.u64()?,
+                        owner_slot,
+                        fee_tier,
+                    });
+                }
+                _ => return Err(SerumError::InvalidNode(index)),
+            }
+        }
+
+        // Bids keep their best order at the highest key
+        if is_bid {
+            orders.reverse();
+        }
+        Ok(Slab { account_flags, orders })
+    }
+
+    /// Aggregate resting orders into at most `depth` price levels, best first
+    pub fn levels(&self, depth: usize) -> Vec<PriceLevel> {
+        aggregate_levels(self.orders.iter().map(|o| (o.price, o.quantity)), depth)
+    }
+}
+
+/// An entry of the event queue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// Sequence number of the event within the queue's lifetime
+    pub seq_num: u64,
+    pub event_flags: u8,
+    pub owner_slot: u8,
+    pub fee_tier: u8,
+    pub native_qty_released: u64,
+    pub native_qty_paid: u64,
+    pub native_fee_or_rebate: u64,
+    pub order_id: u128,
+    pub owner: Pubkey,
+    pub client_order_id: u64,
+}
+
+impl Event {
+    pub fn is_fill(&self) -> bool {
+        self.event_flags & event_flag::FILL != 0
+    }
+
+    pub fn is_out(&self) -> bool {
+        self.event_flags & event_flag::OUT != 0
+    }
+
+    pub fn is_bid(&self) -> bool {
+        self.event_flags & event_flag::BID != 0
+    }
+
+    pub fn is_maker(&self) -> bool {
+        self.event_flags & event_flag::MAKER != 0
+    }
+
+    pub fn price(&self) -> u64 {
+        order_id_price(self.order_id)
+    }
+
+    /// Base-lot quantity affected by this event
+    pub fn quantity(&self, market: &MarketState) -> u64 {
+        let coin_lot_size = market.coin_lot_size.max(1);
+        match (self.is_fill(), self.is_bid()) {
+            // Buyers receive base, sellers pay it
+            (true, true) => self.native_qty_released / coin_lot_size,
+            (true, false) => self.native_qty_paid / coin_lot_size,
+            // Outs unlock base on asks and quote on bids
+            (false, false) => self.native_qty_released / coin_lot_size,
+            (false, true) => {
+                let lot_value = (self.price() as u128) * (market.pc_lot_size.max(1) as u128);
+                (self.native_qty_released as u128 / lot_value.max(1)) as u64
+            }
+        }
+    }
+
+    /// Build the `RecordEvent` instruction payload for this event.
+    ///
+    /// Maker fills map to `OrderFilled` and outs that unlock funds to
+    /// `OrderCancelled`. Taker fills are skipped: the queue pushes a fill for
+    /// both sides of a trade, and the taker's order id carries its limit price
+    /// rather than the price it traded at. Outs that unlock nothing only remove
+    /// fully filled orders and are skipped too.
+    pub fn record_event_payload(
+        &self,
+        market: &MarketState,
+        market_name: &str,
+    ) -> Option<OrderbookInstruction> {
+        let event_type = if self.is_fill() && self.is_maker() {
+            OrderbookEventType::OrderFilled
+        } else if self.is_fill() {
+            return None;
+        } else if self.is_out() && self.native_qty_released > 0 {
+            OrderbookEventType::OrderCancelled
+        } else {
+            return None;
+        };
+
+        Some(OrderbookInstruction::RecordEvent {
+            market_name: market_name.to_string(),
+            price: self.price(),
+            size: self.quantity(market),
+            is_bid: self.is_bid(),
+            event_type,
+        })
+    }
+}
+
+/// The event queue ring buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventQueue {
+    pub account_flags: u64,
+    pub head: u64,
+    pub count: u64,
+    /// Sequence number the next pushed event will get
+    pub seq_num: u64,
+    /// Events currently in the queue, oldest first
+    pub events: Vec<Event>,
+}
+
+impl EventQueue {
+    pub fn decode(data: &[u8]) -> Result<Self, SerumError> {
+        let inner = unwrap_padding(data)?;
+        if inner.len() < QUEUE_HEADER_LEN {
+            return Err(SerumError::TooShort);
+        }
+        let header = &mut Reader::new(inner);
+        let account_flags = header.u64()?;
+        check_flags(account_flags, account_flag::EVENT_QUEUE)?;
+        let head = header.u64()?;
+        let count = header.u64()?;
+        let seq_num = header.u64()?;
+
+        let ring = &inner[QUEUE_HEADER_LEN..];
+        let capacity = (ring.len() / EVENT_LEN) as u64;
+        if count > capacity || (capacity > 0 && head >= capacity) {
+            return Err(SerumError::InvalidAccountFlags(account_flags));
+        }
+
+        let mut events = Vec::with_capacity(count as usize);
+        for i in 0..count {
+            let offset = ((head + i) % capacity) as usize * EVENT_LEN;
+            let r = &mut Reader::new(&ring[offset..offset + EVENT_LEN]);
+            let event_flags = r.u8()?;
+            let owner_slot = r.u8()?;
+            let fee_tier = r.u8()?;
+            r.take(5)?;
+            events.push(Event {
+                seq_num: seq_num.wrapping_sub(count).wrapping_add(i),
+                event_flags,
+                owner_slot,
+                fee_tier,
+                native_qty_released: r.u64()?,
+                native_qty_paid: r.u64()?,
+                native_fee_or_rebate: r.u64()?,
+                order_id: r.u128()?,
+                owner: r.pubkey()?,
+                client_order_id: r.u64()?,
+            });
+        }
+
+        Ok(EventQueue {
+            account_flags,
+            head,
+            count,
+            seq_num,
+            events,
+        })
+    }
+
+    /// Events with a sequence number at or after `seq_num`
+    pub fn events_since(&self, seq_num: u64) -> impl Iterator<Item = &Event> {
+        self.events.iter().filter(move |e| e.seq_num >= seq_num)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKET: &[u8] = include_bytes!("../tests/fixtures/serum/market.bin");
+    const BIDS: &[u8] = include_bytes!("../tests/fixtures/serum/bids.bin");
+    const ASKS: &[u8] = include_bytes!("../tests/fixtures/serum/asks.bin");
+    const EVENT_QUEUE: &[u8] = include_bytes!("../tests/fixtures/serum/event_queue.bin");
+
+    #[test]
+    fn test_decode_market() {
+        let market = MarketState::decode(MARKET).unwrap();
+        assert_eq!(market.account_flags, account_flag::INITIALIZED | account_flag::MARKET);
+        assert_eq!(market.vault_signer_nonce, 1);
+        assert_eq!(market.coin_lot_size, 1_000_000);
+        assert_eq!(market.pc_lot_size, 1);
+        assert_eq!(market.fee_rate_bps, 22);
+        assert_ne!(market.bids, market.asks);
+
+        assert_eq!(MarketState::decode(ASKS), Err(SerumError::TooShort));
+        assert!(matches!(MarketState::decode(BIDS), Err(SerumError::InvalidAccountFlags(_))));
+        assert_eq!(MarketState::decode(&MARKET[1..]), Err(SerumError::InvalidPadding));
+    }
+
+    #[test]
+    fn test_decode_slabs() {
+        let bids = Slab::decode(BIDS, true).unwrap();
+        let prices: Vec<(u64, u64)> = bids.orders.iter().map(|o| (o.price, o.quantity)).collect();
+        // Same-price bids keep time priority: the older order comes first
+        assert_eq!(prices, vec![(25000, 100), (25000, 20), (24990, 50)]);
+        assert_eq!(bids.orders[0].client_order_id, 9);
+        assert_eq!(
+            bids.levels(10),
+            vec![PriceLevel { price: 25000, size: 120 }, PriceLevel { price: 24990, size: 50 }]
+        );
+        assert_eq!(bids.levels(1).len(), 1);
+
+        let asks = Slab::decode(ASKS, false).unwrap();
+        let prices: Vec<u64> = asks.orders.iter().map(|o| o.price).collect();
+        assert_eq!(prices, vec![25010, 25020]);
+
+        // A bids account is not an asks account
+        assert!(matches!(Slab::decode(BIDS, false), Err(SerumError::InvalidAccountFlags(_))));
+    }
+
+    #[test]
+    fn test_decode_event_queue() {
+        let market = MarketState::decode(MARKET).unwrap();
+        let queue = EventQueue::decode(EVENT_QUEUE).unwrap();
+        assert_eq!(queue.count, 4);
+        assert_eq!(queue.seq_num, 10);
+
+        let seqs: Vec<u64> = queue.events.iter().map(|e| e.seq_num).collect();
+        assert_eq!(seqs, vec![6, 7, 8, 9]);
+        assert_eq!(queue.events_since(8).count(), 2);
+
+        let taker = &queue.events[0];
+        assert!(taker.is_fill() && taker.is_bid() && !taker.is_maker());
+        assert_eq!(taker.price(), 25010);
+        assert_eq!(taker.quantity(&market), 10);
+
+        let payloads: Vec<OrderbookInstruction> = queue
+            .events
+            .iter()
+            .filter_map(|e| e.record_event_payload(&market, "SOL/USDC"))
+            .collect();
+        // The taker fill is priced at its limit and left to the maker's
+        assert_eq!(payloads.len(), 2);
+        assert!(matches!(payloads[0], OrderbookInstruction::RecordEvent { price: 25010, size: 10, is_bid: false, .. }));
+        assert_eq!(
+            payloads[1],
+            OrderbookInstruction::RecordEvent {
+                market_name: "SOL/USDC".to_string(),
+                price: 25020,
+                size: 70,
+                is_bid: false,
+                event_type: OrderbookEventType::OrderCancelled,
+            }
+        );
+    }
+}
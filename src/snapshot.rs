@@ -0,0 +1,276 @@
+//! Off-chain helpers for reading recorded book snapshots.
+//!
+//! A `BookSnapshot` only holds the top levels of each side, so replaying the
+//! event stream from one snapshot can only be checked against the next one
+//! within the price range the first snapshot covered.
+
+use crate::{BookSnapshot, OrderbookEvent, OrderbookEventType, OrderbookMonitor, PriceLevel};
+use std::collections::BTreeMap;
+
+/// A price level with the cumulative size from the best price down to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthPoint {
+    pub price: u64,
+    pub size: u64,
+    pub cumulative: u64,
+}
+
+/// Cumulative depth of both sides of a snapshot, best price first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepthChart {
+    pub bids: Vec<DepthPoint>,
+    pub asks: Vec<DepthPoint>,
+}
+
+fn cumulate(levels: &[PriceLevel]) -> Vec<DepthPoint> {
+    let mut cumulative = 0u64;
+    levels
+        .iter()
+        .map(|level| {
+            cumulative = cumulative.saturating_add(level.size);
+            DepthPoint {
+                price: level.price,
+                size: level.size,
+                cumulative,
+            }
+        })
+        .collect()
+}
+
+/// Build the depth chart of a recorded snapshot
+pub fn depth_chart(snapshot: &BookSnapshot) -> DepthChart {
+    DepthChart {
+        bids: cumulate(&snapshot.bids),
+        asks: cumulate(&snapshot.asks),
+    }
+}
+
+/// An aggregated L2 book rebuilt by replaying events on top of a snapshot
+#[derive(Debug, Clone, Default)]
+pub struct L2Book {
+    bids: BTreeMap<u64, u64>,
+    asks: BTreeMap<u64, u64>,
+}
+
+impl L2Book {
+    pub fn from_snapshot(snapshot: &BookSnapshot) -> Self {
+        L2Book {
+            bids: snapshot.bids.iter().map(|l| (l.price, l.size)).collect(),
+            asks: snapshot.asks.iter().map(|l| (l.price, l.size)).collect(),
+        }
+    }
+
+    /// Apply one event: placements add size at their level, fills and
+    /// cancellations remove it
+    pub fn apply(&mut self, event: &OrderbookEvent) {
+        let side = if event.is_bid { &mut self.bids } else { &mut self.asks };
+        match event.event_type {
+            OrderbookEventType::OrderPlaced => {
+                let size = side.entry(event.price).or_insert(0);
+                *size = size.saturating_add(event.size);
+            }
+            OrderbookEventType::OrderFilled | OrderbookEventType::OrderCancelled => {
+                if let Some(size) = side.get_mut(&event.price) {
+                    *size = size.saturating_sub(event.size);
+                    if *size == 0 {
+                        side.remove(&event.price);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Size resting at a price on one side, zero if the level is empty
+    pub fn size_at(&self, is_bid: bool, price: u64) -> u64 {
+        let side = if is_bid { &self.bids } else { &self.asks };
+        side.get(&price).copied().unwrap_or(0)
+    }
+
+    /// Top `depth` bid levels, best price first
+    pub fn bids(&self, depth: usize) -> Vec<PriceLevel> {
+        self.bids
+            .iter()
+            .rev()
+            .take(depth)
+            .map(|(&price, &size)| PriceLevel { price, size })
+            .collect()
+    }
+
+    /// Top `depth` ask levels, best price first
+    pub fn asks(&self, depth: usize) -> Vec<PriceLevel> {
+        self.asks
+            .iter()
+            .take(depth)
+            .map(|(&price, &size)| PriceLevel { price, size })
+            .collect()
+    }
+}
+
+/// A level where the replayed book disagrees with the recorded snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelMismatch {
+    pub is_bid: bool,
+    pub price: u64,
+    pub replayed: u64,
+    pub recorded: u64,
+}
+
+// Prices at or better than the worst level of `prev` are fully known to the
+// replay; an empty side in `prev` means the side was empty.
+fn covered(prev: &[PriceLevel], is_bid: bool, price: u64) -> bool {
+    match prev.last() {
+        None => true,
+        Some(worst) if is_bid => price >= worst.price,
+        Some(worst) => price <= worst.price,
+    }
+}
+
+fn compare_side(
+    is_bid: bool,
+    prev: &[PriceLevel],
+    replayed: &[PriceLevel],
+    recorded: &[PriceLevel],
+    mismatches: &mut Vec<LevelMismatch>,
+) {
+    let mut prices: Vec<u64> = replayed
+        .iter()
+        .chain(recorded.iter())
+        .map(|l| l.price)
+        .filter(|&price| covered(prev, is_bid, price))
+        .collect();
+    prices.sort_unstable();
+    prices.dedup();
+
+    for price in prices {
+        let find = |levels: &[PriceLevel]| {
+            levels.iter().find(|l| l.price == price).map_or(0, |l| l.size)
+        };
+        let (replayed, recorded) = (find(replayed), find(recorded));
+        if replayed != recorded {
+            mismatches.push(LevelMismatch { is_bid, price, replayed, recorded });
+        }
+    }
+}
+
+/// Replay `events` on top of `prev` and compare the result with `next`.
+///
+/// `events` must be the events of the snapshot's market recorded between the
+/// two snapshots. Returns every covered level whose size differs.
+pub fn verify(
+    prev: &BookSnapshot,
+    events: &[OrderbookEvent],
+    next: &BookSnapshot,
+) -> Vec<LevelMismatch> {
+    let mut book = L2Book::from_snapshot(prev);
+    for event in events {
+        book.apply(event);
+    }
+
+    let mut mismatches = Vec::new();
+    compare_side(true, &prev.bids, &book.bids(next.bids.len()), &next.bids, &mut mismatches);
+    compare_side(false, &prev.asks, &book.asks(next.asks.len()), &next.asks, &mut mismatches);
+    mismatches
+}
+
+/// Result of checking one pair of consecutive snapshots of a market
+#[derive(Debug, Clone)]
+pub struct SnapshotCheck {
+    pub market_name: String,
+    pub from_slot: u64,
+    pub to_slot: u64,
+    pub events_replayed: usize,
+    pub mismatches: Vec<LevelMismatch>,
+}
+
+/// Check every pair of consecutive snapshots in a monitor against the events
+/// recorded between them
+pub fn verify_monitor(monitor: &OrderbookMonitor) -> Vec<SnapshotCheck> {
+    let mut latest: BTreeMap<&str, &BookSnapshot> = BTreeMap::new();
+    let mut checks = Vec::new();
+
+    for next in &monitor.snapshots {
+        if let Some(prev) = latest.insert(&next.market_name, next) {
+            let start = (prev.event_index as usize).min(monitor.events.len());
+            let end = (next.event_index as usize).clamp(start, monitor.events.len());
+            let events: Vec<OrderbookEvent> = monitor.events[start..end]
+                .iter()
+                .filter(|e| e.market_name == next.market_name)
+                .cloned()
+                .collect();
+
+            checks.push(SnapshotCheck {
+                market_name: next.market_name.clone(),
+                from_slot: prev.slot,
+                to_slot: next.slot,
+                events_replayed: events.len(),
+                mismatches: verify(prev, &events, next),
+            });
+        }
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: u64, size: u64) -> PriceLevel {
+        PriceLevel { price, size }
+    }
+
+    fn snapshot(event_index: u64, bids: Vec<PriceLevel>, asks: Vec<PriceLevel>) -> BookSnapshot {
+        BookSnapshot {
+            slot: event_index,
+            timestamp: 0,
+            market_name: "SOL/USDC".to_string(),
+            event_index,
+            bids,
+            asks,
+        }
+    }
+
+    fn event(price: u64, size: u64, is_bid: bool, event_type: OrderbookEventType) -> OrderbookEvent {
+        OrderbookEvent {
+            timestamp: 0,
+            market_name: "SOL/USDC".to_string(),
+            price,
+            size,
+            is_bid,
+            event_type,
+        }
+    }
+
+    #[test]
+    fn test_depth_chart_is_cumulative() {
+        let chart = depth_chart(&snapshot(0, vec![level(100, 5), level(99, 3)], vec![level(101, 2)]));
+        assert_eq!(chart.bids[1].cumulative, 8);
+        assert_eq!(chart.asks[0].cumulative, 2);
+    }
+
+    #[test]
+    fn test_verify_replayed_events() {
+        let prev = snapshot(0, vec![level(100, 5), level(99, 3)], vec![level(101, 2), level(102, 4)]);
+        let events = vec![
+            event(100, 5, true, OrderbookEventType::OrderFilled),
+            event(101, 1, false, OrderbookEventType::OrderPlaced),
+            event(102, 4, false, OrderbookEventType::OrderCancelled),
+        ];
+
+        let next = snapshot(3, vec![level(99, 3)], vec![level(101, 3)]);
+        assert!(verify(&prev, &events, &next).is_empty());
+
+        let wrong = snapshot(3, vec![level(99, 2)], vec![level(101, 3)]);
+        assert_eq!(
+            verify(&prev, &events, &wrong),
+            vec![LevelMismatch { is_bid: true, price: 99, replayed: 3, recorded: 2 }]
+        );
+    }
+
+    #[test]
+    fn test_verify_ignores_levels_outside_previous_range() {
+        let prev = snapshot(0, vec![level(100, 5)], vec![level(101, 2)]);
+        // Level 98 was below the recorded depth, so its size is unknown to the replay
+        let next = snapshot(0, vec![level(100, 5), level(98, 7)], vec![level(101, 2)]);
+        assert!(verify(&prev, &[], &next).is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+//! Market-abuse heuristics and market-maker obligations over per-trader
+//! order lifecycles.
+//!
+//! Recorded `OrderbookEvent`s say nothing about who placed an order, so the
+//! input is `TraderEvent`: an event tied to an order id and the order's
+//! owner. Fills and cancellations come from venue events that carry both
+//! (`SequencedEvent::owner`, set by the Serum v1 adapter together with the
+//! order id). Venue event queues hold no placements; `placements` finds them
+//! by comparing two reads of a Serum bids or asks slab. Serum events carry no
+//! time either, so both are stamped with the time of the read.
+
+use crate::adapter::SequencedEvent;
+use crate::serum::Slab;
+use crate::OrderbookEventType;
+use solana_program::pubkey::Pubkey;
+use std::collections::HashSet;
+
+pub mod obligations;
+pub mod spoofing;
+pub mod wash;
+
+/// One event of a market's stream, identifying the order and its owner
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraderEvent {
+    pub timestamp: i64,
+    pub trader: Pubkey,
+    pub order_id: u128,
+    pub market_name: String,
+    pub price: u64,
+    pub size: u64,
+    pub is_bid: bool,
+    pub event_type: OrderbookEventType,
+}
+
+impl TraderEvent {
+    /// `None` if the venue did not report the order id or its owner. Events
+    /// without a time of their own (timestamp 0, as on Serum v1) get
+    /// `read_at`, the time the event queue was read.
+    pub fn from_sequenced(event: &SequencedEvent, read_at: i64) -> Option<Self> {
+        Some(TraderEvent {
+            timestamp: if event.event.timestamp == 0 { read_at } else { event.event.timestamp },
+            trader: event.owner?,
+            order_id: event.order_id?,
+            market_name: event.event.market_name.clone(),
+            price: event.event.price,
+            size: event.event.size,
+            is_bid: event.event.is_bid,
+            event_type: event.event.event_type.clone(),
+        })
+    }
+}
+
+/// Orders resting in `current` but not in `previous`, as placements at
+/// `timestamp`. Both reads must be of the same side (`is_bid`) of one market.
+pub fn placements(previous: &Slab, current: &Slab, is_bid: bool, market_name: &str, timestamp: i64) -> Vec<TraderEvent> {
+    let known: HashSet<u128> = previous.orders.iter().map(|o| o.order_id).collect();
+    current
+        .orders
+        .iter()
+        .filter(|o| !known.contains(&o.order_id))
+        .map(|o| TraderEvent {
+            timestamp,
+            trader: o.owner,
+            order_id: o.order_id,
+            market_name: market_name.to_string(),
+            price: o.price,
+            size: o.quantity,
+            is_bid,
+            event_type: OrderbookEventType::OrderPlaced,
+        })
+        .collect()
+}
+
+/// A SOL/USDC event, for the detectors' tests
+#[cfg(test)]
+pub(crate) fn event(
+    timestamp: i64,
+    trader: Pubkey,
+    order_id: u128,
+    price: u64,
+    size: u64,
+    is_bid: bool,
+    event_type: OrderbookEventType,
+) -> TraderEvent {
+    TraderEvent { timestamp, trader, order_id, market_name: "SOL/USDC".to_string(), price, size, is_bid, event_type }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::{MarketAdapter, SerumAdapter};
+
+    const MARKET: &[u8] = include_bytes!("../../tests/fixtures/serum/market.bin");
+    const BIDS: &[u8] = include_bytes!("../../tests/fixtures/serum/bids.bin");
+    const EVENT_QUEUE: &[u8] = include_bytes!("../../tests/fixtures/serum/event_queue.bin");
+
+    #[test]
+    fn test_trader_events_from_serum() {
+        let adapter = SerumAdapter::new(Pubkey::new_unique(), MARKET, "SOL/USDC").unwrap();
+        let events: Vec<TraderEvent> =
+            adapter.decode_events(EVENT_QUEUE).unwrap().iter().filter_map(|e| TraderEvent::from_sequenced(e, 1_005)).collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event_type, OrderbookEventType::OrderFilled);
+        assert_eq!(events[0].timestamp, 1_005);
+
+        let bids = Slab::decode(BIDS, true).unwrap();
+        let mut previous = bids.clone();
+        let placed = previous.orders.remove(1);
+        let new = placements(&previous, &bids, true, "SOL/USDC", 1_000);
+        assert_eq!(new.len(), 1);
+        assert_eq!((new[0].order_id, new[0].trader, new[0].size), (placed.order_id, placed.owner, placed.quantity));
+        assert!(placements(&bids, &bids, true, "SOL/USDC", 1_000).is_empty());
+    }
+}
@@ -0,0 +1,345 @@
+//! Quoting obligations of market makers: how long each maker quoted both
+//! sides, how wide and how deep, per market and UTC day.
+//!
+//! `ObligationTracker` rebuilds the resting orders of every market from
+//! placements, fills and cancellations, keeping which maker owns each order.
+//! Between two events the books do not change, so every metric is weighted
+//! by the seconds until the next event of the same market. A maker is two
+//! sided while it has both a bid and an ask resting; it meets its obligation
+//! while, in addition, its own spread is at most `max_spread_bps` and it rests
+//! at least `min_depth` on each side within `depth_bps` of the market mid.
+
+use super::TraderEvent;
+use crate::OrderbookEventType;
+use solana_program::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObligationConfig {
+    /// Makers to report; empty for every trader placing an order
+    pub makers: Vec<Pubkey>,
+    /// Distance from the market mid within which a maker's size counts as depth
+    pub depth_bps: f64,
+    /// Widest own spread of a quote meeting the obligation
+    pub max_spread_bps: f64,
+    /// Smallest size on each side within `depth_bps` of the mid
+    pub min_depth: u64,
+    /// Smallest share of the observed time, in percent, quoted within obligation
+    pub min_uptime_pct: f64,
+}
+
+impl Default for ObligationConfig {
+    fn default() -> Self {
+        ObligationConfig {
+            makers: Vec::new(),
+            depth_bps: 50.0,
+            max_spread_bps: 100.0,
+            min_depth: 0,
+            min_uptime_pct: 90.0,
+        }
+    }
+}
+
+/// A maker's quoting in one market over one UTC day
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyCompliance {
+    /// Unix timestamp of the day's midnight (UTC)
+    pub day: i64,
+    pub market_name: String,
+    pub maker: Pubkey,
+    /// Seconds of the day covered by the stream since the maker was tracked
+    pub observed_secs: i64,
+    pub two_sided_secs: i64,
+    /// Seconds two sided within the spread and depth obligation
+    pub compliant_secs: i64,
+    /// Seconds with an order at the market's best bid or best ask
+    pub at_touch_secs: i64,
+    pub two_sided_pct: f64,
+    pub uptime_pct: f64,
+    pub at_touch_pct: f64,
+    /// Own spread in basis points of the own mid, averaged over the two-sided time
+    pub avg_spread_bps: Option<f64>,
+    /// Size within `depth_bps` of the market mid, averaged over the observed time
+    pub avg_bid_depth: f64,
+    pub avg_ask_depth: f64,
+    /// `uptime_pct` reached `min_uptime_pct`
+    pub compliant: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Tally {
+    observed: i64,
+    two_sided: i64,
+    compliant: i64,
+    at_touch: i64,
+    spread: f64,
+    bid_depth: f64,
+    ask_depth: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Quote {
+    maker: Pubkey,
+    price: u64,
+    size: u64,
+    is_bid: bool,
+}
+
+/// Size resting per price on each side
+#[derive(Debug, Clone, Default)]
+struct Sides {
+    bids: BTreeMap<u64, u64>,
+    asks: BTreeMap<u64, u64>,
+}
+
+impl Sides {
+    fn side(&mut self, is_bid: bool) -> &mut BTreeMap<u64, u64> {
+        if is_bid {
+            &mut self.bids
+        } else {
+            &mut self.asks
+        }
+    }
+
+    fn add(&mut self, is_bid: bool, price: u64, size: u64) {
+        *self.side(is_bid).entry(price).or_default() += size;
+    }
+
+    fn remove(&mut self, is_bid: bool, price: u64, size: u64) {
+        let side = self.side(is_bid);
+        if let Some(level) = side.get_mut(&price) {
+            *level = level.saturating_sub(size);
+            if *level == 0 {
+                side.remove(&price);
+            }
+        }
+    }
+
+    fn best_bid(&self) -> Option<u64> {
+        self.bids.keys().next_back().copied()
+    }
+
+    fn best_ask(&self) -> Option<u64> {
+        self.asks.keys().next().copied()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Market {
+    /// Timestamp up to which the market's time is tallied
+    last: i64,
+    orders: HashMap<u128, Quote>,
+    book: Sides,
+    makers: BTreeMap<Pubkey, Sides>,
+}
+
+/// Tallies makers' quoting from a stream of `TraderEvent`s
+#[derive(Debug, Clone)]
+pub struct ObligationTracker {
+    config: ObligationConfig,
+    markets: HashMap<String, Market>,
+    tallies: BTreeMap<(i64, String, Pubkey), Tally>,
+}
+
+impl ObligationTracker {
+    pub fn new(config: ObligationConfig) -> Self {
+        ObligationTracker { config, markets: HashMap::new(), tallies: BTreeMap::new() }
+    }
+
+    /// Apply the next event of the stream. Events of a market must come in
+    /// time order; fills and cancellations of orders placed before the stream
+    /// started are ignored.
+    pub fn apply(&mut self, event: &TraderEvent) {
+        let market = self.markets.entry(event.market_name.clone()).or_insert_with(|| Market {
+            last: event.timestamp,
+            makers: self.config.makers.iter().map(|&maker| (maker, Sides::default())).collect(),
+            ..Market::default()
+        });
+        tally(&self.config, &mut self.tallies, &event.market_name, market, event.timestamp);
+
+        match event.event_type {
+            OrderbookEventType::OrderPlaced => {
+                if self.config.makers.is_empty() {
+                    market.makers.entry(event.trader).or_default();
+                }
+                if let Some(sides) = market.makers.get_mut(&event.trader) {
+                    sides.add(event.is_bid, event.price, event.size);
+                }
+                market.book.add(event.is_bid, event.price, event.size);
+                market.orders.insert(
+                    event.order_id,
+                    Quote { maker: event.trader, price: event.price, size: event.size, is_bid: event.is_bid },
+                );
+            }
+            OrderbookEventType::OrderFilled | OrderbookEventType::OrderCancelled => {
+                let Some(quote) = market.orders.get_mut(&event.order_id) else {
+                    return;
+                };
+                let size = match event.event_type {
+                    OrderbookEventType::OrderFilled => event.size.min(quote.size),
+                    _ => quote.size,
+                };
+                quote.size -= size;
+                let quote = *quote;
+                if quote.size == 0 {
+                    market.orders.remove(&event.order_id);
+                }
+                market.book.remove(quote.is_bid, quote.price, size);
+                if let Some(sides) = market.makers.get_mut(&quote.maker) {
+                    sides.remove(quote.is_bid, quote.price, size);
+                }
+            }
+        }
+    }
+
+    /// Tally every market's time up to `until`, as if nothing changed since
+    /// its last event
+    pub fn advance(&mut self, until: i64) {
+        for (market_name, market) in self.markets.iter_mut() {
+            tally(&self.config, &mut self.tallies, market_name, market, until);
+        }
+    }
+
+    /// Daily compliance of every tracked maker, by day, market and maker
+    pub fn report(&self) -> Vec<DailyCompliance> {
+        self.tallies
+            .iter()
+            .filter(|(_, tally)| tally.observed > 0)
+            .map(|((day, market_name, maker), tally)| {
+                let observed = tally.observed as f64;
+                let pct = |secs: i64| secs as f64 / observed * 100.0;
+                let uptime_pct = pct(tally.compliant);
+                DailyCompliance {
+                    day: *day,
+                    market_name: market_name.clone(),
+                    maker: *maker,
+                    observed_secs: tally.observed,
+                    two_sided_secs: tally.two_sided,
+                    compliant_secs: tally.compliant,
+                    at_touch_secs: tally.at_touch,
+                    two_sided_pct: pct(tally.two_sided),
+                    uptime_pct,
+                    at_touch_pct: pct(tally.at_touch),
+                    avg_spread_bps: (tally.two_sided > 0).then(|| tally.spread / tally.two_sided as f64),
+                    avg_bid_depth: tally.bid_depth / observed,
+                    avg_ask_depth: tally.ask_depth / observed,
+                    compliant: uptime_pct >= self.config.min_uptime_pct,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Add the market's state from its last tallied timestamp to `until`, split
+/// at UTC midnights
+fn tally(
+    config: &ObligationConfig,
+    tallies: &mut BTreeMap<(i64, String, Pubkey), Tally>,
+    market_name: &str,
+    market: &mut Market,
+    until: i64,
+) {
+    let (best_bid, best_ask) = (market.book.best_bid(), market.book.best_ask());
+    let mid = best_bid.zip(best_ask).map(|(bid, ask)| (bid as f64 + ask as f64) / 2.0);
+    while market.last < until {
+        let day = market.last.div_euclid(SECONDS_PER_DAY) * SECONDS_PER_DAY;
+        let end = until.min(day + SECONDS_PER_DAY);
+        let secs = end - market.last;
+        for (&maker, sides) in &market.makers {
+            let tally = tallies.entry((day, market_name.to_string(), maker)).or_default();
+            tally.observed += secs;
+            let (bid, ask) = (sides.best_bid(), sides.best_ask());
+            if (bid.is_some() && bid == best_bid) || (ask.is_some() && ask == best_ask) {
+                tally.at_touch += secs;
+            }
+            let (bid_depth, ask_depth) = mid.map_or((0, 0), |mid| {
+                let width = mid * config.depth_bps / 10_000.0;
+                let (low, high) = (mid - width, mid + width);
+                (
+                    sides.bids.iter().filter(|(&p, _)| p as f64 >= low).map(|(_, &s)| s).sum::<u64>(),
+                    sides.asks.iter().filter(|(&p, _)| p as f64 <= high).map(|(_, &s)| s).sum::<u64>(),
+                )
+            });
+            tally.bid_depth += bid_depth as f64 * secs as f64;
+            tally.ask_depth += ask_depth as f64 * secs as f64;
+            if let (Some(bid), Some(ask)) = (bid, ask) {
+                let own_mid = (bid as f64 + ask as f64) / 2.0;
+                let spread_bps = (ask as f64 - bid as f64) / own_mid * 10_000.0;
+                tally.two_sided += secs;
+                tally.spread += spread_bps * secs as f64;
+                let deep = bid_depth >= config.min_depth && ask_depth >= config.min_depth;
+                if spread_bps <= config.max_spread_bps && deep {
+                    tally.compliant += secs;
+                }
+            }
+        }
+        market.last = end;
+    }
+}
+
+/// Daily compliance over `events`, up to the last event's timestamp
+pub fn daily_compliance(events: &[TraderEvent], config: &ObligationConfig) -> Vec<DailyCompliance> {
+    let mut tracker = ObligationTracker::new(config.clone());
+    for event in events {
+        tracker.apply(event);
+    }
+    if let Some(last) = events.iter().map(|e| e.timestamp).max() {
+        tracker.advance(last);
+    }
+    tracker.report()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surveillance::event;
+    use OrderbookEventType::*;
+
+    #[test]
+    fn test_daily_compliance() {
+        let (maker, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let day = 19_000 * SECONDS_PER_DAY;
+        let events = vec![
+            // The maker quotes 99 / 101 from the start, the other trader improves the bid after 600s
+            event(day, maker, 1, 99, 10, true, OrderPlaced),
+            event(day, maker, 2, 101, 10, false, OrderPlaced),
+            event(day + 600, other, 3, 100, 5, true, OrderPlaced),
+            // The maker's ask is lifted after 900s and re-quoted wide after 1200s
+            event(day + 900, maker, 2, 101, 10, false, OrderFilled),
+            event(day + 1_200, maker, 4, 103, 10, false, OrderPlaced),
+            event(day + SECONDS_PER_DAY - 600, maker, 1, 99, 10, true, OrderCancelled),
+            // Next day, left unquoted
+            event(day + SECONDS_PER_DAY + 600, other, 3, 100, 5, true, OrderCancelled),
+        ];
+        let config = ObligationConfig {
+            makers: vec![maker],
+            depth_bps: 300.0,
+            max_spread_bps: 250.0,
+            min_depth: 10,
+            ..ObligationConfig::default()
+        };
+        let report = daily_compliance(&events, &config);
+        assert_eq!(report.len(), 2);
+
+        let first = &report[0];
+        assert_eq!((first.day, first.maker, first.observed_secs), (day, maker, SECONDS_PER_DAY));
+        // Off the touch only while the ask was missing
+        assert_eq!((first.two_sided_secs, first.at_touch_secs), (SECONDS_PER_DAY - 900, SECONDS_PER_DAY - 300));
+        // 99 / 103 is too wide
+        assert_eq!(first.compliant_secs, 900);
+        let spread = (900.0 * 200.0 + (SECONDS_PER_DAY - 1_800) as f64 * 40_000.0 / 101.0) / (SECONDS_PER_DAY - 900) as f64;
+        assert!((first.avg_spread_bps.unwrap() - spread).abs() < 1e-6, "{:?}", first.avg_spread_bps);
+        assert!(!first.compliant);
+
+        let second = &report[1];
+        assert_eq!((second.day, second.observed_secs, second.two_sided_secs), (day + SECONDS_PER_DAY, 600, 0));
+        assert_eq!((second.avg_spread_bps, second.uptime_pct, second.avg_bid_depth), (None, 0.0, 0.0));
+
+        let lenient = ObligationConfig { max_spread_bps: 500.0, min_uptime_pct: 95.0, ..config };
+        let first = &daily_compliance(&events, &lenient)[0];
+        assert_eq!(first.compliant_secs, first.two_sided_secs);
+        assert!(first.compliant, "{}", first.uptime_pct);
+    }
+}
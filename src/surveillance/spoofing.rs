@@ -0,0 +1,424 @@
+//! Spoofing and layering: orders placed to sway other traders and pulled
+//! before they trade, while their owner trades on the other side.
+//!
+//! A spoofing incident is one order that is large for its market (at least
+//! `min_size_ratio` times the market's mean placement size so far), rests at
+//! least `min_distance_bps` away from the touch of its side, and is cancelled
+//! mostly unfilled within `max_lifetime_secs`. A layering incident is
+//! `min_layers` or more such short-lived orders of one trader on one side, at
+//! distinct prices and placed within `layer_window_secs`, whatever their size
+//! or distance. Either needs fills of the same trader on the opposite side
+//! between the first placement and `fill_window_secs` after the last
+//! cancellation. A trader's fills are those of their own resting orders.
+//!
+//! Scores run from 0 to 1: 40% for how far the size (or the number of
+//! layers) exceeds its threshold, saturating at twice the threshold, 30% for
+//! how quickly the orders were cancelled, and 30% for the opposite fills,
+//! halved when they came only after the cancellation.
+
+use super::TraderEvent;
+use crate::l3::{L3Book, L3Event};
+use crate::OrderbookEventType;
+use solana_program::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpoofingConfig {
+    /// Smallest placement, as a multiple of the market's mean placement size
+    pub min_size_ratio: f64,
+    /// Smallest distance from the touch of the order's side, in basis points
+    pub min_distance_bps: f64,
+    /// Longest life of an order cancelled quickly
+    pub max_lifetime_secs: i64,
+    /// Largest filled share of an order still counted as unfilled
+    pub max_filled_fraction: f64,
+    /// Seconds after the last cancellation in which opposite fills count
+    pub fill_window_secs: i64,
+    /// Fewest short-lived orders at distinct prices making up a layering pattern
+    pub min_layers: usize,
+    /// Longest span over which the layers are placed
+    pub layer_window_secs: i64,
+    /// Incidents scoring lower are dropped
+    pub min_score: f64,
+}
+
+impl Default for SpoofingConfig {
+    fn default() -> Self {
+        SpoofingConfig {
+            min_size_ratio: 5.0,
+            min_distance_bps: 5.0,
+            max_lifetime_secs: 10,
+            max_filled_fraction: 0.1,
+            fill_window_secs: 5,
+            min_layers: 3,
+            layer_window_secs: 10,
+            min_score: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum IncidentKind {
+    Spoofing,
+    Layering,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incident {
+    pub kind: IncidentKind,
+    pub trader: Pubkey,
+    pub market_name: String,
+    /// Side of the cancelled orders
+    pub is_bid: bool,
+    /// First placement
+    pub start: i64,
+    /// Last cancellation
+    pub end: i64,
+    pub order_ids: Vec<u128>,
+    /// Total size of the cancelled orders
+    pub order_size: u64,
+    /// Size the trader filled on the opposite side around the orders
+    pub opposite_fill_size: u64,
+    pub score: f64,
+}
+
+/// A trader's incidents in one market
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraderScore {
+    pub trader: Pubkey,
+    pub market_name: String,
+    pub spoofing: usize,
+    pub layering: usize,
+    pub max_score: f64,
+    pub total_score: f64,
+}
+
+/// An order from placement to cancellation
+#[derive(Debug, Clone)]
+struct Life {
+    trader: Pubkey,
+    market_name: String,
+    order_id: u128,
+    is_bid: bool,
+    price: u64,
+    size: u64,
+    placed_at: i64,
+    /// Distance behind the touch of its side when placed; `None` on an empty side
+    distance_bps: Option<f64>,
+    /// Size over the market's mean placement size before it; `None` for the first
+    size_ratio: Option<f64>,
+    filled: u64,
+    cancelled_at: Option<i64>,
+}
+
+impl Life {
+    /// Time from placement to cancellation, if short and mostly unfilled. A
+    /// cancellation stamped before its placement is a clock mix-up, not a
+    /// quick cancel.
+    fn lifetime(&self, config: &SpoofingConfig) -> Option<i64> {
+        let lifetime = self.cancelled_at? - self.placed_at;
+        let unfilled = self.filled as f64 <= config.max_filled_fraction * self.size as f64;
+        ((0..=config.max_lifetime_secs).contains(&lifetime) && unfilled).then_some(lifetime)
+    }
+}
+
+#[derive(Default)]
+struct Market {
+    book: L3Book,
+    placed_size: u64,
+    placements: u64,
+}
+
+/// Fills per trader and market as `(timestamp, is_bid, size)`
+type Fills = HashMap<(Pubkey, String), Vec<(i64, bool, u64)>>;
+
+fn lifecycles(events: &[TraderEvent]) -> (Vec<Life>, Fills) {
+    let mut markets: HashMap<&str, Market> = HashMap::new();
+    let mut lives: Vec<Life> = Vec::new();
+    let mut index: HashMap<(&str, u128), usize> = HashMap::new();
+    let mut fills = Fills::new();
+    for (seq_num, event) in events.iter().enumerate() {
+        let market = markets.entry(&event.market_name).or_default();
+        let key = (event.market_name.as_str(), event.order_id);
+        match event.event_type {
+            OrderbookEventType::OrderPlaced => {
+                let touch = if event.is_bid { market.book.best_bid() } else { market.book.best_ask() };
+                let distance_bps = touch.map(|touch| {
+                    let behind = if event.is_bid {
+                        touch.price as f64 - event.price as f64
+                    } else {
+                        event.price as f64 - touch.price as f64
+                    };
+                    (behind / touch.price as f64 * 10_000.0).max(0.0)
+                });
+                let size_ratio = (market.placements > 0)
+                    .then(|| event.size as f64 * market.placements as f64 / market.placed_size as f64);
+                market.placed_size = market.placed_size.saturating_add(event.size);
+                market.placements += 1;
+                index.insert(key, lives.len());
+                lives.push(Life {
+                    trader: event.trader,
+                    market_name: event.market_name.clone(),
+                    order_id: event.order_id,
+                    is_bid: event.is_bid,
+                    price: event.price,
+                    size: event.size,
+                    placed_at: event.timestamp,
+                    distance_bps,
+                    size_ratio,
+                    filled: 0,
+                    cancelled_at: None,
+                });
+            }
+            OrderbookEventType::OrderFilled => {
+                fills.entry((event.trader, event.market_name.clone())).or_default().push((
+                    event.timestamp,
+                    event.is_bid,
+                    event.size,
+                ));
+                if let Some(&i) = index.get(&key) {
+                    lives[i].filled = lives[i].filled.saturating_add(event.size);
+                }
+            }
+            OrderbookEventType::OrderCancelled => {
+                if let Some(&i) = index.get(&key) {
+                    lives[i].cancelled_at.get_or_insert(event.timestamp);
+                }
+            }
+        }
+        // Orders resting before the stream starts are unknown to the book
+        let _ = market.book.apply(&L3Event {
+            seq_num: seq_num as u64,
+            order_id: event.order_id,
+            market_name: event.market_name.clone(),
+            price: event.price,
+            size: event.size,
+            is_bid: event.is_bid,
+            event_type: event.event_type.clone(),
+        });
+    }
+    (lives, fills)
+}
+
+/// Opposite-side fill size of `orders` and whether any came while one rested
+fn opposite_fills(orders: &[&Life], fills: &Fills, config: &SpoofingConfig) -> (u64, bool) {
+    let first = &orders[0];
+    let start = orders.iter().map(|o| o.placed_at).min().unwrap_or(first.placed_at);
+    let last_cancel = orders.iter().filter_map(|o| o.cancelled_at).max().unwrap_or(start);
+    let end = last_cancel + config.fill_window_secs;
+    let mut size = 0;
+    let mut while_resting = false;
+    for &(timestamp, is_bid, fill) in fills.get(&(first.trader, first.market_name.clone())).into_iter().flatten() {
+        if is_bid != first.is_bid && (start..=end).contains(&timestamp) {
+            size += fill;
+            while_resting |= timestamp <= last_cancel;
+        }
+    }
+    (size, while_resting)
+}
+
+fn incident(kind: IncidentKind, orders: &[&Life], fills: &Fills, strength: f64, config: &SpoofingConfig) -> Option<Incident> {
+    let (opposite_fill_size, while_resting) = opposite_fills(orders, fills, config);
+    if opposite_fill_size == 0 {
+        return None;
+    }
+    let lifetimes: Vec<i64> = orders.iter().filter_map(|o| o.lifetime(config)).collect();
+    let mean_lifetime = lifetimes.iter().sum::<i64>() as f64 / lifetimes.len() as f64;
+    let speed = if config.max_lifetime_secs > 0 {
+        1.0 - (mean_lifetime / config.max_lifetime_secs as f64).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    let score = 0.4 * strength.clamp(0.0, 1.0) + 0.3 * speed + 0.3 * if while_resting { 1.0 } else { 0.5 };
+    Some(Incident {
+        kind,
+        trader: orders[0].trader,
+        market_name: orders[0].market_name.clone(),
+        is_bid: orders[0].is_bid,
+        start: orders.iter().map(|o| o.placed_at).min()?,
+        end: orders.iter().filter_map(|o| o.cancelled_at).max()?,
+        order_ids: orders.iter().map(|o| o.order_id).collect(),
+        order_size: orders.iter().map(|o| o.size).sum(),
+        opposite_fill_size,
+        score,
+    })
+    .filter(|incident| incident.score >= config.min_score)
+}
+
+/// Find spoofing and layering incidents in `events`, ordered by start time.
+/// An order found layered is not reported again as spoofing.
+pub fn detect(events: &[TraderEvent], config: &SpoofingConfig) -> Vec<Incident> {
+    let (lives, fills) = lifecycles(events);
+    let mut incidents = Vec::new();
+
+    // Short-lived orders of each trader, market and side, by placement time
+    let mut groups: BTreeMap<(Pubkey, &str, bool), Vec<&Life>> = BTreeMap::new();
+    for life in lives.iter().filter(|life| life.lifetime(config).is_some()) {
+        groups.entry((life.trader, life.market_name.as_str(), life.is_bid)).or_default().push(life);
+    }
+
+    let mut layered: HashSet<(&str, u128)> = HashSet::new();
+    for orders in groups.values_mut() {
+        orders.sort_by_key(|o| o.placed_at);
+        let mut first = 0;
+        while first < orders.len() {
+            let last = orders[first..].partition_point(|o| o.placed_at - orders[first].placed_at <= config.layer_window_secs);
+            let cluster = &orders[first..first + last];
+            let layers = cluster.iter().map(|o| o.price).collect::<HashSet<_>>().len();
+            if layers >= config.min_layers.max(2) {
+                let strength = layers as f64 / (2 * config.min_layers.max(1)) as f64;
+                if let Some(incident) = incident(IncidentKind::Layering, cluster, &fills, strength, config) {
+                    layered.extend(cluster.iter().map(|o| (o.market_name.as_str(), o.order_id)));
+                    incidents.push(incident);
+                    first += last;
+                    continue;
+                }
+            }
+            first += 1;
+        }
+    }
+
+    for order in groups.values().flatten() {
+        if layered.contains(&(order.market_name.as_str(), order.order_id))
+            || order.distance_bps.is_none_or(|d| d < config.min_distance_bps)
+        {
+            continue;
+        }
+        let Some(ratio) = order.size_ratio.filter(|&r| r >= config.min_size_ratio) else {
+            continue;
+        };
+        let strength = ratio / (2.0 * config.min_size_ratio);
+        incidents.extend(incident(IncidentKind::Spoofing, &[order], &fills, strength, config));
+    }
+
+    incidents.sort_by(|a, b| (a.start, a.trader, &a.market_name).cmp(&(b.start, b.trader, &b.market_name)));
+    incidents
+}
+
+/// Incidents summed per trader and market, highest total score first
+pub fn trader_scores(incidents: &[Incident]) -> Vec<TraderScore> {
+    let mut scores: BTreeMap<(Pubkey, &str), TraderScore> = BTreeMap::new();
+    for incident in incidents {
+        let score = scores.entry((incident.trader, &incident.market_name)).or_insert_with(|| TraderScore {
+            trader: incident.trader,
+            market_name: incident.market_name.clone(),
+            spoofing: 0,
+            layering: 0,
+            max_score: 0.0,
+            total_score: 0.0,
+        });
+        match incident.kind {
+            IncidentKind::Spoofing => score.spoofing += 1,
+            IncidentKind::Layering => score.layering += 1,
+        }
+        score.max_score = score.max_score.max(incident.score);
+        score.total_score += incident.score;
+    }
+    let mut scores: Vec<TraderScore> = scores.into_values().collect();
+    scores.sort_by(|a, b| b.total_score.total_cmp(&a.total_score));
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surveillance::event;
+    use OrderbookEventType::*;
+
+    /// A book of honest quotes 100 / 101, sized 10 each
+    fn book(honest: Pubkey) -> Vec<TraderEvent> {
+        (0..4)
+            .map(|i| event(0, honest, 1 + i as u128, if i % 2 == 0 { 100 } else { 101 }, 10, i % 2 == 0, OrderPlaced))
+            .collect()
+    }
+
+    #[test]
+    fn test_spoofing() {
+        let (honest, spoofer) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut events = book(honest);
+        events.extend([
+            // A bid five times the usual size, 1% behind the touch, pulled after 2s
+            event(10, spoofer, 10, 99, 60, true, OrderPlaced),
+            event(10, spoofer, 11, 101, 5, false, OrderPlaced),
+            event(11, spoofer, 11, 101, 5, false, OrderFilled),
+            event(12, spoofer, 10, 99, 60, true, OrderCancelled),
+            // The honest trader's large bid stays and trades
+            event(20, honest, 12, 99, 60, true, OrderPlaced),
+            event(21, honest, 13, 101, 5, false, OrderPlaced),
+            event(22, honest, 13, 101, 5, false, OrderFilled),
+            event(40, honest, 12, 99, 60, true, OrderCancelled),
+        ]);
+        let incidents = detect(&events, &SpoofingConfig::default());
+        assert_eq!(incidents.len(), 1);
+        let incident = &incidents[0];
+        assert_eq!((incident.kind, incident.trader, incident.is_bid), (IncidentKind::Spoofing, spoofer, true));
+        assert_eq!((incident.start, incident.end, incident.order_size, incident.opposite_fill_size), (10, 12, 60, 5));
+        assert!((incident.score - 0.78).abs() < 1e-9, "{}", incident.score);
+
+        // Without the opposite fill there is nothing to gain
+        let without_fill: Vec<TraderEvent> =
+            events.iter().filter(|e| !(e.trader == spoofer && e.event_type == OrderFilled)).cloned().collect();
+        assert!(detect(&without_fill, &SpoofingConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_layering_and_scores() {
+        let (honest, layerer) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut events = book(honest);
+        // Three asks stacked above the touch while the trader buys, then pulled
+        for (i, price) in [102, 103, 104].into_iter().enumerate() {
+            events.push(event(10 + i as i64, layerer, 20 + i as u128, price, 10, false, OrderPlaced));
+        }
+        events.push(event(13, layerer, 30, 100, 5, true, OrderPlaced));
+        events.push(event(14, layerer, 30, 100, 5, true, OrderFilled));
+        for (i, price) in [102, 103, 104].into_iter().enumerate() {
+            events.push(event(15, layerer, 20 + i as u128, price, 10, false, OrderCancelled));
+        }
+        let incidents = detect(&events, &SpoofingConfig::default());
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].kind, IncidentKind::Layering);
+        assert_eq!(incidents[0].order_ids, vec![20, 21, 22]);
+        assert!(!incidents[0].is_bid);
+
+        let strict = SpoofingConfig { min_layers: 4, ..SpoofingConfig::default() };
+        assert!(detect(&events, &strict).is_empty());
+        let scores = trader_scores(&incidents);
+        assert_eq!(scores.len(), 1);
+        assert_eq!((scores[0].trader, scores[0].layering, scores[0].spoofing), (layerer, 1, 0));
+        assert_eq!(scores[0].total_score, incidents[0].score);
+    }
+
+    #[test]
+    fn test_spoofing_on_serum_events() {
+        use crate::adapter::{MarketAdapter, SerumAdapter};
+        use crate::serum::Slab;
+        use crate::surveillance::placements;
+
+        let market = include_bytes!("../../tests/fixtures/serum/market.bin");
+        let adapter = SerumAdapter::new(Pubkey::new_unique(), market, "SOL/USDC").unwrap();
+        let queue = adapter.decode_events(include_bytes!("../../tests/fixtures/serum/event_queue.bin")).unwrap();
+        let bids = Slab::decode(include_bytes!("../../tests/fixtures/serum/bids.bin"), true).unwrap();
+        let asks = Slab::decode(include_bytes!("../../tests/fixtures/serum/asks.bin"), false).unwrap();
+        let empty = |slab: &Slab| Slab { orders: vec![], ..slab.clone() };
+
+        // Both books appear in a read at 1000; the queue, read at `read_at`,
+        // fills 10 lots of the 25010 ask and cancels the 70 lots at 25020
+        let events_read_at = |read_at: i64| {
+            let mut events = placements(&empty(&bids), &bids, true, "SOL/USDC", 1_000);
+            events.extend(placements(&empty(&asks), &asks, false, "SOL/USDC", 1_000));
+            events.extend(queue.iter().filter_map(|e| TraderEvent::from_sequenced(e, read_at)));
+            let canceller = events.last().unwrap().trader;
+            events.push(event(1_002, canceller, 1, 25_000, 5, true, OrderFilled));
+            events
+        };
+        let config = SpoofingConfig { min_size_ratio: 1.2, min_distance_bps: 1.0, ..SpoofingConfig::default() };
+        let incidents = detect(&events_read_at(1_003), &config);
+        assert_eq!(incidents.len(), 1);
+        assert_eq!((incidents[0].kind, incidents[0].start, incidents[0].end), (IncidentKind::Spoofing, 1_000, 1_003));
+        assert_eq!(incidents[0].order_size, 70);
+
+        // A cancellation stamped before its placement is not a quick one
+        assert!(detect(&events_read_at(0), &config).is_empty());
+    }
+}
+
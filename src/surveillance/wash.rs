@@ -0,0 +1,387 @@
+//! Wash trading: fills that change no one's position because both sides
+//! belong to the same party.
+//!
+//! Three kinds of fills are suspect. A self-trade has the same owner on both
+//! sides. A round trip is a chain of fills, each passing about the same size
+//! at about the same price from the previous buyer to a new one, that ends
+//! with the first seller buying it back within `window_secs`. Wallets caught
+//! in `min_round_trips` round trips together, or given as related, form a
+//! cluster, and any other fill between two wallets of one cluster is a
+//! cluster trade.
+
+use crate::adapter::SequencedEvent;
+use crate::OrderbookEventType;
+use solana_program::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WashConfig {
+    /// Longest time for traded size to come back to its first seller
+    pub window_secs: i64,
+    /// Largest size difference between the legs of a round trip, as a share of the first
+    pub size_tolerance: f64,
+    /// Largest price difference between the legs of a round trip, in basis points of the first
+    pub max_price_bps: f64,
+    /// Most wallets in one round trip
+    pub max_cycle_len: usize,
+    /// Round trips two wallets share before they are treated as related
+    pub min_round_trips: usize,
+}
+
+impl Default for WashConfig {
+    fn default() -> Self {
+        WashConfig { window_secs: 300, size_tolerance: 0.05, max_price_bps: 50.0, max_cycle_len: 4, min_round_trips: 2 }
+    }
+}
+
+/// A fill with the owners of both sides
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedFill {
+    pub timestamp: i64,
+    pub market_name: String,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub price: u64,
+    pub size: u64,
+    pub taker_is_bid: bool,
+}
+
+impl OwnedFill {
+    /// `None` unless the event is a fill whose venue reported both owners
+    pub fn from_sequenced(event: &SequencedEvent) -> Option<Self> {
+        if event.event.event_type != OrderbookEventType::OrderFilled {
+            return None;
+        }
+        Some(OwnedFill {
+            timestamp: event.event.timestamp,
+            market_name: event.event.market_name.clone(),
+            maker: event.owner?,
+            taker: event.taker?,
+            price: event.event.price,
+            size: event.event.size,
+            // Fills are reported on the maker's side
+            taker_is_bid: !event.event.is_bid,
+        })
+    }
+
+    fn seller(&self) -> Pubkey {
+        if self.taker_is_bid { self.maker } else { self.taker }
+    }
+
+    fn buyer(&self) -> Pubkey {
+        if self.taker_is_bid { self.taker } else { self.maker }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum WashReason {
+    SelfTrade,
+    RoundTrip,
+    Cluster,
+}
+
+/// A suspect fill, by its index in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuspectFill {
+    pub index: usize,
+    pub reason: WashReason,
+}
+
+/// How much of a market's volume is suspect, in base size
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketWash {
+    pub market_name: String,
+    pub volume: u64,
+    pub self_trade_volume: u64,
+    pub round_trip_volume: u64,
+    pub cluster_volume: u64,
+    pub suspect_share: f64,
+}
+
+impl MarketWash {
+    pub fn suspect_volume(&self) -> u64 {
+        self.self_trade_volume.saturating_add(self.round_trip_volume).saturating_add(self.cluster_volume)
+    }
+}
+
+/// A wallet's part in the suspect fills of one market
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletWash {
+    pub wallet: Pubkey,
+    pub market_name: String,
+    pub suspect_volume: u64,
+    /// Suspect volume over the market's volume
+    pub suspect_share: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WashReport {
+    pub markets: Vec<MarketWash>,
+    /// Groups of related wallets, each sorted, found or given
+    pub clusters: Vec<Vec<Pubkey>>,
+    pub suspects: Vec<SuspectFill>,
+}
+
+/// Union-find over wallets
+#[derive(Default)]
+struct Clusters {
+    parent: HashMap<Pubkey, Pubkey>,
+}
+
+impl Clusters {
+    fn root(&mut self, wallet: Pubkey) -> Pubkey {
+        let parent = *self.parent.entry(wallet).or_insert(wallet);
+        if parent == wallet {
+            return wallet;
+        }
+        let root = self.root(parent);
+        self.parent.insert(wallet, root);
+        root
+    }
+
+    fn join(&mut self, a: Pubkey, b: Pubkey) {
+        let (a, b) = (self.root(a), self.root(b));
+        if a != b {
+            self.parent.insert(a.max(b), a.min(b));
+        }
+    }
+
+    fn related(&mut self, a: Pubkey, b: Pubkey) -> bool {
+        self.parent.contains_key(&a) && self.parent.contains_key(&b) && self.root(a) == self.root(b)
+    }
+
+    fn groups(&mut self) -> Vec<Vec<Pubkey>> {
+        let wallets: Vec<Pubkey> = self.parent.keys().copied().collect();
+        let mut groups: BTreeMap<Pubkey, Vec<Pubkey>> = BTreeMap::new();
+        for wallet in wallets {
+            groups.entry(self.root(wallet)).or_default().push(wallet);
+        }
+        let mut groups: Vec<Vec<Pubkey>> = groups.into_values().filter(|g| g.len() > 1).collect();
+        for group in &mut groups {
+            group.sort();
+        }
+        groups.sort();
+        groups
+    }
+}
+
+/// Fills after `path`'s last one that carry its size on from `holder`,
+/// closing the cycle back to `origin` or extending it
+fn find_cycle(
+    fills: &[OwnedFill],
+    market: &[usize],
+    used: &[bool],
+    path: &mut Vec<usize>,
+    holder: Pubkey,
+    config: &WashConfig,
+) -> bool {
+    let first = &fills[path[0]];
+    let origin = first.seller();
+    let after = market.iter().position(|&i| i == *path.last().expect("path is not empty")).expect("in market") + 1;
+    for &j in &market[after..] {
+        let fill = &fills[j];
+        if fill.timestamp > first.timestamp + config.window_secs {
+            break;
+        }
+        let size_off = (fill.size as f64 - first.size as f64).abs() / first.size.max(1) as f64;
+        let price_off = (fill.price as f64 - first.price as f64).abs() / first.price.max(1) as f64 * 10_000.0;
+        if used[j] || fill.seller() != holder || fill.maker == fill.taker || size_off > config.size_tolerance
+            || price_off > config.max_price_bps
+        {
+            continue;
+        }
+        let buyer = fill.buyer();
+        let visited = path.iter().any(|&i| fills[i].seller() == buyer);
+        path.push(j);
+        if buyer == origin {
+            return true;
+        }
+        if !visited && path.len() < config.max_cycle_len && find_cycle(fills, market, used, path, buyer, config) {
+            return true;
+        }
+        path.pop();
+    }
+    false
+}
+
+/// Classify `fills`, which must be in the order they happened. `related`
+/// lists groups of wallets already known to belong together.
+pub fn detect(fills: &[OwnedFill], related: &[Vec<Pubkey>], config: &WashConfig) -> WashReport {
+    let mut reasons: Vec<Option<WashReason>> = vec![None; fills.len()];
+    let mut by_market: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (i, fill) in fills.iter().enumerate() {
+        by_market.entry(&fill.market_name).or_default().push(i);
+        if fill.maker == fill.taker {
+            reasons[i] = Some(WashReason::SelfTrade);
+        }
+    }
+
+    let mut clusters = Clusters::default();
+    for group in related {
+        for pair in group.windows(2) {
+            clusters.join(pair[0], pair[1]);
+        }
+    }
+    let mut round_trips: HashMap<(Pubkey, Pubkey), usize> = HashMap::new();
+    for market in by_market.values() {
+        let mut used = vec![false; fills.len()];
+        for &i in market {
+            if used[i] || reasons[i].is_some() {
+                continue;
+            }
+            let mut path = vec![i];
+            if find_cycle(fills, market, &used, &mut path, fills[i].buyer(), config) {
+                let mut wallets: Vec<Pubkey> = path.iter().map(|&j| fills[j].seller()).collect();
+                wallets.sort();
+                wallets.dedup();
+                for (a, &x) in wallets.iter().enumerate() {
+                    for &y in &wallets[a + 1..] {
+                        *round_trips.entry((x, y)).or_default() += 1;
+                    }
+                }
+                for j in path {
+                    used[j] = true;
+                    reasons[j] = Some(WashReason::RoundTrip);
+                }
+            }
+        }
+    }
+    for (&(a, b), &count) in &round_trips {
+        if count >= config.min_round_trips {
+            clusters.join(a, b);
+        }
+    }
+
+    let mut markets: BTreeMap<&str, MarketWash> = BTreeMap::new();
+    for (i, fill) in fills.iter().enumerate() {
+        if reasons[i].is_none() && clusters.related(fill.maker, fill.taker) {
+            reasons[i] = Some(WashReason::Cluster);
+        }
+        let market = markets.entry(&fill.market_name).or_insert_with(|| MarketWash {
+            market_name: fill.market_name.clone(),
+            volume: 0,
+            self_trade_volume: 0,
+            round_trip_volume: 0,
+            cluster_volume: 0,
+            suspect_share: 0.0,
+        });
+        market.volume = market.volume.saturating_add(fill.size);
+        match reasons[i] {
+            Some(WashReason::SelfTrade) => market.self_trade_volume = market.self_trade_volume.saturating_add(fill.size),
+            Some(WashReason::RoundTrip) => market.round_trip_volume = market.round_trip_volume.saturating_add(fill.size),
+            Some(WashReason::Cluster) => market.cluster_volume = market.cluster_volume.saturating_add(fill.size),
+            None => {}
+        }
+    }
+    let markets = markets
+        .into_values()
+        .map(|mut market| {
+            if market.volume > 0 {
+                market.suspect_share = market.suspect_volume() as f64 / market.volume as f64;
+            }
+            market
+        })
+        .collect();
+
+    WashReport {
+        markets,
+        clusters: clusters.groups(),
+        suspects: reasons
+            .iter()
+            .enumerate()
+            .filter_map(|(index, reason)| Some(SuspectFill { index, reason: (*reason)? }))
+            .collect(),
+    }
+}
+
+/// Suspect volume per wallet and market, largest share first. Each suspect
+/// fill counts for both of its wallets.
+pub fn wallet_volumes(fills: &[OwnedFill], report: &WashReport) -> Vec<WalletWash> {
+    let volumes: HashMap<&str, u64> = report.markets.iter().map(|m| (m.market_name.as_str(), m.volume)).collect();
+    let mut wallets: BTreeMap<(Pubkey, &str), WalletWash> = BTreeMap::new();
+    for suspect in &report.suspects {
+        let fill = &fills[suspect.index];
+        let mut owners = vec![fill.maker, fill.taker];
+        owners.dedup();
+        for wallet in owners {
+            let entry = wallets.entry((wallet, &fill.market_name)).or_insert_with(|| WalletWash {
+                wallet,
+                market_name: fill.market_name.clone(),
+                suspect_volume: 0,
+                suspect_share: 0.0,
+            });
+            entry.suspect_volume = entry.suspect_volume.saturating_add(fill.size);
+        }
+    }
+    let mut wallets: Vec<WalletWash> = wallets
+        .into_values()
+        .map(|mut wallet| {
+            wallet.suspect_share = wallet.suspect_volume as f64 / volumes[wallet.market_name.as_str()].max(1) as f64;
+            wallet
+        })
+        .collect();
+    wallets.sort_by(|a, b| b.suspect_share.total_cmp(&a.suspect_share));
+    wallets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(timestamp: i64, seller: Pubkey, buyer: Pubkey, price: u64, size: u64) -> OwnedFill {
+        // The seller rests the order and the buyer takes it
+        OwnedFill {
+            timestamp,
+            market_name: "SOL/USDC".to_string(),
+            maker: seller,
+            taker: buyer,
+            price,
+            size,
+            taker_is_bid: true,
+        }
+    }
+
+    #[test]
+    fn test_wash_trading() {
+        let [a, b, c, d, e] = [(); 5].map(|_| Pubkey::new_unique());
+        let fills = vec![
+            // a -> b -> c -> a: a three-wallet round trip
+            fill(0, a, b, 100, 10),
+            fill(10, b, c, 100, 10),
+            fill(20, c, a, 100, 10),
+            // A self-trade
+            fill(30, d, d, 100, 5),
+            // Honest trading, and b buying back too late to close a cycle
+            fill(40, e, d, 101, 20),
+            fill(50, a, b, 100, 10),
+            fill(400, b, a, 100, 10),
+            // a and c round-trip a second time, which relates them
+            fill(500, a, c, 100, 8),
+            fill(510, c, a, 100, 8),
+            fill(600, c, a, 150, 30),
+        ];
+        let report = detect(&fills, &[], &WashConfig::default());
+        let reason = |i: usize| report.suspects.iter().find(|s| s.index == i).map(|s| s.reason);
+        assert_eq!((reason(0), reason(1), reason(2)), (Some(WashReason::RoundTrip), Some(WashReason::RoundTrip), Some(WashReason::RoundTrip)));
+        assert_eq!(reason(3), Some(WashReason::SelfTrade));
+        assert_eq!((reason(4), reason(5), reason(6)), (None, None, None));
+        assert_eq!((reason(7), reason(8)), (Some(WashReason::RoundTrip), Some(WashReason::RoundTrip)));
+        // Not a round trip at a 50% price jump, but a and c are related by now
+        assert_eq!(reason(9), Some(WashReason::Cluster));
+        let mut related = vec![a, c];
+        related.sort();
+        assert_eq!(report.clusters, vec![related]);
+
+        let market = &report.markets[0];
+        assert_eq!((market.volume, market.self_trade_volume, market.round_trip_volume, market.cluster_volume), (121, 5, 46, 30));
+        assert!((market.suspect_share - 81.0 / 121.0).abs() < 1e-12);
+
+        // Wallets given as related make their other fills suspect
+        let report = detect(&fills, &[vec![e, d]], &WashConfig::default());
+        assert_eq!(report.suspects.iter().find(|s| s.index == 4).map(|s| s.reason), Some(WashReason::Cluster));
+        let wallets = wallet_volumes(&fills, &report);
+        let volume = |w: Pubkey| wallets.iter().find(|v| v.wallet == w).map_or(0, |v| v.suspect_volume);
+        assert_eq!((volume(a), volume(b), volume(c), volume(d), volume(e)), (66, 20, 66, 25, 20));
+        assert!((wallets[0].suspect_share - 66.0 / 121.0).abs() < 1e-12);
+    }
+}